@@ -0,0 +1,166 @@
+//! Archives
+//!
+//! Images within zip and tar archives, such as wallpaper packs, are shown as if the archive
+//! was a directory, with paths such as `pack.zip/image.png`.
+//!
+//! Archives are listed with `unzip` or `tar`, and each image is extracted, once, when it's
+//! first loaded, into `$XDG_CACHE_HOME/zss/archives`, and then loaded like any other file.
+//!
+//! Once the extracted files grow over `MAX_CACHE_SIZE`, the oldest are removed, and
+//! extracted again if loaded again.
+
+// Imports
+use crate::storage;
+use anyhow::Context;
+use std::{
+	os::unix::ffi::OsStrExt,
+	path::{Component, Path, PathBuf},
+	process::Command,
+	time::SystemTime,
+};
+
+/// Maximum size of all extracted files, in bytes
+const MAX_CACHE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Archive kind
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveKind {
+	/// Zip, listed and extracted with `unzip`
+	Zip,
+
+	/// Tar, possibly compressed, listed and extracted with `tar`
+	Tar,
+}
+
+impl ArchiveKind {
+	/// Extensions of tar archives
+	const TAR_EXTENSIONS: &'static [&'static str] = &[
+		".tar", ".cbt", ".tgz", ".txz", ".tbz2", ".tar.gz", ".tar.xz", ".tar.bz2", ".tar.zst",
+	];
+	/// Extensions of zip archives
+	const ZIP_EXTENSIONS: &'static [&'static str] = &[".zip", ".cbz"];
+
+	/// Returns the kind of archive `path` is, by it's extension, if any
+	pub fn from_path(path: &Path) -> Option<Self> {
+		let file_name = path.file_name()?.to_str()?.to_lowercase();
+		[(Self::Zip, Self::ZIP_EXTENSIONS), (Self::Tar, Self::TAR_EXTENSIONS)]
+			.iter()
+			.find(|(_, extensions)| extensions.iter().any(|extension| file_name.ends_with(extension)))
+			.map(|&(kind, _)| kind)
+	}
+}
+
+/// Returns if `path` is an archive
+pub fn is_archive(path: &Path) -> bool {
+	ArchiveKind::from_path(path).is_some() && path.is_file()
+}
+
+/// Returns the paths of all files within archive `path`
+pub fn entries(path: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+	let kind = ArchiveKind::from_path(path).context("File wasn't an archive")?;
+	let output = match kind {
+		ArchiveKind::Zip => self::run(Command::new("unzip").arg("-Z1").arg(path), "unzip")?,
+		ArchiveKind::Tar => self::run(Command::new("tar").arg("-tf").arg(path), "tar")?,
+	};
+	let output = String::from_utf8(output).context("Archive entries weren't utf-8")?;
+
+	// Note: Directories are listed with a trailing `/`
+	let entries = output
+		.lines()
+		.filter(|entry| !entry.is_empty() && !entry.ends_with('/'))
+		.filter(|entry| match self::is_safe_entry(Path::new(entry)) {
+			true => true,
+			false => {
+				log::warn!("Ignoring unsafe entry {entry:?} in archive {path:?}");
+				false
+			},
+		})
+		.map(|entry| path.join(entry))
+		.collect();
+
+	Ok(entries)
+}
+
+/// Returns the path to load `path` from.
+///
+/// If `path` is within an archive, extracts it, if not already extracted, and returns the
+/// extracted path, else returns `path` itself.
+pub fn resolve(path: &Path) -> Result<PathBuf, anyhow::Error> {
+	let (archive_path, kind) = match path
+		.ancestors()
+		.skip(1)
+		.find_map(|path| ArchiveKind::from_path(path).map(|kind| (path, kind)))
+	{
+		Some((archive_path, kind)) if archive_path.is_file() => (archive_path, kind),
+		_ => return Ok(path.to_path_buf()),
+	};
+	let entry = path
+		.strip_prefix(archive_path)
+		.expect("Archive was an ancestor of the path");
+	anyhow::ensure!(
+		self::is_safe_entry(entry),
+		"Archive entry {:?} must be relative, not contain `..` and not start with `-`",
+		entry
+	);
+
+	// Note: The archive might change, so we use it's modification time as part of the key
+	let modified = std::fs::metadata(archive_path)
+		.and_then(|metadata| metadata.modified())
+		.context("Unable to get archive modification time")?;
+	let modified = modified.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+	let key = storage::stable_hash(&[path.as_os_str().as_bytes(), &modified.as_nanos().to_le_bytes()]);
+
+	// Note: We keep the extension, so the file may still be decoded by it
+	let archives_dir = storage::cache_dir()
+		.context("Unable to get cache directory")?
+		.join("archives");
+	let file_name = match entry.extension().and_then(|extension| extension.to_str()) {
+		Some(extension) => format!("{key:016x}.{extension}"),
+		None => format!("{key:016x}"),
+	};
+	let extracted_path = archives_dir.join(file_name);
+	if extracted_path.exists() {
+		return Ok(extracted_path);
+	}
+
+	// Note: We pass `--` before the entry, so it's never parsed as an option.
+	//       `unzip` only accepts it before the archive, but entries starting with `-` are rejected above.
+	log::debug!("Extracting {entry:?} from {archive_path:?}");
+	let data = match kind {
+		ArchiveKind::Zip => self::run(
+			Command::new("unzip").arg("-p").arg("--").arg(archive_path).arg(entry),
+			"unzip",
+		)?,
+		ArchiveKind::Tar => self::run(
+			Command::new("tar").arg("-xOf").arg(archive_path).arg("--").arg(entry),
+			"tar",
+		)?,
+	};
+	storage::write_atomic(&extracted_path, &data).context("Unable to write extracted file")?;
+	if let Err(err) = storage::evict(&archives_dir, MAX_CACHE_SIZE) {
+		log::warn!("Unable to remove old extracted files: {err:?}");
+	}
+
+	Ok(extracted_path)
+}
+
+/// Returns if archive entry `entry` is relative and doesn't contain `..`, so it stays within the archive,
+/// and doesn't start with `-`, so it can't be parsed as an option
+fn is_safe_entry(entry: &Path) -> bool {
+	!entry.as_os_str().to_string_lossy().starts_with('-') &&
+		entry
+			.components()
+			.all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Runs `command`, named `name`, returning it's output
+fn run(command: &mut Command, name: &str) -> Result<Vec<u8>, anyhow::Error> {
+	let output = command.output().with_context(|| format!("Unable to run `{name}`"))?;
+	anyhow::ensure!(
+		output.status.success(),
+		"`{name}` failed: {}",
+		String::from_utf8_lossy(&output.stderr).trim()
+	);
+
+	Ok(output.stdout)
+}
@@ -1,32 +1,660 @@
 //! Args
 
 // Imports
+use crate::{
+	grid::Layout,
+	night_light::NightLight,
+	overlay::{Widget, WidgetKind},
+	remote, storage, tempo,
+};
 use anyhow::Context;
-use clap::{App as ClapApp, Arg as ClapArg};
-use std::{path::PathBuf, time::Duration};
+use chrono::NaiveTime;
+use clap::{App as ClapApp, AppSettings, Arg as ClapArg, SubCommand};
+use std::{collections::HashMap, error::Error, ffi::OsString, fmt, path::PathBuf, str::FromStr, time::Duration};
+
+/// Command
+#[allow(clippy::large_enum_variant)] // It's only created once, at start-up
+pub enum Command {
+	/// Runs the wallpaper
+	Run(Args),
+
+	/// Shows a single image on a window
+	Set(SetArgs),
+
+	/// Previews a grid layout
+	LayoutPreview(LayoutPreviewArgs),
+
+	/// Verifies rendered frames against a reference
+	Verify(VerifyArgs),
+
+	/// Renders a single frame to an image
+	Preview(PreviewArgs),
+
+	/// Renders the slideshow to a video
+	Render(RenderArgs),
+
+	/// Prints statistics about an images directory
+	Stats(StatsArgs),
+
+	/// Checks that all images in a directory can be loaded
+	Check(CheckArgs),
+
+	/// Measures how long each stage of loading images takes
+	Bench(BenchArgs),
+
+	/// Replays a recorded session
+	Replay(ReplayArgs),
+
+	/// Sends a command to a running wallpaper
+	Ctl(CtlArgs),
+}
 
 /// Args
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)] // They're all independent flags
 pub struct Args {
-	/// Window id
-	pub window_id: u64,
+	/// Outputs
+	///
+	/// Never empty.
+	pub outputs: Vec<Output>,
+
+	/// Config path
+	pub config_path: Option<PathBuf>,
 
 	/// Duration
 	pub duration: Duration,
 
+	/// Fraction by which the duration of each image is randomly lengthened or shortened
+	pub duration_jitter: f32,
+
 	/// Images directory
 	pub images_dir: PathBuf,
 
+	/// File to read the image paths from, or `-` for `stdin`, if any
+	pub images_from: Option<PathBuf>,
+
+	/// Urls of images to show
+	pub urls: Vec<String>,
+
+	/// Command to read images from, if any
+	pub images_command: Option<String>,
+
 	/// Fade
 	pub fade: f32,
 
 	/// Image backlog
 	pub image_backlog: usize,
 
+	/// Minimum image resolution
+	pub min_resolution: Option<[u32; 2]>,
+
+	/// Maximum image file size, in bytes
+	pub max_file_size: Option<u64>,
+
+	/// Maximum memory of the image backlog, in bytes
+	pub max_backlog_memory: Option<u64>,
+
+	/// Decode timeout
+	pub decode_timeout: Duration,
+
+	/// Interval between rescans of the images directory, if any
+	pub rescan_interval: Option<Duration>,
+
+	/// If the images directory shouldn't be watched
+	pub no_watch: bool,
+
+	/// Number of recently loaded images to avoid repeating, if any
+	pub no_repeat_last: Option<usize>,
+
+	/// If the recently loaded images should be persisted
+	pub persist_history: bool,
+
+	/// Weights of each directory, relative to the images directory
+	pub weights: HashMap<PathBuf, f64>,
+
+	/// Deduplication mode, if any
+	pub dedup: Option<DedupMode>,
+
+	/// Maximum image cache size, in bytes
+	pub image_cache_size: u64,
+
+	/// Quarantine log path
+	pub quarantine_log: Option<PathBuf>,
+
+	/// Ban list path, if any
+	pub ban_list: Option<PathBuf>,
+
+	/// Favorites list path, if any
+	pub favorites_list: Option<PathBuf>,
+
+	/// If only favorites should be shown
+	pub only_favorites: bool,
+
+	/// Late policy
+	pub late_policy: LatePolicy,
+
+	/// Fade space
+	pub fade_space: FadeSpace,
+
 	/// Mode
 	pub mode: Mode,
+
+	/// Gap between grid cells, in pixels
+	pub grid_gap: u32,
+
+	/// Margin around the grid, in pixels
+	pub grid_margin: u32,
+
+	/// Color shown between and around grid cells, if not the background color
+	pub grid_border_color: Option<[f32; 3]>,
+
+	/// When grid cells transition, relative to each other
+	pub grid_timing: GridTiming,
+
+	/// If all grid cells show the same image
+	pub grid_mirror: bool,
+
+	/// Images always shown on the grid cell at each slot
+	pub pinned_cells: Vec<([u32; 2], PathBuf)>,
+
+	/// Scaling
+	pub scaling: Scaling,
+
+	/// If images are shown on panels of the same shape
+	pub aspect_routing: bool,
+
+	/// Axes to mirror images across, if any
+	pub kaleidoscope: Option<Kaleidoscope>,
+
+	/// Blend mode of a second exposure over each image, if any
+	pub double_exposure: Option<BlendMode>,
+
+	/// Period of the mix factor of the second exposure
+	pub double_exposure_period: Duration,
+
+	/// Resize filter
+	pub resize_filter: ResizeFilter,
+
+	/// If images should be resized on the gpu
+	pub gpu_resize: bool,
+
+	/// If images should be kept as rgb, without alpha
+	pub rgb: bool,
+
+	/// Format to compress textures to, if any
+	pub compress_textures: Option<TextureCompression>,
+
+	/// If frames should be drawn on the cpu, instead of with open-gl
+	pub software_rendering: bool,
+
+	/// Background color
+	pub bg_color: [f32; 3],
+
+	/// If each image's dominant color should be used as it's background
+	pub dominant_bg: bool,
+
+	/// Number of sub-frame samples to interpolate scrolling with
+	pub interpolation_samples: u32,
+
+	/// Brightness
+	pub brightness: f32,
+
+	/// Contrast
+	pub contrast: f32,
+
+	/// Gamma
+	pub gamma: f32,
+
+	/// Saturation
+	pub saturation: f32,
+
+	/// Night light
+	pub night_light: Option<NightLight>,
+
+	/// Overlay widgets
+	pub overlays: Vec<Widget>,
+
+	/// Overlay scale, overriding each output's scale factor, if any
+	pub overlay_scale: Option<f32>,
+
+	/// Beats per minute
+	pub bpm: Option<f32>,
+
+	/// Beats per image
+	pub beats_per_image: usize,
+
+	/// Seed to synchronize to the wall clock with, if any
+	pub clock_sync: Option<u64>,
+
+	/// Address to listen for sync followers on, if any
+	pub sync_lead: Option<String>,
+
+	/// Address of the sync leader to follow, if any
+	pub sync_follow: Option<String>,
+
+	/// Number of passes through all images before exiting, if any
+	pub cycles: Option<usize>,
+
+	/// Duration to exit after, if any
+	pub exit_after: Option<Duration>,
+
+	/// Ipc socket path
+	pub ipc_socket: PathBuf,
+
+	/// File to write the current images to, if any
+	pub current_file: Option<PathBuf>,
+
+	/// Command to run whenever a new image is shown, if any
+	pub on_change: Option<String>,
+
+	/// File to write the palette of the current image to, if any
+	pub palette_out: Option<PathBuf>,
+
+	/// Template for the palette file, if any
+	pub palette_template: Option<PathBuf>,
+
+	/// Number of previously shown images kept, for each panel
+	pub prev_images: usize,
+
+	/// If we start paused
+	pub start_held: bool,
+
+	/// Path to record the session to
+	pub record: Option<PathBuf>,
+}
+
+/// Layout preview args
+pub struct LayoutPreviewArgs {
+	/// Grid width
+	pub grid_width: usize,
+
+	/// Grid height
+	pub grid_height: usize,
+
+	/// Output image size
+	pub size: [u32; 2],
+
+	/// Output path
+	pub out: PathBuf,
+}
+
+/// Verify args
+pub struct VerifyArgs {
+	/// Images directory
+	pub images_dir: PathBuf,
+
+	/// Config path
+	pub config_path: Option<PathBuf>,
+
+	/// Number of frames to render
+	pub frames: usize,
+
+	/// Frame size
+	pub size: [u32; 2],
+
+	/// Fade
+	pub fade: f32,
+
+	/// Fade space
+	pub fade_space: FadeSpace,
+
+	/// Reference directory to compare against
+	pub compare: Option<PathBuf>,
+
+	/// Directory to output the frames to
+	pub out: Option<PathBuf>,
+
+	/// Maximum mean difference allowed
+	pub threshold: f32,
+}
+
+/// Preview args
+pub struct PreviewArgs {
+	/// Image to show
+	pub image: PathBuf,
+
+	/// Image to fade into, if different from `image`
+	pub next_image: Option<PathBuf>,
+
+	/// Progress through the display of the image
+	pub at: f32,
+
+	/// Frame size
+	pub size: [u32; 2],
+
+	/// Scaling
+	pub scaling: Scaling,
+
+	/// Fade
+	pub fade: f32,
+
+	/// Fade space
+	pub fade_space: FadeSpace,
+
+	/// Background color
+	pub bg_color: [f32; 3],
+
+	/// Output path
+	pub out: PathBuf,
+}
+
+/// Render args
+pub struct RenderArgs {
+	/// Images directory
+	pub images_dir: PathBuf,
+
+	/// Length of the video
+	pub length: Duration,
+
+	/// Frames per second
+	pub fps: u32,
+
+	/// Duration of each image
+	pub duration: Duration,
+
+	/// Frame size
+	pub size: [u32; 2],
+
+	/// Scaling
+	pub scaling: Scaling,
+
+	/// Fade
+	pub fade: f32,
+
+	/// Fade space
+	pub fade_space: FadeSpace,
+
+	/// Background color
+	pub bg_color: [f32; 3],
+
+	/// Output path
+	pub out: PathBuf,
+}
+
+/// Stats args
+pub struct StatsArgs {
+	/// Images directory
+	pub images_dir: PathBuf,
+
+	/// If images should be loaded, to time them
+	pub decode: bool,
+
+	/// Window size to load images for
+	pub size: [u32; 2],
+
+	/// Scaling to load images with
+	pub scaling: Scaling,
+
+	/// Resize filter to load images with
+	pub resize_filter: ResizeFilter,
+}
+
+/// Check args
+pub struct CheckArgs {
+	/// Images directory
+	pub images_dir: PathBuf,
+
+	/// Config path
+	pub config_path: Option<PathBuf>,
+
+	/// Window size to load images for
+	pub size: [u32; 2],
+
+	/// Scaling to load images with
+	pub scaling: Scaling,
+
+	/// Maximum aspect ratio difference to the window before an image is reported
+	pub max_aspect_ratio: f32,
+}
+
+/// Bench args
+pub struct BenchArgs {
+	/// Images directory
+	pub images_dir: PathBuf,
+
+	/// Window size to load images for
+	pub size: [u32; 2],
+
+	/// Scaling to load images with
+	pub scaling: Scaling,
+
+	/// Resize filter to load images with
+	pub resize_filter: ResizeFilter,
+
+	/// Maximum number of images to measure, if any
+	pub limit: Option<usize>,
+}
+
+/// Replay args
+pub struct ReplayArgs {
+	/// Replay file
+	pub path: PathBuf,
+
+	/// Window id
+	pub window_id: u64,
+}
+
+/// Set args
+pub struct SetArgs {
+	/// Window id
+	pub window_id: u64,
+
+	/// Image to show
+	pub image: PathBuf,
+
+	/// Scaling
+	pub scaling: Scaling,
+
+	/// Background color
+	pub bg_color: [f32; 3],
+}
+
+/// Ctl args
+pub struct CtlArgs {
+	/// Ipc socket path
+	pub ipc_socket: PathBuf,
+
+	/// Command
+	pub command: String,
+}
+
+/// Output to show the wallpaper on
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Output {
+	/// Name, to find it's section in the config
+	pub name: Option<String>,
+
+	/// Kind
+	pub kind: OutputKind,
+}
+
+impl fmt::Display for Output {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.name {
+			Some(name) => write!(f, "{name} ({})", self.kind),
+			None => write!(f, "{}", self.kind),
+		}
+	}
+}
+
+/// Output kind
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OutputKind {
+	/// Existing `X` window, by it's id
+	Window(u64),
+
+	/// Display connected to a drm device, without `X`
+	Kms(PathBuf),
+}
+
+impl OutputKind {
+	/// Default drm device
+	pub const DEFAULT_KMS_DEVICE: &'static str = "/dev/dri/card0";
+}
+
+impl fmt::Display for OutputKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Window(id) => write!(f, "window {id:#x}"),
+			Self::Kms(device) => write!(f, "drm device {}", device.display()),
+		}
+	}
+}
+
+/// Policy for when the next image is late
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LatePolicy {
+	/// Blocks until the next image arrives
+	Wait,
+
+	/// Extends the current image's duration until the next image arrives
+	Extend,
+
+	/// Replays the current image, scrolling back through it
+	Replay,
+
+	/// Fades into a placeholder until the next image arrives
+	Placeholder,
+}
+
+/// When grid cells transition, relative to each other
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GridTiming {
+	/// Each cell starts at a random progress
+	Random,
+
+	/// All cells transition together
+	Sync,
+
+	/// Each cell transitions this long after the previous one
+	Stagger(Duration),
+}
+
+/// Color space to fade between images in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FadeSpace {
+	/// Linear light, avoiding a brightness dip mid-fade
+	Linear,
+
+	/// Srgb, blending the encoded values directly
+	Srgb,
+}
+
+/// Scaling
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Scaling {
+	/// Fills the window, cropping the image and scrolling through it
+	Fill,
+
+	/// Fits the whole image within the window
+	Fit,
+
+	/// Fits the whole image within the window, over a blurred copy of itself
+	ContainBlur,
+
+	/// Stretches the image to the window
+	Stretch,
+
+	/// Centers the image on the window, without scaling it
+	Center,
+
+	/// Tiles the image over the window, without scaling it
+	Tile,
+}
+
+/// Axes to mirror images across
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kaleidoscope {
+	/// Mirrors the left half onto the right half
+	Horizontal,
+
+	/// Mirrors the bottom half onto the top half
+	Vertical,
+
+	/// Mirrors the bottom-left quarter onto the others
+	Both,
+}
+
+/// Blend mode of a second exposure
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+	/// Multiplies both images, darkening them
+	Multiply,
+
+	/// Multiplies the inverse of both images, lightening them
+	Screen,
+
+	/// Multiplies dark areas and screens light areas of the first image, increasing contrast
+	Overlay,
+}
+
+/// Logging args
+#[derive(Clone, Debug)]
+pub struct LogArgs {
+	/// Default level
+	pub level: log::LevelFilter,
+
+	/// Levels of specific modules, overriding the default level
+	pub module_levels: Vec<(String, log::LevelFilter)>,
+
+	/// File to log to, instead of `stderr`
+	pub file: Option<PathBuf>,
+
+	/// Maximum size of the log file, before it's rotated
+	pub max_file_size: u64,
+
+	/// Format
+	pub format: LogFormat,
+}
+
+/// Log format
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogFormat {
+	/// Human readable text
+	Text,
+
+	/// A json object per line
+	Json,
+}
+
+/// Filter used when resizing images
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ResizeFilter {
+	/// Nearest neighbor
+	Nearest,
+
+	/// Linear
+	Triangle,
+
+	/// Cubic
+	CatmullRom,
+
+	/// Lanczos with a window of 3
+	Lanczos3,
+}
+
+/// Deduplication mode
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DedupMode {
+	/// Identical files
+	Exact,
+
+	/// Identical files and similar looking images
+	Perceptual,
+}
+
+/// Texture compression format
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TextureCompression {
+	/// BC1 (S3TC DXT1), 8x smaller
+	Bc1,
+
+	/// BC7 (BPTC), 4x smaller, but with a higher quality
+	Bc7,
 }
 
 /// Mode
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Mode {
 	/// Single image
 	Single,
@@ -39,128 +667,2949 @@ pub enum Mode {
 		/// Height
 		height: usize,
 	},
+
+	/// Grid with a custom layout
+	Layout(Layout),
+
+	/// Grid with a random collage of cells spanning several slots, re-arranged every `interval`
+	Collage {
+		/// Width
+		width: usize,
+
+		/// Height
+		height: usize,
+
+		/// Interval between arrangements
+		interval: Duration,
+	},
 }
 
-impl Args {
-	/// Parses all arguments
+impl Mode {
+	/// Returns the layout of the grid, if any
+	///
+	/// Collages are arranged randomly on each call.
+	pub fn layout(&self) -> Option<Layout> {
+		match *self {
+			Self::Single => None,
+			#[allow(clippy::cast_possible_truncation)] // Widths and heights will be small enough for this to not matter
+			Self::Grid { width, height } => Some(Layout::uniform([width as u32, height as u32])),
+			Self::Layout(ref layout) => Some(layout.clone()),
+			#[allow(clippy::cast_possible_truncation)] // See above
+			Self::Collage { width, height, .. } => Some(Layout::collage([width as u32, height as u32])),
+		}
+	}
+}
+
+impl Command {
+	/// Parses all arguments, along with the logging arguments
+	pub fn new() -> Result<(Self, LogArgs), anyhow::Error> {
+		Self::from_args(std::env::args_os())
+	}
+
+	/// Parses all arguments from `args`, including the binary name, along with the logging arguments
 	#[allow(clippy::too_many_lines)] // TODO: Refactor
-	pub fn new() -> Result<Self, anyhow::Error> {
+	pub fn from_args(args: impl IntoIterator<Item = OsString>) -> Result<(Self, LogArgs), anyhow::Error> {
 		const WINDOW_ID_STR: &str = "window-id";
+		const OUTPUT_STR: &str = "output";
 		const IMAGES_DIR_STR: &str = "images-dir";
+		const IMAGES_FROM_STR: &str = "images-from";
+		const URL_STR: &str = "url";
+		const IMAGES_COMMAND_STR: &str = "images-command";
+		const CONFIG_STR: &str = "config";
 		const DURATION_STR: &str = "duration";
+		const DURATION_JITTER_STR: &str = "duration-jitter";
 		const FADE_STR: &str = "fade";
 		const IMAGE_BACKLOG_STR: &str = "image-backlog";
 		const GRID_STR: &str = "grid";
+		const SPAN_STR: &str = "span";
+		const COLLAGE_STR: &str = "collage";
+		const LAYOUT_FILE_STR: &str = "layout-file";
+		const GRID_GAP_STR: &str = "grid-gap";
+		const GRID_MARGIN_STR: &str = "grid-margin";
+		const GRID_BORDER_COLOR_STR: &str = "grid-border-color";
+		const GRID_SYNC_STR: &str = "grid-sync";
+		const GRID_STAGGER_STR: &str = "grid-stagger";
+		const GRID_MIRROR_STR: &str = "grid-mirror";
+		const CELL_STR: &str = "cell";
+		const MIN_RESOLUTION_STR: &str = "min-resolution";
+		const MAX_FILE_SIZE_STR: &str = "max-file-size";
+		const MAX_BACKLOG_MEMORY_STR: &str = "max-backlog-memory";
+		const DECODE_TIMEOUT_STR: &str = "decode-timeout";
+		const RESCAN_INTERVAL_STR: &str = "rescan-interval";
+		const NO_WATCH_STR: &str = "no-watch";
+		const NO_REPEAT_LAST_STR: &str = "no-repeat-last";
+		const PERSIST_HISTORY_STR: &str = "persist-history";
+		const WEIGHT_STR: &str = "weight";
+		const DEDUP_STR: &str = "dedup";
+		const IMAGE_CACHE_SIZE_STR: &str = "image-cache-size";
+		const QUARANTINE_LOG_STR: &str = "quarantine-log";
+		const BAN_LIST_STR: &str = "ban-list";
+		const FAVORITES_LIST_STR: &str = "favorites-list";
+		const ONLY_FAVORITES_STR: &str = "only-favorites";
+		const LATE_POLICY_STR: &str = "late-policy";
+		const FADE_SPACE_STR: &str = "fade-space";
+		const SCALING_STR: &str = "scaling";
+		const ASPECT_ROUTING_STR: &str = "aspect-routing";
+		const KALEIDOSCOPE_STR: &str = "kaleidoscope";
+		const DOUBLE_EXPOSURE_STR: &str = "double-exposure";
+		const DOUBLE_EXPOSURE_PERIOD_STR: &str = "double-exposure-period";
+		const RESIZE_FILTER_STR: &str = "resize-filter";
+		const GPU_RESIZE_STR: &str = "gpu-resize";
+		const RGB_STR: &str = "rgb";
+		const COMPRESS_TEXTURES_STR: &str = "compress-textures";
+		const SOFTWARE_RENDERING_STR: &str = "software-rendering";
+		const BG_COLOR_STR: &str = "bg-color";
+		const DOMINANT_BG_STR: &str = "dominant-bg";
+		const INTERPOLATION_SAMPLES_STR: &str = "interpolation-samples";
+		const BRIGHTNESS_STR: &str = "brightness";
+		const CONTRAST_STR: &str = "contrast";
+		const GAMMA_STR: &str = "gamma";
+		const SATURATION_STR: &str = "saturation";
+		const NIGHT_LIGHT_STR: &str = "night-light";
+		const NIGHT_BRIGHTNESS_STR: &str = "night-brightness";
+		const NIGHT_TEMPERATURE_STR: &str = "night-temperature";
+		const NIGHT_TRANSITION_STR: &str = "night-transition";
+		const OVERLAY_STR: &str = "overlay";
+		const OVERLAY_SCALE_STR: &str = "overlay-scale";
+		const BPM_STR: &str = "bpm";
+		const CLOCK_SYNC_STR: &str = "clock-sync";
+		const SYNC_LEAD_STR: &str = "sync-lead";
+		const SYNC_FOLLOW_STR: &str = "sync-follow";
+		const CYCLES_STR: &str = "cycles";
+		const EXIT_AFTER_STR: &str = "exit-after";
+		const BEATS_PER_IMAGE_STR: &str = "beats-per-image";
+		const IPC_SOCKET_STR: &str = "ipc-socket";
+		const CURRENT_FILE_STR: &str = "current-file";
+		const ON_CHANGE_STR: &str = "on-change";
+		const PALETTE_OUT_STR: &str = "palette-out";
+		const PALETTE_TEMPLATE_STR: &str = "palette-template";
+		const PREV_IMAGES_STR: &str = "prev-images";
+		const START_HELD_STR: &str = "start-held";
+		const LAYOUT_STR: &str = "layout";
+		const PREVIEW_STR: &str = "preview";
+		const SIZE_STR: &str = "size";
+		const OUT_STR: &str = "out";
+		const VERIFY_STR: &str = "verify";
+		const FRAMES_STR: &str = "frames";
+		const COMPARE_STR: &str = "compare";
+		const THRESHOLD_STR: &str = "threshold";
+		const IMAGE_STR: &str = "image";
+		const NEXT_IMAGE_STR: &str = "next-image";
+		const AT_STR: &str = "at";
+		const RENDER_STR: &str = "render";
+		const SECONDS_STR: &str = "seconds";
+		const FPS_STR: &str = "fps";
+		const STATS_STR: &str = "stats";
+		const DECODE_STR: &str = "decode";
+		const RECORD_STR: &str = "record";
+		const REPLAY_STR: &str = "replay";
+		const REPLAY_FILE_STR: &str = "replay-file";
+		const CTL_STR: &str = "ctl";
+		const COMMAND_STR: &str = "command";
+		const RUN_STR: &str = "run";
+		const SET_STR: &str = "set";
+		const CHECK_STR: &str = "check";
+		const MAX_ASPECT_RATIO_STR: &str = "max-aspect-ratio";
+		const BENCH_STR: &str = "bench";
+		const LIMIT_STR: &str = "limit";
+		const VERBOSE_STR: &str = "verbose";
+		const QUIET_STR: &str = "quiet";
+		const LOG_FILE_STR: &str = "log-file";
+		const LOG_MAX_SIZE_STR: &str = "log-max-size";
+		const LOG_FORMAT_STR: &str = "log-format";
+		const GLOBAL_FLAGS: &[&str] = &["-v", "-vv", "-vvv", "--verbose", "-q", "-qq", "--quiet"];
+		const GLOBAL_OPTIONS: &[&str] = &["--log-file", "--log-max-size", "--log-format"];
+		const SUBCOMMANDS: &[&str] = &[
+			RUN_STR,
+			SET_STR,
+			LAYOUT_STR,
+			VERIFY_STR,
+			PREVIEW_STR,
+			RENDER_STR,
+			STATS_STR,
+			CHECK_STR,
+			BENCH_STR,
+			REPLAY_STR,
+			CTL_STR,
+			"help",
+		];
+
+		// If we didn't get a subcommand, default to `run`
+		// Note: This keeps the arguments of older versions, such as in recorded sessions, working.
+		// Note: Any global arguments may come before the subcommand, so we skip them
+		let mut args = args.into_iter().collect::<Vec<_>>();
+		let mut subcommand_idx = 1;
+		while let Some(arg) = args.get(subcommand_idx).and_then(|arg| arg.to_str()) {
+			let option = arg.split('=').next().expect("Split always yields at least 1 item");
+			match arg {
+				_ if GLOBAL_FLAGS.contains(&arg) => subcommand_idx += 1,
+				_ if GLOBAL_OPTIONS.contains(&arg) => subcommand_idx += 2,
+				_ if GLOBAL_OPTIONS.contains(&option) => subcommand_idx += 1,
+				_ => break,
+			}
+		}
+		let has_subcommand = match args.get(subcommand_idx).and_then(|arg| arg.to_str()) {
+			Some(arg) => SUBCOMMANDS.contains(&arg) || ["-h", "--help", "-V", "--version"].contains(&arg),
+			None => true,
+		};
+		if !has_subcommand {
+			args.insert(subcommand_idx, OsString::from(RUN_STR));
+		}
 
 		// Get all matches from cli
 		let matches = ClapApp::new("Zss")
 			.version("1.0")
 			.author("Filipe Rodrigues <filipejacintorodrigues1@gmail.com>")
 			.about("Displays a scrolling wallpaper with Multiple images")
+			.setting(AppSettings::SubcommandRequiredElseHelp)
 			.arg(
-				ClapArg::with_name(WINDOW_ID_STR)
-					.help("The window id")
-					.long_help("An `X` window id. Typically obtained from `xwinwrap`")
-					.takes_value(true)
-					.required(true)
-					.long("window-id")
-					.short("w")
-					.index(1),
+				ClapArg::with_name(VERBOSE_STR)
+					.help("Increases verbosity")
+					.long_help(
+						"Logs more, `-v` for debug and `-vv` for trace messages. Per-module levels may be set with \
+						 the `ZSS_LOG` environment variable, such as `ZSS_LOG=warn,zss::images=debug`.",
+					)
+					.long("verbose")
+					.short("v")
+					.multiple(true)
+					.global(true),
 			)
 			.arg(
-				ClapArg::with_name(IMAGES_DIR_STR)
-					.help("Images Directory")
-					.long_help("Path to directory with images. Non-images will be ignored.")
-					.takes_value(true)
-					.required(true)
-					.long("images-dir")
-					.short("i")
-					.index(2),
+				ClapArg::with_name(QUIET_STR)
+					.help("Decreases verbosity")
+					.long_help("Logs less, `-q` for only warnings and `-qq` for only errors.")
+					.long("quiet")
+					.short("q")
+					.multiple(true)
+					.global(true),
 			)
 			.arg(
-				ClapArg::with_name(DURATION_STR)
-					.help("Duration (in seconds) of each image")
-					.long_help("Duration, in seconds, each image will take up on screen, including during fading.")
+				ClapArg::with_name(LOG_FILE_STR)
+					.help("Log file")
+					.long_help(
+						"Logs to this file, instead of `stderr`. Once it grows larger than `--log-max-size`, it's \
+						 rotated, renaming it to `<log-file>.1`, with the previous ones being renamed to \
+						 `<log-file>.2` and `<log-file>.3`, and the oldest deleted.",
+					)
 					.takes_value(true)
-					.long("duration")
-					.short("d")
-					.default_value("30"),
+					.long("log-file")
+					.global(true),
 			)
 			.arg(
-				ClapArg::with_name(FADE_STR)
-					.help("Fade percentage (0.5 .. 1.0)")
-					.long_help("Percentage, from 0.5 to 1.0, of when to start fading the image during it's display.")
+				ClapArg::with_name(LOG_MAX_SIZE_STR)
+					.help("Maximum log file size")
+					.long_help("Maximum size of the log file before it's rotated. Defaults to `10M`.")
 					.takes_value(true)
-					.long("fade")
-					.short("f")
-					.default_value("0.8"),
+					.long("log-max-size")
+					.requires(LOG_FILE_STR)
+					.global(true),
 			)
 			.arg(
-				ClapArg::with_name(IMAGE_BACKLOG_STR)
-					.help("Image backlog")
-					.long_help("Number of images to keep loaded, aside from 2/3 that must be always loaded.")
+				ClapArg::with_name(LOG_FORMAT_STR)
+					.help("Log format")
+					.long_help(
+						"Format of each logged message, either `text` or `json`, with a json object per line, with \
+						 the `time`, `level`, `target` and `message` fields. Defaults to `text`.",
+					)
 					.takes_value(true)
-					.long("backlog")
-					.short("b")
-					.default_value("0"),
+					.long("log-format")
+					.possible_values(&["text", "json"])
+					.global(true),
 			)
-			.arg(
-				ClapArg::with_name(GRID_STR)
-					.help("Grid")
-					.long_help("Displays a grid of images, as `{width}x{height}`")
-					.takes_value(true)
-					.long("grid"),
+			.subcommand(
+				SubCommand::with_name(RUN_STR)
+					.about("Runs the wallpaper")
+					.arg(
+						ClapArg::with_name(OUTPUT_STR)
+							.help("The window id")
+							.long_help(
+								"An `X` window id. Typically obtained from `xwinwrap`. With `kms`, or `kms:<device>`, \
+								 shows directly on the display connected to a drm device, `/dev/dri/card0` by \
+								 default, without `X`. May be prefixed by `<name>=` to use the `[outputs.<name>]` \
+								 section of the config.",
+							)
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(WINDOW_ID_STR)
+							.help("Additional window id")
+							.long_help(
+								"Also shows on another window, such as one per monitor, in the same format as the \
+								 window id. May be repeated. All windows share the same images, so each image is only \
+								 loaded once.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.number_of_values(1)
+							.long("window-id")
+							.short("w"),
+					)
+					.arg(
+						ClapArg::with_name(IMAGES_DIR_STR)
+							.help("Images Directory")
+							.long_help(
+								"Path to directory with images. Non-images will be ignored. With `--images-from`, \
+								 `--url` or `--images-command`, defaults to the current directory, and is only used \
+								 for directory weights.",
+							)
+							.takes_value(true)
+							.required_unless_one(&[IMAGES_FROM_STR, URL_STR, IMAGES_COMMAND_STR])
+							.long("images-dir")
+							.short("i")
+							.index(2),
+					)
+					.arg(
+						ClapArg::with_name(IMAGES_FROM_STR)
+							.help("File to read image paths from")
+							.long_help(
+								"Reads the paths of the images to show from a file, or `stdin`, if `-`, one per line, \
+								 instead of the images directory. Paths are loaded as they're written. `http` and \
+								 `https` urls are downloaded with `curl`.",
+							)
+							.takes_value(true)
+							.long("images-from"),
+					)
+					.arg(
+						ClapArg::with_name(URL_STR)
+							.help("Url of an image")
+							.long_help(
+								"Url of an image to show, instead of the images directory, downloaded with `curl` \
+								 into `$XDG_CACHE_HOME/zss/remote`. May be given multiple times.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.number_of_values(1)
+							.conflicts_with(IMAGES_FROM_STR)
+							.long("url"),
+					)
+					.arg(
+						ClapArg::with_name(IMAGES_COMMAND_STR)
+							.help("Command to read images from")
+							.long_help(
+								"Runs this command with `sh` and shows the images it writes to it's `stdout`, instead \
+								 of the images directory. Each image starts with a header line, either `rgba <width> \
+								 <height>`, followed by the raw pixels, or `png <length>`, followed by the encoded \
+								 image.",
+							)
+							.takes_value(true)
+							.conflicts_with_all(&[IMAGES_FROM_STR, URL_STR, RECORD_STR])
+							.long("images-command"),
+					)
+					.arg(
+						ClapArg::with_name(CONFIG_STR)
+							.help("Config file")
+							.long_help("Path to a toml config file, for settings not available as arguments.")
+							.takes_value(true)
+							.long("config")
+							.short("c"),
+					)
+					.arg(
+						ClapArg::with_name(DURATION_STR)
+							.help("Duration of each image")
+							.long_help(
+								"Duration each image will take up on screen, including during fading. In seconds, or \
+								 with a `ms`, `s`, `m` or `h` suffix.",
+							)
+							.takes_value(true)
+							.long("duration")
+							.short("d")
+							.default_value("30"),
+					)
+					.arg(
+						ClapArg::with_name(DURATION_JITTER_STR)
+							.help("Randomize the duration of each image")
+							.long_help(
+								"Randomly lengthens or shortens the duration of each image by up to this fraction, \
+								 such as `0.1` for up to 10%, so grid cells gradually stop transitioning together.",
+							)
+							.takes_value(true)
+							.long("duration-jitter")
+							.default_value("0"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_STR)
+							.help("Fade percentage (0.5 .. 1.0)")
+							.long_help(
+								"Percentage, from 0.5 to 1.0, of when to start fading the image during it's display.",
+							)
+							.takes_value(true)
+							.long("fade")
+							.short("f")
+							.default_value("0.8"),
+					)
+					.arg(
+						ClapArg::with_name(IMAGE_BACKLOG_STR)
+							.help("Image backlog")
+							.long_help("Number of images to keep loaded, aside from 2/3 that must be always loaded.")
+							.takes_value(true)
+							.long("backlog")
+							.short("b")
+							.default_value("0"),
+					)
+					.arg(
+						ClapArg::with_name(MIN_RESOLUTION_STR)
+							.help("Minimum resolution")
+							.long_help("Skips images smaller than this resolution, as `{width}x{height}`")
+							.takes_value(true)
+							.long("min-resolution"),
+					)
+					.arg(
+						ClapArg::with_name(MAX_FILE_SIZE_STR)
+							.help("Maximum file size")
+							.long_help(
+								"Skips files larger than this size, in bytes, with an optional `K`, `M` or `G` suffix.",
+							)
+							.takes_value(true)
+							.long("max-file-size"),
+					)
+					.arg(
+						ClapArg::with_name(MAX_BACKLOG_MEMORY_STR)
+							.help("Maximum backlog memory")
+							.long_help(
+								"Maximum memory used by the decoded images in the backlog, in bytes, with an optional \
+								 `K`, `M` or `G` suffix. Images larger than it are still loaded once the backlog is \
+								 empty.",
+							)
+							.takes_value(true)
+							.long("max-backlog-memory"),
+					)
+					.arg(
+						ClapArg::with_name(DECODE_TIMEOUT_STR)
+							.help("Decode timeout")
+							.long_help(
+								"Maximum time to spend loading a single file, before skipping it. In seconds, or with \
+								 a `ms`, `s`, `m` or `h` suffix. Files that fail to load 3 times are quarantined and \
+								 skipped in future runs.",
+							)
+							.takes_value(true)
+							.long("decode-timeout")
+							.default_value("30"),
+					)
+					.arg(
+						ClapArg::with_name(RESCAN_INTERVAL_STR)
+							.help("Rescan interval")
+							.long_help(
+								"Interval to rescan the images directory at, for changes the watcher doesn't notice, \
+								 such as on network mounts. In seconds, or with a `ms`, `s`, `m` or `h` suffix.",
+							)
+							.takes_value(true)
+							.long("rescan-interval"),
+					)
+					.arg(
+						ClapArg::with_name(NO_WATCH_STR)
+							.help("Don't watch the images directory")
+							.long_help(
+								"Doesn't watch the images directory for changes, only scanning it at start up, and \
+								 every `--rescan-interval`, if given.",
+							)
+							.long("no-watch"),
+					)
+					.arg(
+						ClapArg::with_name(NO_REPEAT_LAST_STR)
+							.help("Number of recent images to not repeat")
+							.long_help(
+								"Avoids repeating any of the last N loaded images, even across passes, by moving them \
+								 to the end of each pass.",
+							)
+							.takes_value(true)
+							.long("no-repeat-last"),
+					)
+					.arg(
+						ClapArg::with_name(PERSIST_HISTORY_STR)
+							.help("Persist the recent images")
+							.long_help(
+								"Persists the images recently loaded for `--no-repeat-last` in \
+								 `$XDG_STATE_HOME/zss/history.toml`, so they're also not repeated after restarting, \
+								 and how many times each image was shown in `$XDG_STATE_HOME/zss/shows.toml`, for \
+								 `zss stats`.",
+							)
+							.long("persist-history"),
+					)
+					.arg(
+						ClapArg::with_name(WEIGHT_STR)
+							.help("Weight of a directory")
+							.long_help(
+								"Weight of each image in a directory, relative to the images directory, as \
+								 `<dir>=<weight>`, such as `nature=3`, biasing the random selection towards it. May \
+								 be given multiple times. Images outside of all weighted directories have a weight of \
+								 `1`.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.number_of_values(1)
+							.long("weight"),
+					)
+					.arg(
+						ClapArg::with_name(DEDUP_STR)
+							.help("Skip duplicate images")
+							.long_help(
+								"Skips copies of images that were already loaded, so they aren't shown more often. \
+								 `exact` skips identical files, while `perceptual` also skips similar looking images, \
+								 such as re-encoded or resized copies.",
+							)
+							.takes_value(true)
+							.long("dedup")
+							.possible_values(&["exact", "perceptual"]),
+					)
+					.arg(
+						ClapArg::with_name(IMAGE_CACHE_SIZE_STR)
+							.help("Image cache size")
+							.long_help(
+								"Maximum size of the on-disk cache of resized images, in bytes, with an optional `K`, \
+								 `M` or `G` suffix. `0` disables the cache.",
+							)
+							.takes_value(true)
+							.long("image-cache-size")
+							.default_value("1G"),
+					)
+					.arg(
+						ClapArg::with_name(QUARANTINE_LOG_STR)
+							.help("Quarantine log")
+							.long_help(
+								"File to append the paths of newly quarantined files to, along with their last error.",
+							)
+							.takes_value(true)
+							.long("quarantine-log"),
+					)
+					.arg(
+						ClapArg::with_name(BAN_LIST_STR)
+							.help("Ban list")
+							.long_help(
+								"File with the paths of all banned images, one per line, which are never shown. \
+								 Images are added to it with the `ban` ipc command. Defaults to \
+								 `$XDG_STATE_HOME/zss/banned.txt`.",
+							)
+							.takes_value(true)
+							.long("ban-list"),
+					)
+					.arg(
+						ClapArg::with_name(FAVORITES_LIST_STR)
+							.help("Favorites list")
+							.long_help(
+								"File with the paths of all favorite images, one per line. Images are added to it \
+								 with the `fav` ipc command. Defaults to `$XDG_STATE_HOME/zss/favorites.txt`.",
+							)
+							.takes_value(true)
+							.long("favorites-list"),
+					)
+					.arg(
+						ClapArg::with_name(ONLY_FAVORITES_STR)
+							.help("Only show favorites")
+							.long_help("Only shows images in the favorites list, skipping all others.")
+							.long("only-favorites"),
+					)
+					.arg(
+						ClapArg::with_name(LATE_POLICY_STR)
+							.help("Late policy")
+							.long_help(
+								"What to do when the next image hasn't been loaded by the time it should start fading \
+								 in. `wait` blocks until it's loaded, `extend` extends the current image's duration, \
+								 `replay` scrolls back through the current image and `placeholder` fades into the \
+								 background color.",
+							)
+							.takes_value(true)
+							.long("late-policy")
+							.possible_values(&["wait", "extend", "replay", "placeholder"])
+							.default_value("extend"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_SPACE_STR)
+							.help("Fade color space")
+							.long_help(
+								"Color space to fade between images in. `linear` blends in linear light, while `srgb` \
+								 blends the srgb values directly, which dims the image mid-fade.",
+							)
+							.takes_value(true)
+							.long("fade-space")
+							.possible_values(&["linear", "srgb"])
+							.default_value("linear"),
+					)
+					.arg(
+						ClapArg::with_name(GRID_STR)
+							.help("Grid")
+							.long_help("Displays a grid of images, as `{width}x{height}`")
+							.takes_value(true)
+							.long("grid"),
+					)
+					.arg(
+						ClapArg::with_name(SPAN_STR)
+							.help("Grid cell spanning several slots")
+							.long_help(
+								"Merges the slots of the grid starting at a slot into a single cell, as \
+								 `{x},{y}:{width}x{height}`, with `0,0` being the bottom-left slot, such as `0,0:2x2` \
+								 for a cell twice as wide and tall as the others. May be given multiple times, as \
+								 long as they don't overlap.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.number_of_values(1)
+							.long("span")
+							.requires(GRID_STR),
+					)
+					.arg(
+						ClapArg::with_name(COLLAGE_STR)
+							.help("Random collage")
+							.long_help(
+								"Shows a random collage on the slots of the grid, with cells spanning different \
+								 numbers of slots, re-arranged after this duration, such as `10m`. Implies \
+								 `--aspect-routing`, so images are shown on cells of their shape.",
+							)
+							.takes_value(true)
+							.long("collage")
+							.requires(GRID_STR)
+							.conflicts_with(SPAN_STR),
+					)
+					.arg(
+						ClapArg::with_name(LAYOUT_FILE_STR)
+							.help("Grid layout file")
+							.long_help(
+								"Displays a grid of images with the layout in this file. Each line is a row of slots, \
+								 and each character a slot, with all slots of the same character making up a single, \
+								 rectangular, cell, and `.` leaving a slot empty, such as the lines `AAB` and `AAC` \
+								 for a large cell next to 2 small ones.",
+							)
+							.takes_value(true)
+							.long("layout")
+							.conflicts_with(GRID_STR),
+					)
+					.arg(
+						ClapArg::with_name(GRID_GAP_STR)
+							.help("Gap between grid cells")
+							.long_help(
+								"Gap between grid cells, in pixels, optionally with a `px` suffix, such as `8px`",
+							)
+							.takes_value(true)
+							.long("grid-gap")
+							.default_value("0"),
+					)
+					.arg(
+						ClapArg::with_name(GRID_MARGIN_STR)
+							.help("Margin around the grid")
+							.long_help(
+								"Margin between the grid and the edges of the window, in pixels, optionally with a \
+								 `px` suffix, such as `16px`",
+							)
+							.takes_value(true)
+							.long("grid-margin")
+							.default_value("0"),
+					)
+					.arg(
+						ClapArg::with_name(GRID_BORDER_COLOR_STR)
+							.help("Color between grid cells")
+							.long_help(
+								"Color, as `#rrggbb`, shown in the gaps between grid cells and in the margin around \
+								 the grid. Defaults to the background color.",
+							)
+							.takes_value(true)
+							.long("grid-border-color"),
+					)
+					.arg(
+						ClapArg::with_name(GRID_SYNC_STR)
+							.help("Transition all grid cells together")
+							.long_help(
+								"Starts all grid cells at the same time, so they all fade at once, instead of \
+								 randomly.",
+							)
+							.long("grid-sync"),
+					)
+					.arg(
+						ClapArg::with_name(GRID_STAGGER_STR)
+							.help("Transition grid cells one after another")
+							.long_help(
+								"Starts each grid cell this long after the previous one, such as `2s`, so they fade \
+								 one after another, from the bottom-left, instead of randomly.",
+							)
+							.takes_value(true)
+							.long("grid-stagger")
+							.conflicts_with(GRID_SYNC_STR),
+					)
+					.arg(
+						ClapArg::with_name(GRID_MIRROR_STR)
+							.help("Show the same image on all grid cells")
+							.long_help(
+								"Shows the same image on all grid cells, each randomly flipped, so it scrolls in a \
+								 different direction, and only loading each image once. Grid cells transition \
+								 together, and must all be the same size, so spans, layouts and collages are \
+								 unsupported. Ignored with software rendering.",
+							)
+							.long("grid-mirror")
+							.requires(GRID_STR)
+							.conflicts_with_all(&[SPAN_STR, COLLAGE_STR]),
+					)
+					.arg(
+						ClapArg::with_name(CELL_STR)
+							.help("Image always shown on a grid cell")
+							.long_help(
+								"Always shows an image on the grid cell at a slot, as `{x},{y}={path}`, with `0,0` \
+								 being the bottom-left slot, such as `0,0=~/logo.png`, while the other cells show the \
+								 slideshow. May be given multiple times. Ignored with software rendering.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.number_of_values(1)
+							.long("cell")
+							.conflicts_with(GRID_MIRROR_STR),
+					)
+					.arg(
+						ClapArg::with_name(SCALING_STR)
+							.help("Scaling")
+							.long_help(
+								"How to scale images to the window. `fill` crops the image and scrolls through it, \
+								 `fit` shows the whole image, `contain-blur` shows the whole image over a blurred \
+								 copy of itself, `stretch` stretches the image to the window, `center` centers the \
+								 image without scaling it and `tile` repeats the image without scaling it.",
+							)
+							.takes_value(true)
+							.long("scaling")
+							.alias("fit")
+							.possible_values(&["fill", "fit", "contain-blur", "stretch", "center", "tile"])
+							.default_value("fill"),
+					)
+					.arg(
+						ClapArg::with_name(ASPECT_ROUTING_STR)
+							.help("Show images on panels of the same shape")
+							.long_help(
+								"Shows portrait images on portrait panels and landscape images on landscape ones, \
+								 such as on a grid, or on several windows, so less of each image is cropped away. \
+								 Images are held back until a panel of their shape needs one, unless too many are.",
+							)
+							.long("aspect-routing"),
+					)
+					.arg(
+						ClapArg::with_name(KALEIDOSCOPE_STR)
+							.help("Mirror images across the center")
+							.long_help(
+								"Mirrors each image across the center, for an abstract look while it scrolls. \
+								 `horizontal` mirrors the left half onto the right, `vertical` the bottom half onto \
+								 the top, and `both` the bottom-left quarter onto the others. Ignored with software \
+								 rendering.",
+							)
+							.takes_value(true)
+							.long("kaleidoscope")
+							.possible_values(&["horizontal", "vertical", "both"]),
+					)
+					.arg(
+						ClapArg::with_name(DOUBLE_EXPOSURE_STR)
+							.help("Blend a second image over each image")
+							.long_help(
+								"Continuously blends a second image over each image, each changing images at their \
+								 own time, with a slowly changing mix factor. `multiply` darkens, `screen` lightens \
+								 and `overlay` increases contrast. Ignored with software rendering.",
+							)
+							.takes_value(true)
+							.long("double-exposure")
+							.possible_values(&["multiply", "screen", "overlay"]),
+					)
+					.arg(
+						ClapArg::with_name(DOUBLE_EXPOSURE_PERIOD_STR)
+							.help("Period of the double exposure mix")
+							.long_help(
+								"Duration the mix factor of the second image takes to go from faint to strong and \
+								 back, such as `2m`. Only used with `--double-exposure`.",
+							)
+							.takes_value(true)
+							.long("double-exposure-period")
+							.default_value("1m"),
+					)
+					.arg(
+						ClapArg::with_name(RESIZE_FILTER_STR)
+							.help("Resize filter")
+							.long_help(
+								"Filter used when resizing images, from fastest to highest quality: `nearest`, \
+								 `triangle`, `catmull-rom` and `lanczos3`.",
+							)
+							.takes_value(true)
+							.long("resize-filter")
+							.possible_values(&["nearest", "triangle", "catmull-rom", "lanczos3"])
+							.default_value("lanczos3"),
+					)
+					.arg(
+						ClapArg::with_name(GPU_RESIZE_STR)
+							.help("Resize images on the gpu")
+							.long_help(
+								"Uploads images at their full resolution and lets the gpu scale them when sampling, \
+								 instead of resizing them beforehand. Uses less cpu time when images change, but more \
+								 video memory. Videos are still resized by `ffmpeg`.",
+							)
+							.long("gpu-resize"),
+					)
+					.arg(
+						ClapArg::with_name(RGB_STR)
+							.help("Keep images as rgb, without alpha")
+							.long_help(
+								"Drops the alpha channel of images when loading them, which is never shown anyway, \
+								 using 25% less memory, video memory and upload bandwidth. Uploads may be slower if \
+								 the width of the images isn't a multiple of 4.",
+							)
+							.long("rgb"),
+					)
+					.arg(
+						ClapArg::with_name(COMPRESS_TEXTURES_STR)
+							.help("Compress textures on the gpu")
+							.long_help(
+								"Has the gpu compress each image's textures when uploading them, using 4 (bc7) to 8 \
+								 (bc1) times less video memory, at the cost of some quality. Compressed textures have \
+								 no mipmaps, so downscaling with `--gpu-resize` or the `contain-blur` scaling look \
+								 worse. Ignored if the gpu doesn't support the format.",
+							)
+							.takes_value(true)
+							.long("compress-textures")
+							.possible_values(&["bc1", "bc7"]),
+					)
+					.arg(
+						ClapArg::with_name(SOFTWARE_RENDERING_STR)
+							.help("Draw frames on the cpu")
+							.long_help(
+								"Draws each frame on the cpu and copies it to the window, instead of using open-gl. \
+								 Used automatically if open-gl is unavailable, such as in virtual machines. Only \
+								 scrolling and fading are supported, effects, animations and most `ctl` commands are \
+								 ignored.",
+							)
+							.long("software-rendering"),
+					)
+					.arg(
+						ClapArg::with_name(BG_COLOR_STR)
+							.help("Background color")
+							.long_help(
+								"Background color, as `#rrggbb`, shown in letterboxed areas and between grid cells.",
+							)
+							.takes_value(true)
+							.long("bg-color")
+							.default_value("#000000"),
+					)
+					.arg(
+						ClapArg::with_name(DOMINANT_BG_STR)
+							.help("Use each image's dominant color as it's background")
+							.long_help(
+								"Uses each image's dominant color, instead of the background color, for it's \
+								 letterboxed areas.",
+							)
+							.long("dominant-bg"),
+					)
+					.arg(
+						ClapArg::with_name(INTERPOLATION_SAMPLES_STR)
+							.help("Sub-frame samples for scrolling")
+							.long_help(
+								"Number of samples taken across each frame's scroll movement, which are then blended \
+								 together. Smooths out slow scrolls at low framerates. `1` disables interpolation.",
+							)
+							.takes_value(true)
+							.long("interpolation-samples")
+							.default_value("1"),
+					)
+					.arg(
+						ClapArg::with_name(BRIGHTNESS_STR)
+							.help("Brightness multiplier")
+							.long_help(
+								"Multiplier applied to the color of every image. Values below 1.0 dim the wallpaper.",
+							)
+							.takes_value(true)
+							.long("brightness")
+							.default_value("1.0"),
+					)
+					.arg(
+						ClapArg::with_name(CONTRAST_STR)
+							.help("Contrast multiplier")
+							.long_help("Multiplier applied to the distance of every color from the mid-gray.")
+							.takes_value(true)
+							.long("contrast")
+							.default_value("1.0"),
+					)
+					.arg(
+						ClapArg::with_name(GAMMA_STR)
+							.help("Gamma")
+							.long_help(
+								"Gamma correction applied to every image. Values above 1.0 brighten the mid-tones.",
+							)
+							.takes_value(true)
+							.long("gamma")
+							.default_value("1.0"),
+					)
+					.arg(
+						ClapArg::with_name(SATURATION_STR)
+							.help("Saturation multiplier")
+							.long_help("Multiplier applied to the saturation of every image. 0.0 results in grayscale.")
+							.takes_value(true)
+							.long("saturation")
+							.default_value("1.0"),
+					)
+					.arg(
+						ClapArg::with_name(NIGHT_LIGHT_STR)
+							.help("Night light schedule")
+							.long_help(
+								"Dims and warms the wallpaper during the night, as `{start}-{end}` in local time, \
+								 such as `20:00-07:00`.",
+							)
+							.takes_value(true)
+							.long("night-light"),
+					)
+					.arg(
+						ClapArg::with_name(NIGHT_BRIGHTNESS_STR)
+							.help("Night light brightness")
+							.long_help("Brightness multiplier applied during the night.")
+							.takes_value(true)
+							.long("night-brightness")
+							.default_value("0.7"),
+					)
+					.arg(
+						ClapArg::with_name(NIGHT_TEMPERATURE_STR)
+							.help("Night light color temperature (in kelvin)")
+							.long_help("Color temperature, in kelvin, to tint the wallpaper with during the night.")
+							.takes_value(true)
+							.long("night-temperature")
+							.default_value("3400"),
+					)
+					.arg(
+						ClapArg::with_name(NIGHT_TRANSITION_STR)
+							.help("Night light transition")
+							.long_help(
+								"Duration of the transition into and out of the night. In seconds, or with a `ms`, \
+								 `s`, `m` or `h` suffix.",
+							)
+							.takes_value(true)
+							.long("night-transition")
+							.default_value("3600"),
+					)
+					.arg(
+						ClapArg::with_name(OVERLAY_STR)
+							.help("Overlay widget")
+							.long_help(
+								"Draws a widget over the wallpaper, as `<widget>[=<scale>]`, with `clock` showing the \
+								 time on the bottom-right, `caption` the first image's file name on the bottom-left \
+								 and `hud` if it's paused, or else the tempo, if following one, on the top-left. May \
+								 be given multiple times. Widgets are scaled by the output's scale factor, from it's \
+								 physical size, unless given a scale, such as `clock=2`. Ignored with software \
+								 rendering.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.number_of_values(1)
+							.long("overlay"),
+					)
+					.arg(
+						ClapArg::with_name(OVERLAY_SCALE_STR)
+							.help("Overlay scale")
+							.long_help(
+								"Scale of all overlay widgets without their own scale, instead of the output's scale \
+								 factor.",
+							)
+							.takes_value(true)
+							.long("overlay-scale"),
+					)
+					.arg(
+						ClapArg::with_name(BPM_STR)
+							.help("Beats per minute")
+							.long_help(
+								"Synchronizes image changes to a tempo, in beats per minute, from 1 to 1000. The \
+								 tempo may be adjusted at runtime with the `tap` ipc command.",
+							)
+							.takes_value(true)
+							.long("bpm"),
+					)
+					.arg(
+						ClapArg::with_name(BEATS_PER_IMAGE_STR)
+							.help("Beats per image")
+							.long_help("Number of beats each image is displayed for, when using `--bpm`.")
+							.takes_value(true)
+							.long("beats-per-image")
+							.default_value("4"),
+					)
+					.arg(
+						ClapArg::with_name(CLOCK_SYNC_STR)
+							.help("Synchronize to the wall clock")
+							.long_help(
+								"Synchronizes the slideshow to the wall clock, changing images at fixed times and \
+								 choosing each image from the time and this seed, so all instances with the same \
+								 seed, images and duration show the same image at the same time. Skipping or pausing \
+								 desynchronizes the slideshow, until restarted.",
+							)
+							.takes_value(true)
+							.long("clock-sync")
+							.conflicts_with_all(&[GRID_STR, LAYOUT_FILE_STR, BPM_STR]),
+					)
+					.arg(
+						ClapArg::with_name(SYNC_LEAD_STR)
+							.help("Lead other instances")
+							.long_help(
+								"Listens for followers on this address, such as `0.0.0.0:7415`, and sends them the \
+								 path of each image shown in the first panel, relative to the images directory.",
+							)
+							.takes_value(true)
+							.long("sync-lead"),
+					)
+					.arg(
+						ClapArg::with_name(SYNC_FOLLOW_STR)
+							.help("Follow another instance")
+							.long_help(
+								"Follows the leader at this address, such as `192.168.1.2:7415`, showing the same \
+								 images as it, from the images directory, whenever it does. The images directory must \
+								 have the same images as the leader's.",
+							)
+							.takes_value(true)
+							.long("sync-follow")
+							.conflicts_with_all(&[
+								SYNC_LEAD_STR,
+								IMAGES_FROM_STR,
+								URL_STR,
+								IMAGES_COMMAND_STR,
+								GRID_STR,
+								BPM_STR,
+								CLOCK_SYNC_STR,
+							]),
+					)
+					.arg(
+						ClapArg::with_name(CYCLES_STR)
+							.help("Number of cycles")
+							.long_help(
+								"Exits after showing this many passes through all images, such as `--cycles 1` to \
+								 show each image once. Passes are chosen by the selection strategy.",
+							)
+							.takes_value(true)
+							.long("cycles")
+							.conflicts_with_all(&[IMAGES_COMMAND_STR, SYNC_FOLLOW_STR]),
+					)
+					.arg(
+						ClapArg::with_name(EXIT_AFTER_STR)
+							.help("Exit after")
+							.long_help(
+								"Exits after running for this long. In seconds, or with a `ms`, `s`, `m` or `h` \
+								 suffix.",
+							)
+							.takes_value(true)
+							.long("exit-after"),
+					)
+					.arg(
+						ClapArg::with_name(IPC_SOCKET_STR)
+							.help("Ipc socket path")
+							.long_help(
+								"Path of the unix socket to listen for commands on, such as those sent by `zss ctl`. \
+								 Defaults to `$XDG_RUNTIME_DIR/zss.sock`",
+							)
+							.takes_value(true)
+							.long("ipc-socket"),
+					)
+					.arg(
+						ClapArg::with_name(CURRENT_FILE_STR)
+							.help("Current images file")
+							.long_help(
+								"Writes the path of the image shown in each panel to this file, one per line, \
+								 whenever they change.",
+							)
+							.takes_value(true)
+							.long("current-file"),
+					)
+					.arg(
+						ClapArg::with_name(ON_CHANGE_STR)
+							.help("Command to run on each new image")
+							.long_help(
+								"Runs this command with `sh`, without waiting for it, whenever a new image is shown, \
+								 with `{path}` replaced by the image's path and `{panel}` by the index of the panel \
+								 it's shown in, such as `--on-change 'wal -n -i {path}'`.",
+							)
+							.takes_value(true)
+							.long("on-change"),
+					)
+					.arg(
+						ClapArg::with_name(PALETTE_OUT_STR)
+							.help("Palette file")
+							.long_help(
+								"Writes a palette of the dominant colors of the image shown in the first panel to \
+								 this file, whenever it changes. By default, it's written as Xresources, with \
+								 `*.color0` through `*.color7`, `*.background` and `*.foreground`.",
+							)
+							.takes_value(true)
+							.long("palette-out"),
+					)
+					.arg(
+						ClapArg::with_name(PALETTE_TEMPLATE_STR)
+							.help("Palette file template")
+							.long_help(
+								"Writes the palette file using this template, with `{color0}` through `{color7}`, \
+								 `{background}` and `{foreground}` replaced by the colors, as `#rrggbb`, ordered from \
+								 darkest to lightest, and `{path}` by the image's path.",
+							)
+							.takes_value(true)
+							.requires(PALETTE_OUT_STR)
+							.long("palette-template"),
+					)
+					.arg(
+						ClapArg::with_name(PREV_IMAGES_STR)
+							.help("Previous images kept")
+							.long_help(
+								"Number of previously shown images kept, for each panel, to go back to with the \
+								 `prev` ipc command. They're kept uploaded, so each uses as much video memory as a \
+								 shown image.",
+							)
+							.takes_value(true)
+							.long("prev-images")
+							.default_value("3"),
+					)
+					.arg(
+						ClapArg::with_name(START_HELD_STR)
+							.help("Start held")
+							.long_help(
+								"Starts with the first image held, as if paused, until released with the `resume` or \
+								 `toggle-pause` ipc commands. Images are still loaded in the background.",
+							)
+							.long("start-held"),
+					)
+					.arg(
+						ClapArg::with_name(RECORD_STR)
+							.help("Record file")
+							.long_help(
+								"Records the session to this file, along with it's arguments, so it may be replayed \
+								 with `zss replay`.",
+							)
+							.takes_value(true)
+							.long("record"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(LAYOUT_STR)
+					.about("Grid layout utilities")
+					.setting(AppSettings::SubcommandRequiredElseHelp)
+					.subcommand(
+						SubCommand::with_name(PREVIEW_STR)
+							.about("Renders a grid layout with numbered placeholder cells to an image")
+							.arg(
+								ClapArg::with_name(GRID_STR)
+									.help("Grid")
+									.long_help("Grid to preview, as `{width}x{height}`")
+									.takes_value(true)
+									.required(true)
+									.long("grid"),
+							)
+							.arg(
+								ClapArg::with_name(SIZE_STR)
+									.help("Output size")
+									.long_help("Size of the output image, as `{width}x{height}`")
+									.takes_value(true)
+									.long("size")
+									.default_value("1920x1080"),
+							)
+							.arg(
+								ClapArg::with_name(OUT_STR)
+									.help("Output path")
+									.long_help(
+										"Path to write the preview image to. The format is guessed from the extension.",
+									)
+									.takes_value(true)
+									.required(true)
+									.long("out")
+									.short("o"),
+							),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(VERIFY_STR)
+					.about("Renders deterministic frames and compares them against a reference directory")
+					.arg(
+						ClapArg::with_name(IMAGES_DIR_STR)
+							.help("Images Directory")
+							.long_help("Path to directory with images. The first 2 images, by path, are rendered.")
+							.takes_value(true)
+							.required(true)
+							.long("images-dir")
+							.short("i"),
+					)
+					.arg(
+						ClapArg::with_name(CONFIG_STR)
+							.help("Config file")
+							.long_help(
+								"Path to a toml config file, whose `[draw]` section overrides how the frames are \
+								 drawn, such as their fade or scaling.",
+							)
+							.takes_value(true)
+							.long("config")
+							.short("c"),
+					)
+					.arg(
+						ClapArg::with_name(FRAMES_STR)
+							.help("Number of frames")
+							.long_help("Number of frames to render, evenly spread throughout the display of an image.")
+							.takes_value(true)
+							.long("frames")
+							.default_value("10"),
+					)
+					.arg(
+						ClapArg::with_name(SIZE_STR)
+							.help("Frame size")
+							.long_help("Size of each frame, as `{width}x{height}`")
+							.takes_value(true)
+							.long("size")
+							.default_value("1920x1080"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_STR)
+							.help("Fade percentage (0.5 .. 1.0)")
+							.long_help(
+								"Percentage, from 0.5 to 1.0, of when to start fading the image during it's display.",
+							)
+							.takes_value(true)
+							.long("fade")
+							.short("f")
+							.default_value("0.8"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_SPACE_STR)
+							.help("Fade color space")
+							.long_help("Color space to fade between images in. Either `linear` or `srgb`.")
+							.takes_value(true)
+							.long("fade-space")
+							.possible_values(&["linear", "srgb"])
+							.default_value("linear"),
+					)
+					.arg(
+						ClapArg::with_name(COMPARE_STR)
+							.help("Reference directory")
+							.long_help("Directory with reference frames to compare the rendered frames against.")
+							.takes_value(true)
+							.long("compare")
+							.required_unless(OUT_STR),
+					)
+					.arg(
+						ClapArg::with_name(OUT_STR)
+							.help("Output directory")
+							.long_help("Directory to write the rendered frames to, to be used as a future reference.")
+							.takes_value(true)
+							.long("out")
+							.short("o"),
+					)
+					.arg(
+						ClapArg::with_name(THRESHOLD_STR)
+							.help("Difference threshold")
+							.long_help("Maximum mean perceptual difference (CIE76 delta E) allowed for each frame.")
+							.takes_value(true)
+							.long("threshold")
+							.default_value("1.0"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(PREVIEW_STR)
+					.about("Renders a single frame of the slideshow to an image, without a window")
+					.arg(
+						ClapArg::with_name(IMAGE_STR)
+							.help("Image")
+							.long_help("Path of the image to show")
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(NEXT_IMAGE_STR)
+							.help("Next image")
+							.long_help("Path of the image to fade into. Defaults to the image itself.")
+							.takes_value(true)
+							.long("next-image"),
+					)
+					.arg(
+						ClapArg::with_name(AT_STR)
+							.help("Progress (0.0 .. 1.0)")
+							.long_help(
+								"Progress, from 0.0 to 1.0, through the display of the image to render the frame at.",
+							)
+							.takes_value(true)
+							.long("at")
+							.default_value("0.0"),
+					)
+					.arg(
+						ClapArg::with_name(SIZE_STR)
+							.help("Frame size")
+							.long_help("Size of the frame, as `{width}x{height}`")
+							.takes_value(true)
+							.long("size")
+							.default_value("1920x1080"),
+					)
+					.arg(
+						ClapArg::with_name(SCALING_STR)
+							.help("Scaling")
+							.long_help("How to scale the images to the frame. See the `run` `--scaling` argument.")
+							.takes_value(true)
+							.long("scaling")
+							.possible_values(&["fill", "fit", "contain-blur", "stretch", "center", "tile"])
+							.default_value("fill"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_STR)
+							.help("Fade percentage (0.5 .. 1.0)")
+							.long_help(
+								"Percentage, from 0.5 to 1.0, of when to start fading the image during it's display.",
+							)
+							.takes_value(true)
+							.long("fade")
+							.short("f")
+							.default_value("0.8"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_SPACE_STR)
+							.help("Fade color space")
+							.long_help("Color space to fade between images in. Either `linear` or `srgb`.")
+							.takes_value(true)
+							.long("fade-space")
+							.possible_values(&["linear", "srgb"])
+							.default_value("linear"),
+					)
+					.arg(
+						ClapArg::with_name(BG_COLOR_STR)
+							.help("Background color")
+							.long_help("Background color, as `#rrggbb`, shown in letterboxed areas.")
+							.takes_value(true)
+							.long("bg-color")
+							.default_value("#000000"),
+					)
+					.arg(
+						ClapArg::with_name(OUT_STR)
+							.help("Output path")
+							.long_help("Path to write the frame to. The format is guessed from the extension.")
+							.takes_value(true)
+							.required(true)
+							.long("out")
+							.short("o"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(RENDER_STR)
+					.about("Renders the slideshow to a video, without a window, using `ffmpeg`")
+					.arg(
+						ClapArg::with_name(IMAGES_DIR_STR)
+							.help("Images Directory")
+							.long_help("Path to directory with images. Images are shown in a random order.")
+							.takes_value(true)
+							.required(true)
+							.long("images-dir")
+							.short("i"),
+					)
+					.arg(
+						ClapArg::with_name(SECONDS_STR)
+							.help("Length of the video")
+							.long_help("Length of the video. In seconds, or with a `ms`, `s`, `m` or `h` suffix.")
+							.takes_value(true)
+							.long("seconds")
+							.default_value("60"),
+					)
+					.arg(
+						ClapArg::with_name(FPS_STR)
+							.help("Frames per second")
+							.long_help("Frames per second of the video")
+							.takes_value(true)
+							.long("fps")
+							.default_value("30"),
+					)
+					.arg(
+						ClapArg::with_name(DURATION_STR)
+							.help("Duration of each image")
+							.long_help(
+								"Duration each image will take up in the video, including during fading. In seconds, \
+								 or with a `ms`, `s`, `m` or `h` suffix.",
+							)
+							.takes_value(true)
+							.long("duration")
+							.short("d")
+							.default_value("30"),
+					)
+					.arg(
+						ClapArg::with_name(SIZE_STR)
+							.help("Frame size")
+							.long_help("Size of the video, as `{width}x{height}`. Both must be even.")
+							.takes_value(true)
+							.long("size")
+							.default_value("1920x1080"),
+					)
+					.arg(
+						ClapArg::with_name(SCALING_STR)
+							.help("Scaling")
+							.long_help("How to scale the images to the video. See the `run` `--scaling` argument.")
+							.takes_value(true)
+							.long("scaling")
+							.possible_values(&["fill", "fit", "contain-blur", "stretch", "center", "tile"])
+							.default_value("fill"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_STR)
+							.help("Fade percentage (0.5 .. 1.0)")
+							.long_help(
+								"Percentage, from 0.5 to 1.0, of when to start fading the image during it's display.",
+							)
+							.takes_value(true)
+							.long("fade")
+							.short("f")
+							.default_value("0.8"),
+					)
+					.arg(
+						ClapArg::with_name(FADE_SPACE_STR)
+							.help("Fade color space")
+							.long_help("Color space to fade between images in. Either `linear` or `srgb`.")
+							.takes_value(true)
+							.long("fade-space")
+							.possible_values(&["linear", "srgb"])
+							.default_value("linear"),
+					)
+					.arg(
+						ClapArg::with_name(BG_COLOR_STR)
+							.help("Background color")
+							.long_help("Background color, as `#rrggbb`, shown in letterboxed areas.")
+							.takes_value(true)
+							.long("bg-color")
+							.default_value("#000000"),
+					)
+					.arg(
+						ClapArg::with_name(OUT_STR)
+							.help("Output path")
+							.long_help(
+								"Path to write the video to. The format is guessed by `ffmpeg` from the extension.",
+							)
+							.takes_value(true)
+							.required(true)
+							.long("out")
+							.short("o"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(STATS_STR)
+					.about("Prints statistics about an images directory")
+					.arg(
+						ClapArg::with_name(IMAGES_DIR_STR)
+							.help("Images Directory")
+							.long_help("Path to directory with images. Ignored files aren't counted.")
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(DECODE_STR)
+							.help("Time image loading")
+							.long_help("Loads every image, as when displaying them, to report the average load time.")
+							.long("decode"),
+					)
+					.arg(
+						ClapArg::with_name(SIZE_STR)
+							.help("Window size")
+							.long_help("Window size to load images for with `--decode`, as `{width}x{height}`")
+							.takes_value(true)
+							.long("size")
+							.default_value("1920x1080"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(CHECK_STR)
+					.about("Checks that all images in a directory can be loaded, without a window")
+					.arg(
+						ClapArg::with_name(IMAGES_DIR_STR)
+							.help("Images Directory")
+							.long_help("Path to directory with images. Ignored files aren't checked.")
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(CONFIG_STR)
+							.help("Config file")
+							.long_help(
+								"Path to a toml config file, whose `[handlers]` section selects how each file is \
+								 loaded, as when displaying them. Skipped files aren't checked.",
+							)
+							.takes_value(true)
+							.long("config")
+							.short("c"),
+					)
+					.arg(
+						ClapArg::with_name(SIZE_STR)
+							.help("Window size")
+							.long_help(
+								"Window size to load images for, as `{width}x{height}`. Used to estimate the memory \
+								 used by the images and to find images with an unusual aspect ratio.",
+							)
+							.takes_value(true)
+							.long("size")
+							.default_value("1920x1080"),
+					)
+					.arg(
+						ClapArg::with_name(SCALING_STR)
+							.help("Scaling")
+							.long_help("How to scale the images to the window. See the `run` `--scaling` argument.")
+							.takes_value(true)
+							.long("scaling")
+							.possible_values(&["fill", "fit", "contain-blur", "stretch", "center", "tile"])
+							.default_value("fill"),
+					)
+					.arg(
+						ClapArg::with_name(MAX_ASPECT_RATIO_STR)
+							.help("Maximum aspect ratio difference")
+							.long_help(
+								"Images whose aspect ratio is more than this many times wider or taller than the \
+								 window's are reported, as most of them will be cropped away or letterboxed.",
+							)
+							.takes_value(true)
+							.long("max-aspect-ratio")
+							.default_value("2.0"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(BENCH_STR)
+					.about("Measures how long decoding, resizing and uploading each image in a directory takes")
+					.arg(
+						ClapArg::with_name(IMAGES_DIR_STR)
+							.help("Images Directory")
+							.long_help("Path to directory with images. Ignored files aren't measured.")
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(SIZE_STR)
+							.help("Window size")
+							.long_help("Window size to load images for, as `{width}x{height}`")
+							.takes_value(true)
+							.long("size")
+							.default_value("1920x1080"),
+					)
+					.arg(
+						ClapArg::with_name(SCALING_STR)
+							.help("Scaling")
+							.long_help("How to scale the images to the window. See the `run` `--scaling` argument.")
+							.takes_value(true)
+							.long("scaling")
+							.possible_values(&["fill", "fit", "contain-blur", "stretch", "center", "tile"])
+							.default_value("fill"),
+					)
+					.arg(
+						ClapArg::with_name(RESIZE_FILTER_STR)
+							.help("Resize filter")
+							.long_help("Filter used when resizing images. See the `run` `--resize-filter` argument.")
+							.takes_value(true)
+							.long("resize-filter")
+							.possible_values(&["nearest", "triangle", "catmull-rom", "lanczos3"])
+							.default_value("lanczos3"),
+					)
+					.arg(
+						ClapArg::with_name(LIMIT_STR)
+							.help("Maximum images")
+							.long_help("Maximum number of images to measure, chosen at random, for large directories.")
+							.takes_value(true)
+							.long("limit"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(REPLAY_STR)
+					.about("Replays a session recorded with `--record`")
+					.arg(
+						ClapArg::with_name(REPLAY_FILE_STR)
+							.help("Replay file")
+							.long_help("Path to a file recorded with `--record`")
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(WINDOW_ID_STR)
+							.help("The window id")
+							.long_help("An `X` window id to replay the session on. Typically obtained from `xwinwrap`")
+							.takes_value(true)
+							.required(true)
+							.long("window-id")
+							.short("w"),
+					),
 			)
-			.get_matches();
+			.subcommand(
+				SubCommand::with_name(CTL_STR)
+					.about("Sends a command to a running wallpaper")
+					.arg(
+						ClapArg::with_name(COMMAND_STR)
+							.help("Command")
+							.long_help(
+								"Command to send. One of `next`, `prev`, `pause`, `resume`, `toggle-pause`, `hold`, \
+								 `set-duration <duration>`, `reload`, `tap`, `ban [<panel>]`, `fav [<panel>]`, \
+								 `screenshot <path>`, `quit` or `current`.",
+							)
+							.takes_value(true)
+							.multiple(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(IPC_SOCKET_STR)
+							.help("Ipc socket path")
+							.long_help(
+								"Path of the unix socket the wallpaper is listening on. Defaults to \
+								 `$XDG_RUNTIME_DIR/zss.sock`",
+							)
+							.takes_value(true)
+							.long("ipc-socket"),
+					),
+			)
+			.subcommand(
+				SubCommand::with_name(SET_STR)
+					.about("Shows a single image on a window, without a slideshow, and exits")
+					.arg(
+						ClapArg::with_name(WINDOW_ID_STR)
+							.help("The window id")
+							.long_help("An `X` window id. Typically obtained from `xwinwrap`")
+							.takes_value(true)
+							.required(true)
+							.index(1),
+					)
+					.arg(
+						ClapArg::with_name(IMAGE_STR)
+							.help("Image")
+							.long_help("Path of the image to show")
+							.takes_value(true)
+							.required(true)
+							.index(2),
+					)
+					.arg(
+						ClapArg::with_name(SCALING_STR)
+							.help("Scaling")
+							.long_help("How to scale the image to the window. See the `run` `--scaling` argument.")
+							.takes_value(true)
+							.long("scaling")
+							.possible_values(&["fill", "fit", "contain-blur", "stretch", "center", "tile"])
+							.default_value("fill"),
+					)
+					.arg(
+						ClapArg::with_name(BG_COLOR_STR)
+							.help("Background color")
+							.long_help("Background color, as `#rrggbb`, shown in letterboxed areas.")
+							.takes_value(true)
+							.long("bg-color")
+							.default_value("#000000"),
+					),
+			)
+			.get_matches_from(args);
+
+		// Note: Global arguments given after the subcommand are only present in it's matches
+		let occurrences_of = |name: &str| {
+			let subcommand_occurrences = matches
+				.subcommand()
+				.1
+				.map_or(0, |subcommand_matches| subcommand_matches.occurrences_of(name));
+			matches.occurrences_of(name).max(subcommand_occurrences)
+		};
+		let level = match (occurrences_of(VERBOSE_STR), occurrences_of(QUIET_STR)) {
+			(0, 0) => log::LevelFilter::Info,
+			(1, 0) => log::LevelFilter::Debug,
+			(_, 0) => log::LevelFilter::Trace,
+			(0, 1) => log::LevelFilter::Warn,
+			(0, _) => log::LevelFilter::Error,
+			_ => anyhow::bail!("`--verbose` and `--quiet` may not be used together"),
+		};
+		let (level, module_levels) = match std::env::var("ZSS_LOG") {
+			Ok(filter) => self::parse_log_filter(&filter, level).context("Unable to parse `ZSS_LOG`")?,
+			Err(_) => (level, vec![]),
+		};
+		let value_of = |name: &str| {
+			matches
+				.subcommand()
+				.1
+				.and_then(|subcommand_matches| subcommand_matches.value_of_os(name))
+				.or_else(|| matches.value_of_os(name))
+		};
+		let file = value_of(LOG_FILE_STR).map(PathBuf::from);
+		let max_file_size = match value_of(LOG_MAX_SIZE_STR) {
+			Some(max_file_size) => max_file_size
+				.to_str()
+				.context("Maximum log file size wasn't utf-8")
+				.and_then(self::parse_file_size)
+				.context("Unable to parse maximum log file size")?,
+			None => 10 << 20,
+		};
+		let format = match value_of(LOG_FORMAT_STR).and_then(|format| format.to_str()) {
+			Some("json") => LogFormat::Json,
+			Some("text") | None => LogFormat::Text,
+			Some(format) => unreachable!("Unknown log format {:?}", format),
+		};
+		let log = LogArgs {
+			level,
+			module_levels,
+			file,
+			max_file_size,
+			format,
+		};
+
+		// If we got a replay, parse it's arguments and return
+		if let Some(replay_matches) = matches.subcommand_matches(REPLAY_STR) {
+			let path = PathBuf::from(
+				replay_matches
+					.value_of_os(REPLAY_FILE_STR)
+					.expect("Required argument was missing"),
+			);
+
+			let window_id = replay_matches
+				.value_of(WINDOW_ID_STR)
+				.expect("Required argument was missing");
+			let window_id = self::parse_window_id(window_id).context("Unable to parse window id")?;
+
+			return Ok((Self::Replay(ReplayArgs { path, window_id }), log));
+		}
+
+		// If we got a set, parse it's arguments and return
+		if let Some(set_matches) = matches.subcommand_matches(SET_STR) {
+			let window_id = set_matches
+				.value_of(WINDOW_ID_STR)
+				.expect("Required argument was missing");
+			let window_id = self::parse_window_id(window_id).context("Unable to parse window id")?;
+
+			let image = PathBuf::from(
+				set_matches
+					.value_of_os(IMAGE_STR)
+					.expect("Required argument was missing"),
+			);
+
+			let scaling = match set_matches
+				.value_of(SCALING_STR)
+				.expect("Argument with default value was missing")
+			{
+				"fill" => Scaling::Fill,
+				"fit" => Scaling::Fit,
+				"contain-blur" => Scaling::ContainBlur,
+				"stretch" => Scaling::Stretch,
+				"center" => Scaling::Center,
+				"tile" => Scaling::Tile,
+				scaling => unreachable!("Unknown scaling {:?}", scaling),
+			};
+
+			let bg_color = set_matches
+				.value_of(BG_COLOR_STR)
+				.expect("Argument with default value was missing");
+			let bg_color = self::parse_color(bg_color).context("Unable to parse background color")?;
+
+			return Ok((
+				Self::Set(SetArgs {
+					window_id,
+					image,
+					scaling,
+					bg_color,
+				}),
+				log,
+			));
+		}
+
+		// If we got a ctl, parse it's arguments and return
+		if let Some(ctl_matches) = matches.subcommand_matches(CTL_STR) {
+			let ipc_socket = ctl_matches
+				.value_of_os(IPC_SOCKET_STR)
+				.map_or_else(self::default_ipc_socket, PathBuf::from);
+
+			let command = ctl_matches
+				.values_of(COMMAND_STR)
+				.expect("Required argument was missing")
+				.collect::<Vec<_>>()
+				.join(" ");
+
+			return Ok((Self::Ctl(CtlArgs { ipc_socket, command }), log));
+		}
+
+		// If we got a verify, parse it's arguments and return
+		if let Some(verify_matches) = matches.subcommand_matches(VERIFY_STR) {
+			let images_dir = PathBuf::from(
+				verify_matches
+					.value_of_os(IMAGES_DIR_STR)
+					.expect("Required argument was missing"),
+			);
+			let config_path = verify_matches.value_of_os(CONFIG_STR).map(PathBuf::from);
+
+			let frames = verify_matches
+				.value_of(FRAMES_STR)
+				.expect("Argument with default value was missing");
+			let frames = self::parse_value(frames, "a positive integer").context("Unable to parse frames")?;
+			anyhow::ensure!(frames != 0, "Must render at least 1 frame");
+
+			let size = verify_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+
+			let fade = verify_matches
+				.value_of(FADE_STR)
+				.expect("Argument with default value was missing");
+			let fade = self::parse_value(fade, "a number from 0.5 to 1.0").context("Unable to parse fade")?;
+			anyhow::ensure!((0.5..=1.0).contains(&fade), "Fade must be within 0.5 .. 1.0");
+
+			let fade_space = self::parse_fade_space(
+				verify_matches
+					.value_of(FADE_SPACE_STR)
+					.expect("Argument with default value was missing"),
+			);
+
+			let compare = verify_matches.value_of_os(COMPARE_STR).map(PathBuf::from);
+			let out = verify_matches.value_of_os(OUT_STR).map(PathBuf::from);
+
+			let threshold = verify_matches
+				.value_of(THRESHOLD_STR)
+				.expect("Argument with default value was missing");
+			let threshold = self::parse_value(threshold, "a number").context("Unable to parse threshold")?;
+
+			return Ok((
+				Self::Verify(VerifyArgs {
+					images_dir,
+					config_path,
+					frames,
+					size: [width, height],
+					fade,
+					fade_space,
+					compare,
+					out,
+					threshold,
+				}),
+				log,
+			));
+		}
+
+		// If we got a preview, parse it's arguments and return
+		if let Some(preview_matches) = matches.subcommand_matches(PREVIEW_STR) {
+			let image = PathBuf::from(
+				preview_matches
+					.value_of_os(IMAGE_STR)
+					.expect("Required argument was missing"),
+			);
+			let next_image = preview_matches.value_of_os(NEXT_IMAGE_STR).map(PathBuf::from);
+
+			let at = preview_matches
+				.value_of(AT_STR)
+				.expect("Argument with default value was missing");
+			let at = self::parse_value(at, "a number from 0.0 to 1.0").context("Unable to parse progress")?;
+			anyhow::ensure!((0.0..=1.0).contains(&at), "Progress must be within 0.0 .. 1.0");
+
+			let size = preview_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+
+			let scaling = match preview_matches
+				.value_of(SCALING_STR)
+				.expect("Argument with default value was missing")
+			{
+				"fill" => Scaling::Fill,
+				"fit" => Scaling::Fit,
+				"contain-blur" => Scaling::ContainBlur,
+				"stretch" => Scaling::Stretch,
+				"center" => Scaling::Center,
+				"tile" => Scaling::Tile,
+				scaling => unreachable!("Unknown scaling {:?}", scaling),
+			};
+
+			let fade = preview_matches
+				.value_of(FADE_STR)
+				.expect("Argument with default value was missing");
+			let fade = self::parse_value(fade, "a number from 0.5 to 1.0").context("Unable to parse fade")?;
+			anyhow::ensure!((0.5..=1.0).contains(&fade), "Fade must be within 0.5 .. 1.0");
+
+			let fade_space = self::parse_fade_space(
+				preview_matches
+					.value_of(FADE_SPACE_STR)
+					.expect("Argument with default value was missing"),
+			);
+
+			let bg_color = preview_matches
+				.value_of(BG_COLOR_STR)
+				.expect("Argument with default value was missing");
+			let bg_color = self::parse_color(bg_color).context("Unable to parse background color")?;
+
+			let out = PathBuf::from(
+				preview_matches
+					.value_of_os(OUT_STR)
+					.expect("Required argument was missing"),
+			);
+
+			return Ok((
+				Self::Preview(PreviewArgs {
+					image,
+					next_image,
+					at,
+					size: [width, height],
+					scaling,
+					fade,
+					fade_space,
+					bg_color,
+					out,
+				}),
+				log,
+			));
+		}
+
+		// If we got a render, parse it's arguments and return
+		if let Some(render_matches) = matches.subcommand_matches(RENDER_STR) {
+			let images_dir = PathBuf::from(
+				render_matches
+					.value_of_os(IMAGES_DIR_STR)
+					.expect("Required argument was missing"),
+			);
+
+			let length = render_matches
+				.value_of(SECONDS_STR)
+				.expect("Argument with default value was missing");
+			let length = self::parse_duration(length).context("Unable to parse length")?;
+			anyhow::ensure!(length > Duration::ZERO, "Length must be positive");
+
+			let fps = render_matches
+				.value_of(FPS_STR)
+				.expect("Argument with default value was missing");
+			let fps = self::parse_value(fps, "a positive integer").context("Unable to parse fps")?;
+			anyhow::ensure!(fps != 0, "Fps must be positive");
+
+			let duration = render_matches
+				.value_of(DURATION_STR)
+				.expect("Argument with default value was missing");
+			let duration = self::parse_duration(duration).context("Unable to parse duration")?;
+			anyhow::ensure!(duration > Duration::ZERO, "Duration must be positive");
+
+			let size = render_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+			// Note: Most video encoders only support even sizes
+			anyhow::ensure!(width % 2 == 0 && height % 2 == 0, "Width and height must be even");
 
-		let window_id = matches.value_of(WINDOW_ID_STR).expect("Required argument was missing");
-		log::info!("Found window id {window_id}");
-		anyhow::ensure!(window_id.starts_with("0x"), "Window id didn't start with `0x`");
-		let window_id = u64::from_str_radix(&window_id[2..], 16).context("Unable to parse window id")?;
+			let scaling = match render_matches
+				.value_of(SCALING_STR)
+				.expect("Argument with default value was missing")
+			{
+				"fill" => Scaling::Fill,
+				"fit" => Scaling::Fit,
+				"contain-blur" => Scaling::ContainBlur,
+				"stretch" => Scaling::Stretch,
+				"center" => Scaling::Center,
+				"tile" => Scaling::Tile,
+				scaling => unreachable!("Unknown scaling {:?}", scaling),
+			};
+
+			let fade = render_matches
+				.value_of(FADE_STR)
+				.expect("Argument with default value was missing");
+			let fade = self::parse_value(fade, "a number from 0.5 to 1.0").context("Unable to parse fade")?;
+			anyhow::ensure!((0.5..=1.0).contains(&fade), "Fade must be within 0.5 .. 1.0");
+
+			let fade_space = self::parse_fade_space(
+				render_matches
+					.value_of(FADE_SPACE_STR)
+					.expect("Argument with default value was missing"),
+			);
+
+			let bg_color = render_matches
+				.value_of(BG_COLOR_STR)
+				.expect("Argument with default value was missing");
+			let bg_color = self::parse_color(bg_color).context("Unable to parse background color")?;
+
+			let out = PathBuf::from(
+				render_matches
+					.value_of_os(OUT_STR)
+					.expect("Required argument was missing"),
+			);
+
+			return Ok((
+				Self::Render(RenderArgs {
+					images_dir,
+					length,
+					fps,
+					duration,
+					size: [width, height],
+					scaling,
+					fade,
+					fade_space,
+					bg_color,
+					out,
+				}),
+				log,
+			));
+		}
+
+		// If we got stats, parse it's arguments and return
+		if let Some(stats_matches) = matches.subcommand_matches(STATS_STR) {
+			let images_dir = PathBuf::from(
+				stats_matches
+					.value_of_os(IMAGES_DIR_STR)
+					.expect("Required argument was missing"),
+			);
+
+			let decode = stats_matches.is_present(DECODE_STR);
+
+			let size = stats_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+
+			return Ok((
+				Self::Stats(StatsArgs {
+					images_dir,
+					decode,
+					size: [width, height],
+					scaling: Scaling::Fill,
+					resize_filter: ResizeFilter::Lanczos3,
+				}),
+				log,
+			));
+		}
+
+		// If we got a check, parse it's arguments and return
+		if let Some(check_matches) = matches.subcommand_matches(CHECK_STR) {
+			let images_dir = PathBuf::from(
+				check_matches
+					.value_of_os(IMAGES_DIR_STR)
+					.expect("Required argument was missing"),
+			);
+			let config_path = check_matches.value_of_os(CONFIG_STR).map(PathBuf::from);
+
+			let size = check_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+
+			let scaling = match check_matches
+				.value_of(SCALING_STR)
+				.expect("Argument with default value was missing")
+			{
+				"fill" => Scaling::Fill,
+				"fit" => Scaling::Fit,
+				"contain-blur" => Scaling::ContainBlur,
+				"stretch" => Scaling::Stretch,
+				"center" => Scaling::Center,
+				"tile" => Scaling::Tile,
+				scaling => unreachable!("Unknown scaling {:?}", scaling),
+			};
+
+			let max_aspect_ratio = check_matches
+				.value_of(MAX_ASPECT_RATIO_STR)
+				.expect("Argument with default value was missing");
+			let max_aspect_ratio = self::parse_value(max_aspect_ratio, "a number of at least 1.0")
+				.context("Unable to parse maximum aspect ratio")?;
+			anyhow::ensure!(max_aspect_ratio >= 1.0, "Maximum aspect ratio must be at least 1.0");
+
+			return Ok((
+				Self::Check(CheckArgs {
+					images_dir,
+					config_path,
+					size: [width, height],
+					scaling,
+					max_aspect_ratio,
+				}),
+				log,
+			));
+		}
+
+		// If we got a bench, parse it's arguments and return
+		if let Some(bench_matches) = matches.subcommand_matches(BENCH_STR) {
+			let images_dir = PathBuf::from(
+				bench_matches
+					.value_of_os(IMAGES_DIR_STR)
+					.expect("Required argument was missing"),
+			);
+
+			let size = bench_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+
+			let scaling = match bench_matches
+				.value_of(SCALING_STR)
+				.expect("Argument with default value was missing")
+			{
+				"fill" => Scaling::Fill,
+				"fit" => Scaling::Fit,
+				"contain-blur" => Scaling::ContainBlur,
+				"stretch" => Scaling::Stretch,
+				"center" => Scaling::Center,
+				"tile" => Scaling::Tile,
+				scaling => unreachable!("Unknown scaling {:?}", scaling),
+			};
+
+			let resize_filter = match bench_matches
+				.value_of(RESIZE_FILTER_STR)
+				.expect("Argument with default value was missing")
+			{
+				"nearest" => ResizeFilter::Nearest,
+				"triangle" => ResizeFilter::Triangle,
+				"catmull-rom" => ResizeFilter::CatmullRom,
+				"lanczos3" => ResizeFilter::Lanczos3,
+				resize_filter => unreachable!("Unknown resize filter {:?}", resize_filter),
+			};
+
+			let limit = bench_matches
+				.value_of(LIMIT_STR)
+				.map(|limit| self::parse_value(limit, "a positive integer"))
+				.transpose()
+				.context("Unable to parse limit")?;
+
+			return Ok((
+				Self::Bench(BenchArgs {
+					images_dir,
+					size: [width, height],
+					scaling,
+					resize_filter,
+					limit,
+				}),
+				log,
+			));
+		}
+
+		// If we got a layout preview, parse it's arguments and return
+		if let Some(preview_matches) = matches
+			.subcommand_matches(LAYOUT_STR)
+			.and_then(|matches| matches.subcommand_matches(PREVIEW_STR))
+		{
+			let grid = preview_matches
+				.value_of(GRID_STR)
+				.expect("Required argument was missing");
+			let (grid_width, grid_height) = self::parse_width_height(grid).context("Unable to parse grid")?;
+			anyhow::ensure!(
+				grid_width != 0 && grid_height != 0,
+				"Grid must have at least 1 cell, found {:?}",
+				grid
+			);
+
+			let size = preview_matches
+				.value_of(SIZE_STR)
+				.expect("Argument with default value was missing");
+			let (width, height) = self::parse_size(size).context("Unable to parse size")?;
+			anyhow::ensure!(
+				grid_width <= width as usize && grid_height <= height as usize,
+				"Grid {}x{} has more cells than the {}x{} preview has pixels, use a smaller `--grid`",
+				grid_width,
+				grid_height,
+				width,
+				height
+			);
+
+			let out = PathBuf::from(
+				preview_matches
+					.value_of_os(OUT_STR)
+					.expect("Required argument was missing"),
+			);
+
+			return Ok((
+				Self::LayoutPreview(LayoutPreviewArgs {
+					grid_width,
+					grid_height,
+					size: [width, height],
+					out,
+				}),
+				log,
+			));
+		}
+
+		// Else we must have gotten a run
+		let matches = matches.subcommand_matches(RUN_STR).expect("Subcommand was missing");
+
+		let output = matches.value_of(OUTPUT_STR).expect("Required argument was missing");
+		let outputs = std::iter::once(output)
+			.chain(matches.values_of(WINDOW_ID_STR).into_iter().flatten())
+			.map(|output| self::parse_output(output).with_context(|| format!("Unable to parse window id {output:?}")))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let config_path = matches.value_of_os(CONFIG_STR).map(PathBuf::from);
 
 		let duration = matches
 			.value_of(DURATION_STR)
 			.expect("Argument with default value was missing");
-		let duration = duration.parse().context("Unable to parse duration")?;
-		let duration = Duration::from_secs_f32(duration);
+		let duration = self::parse_duration(duration).context("Unable to parse duration")?;
+		anyhow::ensure!(duration > Duration::ZERO, "Duration must be positive");
 
-		let images_dir = PathBuf::from(
-			matches
-				.value_of_os(IMAGES_DIR_STR)
-				.expect("Required argument was missing"),
+		let duration_jitter = matches
+			.value_of(DURATION_JITTER_STR)
+			.expect("Argument with default value was missing");
+		let duration_jitter =
+			self::parse_value(duration_jitter, "a fraction").context("Unable to parse duration jitter")?;
+		anyhow::ensure!(
+			(0.0..1.0).contains(&duration_jitter),
+			"Duration jitter must be at least 0.0 and less than 1.0"
 		);
 
+		let images_from = matches.value_of_os(IMAGES_FROM_STR).map(PathBuf::from);
+		let urls = matches
+			.values_of(URL_STR)
+			.into_iter()
+			.flatten()
+			.map(|url| match remote::is_url(url) {
+				true => Ok(url.to_owned()),
+				false => Err(anyhow::anyhow!("Expected a `http` or `https` url, found {:?}", url)),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		let images_command = matches.value_of(IMAGES_COMMAND_STR).map(str::to_owned);
+		let images_dir = match matches.value_of_os(IMAGES_DIR_STR) {
+			Some(images_dir) => PathBuf::from(images_dir),
+			None if images_from.is_some() || !urls.is_empty() || images_command.is_some() => PathBuf::from("."),
+			None => unreachable!("Required argument was missing"),
+		};
+
 		let fade = matches
 			.value_of(FADE_STR)
 			.expect("Argument with default value was missing");
-		let fade = fade.parse().context("Unable to parse fade")?;
+		let fade = self::parse_value(fade, "a number from 0.5 to 1.0").context("Unable to parse fade")?;
 		anyhow::ensure!((0.5..=1.0).contains(&fade), "Fade must be within 0.5 .. 1.0");
 
+		// Note: A fade shorter than a frame would just cut to the next image, so it's likely a mistake
+		let fade_duration = duration.mul_f32(1.0 - fade);
+		anyhow::ensure!(
+			fade >= 1.0 || fade_duration >= Duration::from_secs_f32(1.0 / 60.0),
+			"A fade of {} with a duration of {:.2?} only fades for {:.2?}, which is less than a frame. Use a longer \
+			 `--duration`, a lower `--fade`, or `--fade 1.0` to not fade at all",
+			fade,
+			duration,
+			fade_duration
+		);
+
 		let image_backlog = matches
 			.value_of(IMAGE_BACKLOG_STR)
 			.expect("Argument with default value was missing");
-		let image_backlog = image_backlog.parse().context("Unable to parse image backlog")?;
+		let image_backlog =
+			self::parse_value(image_backlog, "a non-negative integer").context("Unable to parse image backlog")?;
+
+		let min_resolution = matches
+			.value_of(MIN_RESOLUTION_STR)
+			.map(|min_resolution| {
+				self::parse_width_height(min_resolution)
+					.map(|(width, height)| [width, height])
+					.context("Unable to parse minimum resolution")
+			})
+			.transpose()?;
+
+		let max_file_size = matches
+			.value_of(MAX_FILE_SIZE_STR)
+			.map(|max_file_size| self::parse_file_size(max_file_size).context("Unable to parse maximum file size"))
+			.transpose()?;
+
+		let max_backlog_memory = matches
+			.value_of(MAX_BACKLOG_MEMORY_STR)
+			.map(|max_backlog_memory| {
+				self::parse_file_size(max_backlog_memory).context("Unable to parse maximum backlog memory")
+			})
+			.transpose()?;
+
+		let decode_timeout = matches
+			.value_of(DECODE_TIMEOUT_STR)
+			.expect("Argument with default value was missing");
+		let decode_timeout = self::parse_duration(decode_timeout).context("Unable to parse decode timeout")?;
+		anyhow::ensure!(decode_timeout > Duration::ZERO, "Decode timeout must be positive");
+
+		let rescan_interval = matches
+			.value_of(RESCAN_INTERVAL_STR)
+			.map(|rescan_interval| self::parse_duration(rescan_interval).context("Unable to parse rescan interval"))
+			.transpose()?;
+		anyhow::ensure!(
+			rescan_interval.map_or(true, |rescan_interval| rescan_interval > Duration::ZERO),
+			"Rescan interval must be positive"
+		);
+
+		let no_watch = matches.is_present(NO_WATCH_STR);
+
+		let no_repeat_last = matches
+			.value_of(NO_REPEAT_LAST_STR)
+			.map(|no_repeat_last| {
+				self::parse_value(no_repeat_last, "a non-negative integer")
+					.context("Unable to parse number of recent images to not repeat")
+			})
+			.transpose()?;
+		let persist_history = matches.is_present(PERSIST_HISTORY_STR);
+
+		let weights = matches
+			.values_of(WEIGHT_STR)
+			.into_iter()
+			.flatten()
+			.map(|weight| self::parse_weight(weight).with_context(|| format!("Unable to parse weight {weight:?}")))
+			.collect::<Result<HashMap<_, _>, _>>()?;
 
-		let mode = match matches.value_of(GRID_STR) {
-			Some(grid) => {
-				let (width, height) = grid
-					.split_once('x')
-					.context("Grid must be of the format `{width}x{height}`")?;
-				let width = width.trim().parse().context("Unable to parse grid width")?;
-				let height = height.trim().parse().context("Unable to parse grid height")?;
+		let dedup = matches.value_of(DEDUP_STR).map(|dedup| match dedup {
+			"exact" => DedupMode::Exact,
+			"perceptual" => DedupMode::Perceptual,
+			dedup => unreachable!("Unknown dedup mode {:?}", dedup),
+		});
 
-				Mode::Grid { width, height }
+		let image_cache_size = matches
+			.value_of(IMAGE_CACHE_SIZE_STR)
+			.expect("Argument with default value was missing");
+		let image_cache_size = self::parse_file_size(image_cache_size).context("Unable to parse image cache size")?;
+
+		let quarantine_log = matches.value_of_os(QUARANTINE_LOG_STR).map(PathBuf::from);
+		let ban_list = matches
+			.value_of_os(BAN_LIST_STR)
+			.map(PathBuf::from)
+			.or_else(|| storage::state_dir().map(|state_dir| state_dir.join("banned.txt")));
+		let favorites_list = matches
+			.value_of_os(FAVORITES_LIST_STR)
+			.map(PathBuf::from)
+			.or_else(|| storage::state_dir().map(|state_dir| state_dir.join("favorites.txt")));
+		let only_favorites = matches.is_present(ONLY_FAVORITES_STR);
+
+		let late_policy = match matches
+			.value_of(LATE_POLICY_STR)
+			.expect("Argument with default value was missing")
+		{
+			"wait" => LatePolicy::Wait,
+			"extend" => LatePolicy::Extend,
+			"replay" => LatePolicy::Replay,
+			"placeholder" => LatePolicy::Placeholder,
+			late_policy => unreachable!("Unknown late policy {:?}", late_policy),
+		};
+
+		let fade_space = self::parse_fade_space(
+			matches
+				.value_of(FADE_SPACE_STR)
+				.expect("Argument with default value was missing"),
+		);
+
+		let mode = match (matches.value_of(GRID_STR), matches.value_of(LAYOUT_FILE_STR)) {
+			(Some(grid), _) => {
+				let mode = self::parse_grid(grid)?;
+				match (matches.values_of(SPAN_STR), matches.value_of(COLLAGE_STR)) {
+					(_, Some(interval)) => {
+						let interval = self::parse_duration(interval).context("Unable to parse collage interval")?;
+						anyhow::ensure!(interval > Duration::ZERO, "Collage interval must be positive");
+						match mode {
+							Mode::Grid { width, height } => Mode::Collage {
+								width,
+								height,
+								interval,
+							},
+							_ => unreachable!("Parsed grid wasn't a grid: {:?}", mode),
+						}
+					},
+					(Some(spans), None) => {
+						let mut layout = mode.layout().expect("Grid had no layout");
+						for span in spans {
+							let (pos, size) =
+								self::parse_span(span).with_context(|| format!("Unable to parse span {span:?}"))?;
+							layout
+								.span(pos, size)
+								.with_context(|| format!("Unable to span {span:?}"))?;
+						}
+						Mode::Layout(layout)
+					},
+					(None, None) => mode,
+				}
+			},
+			(None, Some(layout_file)) => {
+				let layout = std::fs::read_to_string(layout_file)
+					.with_context(|| format!("Unable to read layout file {layout_file:?}"))?;
+				let layout =
+					Layout::parse(&layout).with_context(|| format!("Unable to parse layout file {layout_file:?}"))?;
+				Mode::Layout(layout)
 			},
-			None => Mode::Single,
+			(None, None) => Mode::Single,
 		};
+		let grid_gap = matches
+			.value_of(GRID_GAP_STR)
+			.expect("Argument with default value was missing");
+		let grid_gap = self::parse_pixels(grid_gap).context("Unable to parse grid gap")?;
+		let grid_margin = matches
+			.value_of(GRID_MARGIN_STR)
+			.expect("Argument with default value was missing");
+		let grid_margin = self::parse_pixels(grid_margin).context("Unable to parse grid margin")?;
+		let grid_border_color = matches
+			.value_of(GRID_BORDER_COLOR_STR)
+			.map(self::parse_color)
+			.transpose()
+			.context("Unable to parse grid border color")?;
+		let grid_timing = match (matches.is_present(GRID_SYNC_STR), matches.value_of(GRID_STAGGER_STR)) {
+			(true, _) => GridTiming::Sync,
+			(false, Some(interval)) => {
+				GridTiming::Stagger(self::parse_duration(interval).context("Unable to parse grid stagger interval")?)
+			},
+			(false, None) => GridTiming::Random,
+		};
+		let grid_mirror = matches.is_present(GRID_MIRROR_STR);
+		let pinned_cells = matches
+			.values_of(CELL_STR)
+			.into_iter()
+			.flatten()
+			.map(|cell| self::parse_cell(cell).with_context(|| format!("Unable to parse cell {cell:?}")))
+			.collect::<Result<Vec<_>, _>>()?;
+		self::check_pinned_cells(&mode, &pinned_cells)?;
 
-		Ok(Self {
-			window_id,
-			duration,
-			images_dir,
-			fade,
-			image_backlog,
-			mode,
-		})
+		let scaling = match matches
+			.value_of(SCALING_STR)
+			.expect("Argument with default value was missing")
+		{
+			"fill" => Scaling::Fill,
+			"fit" => Scaling::Fit,
+			"contain-blur" => Scaling::ContainBlur,
+			"stretch" => Scaling::Stretch,
+			"center" => Scaling::Center,
+			"tile" => Scaling::Tile,
+			scaling => unreachable!("Unknown scaling {:?}", scaling),
+		};
+		let aspect_routing = matches.is_present(ASPECT_ROUTING_STR) || matches.is_present(COLLAGE_STR);
+		let kaleidoscope = matches
+			.value_of(KALEIDOSCOPE_STR)
+			.map(|kaleidoscope| match kaleidoscope {
+				"horizontal" => Kaleidoscope::Horizontal,
+				"vertical" => Kaleidoscope::Vertical,
+				"both" => Kaleidoscope::Both,
+				kaleidoscope => unreachable!("Unknown kaleidoscope {:?}", kaleidoscope),
+			});
+		let double_exposure = matches
+			.value_of(DOUBLE_EXPOSURE_STR)
+			.map(|blend_mode| match blend_mode {
+				"multiply" => BlendMode::Multiply,
+				"screen" => BlendMode::Screen,
+				"overlay" => BlendMode::Overlay,
+				blend_mode => unreachable!("Unknown blend mode {:?}", blend_mode),
+			});
+		let double_exposure_period = matches
+			.value_of(DOUBLE_EXPOSURE_PERIOD_STR)
+			.expect("Argument with default value was missing");
+		let double_exposure_period =
+			self::parse_duration(double_exposure_period).context("Unable to parse double exposure period")?;
+		anyhow::ensure!(
+			!double_exposure_period.is_zero(),
+			"Double exposure period must not be zero"
+		);
+
+		let resize_filter = match matches
+			.value_of(RESIZE_FILTER_STR)
+			.expect("Argument with default value was missing")
+		{
+			"nearest" => ResizeFilter::Nearest,
+			"triangle" => ResizeFilter::Triangle,
+			"catmull-rom" => ResizeFilter::CatmullRom,
+			"lanczos3" => ResizeFilter::Lanczos3,
+			resize_filter => unreachable!("Unknown resize filter {:?}", resize_filter),
+		};
+
+		let gpu_resize = matches.is_present(GPU_RESIZE_STR);
+		let rgb = matches.is_present(RGB_STR);
+		let software_rendering = matches.is_present(SOFTWARE_RENDERING_STR);
+
+		let compress_textures =
+			matches
+				.value_of(COMPRESS_TEXTURES_STR)
+				.map(|compress_textures| match compress_textures {
+					"bc1" => TextureCompression::Bc1,
+					"bc7" => TextureCompression::Bc7,
+					compress_textures => unreachable!("Unknown texture compression {:?}", compress_textures),
+				});
+
+		let bg_color = matches
+			.value_of(BG_COLOR_STR)
+			.expect("Argument with default value was missing");
+		let bg_color = self::parse_color(bg_color).context("Unable to parse background color")?;
+
+		let dominant_bg = matches.is_present(DOMINANT_BG_STR);
+
+		let interpolation_samples = matches
+			.value_of(INTERPOLATION_SAMPLES_STR)
+			.expect("Argument with default value was missing");
+		let interpolation_samples = self::parse_value(interpolation_samples, "a positive integer")
+			.context("Unable to parse interpolation samples")?;
+		anyhow::ensure!(interpolation_samples != 0, "Interpolation samples must be at least 1");
+
+		let brightness = matches
+			.value_of(BRIGHTNESS_STR)
+			.expect("Argument with default value was missing");
+		let brightness = self::parse_value(brightness, "a number").context("Unable to parse brightness")?;
+		anyhow::ensure!(brightness >= 0.0, "Brightness must not be negative");
+
+		let contrast = matches
+			.value_of(CONTRAST_STR)
+			.expect("Argument with default value was missing");
+		let contrast = self::parse_value(contrast, "a number").context("Unable to parse contrast")?;
+		anyhow::ensure!(contrast >= 0.0, "Contrast must not be negative");
+
+		let gamma = matches
+			.value_of(GAMMA_STR)
+			.expect("Argument with default value was missing");
+		let gamma = self::parse_value(gamma, "a number").context("Unable to parse gamma")?;
+		anyhow::ensure!(gamma > 0.0, "Gamma must be positive");
+
+		let saturation = matches
+			.value_of(SATURATION_STR)
+			.expect("Argument with default value was missing");
+		let saturation = self::parse_value(saturation, "a number").context("Unable to parse saturation")?;
+		anyhow::ensure!(saturation >= 0.0, "Saturation must not be negative");
+
+		let night_light = match matches.value_of(NIGHT_LIGHT_STR) {
+			Some(night_light) => {
+				let (start, end) = night_light
+					.split_once('-')
+					.context("Night light must be of the format `{start}-{end}`")?;
+				let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").context("Unable to parse night start")?;
+				let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").context("Unable to parse night end")?;
+
+				let brightness = matches
+					.value_of(NIGHT_BRIGHTNESS_STR)
+					.expect("Argument with default value was missing");
+				let brightness =
+					self::parse_value(brightness, "a number").context("Unable to parse night brightness")?;
+				anyhow::ensure!(brightness >= 0.0, "Night brightness must not be negative");
+
+				let temperature = matches
+					.value_of(NIGHT_TEMPERATURE_STR)
+					.expect("Argument with default value was missing");
+				let temperature = self::parse_value(temperature, "a temperature, in kelvin")
+					.context("Unable to parse night temperature")?;
+
+				let transition = matches
+					.value_of(NIGHT_TRANSITION_STR)
+					.expect("Argument with default value was missing");
+				let transition = self::parse_duration(transition).context("Unable to parse night transition")?;
+
+				Some(NightLight {
+					start,
+					end,
+					transition,
+					brightness,
+					temperature,
+				})
+			},
+			None => None,
+		};
+
+		let overlays = matches
+			.values_of(OVERLAY_STR)
+			.into_iter()
+			.flatten()
+			.map(|widget| self::parse_widget(widget).with_context(|| format!("Unable to parse overlay {widget:?}")))
+			.collect::<Result<Vec<_>, _>>()?;
+		let overlay_scale = matches
+			.value_of(OVERLAY_SCALE_STR)
+			.map(|scale| self::parse_value(scale, "a positive number"))
+			.transpose()
+			.context("Unable to parse overlay scale")?;
+		if let Some(overlay_scale) = overlay_scale {
+			anyhow::ensure!(overlay_scale > 0.0, "Overlay scale must be positive");
+		}
+
+		let bpm = match matches.value_of(BPM_STR) {
+			Some(bpm) => {
+				let bpm = self::parse_value(bpm, "a number").context("Unable to parse bpm")?;
+				anyhow::ensure!(
+					(tempo::MIN_BPM..=tempo::MAX_BPM).contains(&bpm),
+					"Bpm must be within {}..={}, found {}",
+					tempo::MIN_BPM,
+					tempo::MAX_BPM,
+					bpm
+				);
+				Some(bpm)
+			},
+			None => None,
+		};
+
+		let beats_per_image = matches
+			.value_of(BEATS_PER_IMAGE_STR)
+			.expect("Argument with default value was missing");
+		let beats_per_image =
+			self::parse_value(beats_per_image, "a positive integer").context("Unable to parse beats per image")?;
+		anyhow::ensure!(beats_per_image != 0, "Beats per image must be at least 1");
+
+		let clock_sync = matches
+			.value_of(CLOCK_SYNC_STR)
+			.map(|seed| self::parse_value(seed, "a non-negative integer"))
+			.transpose()
+			.context("Unable to parse clock sync seed")?;
+		let sync_lead = matches.value_of(SYNC_LEAD_STR).map(str::to_owned);
+		let sync_follow = matches.value_of(SYNC_FOLLOW_STR).map(str::to_owned);
+
+		let cycles = matches
+			.value_of(CYCLES_STR)
+			.map(|cycles| self::parse_value(cycles, "a positive integer"))
+			.transpose()
+			.context("Unable to parse cycles")?;
+		anyhow::ensure!(cycles != Some(0), "Cycles must be at least 1");
+		let exit_after = matches
+			.value_of(EXIT_AFTER_STR)
+			.map(self::parse_duration)
+			.transpose()
+			.context("Unable to parse exit after")?;
+
+		let ipc_socket = matches
+			.value_of_os(IPC_SOCKET_STR)
+			.map_or_else(self::default_ipc_socket, PathBuf::from);
+
+		let current_file = matches.value_of_os(CURRENT_FILE_STR).map(PathBuf::from);
+		let on_change = matches.value_of(ON_CHANGE_STR).map(str::to_owned);
+		let palette_out = matches.value_of_os(PALETTE_OUT_STR).map(PathBuf::from);
+		let palette_template = matches.value_of_os(PALETTE_TEMPLATE_STR).map(PathBuf::from);
+
+		let prev_images = matches
+			.value_of(PREV_IMAGES_STR)
+			.expect("Argument with default value was missing");
+		let prev_images =
+			self::parse_value(prev_images, "a non-negative integer").context("Unable to parse previous images")?;
+		let start_held = matches.is_present(START_HELD_STR);
+
+		let record = matches.value_of_os(RECORD_STR).map(PathBuf::from);
+
+		Ok((
+			Self::Run(Args {
+				outputs,
+				config_path,
+				duration,
+				duration_jitter,
+				images_dir,
+				images_from,
+				urls,
+				images_command,
+				fade,
+				image_backlog,
+				min_resolution,
+				max_file_size,
+				max_backlog_memory,
+				decode_timeout,
+				rescan_interval,
+				no_watch,
+				no_repeat_last,
+				persist_history,
+				weights,
+				dedup,
+				image_cache_size,
+				quarantine_log,
+				ban_list,
+				favorites_list,
+				only_favorites,
+				late_policy,
+				fade_space,
+				mode,
+				grid_gap,
+				grid_margin,
+				grid_border_color,
+				grid_timing,
+				grid_mirror,
+				pinned_cells,
+				scaling,
+				aspect_routing,
+				kaleidoscope,
+				double_exposure,
+				double_exposure_period,
+				resize_filter,
+				gpu_resize,
+				rgb,
+				compress_textures,
+				software_rendering,
+				bg_color,
+				dominant_bg,
+				interpolation_samples,
+				brightness,
+				contrast,
+				gamma,
+				saturation,
+				night_light,
+				overlays,
+				overlay_scale,
+				bpm,
+				beats_per_image,
+				clock_sync,
+				sync_lead,
+				sync_follow,
+				cycles,
+				exit_after,
+				ipc_socket,
+				current_file,
+				on_change,
+				palette_out,
+				palette_template,
+				prev_images,
+				start_held,
+				record,
+			}),
+			log,
+		))
+	}
+}
+
+/// Parses an overlay widget, as `<widget>[=<scale>]`
+fn parse_widget(value: &str) -> Result<Widget, anyhow::Error> {
+	let (kind, scale) = match value.split_once('=') {
+		Some((kind, scale)) => (kind, Some(scale)),
+		None => (value, None),
+	};
+	let kind = WidgetKind::parse(kind.trim())?;
+	let scale = scale
+		.map(|scale| self::parse_value::<f32>(scale, "a positive number"))
+		.transpose()?;
+	if let Some(scale) = scale {
+		anyhow::ensure!(scale > 0.0, "Overlay scale must be positive");
+	}
+
+	Ok(Widget { kind, scale })
+}
+
+/// Parses a log filter, as `[level][,module=level]...`, with `level` being the default level when not given
+#[allow(clippy::type_complexity)] // It's just a level and the module levels
+fn parse_log_filter(
+	value: &str, mut level: log::LevelFilter,
+) -> Result<(log::LevelFilter, Vec<(String, log::LevelFilter)>), anyhow::Error> {
+	let mut module_levels = vec![];
+	for directive in value
+		.split(',')
+		.map(str::trim)
+		.filter(|directive| !directive.is_empty())
+	{
+		match directive.split_once('=') {
+			Some((module, level)) => {
+				let level = level
+					.parse()
+					.with_context(|| format!("Unknown level {level:?} for module {module:?}"))?;
+				module_levels.push((module.to_owned(), level));
+			},
+			None => {
+				level = directive
+					.parse()
+					.with_context(|| format!("Unknown level {directive:?}"))?;
+			},
+		}
+	}
+
+	Ok((level, module_levels))
+}
+
+/// Parses an output, either `kms`, `kms:<device>` or a window id, optionally prefixed by `<name>=`
+fn parse_output(value: &str) -> Result<Output, anyhow::Error> {
+	let (name, kind) = match value.split_once('=') {
+		Some((name, kind)) => (Some(name.to_owned()), kind),
+		None => (None, value),
+	};
+
+	let kind = match kind.strip_prefix("kms") {
+		Some("") => OutputKind::Kms(PathBuf::from(OutputKind::DEFAULT_KMS_DEVICE)),
+		Some(device) => match device.strip_prefix(':') {
+			Some(device) => OutputKind::Kms(PathBuf::from(device)),
+			None => anyhow::bail!("Expected `kms` or `kms:<device>`, found {:?}", kind),
+		},
+		None => OutputKind::Window(self::parse_window_id(kind)?),
+	};
+
+	Ok(Output { name, kind })
+}
+
+/// Parses a `0x`-prefixed hexadecimal window id
+fn parse_window_id(value: &str) -> Result<u64, anyhow::Error> {
+	let hex = value.strip_prefix("0x").context("Window id didn't start with `0x`")?;
+	u64::from_str_radix(hex, 16).with_context(|| format!("Expected a hexadecimal window id, found {value:?}"))
+}
+
+/// Geometry parse error
+#[derive(Debug)]
+pub enum GeometryParseError {
+	/// Missing the `x` separator
+	MissingSeparator(String),
+
+	/// Unable to parse the width
+	Width(String, Box<dyn Error + Send + Sync>),
+
+	/// Unable to parse the height
+	Height(String, Box<dyn Error + Send + Sync>),
+
+	/// Size had no area
+	Empty(String),
+}
+
+impl fmt::Display for GeometryParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingSeparator(value) => write!(f, "Expected `{{width}}x{{height}}`, found {value:?}"),
+			Self::Width(value, _) => write!(f, "Unable to parse width, expected an integer, found {value:?}"),
+			Self::Height(value, _) => write!(f, "Unable to parse height, expected an integer, found {value:?}"),
+			Self::Empty(value) => write!(f, "Size must be at least 1x1, found {value:?}"),
+		}
+	}
+}
+
+impl Error for GeometryParseError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Width(_, err) | Self::Height(_, err) => Some(&**err),
+			Self::MissingSeparator(_) | Self::Empty(_) => None,
+		}
+	}
+}
+
+/// Parses a `{width}x{height}` pair
+pub fn parse_width_height<T>(value: &str) -> Result<(T, T), GeometryParseError>
+where
+	T: FromStr,
+	T::Err: Error + Send + Sync + 'static,
+{
+	let (width, height) = value
+		.split_once('x')
+		.ok_or_else(|| GeometryParseError::MissingSeparator(value.to_owned()))?;
+	let width = width
+		.trim()
+		.parse()
+		.map_err(|err| GeometryParseError::Width(width.to_owned(), Box::new(err)))?;
+	let height = height
+		.trim()
+		.parse()
+		.map_err(|err| GeometryParseError::Height(height.to_owned(), Box::new(err)))?;
+
+	Ok((width, height))
+}
+
+/// Parses a `{width}x{height}` size, which must have a non-zero area
+pub fn parse_size(value: &str) -> Result<(u32, u32), GeometryParseError> {
+	let (width, height) = self::parse_width_height(value)?;
+	if width == 0 || height == 0 {
+		return Err(GeometryParseError::Empty(value.to_owned()));
 	}
+
+	Ok((width, height))
+}
+
+/// Parses `value`, reporting it and what was `expected` on error
+fn parse_value<T>(value: &str, expected: &str) -> Result<T, anyhow::Error>
+where
+	T: FromStr,
+	T::Err: Error + Send + Sync + 'static,
+{
+	value
+		.trim()
+		.parse()
+		.with_context(|| format!("Expected {expected}, found {value:?}"))
+}
+
+/// Returns the default ipc socket path
+fn default_ipc_socket() -> PathBuf {
+	std::env::var_os("XDG_RUNTIME_DIR")
+		.map_or_else(std::env::temp_dir, PathBuf::from)
+		.join("zss.sock")
+}
+
+/// Makes sure all pinned cells in `pinned_cells` are on a cell of the grid of `mode`
+pub fn check_pinned_cells(mode: &Mode, pinned_cells: &[([u32; 2], PathBuf)]) -> Result<(), anyhow::Error> {
+	// Note: Collages are arranged randomly, but always cover every slot
+	let layout = mode.layout();
+	for &([x, y], _) in pinned_cells {
+		let is_on_cell = layout
+			.as_ref()
+			.map_or(false, |layout| layout.cells.iter().any(|cell| cell.contains([x, y])));
+		anyhow::ensure!(is_on_cell, "Cell {},{} isn't on any cell of the grid", x, y);
+	}
+
+	Ok(())
+}
+
+/// Parses a grid, such as `2x2`
+pub fn parse_grid(value: &str) -> Result<Mode, anyhow::Error> {
+	let (width, height) = self::parse_width_height(value).context("Unable to parse grid")?;
+	anyhow::ensure!(
+		width != 0 && height != 0,
+		"Grid must have at least 1 cell, found {:?}",
+		value
+	);
+
+	Ok(Mode::Grid { width, height })
+}
+
+/// Parses a span, as `{x},{y}:{width}x{height}`
+fn parse_span(value: &str) -> Result<([u32; 2], [u32; 2]), anyhow::Error> {
+	let (pos, size) = value
+		.split_once(':')
+		.context("Expected a span, as `{x},{y}:{width}x{height}`, such as `0,0:2x2`")?;
+	let pos = self::parse_slot(pos)?;
+	let (width, height) = self::parse_width_height(size).context("Unable to parse span size")?;
+
+	Ok((pos, [width, height]))
+}
+
+/// Parses a pinned cell, as `{x},{y}={path}`
+fn parse_cell(value: &str) -> Result<([u32; 2], PathBuf), anyhow::Error> {
+	let (pos, path) = value
+		.split_once('=')
+		.context("Expected a cell, as `{x},{y}={path}`, such as `0,0=logo.png`")?;
+	let pos = self::parse_slot(pos)?;
+
+	// Note: Shells don't expand `~` after the `=`, so we do it ourselves
+	let path = match path.strip_prefix("~/") {
+		Some(path) => std::env::var_os("HOME")
+			.map(PathBuf::from)
+			.context("Unable to get home directory")?
+			.join(path),
+		None => PathBuf::from(path),
+	};
+
+	Ok((pos, path))
+}
+
+/// Parses the position of a slot, as `{x},{y}`
+fn parse_slot(value: &str) -> Result<[u32; 2], anyhow::Error> {
+	let (x, y) = value.split_once(',').context("Expected a position, as `{x},{y}`")?;
+
+	Ok([
+		self::parse_value(x, "a slot index")?,
+		self::parse_value(y, "a slot index")?,
+	])
+}
+
+/// Parses a length in pixels, with an optional `px` suffix
+fn parse_pixels(value: &str) -> Result<u32, anyhow::Error> {
+	let value = value.trim();
+	let value = value.strip_suffix("px").unwrap_or(value);
+
+	self::parse_value(
+		value,
+		"a number of pixels, optionally with a `px` suffix, such as `8px`",
+	)
+}
+
+/// Parses a duration, in seconds, with an optional `ms`, `s`, `m` or `h` suffix
+pub fn parse_duration(value: &str) -> Result<Duration, anyhow::Error> {
+	let value = value.trim();
+	let (number, unit_secs) = [("ms", 0.001), ("s", 1.0), ("m", 60.0), ("h", 3600.0)]
+		.iter()
+		.find_map(|&(suffix, unit_secs)| value.strip_suffix(suffix).map(|number| (number, unit_secs)))
+		.unwrap_or((value, 1.0));
+
+	let number: f64 = self::parse_value(
+		number,
+		"a duration, in seconds, optionally with a `ms`, `s`, `m` or `h` suffix, such as `30s` or `1.5m`",
+	)?;
+	anyhow::ensure!(
+		number.is_finite() && number >= 0.0,
+		"Expected a non-negative duration, found {:?}",
+		value
+	);
+
+	// Note: `Duration::from_secs_f64` panics if the duration doesn't fit
+	let secs = number * unit_secs;
+	#[allow(clippy::cast_precision_loss)] // We only need a rough upper bound
+	let max_secs = u64::MAX as f64;
+	anyhow::ensure!(secs < max_secs, "Duration {:?} is too long", value);
+
+	Ok(Duration::from_secs_f64(secs))
+}
+
+/// Parses a directory weight, as `<dir>=<weight>`
+fn parse_weight(value: &str) -> Result<(PathBuf, f64), anyhow::Error> {
+	let (dir, weight) = value
+		.split_once('=')
+		.context("Expected a directory weight, such as `nature=3`")?;
+	let weight = self::parse_value(weight, "a non-negative number")?;
+
+	Ok((PathBuf::from(dir), weight))
+}
+
+/// Parses a file size, with an optional `K`, `M` or `G` suffix
+fn parse_file_size(value: &str) -> Result<u64, anyhow::Error> {
+	let (value, multiplier) = match value.char_indices().last() {
+		Some((idx, 'k' | 'K')) => (&value[..idx], 1 << 10),
+		Some((idx, 'm' | 'M')) => (&value[..idx], 1 << 20),
+		Some((idx, 'g' | 'G')) => (&value[..idx], 1 << 30),
+		_ => (value, 1),
+	};
+
+	let value = self::parse_value::<u64>(value, "a number of bytes, optionally with a `K`, `M` or `G` suffix")?;
+	value.checked_mul(multiplier).context("Size was too large")
+}
+
+/// Parses a fade space
+fn parse_fade_space(value: &str) -> FadeSpace {
+	match value {
+		"linear" => FadeSpace::Linear,
+		"srgb" => FadeSpace::Srgb,
+		fade_space => unreachable!("Unknown fade space {:?}", fade_space),
+	}
+}
+
+/// Parses a `#rrggbb` color
+pub fn parse_color(value: &str) -> Result<[f32; 3], anyhow::Error> {
+	let hex = value.trim().strip_prefix('#').context("Color must start with `#`")?;
+	anyhow::ensure!(
+		hex.len() == 6 && hex.is_ascii(),
+		"Color must be of the format `#rrggbb`"
+	);
+
+	let component = |idx: usize| {
+		u8::from_str_radix(&hex[2 * idx..2 * idx + 2], 16)
+			.map(|value| f32::from(value) / 255.0)
+			.context("Unable to parse color component")
+	};
+	Ok([component(0)?, component(1)?, component(2)?])
 }
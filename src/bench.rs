@@ -0,0 +1,115 @@
+//! Loader benchmark
+//!
+//! Measures each stage of loading an image separately, so the loader's settings may be tuned
+//! for the actual images and hardware.
+
+// Imports
+use crate::stats;
+use anyhow::Context;
+use glium::backend::Facade;
+use rand::seq::SliceRandom;
+use std::{
+	rc::Rc,
+	time::{Duration, Instant},
+};
+use zss::{
+	args::BenchArgs,
+	glium_backend::GliumBackend,
+	glium_facade::GliumFacade,
+	ignores::Ignores,
+	images::{self, LoadOptions},
+	slide_info::SlideSource,
+	window::Window,
+	Image,
+};
+
+/// Measures how long each stage of loading every image takes
+pub fn run(args: &BenchArgs) -> Result<(), anyhow::Error> {
+	let images_dir = std::env::current_dir()
+		.context("Unable to get current directory")?
+		.join(&args.images_dir);
+
+	let mut ignores = Ignores::new(images_dir.clone());
+	let mut paths = vec![];
+	stats::collect_paths(&images_dir, &mut ignores, &mut paths).context("Unable to read images directory")?;
+	if let Some(limit) = args.limit {
+		paths.shuffle(&mut rand::thread_rng());
+		paths.truncate(limit);
+	}
+
+	// Note: We need a context to upload the images, but never show anything
+	let window = Window::headless(args.size)
+		.map(Rc::new)
+		.context("Unable to create headless window")?;
+	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+	let load_options = LoadOptions {
+		window_size:      args.size,
+		scaling:          args.scaling,
+		resize_filter:    args.resize_filter,
+		gpu_resize:       false,
+		rgb:              false,
+		max_texture_size: Some(facade.max_texture_size()),
+		cache_size:       0,
+	};
+
+	let mut decode_times = vec![];
+	let mut resize_times = vec![];
+	let mut upload_times = vec![];
+	let mut total_times = vec![];
+	for path in &paths {
+		let decode_start = Instant::now();
+		let frames = match images::decode(path) {
+			Ok(frames) => frames,
+			Err(err) => {
+				log::info!("Unable to decode {path:?}: {err}");
+				continue;
+			},
+		};
+		let decode_time = decode_start.elapsed();
+
+		let resize_start = Instant::now();
+		let image = images::process_frames(path, frames, load_options, SlideSource::Directory, decode_start)
+			.with_context(|| format!("Unable to process {path:?}"))?;
+		let resize_time = resize_start.elapsed();
+
+		// Note: Uploading is asynchronous, so we wait for the gpu to finish it
+		let upload_start = Instant::now();
+		let mut image = Image::from_image(&facade, image, args.size, args.scaling, None)
+			.with_context(|| format!("Unable to upload {path:?}"))?;
+		image.finish_upload();
+		facade.get_context().finish();
+		let upload_time = upload_start.elapsed();
+
+		decode_times.push(decode_time);
+		resize_times.push(resize_time);
+		upload_times.push(upload_time);
+		total_times.push(decode_time + resize_time + upload_time);
+	}
+
+	println!("Measured {} of {} images", total_times.len(), paths.len());
+	self::print_percentiles("Decode", &mut decode_times);
+	self::print_percentiles("Resize", &mut resize_times);
+	self::print_percentiles("Upload", &mut upload_times);
+	self::print_percentiles("Total", &mut total_times);
+
+	Ok(())
+}
+
+/// Prints the percentiles of `times`, for `stage`
+fn print_percentiles(stage: &str, times: &mut [Duration]) {
+	if times.is_empty() {
+		println!("{stage}: -");
+		return;
+	}
+
+	times.sort_unstable();
+	let percentile = |percent: usize| times[(times.len() - 1) * percent / 100];
+	println!(
+		"{stage}: p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+		percentile(50),
+		percentile(90),
+		percentile(99),
+		percentile(100)
+	);
+}
@@ -0,0 +1,112 @@
+//! Library check
+//!
+//! Loads every image in the images directory, as when displaying them, to find any that can't be
+//! shown before they're reached in a running session.
+
+// Imports
+use crate::stats;
+use anyhow::Context;
+use zss::{
+	args::{CheckArgs, ResizeFilter},
+	config::Config,
+	handlers::Handler,
+	ignores::Ignores,
+	images::{self, LoadOptions},
+};
+
+/// Checks all images in the images directory
+pub fn run(args: &CheckArgs) -> Result<(), anyhow::Error> {
+	let images_dir = std::env::current_dir()
+		.context("Unable to get current directory")?
+		.join(&args.images_dir);
+
+	let mut ignores = Ignores::new(images_dir.clone());
+	let mut paths = vec![];
+	stats::collect_paths(&images_dir, &mut ignores, &mut paths).context("Unable to read images directory")?;
+	paths.sort();
+
+	// Note: Files are loaded with the config's handlers, as when displaying them
+	let config = match &args.config_path {
+		Some(config_path) => Config::load(config_path)
+			.with_context(|| format!("Unable to load config from {}", config_path.display()))?,
+		None => Config::default(),
+	};
+
+	#[allow(clippy::cast_precision_loss)] // We don't need the exact ratio
+	let window_aspect_ratio = args.size[0] as f32 / args.size[1] as f32;
+
+	let mut unreadable = vec![];
+	let mut unusual_aspect_ratios = vec![];
+	let mut skipped = 0;
+	let mut total_memory = 0;
+	for path in &paths {
+		let handler = match config.handlers.get(path) {
+			Handler::Skip => {
+				skipped += 1;
+				continue;
+			},
+			handler => handler,
+		};
+
+		let image = match images::load_with_handler(path, handler, LoadOptions {
+			window_size:      args.size,
+			scaling:          args.scaling,
+			resize_filter:    ResizeFilter::Lanczos3,
+			gpu_resize:       false,
+			rgb:              false,
+			max_texture_size: None,
+			cache_size:       0,
+		}) {
+			Ok(image) => image,
+			Err(err) => {
+				unreadable.push((path, anyhow::Error::new(err)));
+				continue;
+			},
+		};
+		total_memory += image.memory_size();
+
+		// Note: We compare against the original dimensions, as the loaded image has already been resized
+		let [width, height] = image.info.dimensions;
+		#[allow(clippy::cast_precision_loss)] // We don't need the exact ratio
+		let aspect_ratio = width as f32 / height as f32;
+		let ratio = aspect_ratio / window_aspect_ratio;
+		if ratio > args.max_aspect_ratio || ratio.recip() > args.max_aspect_ratio {
+			unusual_aspect_ratios.push((path, width, height));
+		}
+	}
+
+	if !unreadable.is_empty() {
+		println!("Unreadable:");
+		for (path, err) in &unreadable {
+			println!("\t{}: {err:#}", path.display());
+		}
+	}
+
+	if !unusual_aspect_ratios.is_empty() {
+		println!("Unusual aspect ratios:");
+		for (path, width, height) in &unusual_aspect_ratios {
+			println!("\t{}: {width}x{height}", path.display());
+		}
+	}
+
+	println!(
+		"Checked {} images: {} skipped, {} unreadable, {} with an unusual aspect ratio",
+		paths.len() - skipped,
+		skipped,
+		unreadable.len(),
+		unusual_aspect_ratios.len()
+	);
+	println!(
+		"Estimated memory at {}x{}: {}",
+		args.size[0],
+		args.size[1],
+		stats::fmt_size(total_memory)
+	);
+
+	anyhow::ensure!(
+		unreadable.is_empty(),
+		"{} image(s) couldn't be loaded",
+		unreadable.len()
+	);
+	Ok(())
+}
@@ -0,0 +1,32 @@
+//! Wall clock slots
+//!
+//! When synchronizing to the wall clock, time is split into slots, each showing a single
+//! image, starting at the unix epoch, so all instances agree on the current slot without
+//! communicating.
+
+// Imports
+use std::time::{Duration, SystemTime};
+
+/// Returns the slot `now` is in, along with how far into it, from `0.0` to `1.0`
+pub fn slot(now: SystemTime, slot_duration: Duration) -> (u64, f64) {
+	// Note: If the clock is before the epoch, we just treat it as the epoch
+	let elapsed = now.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+	let slots = elapsed.as_secs_f64() / slot_duration.as_secs_f64();
+
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // `slots` is positive and won't reach `2^64`
+	(slots.floor() as u64, slots.fract())
+}
+
+/// Returns the image index for `slot` out of `len` images, given `seed`.
+///
+/// Uses `splitmix64`, so the index is the same across all platforms and versions.
+pub fn slot_index(seed: u64, slot: u64, len: usize) -> usize {
+	let mut value = seed ^ slot.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+	value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	value ^= value >> 31;
+
+	#[allow(clippy::cast_possible_truncation)] // The remainder is less than `len`
+	let idx = (value % len as u64) as usize;
+	idx
+}
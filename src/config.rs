@@ -0,0 +1,266 @@
+//! Config
+
+// Imports
+use crate::{
+	args::{self, Args, FadeSpace, Mode, Scaling},
+	handlers::Handlers,
+	online::Online,
+	overlay::{Widget, WidgetKind},
+	schedule::ScheduleRule,
+	selection::Selection,
+};
+use anyhow::Context;
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+/// Config
+#[derive(Clone, Default, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	/// File type handlers
+	pub handlers: Handlers,
+
+	/// Image selection
+	pub selection: Selection,
+
+	/// Online sources
+	pub online: Online,
+
+	/// Directories to show depending on the time of day
+	pub schedule: Vec<ScheduleRule>,
+
+	/// Drawing
+	pub draw: DrawConfig,
+
+	/// Overlay widgets
+	pub overlay: OverlayConfig,
+
+	/// Config of each output, by name
+	pub outputs: HashMap<String, OutputConfig>,
+}
+
+impl Config {
+	/// Loads the config from `path`
+	pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+		let config = std::fs::read_to_string(path).context("Unable to read file")?;
+		let config: Self = toml::from_str(&config).context("Unable to parse config")?;
+
+		// Note: Without a minimum, an interval of 0 would keep pulling from all sources
+		anyhow::ensure!(
+			config.online.interval >= Online::MIN_INTERVAL,
+			"Online interval must be at least {} seconds",
+			Online::MIN_INTERVAL
+		);
+
+		Ok(config)
+	}
+}
+
+/// Draw config
+///
+/// Overrides how images are drawn, both on the slideshow and when verifying.
+#[derive(Clone, Default, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrawConfig {
+	/// Fade, from 0.5 to 1.0
+	pub fade: Option<f32>,
+
+	/// Fade space, `linear` or `srgb`
+	pub fade_space: Option<String>,
+
+	/// Scaling, such as `fit`
+	pub scaling: Option<String>,
+
+	/// Background color, as `#rrggbb`
+	pub bg_color: Option<String>,
+}
+
+impl DrawConfig {
+	/// Returns the fade, if any
+	pub fn fade(&self) -> Result<Option<f32>, anyhow::Error> {
+		if let Some(fade) = self.fade {
+			anyhow::ensure!((0.5..=1.0).contains(&fade), "Fade must be within 0.5 .. 1.0");
+		}
+
+		Ok(self.fade)
+	}
+
+	/// Returns the fade space, if any
+	pub fn fade_space(&self) -> Result<Option<FadeSpace>, anyhow::Error> {
+		self.fade_space
+			.as_deref()
+			.map(|fade_space| match fade_space {
+				"linear" => Ok(FadeSpace::Linear),
+				"srgb" => Ok(FadeSpace::Srgb),
+				fade_space => Err(anyhow::anyhow!(
+					"Unknown fade space {:?}, expected `linear` or `srgb`",
+					fade_space
+				)),
+			})
+			.transpose()
+	}
+
+	/// Returns the scaling, if any
+	pub fn scaling(&self) -> Result<Option<Scaling>, anyhow::Error> {
+		self.scaling
+			.as_deref()
+			.map(|scaling| match scaling {
+				"fill" => Ok(Scaling::Fill),
+				"fit" => Ok(Scaling::Fit),
+				"contain-blur" => Ok(Scaling::ContainBlur),
+				"stretch" => Ok(Scaling::Stretch),
+				"center" => Ok(Scaling::Center),
+				"tile" => Ok(Scaling::Tile),
+				scaling => Err(anyhow::anyhow!(
+					"Unknown scaling {:?}, expected `fill`, `fit`, `contain-blur`, `stretch`, `center` or `tile`",
+					scaling
+				)),
+			})
+			.transpose()
+	}
+
+	/// Returns the background color, if any
+	pub fn bg_color(&self) -> Result<Option<[f32; 3]>, anyhow::Error> {
+		self.bg_color
+			.as_deref()
+			.map(args::parse_color)
+			.transpose()
+			.context("Unable to parse background color")
+	}
+
+	/// Applies this config to `args`
+	pub fn apply(&self, args: &mut Args) -> Result<(), anyhow::Error> {
+		if let Some(fade) = self.fade()? {
+			args.fade = fade;
+		}
+		if let Some(fade_space) = self.fade_space()? {
+			args.fade_space = fade_space;
+		}
+		if let Some(scaling) = self.scaling()? {
+			args.scaling = scaling;
+		}
+		if let Some(bg_color) = self.bg_color()? {
+			args.bg_color = bg_color;
+		}
+
+		Ok(())
+	}
+}
+
+/// Overlay config
+///
+/// Enables each widget whose section is present, in addition to those given with `--overlay`.
+#[derive(Clone, Default, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverlayConfig {
+	/// Scale of all widgets without their own scale, instead of each output's scale factor
+	pub scale: Option<f32>,
+
+	/// Clock
+	pub clock: Option<WidgetConfig>,
+
+	/// Caption
+	pub caption: Option<WidgetConfig>,
+
+	/// Hud
+	pub hud: Option<WidgetConfig>,
+}
+
+impl OverlayConfig {
+	/// Applies this config to `args`
+	pub fn apply(&self, args: &mut Args) -> Result<(), anyhow::Error> {
+		if let Some(scale) = self.scale {
+			anyhow::ensure!(scale > 0.0, "Overlay scale must be positive");
+			args.overlay_scale = Some(scale);
+		}
+
+		let widgets = [
+			(WidgetKind::Clock, &self.clock),
+			(WidgetKind::Caption, &self.caption),
+			(WidgetKind::Hud, &self.hud),
+		];
+		for (kind, widget) in widgets
+			.iter()
+			.filter_map(|&(kind, widget)| widget.as_ref().map(|widget| (kind, widget)))
+		{
+			if let Some(scale) = widget.scale {
+				anyhow::ensure!(scale > 0.0, "Scale of the {:?} overlay must be positive", kind);
+			}
+
+			// Note: The config overrides any widget of the same kind given in the arguments
+			args.overlays.retain(|widget| widget.kind != kind);
+			args.overlays.push(Widget {
+				kind,
+				scale: widget.scale,
+			});
+		}
+
+		Ok(())
+	}
+}
+
+/// Overlay widget config
+#[derive(Clone, Default, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WidgetConfig {
+	/// Scale, instead of the output's scale factor
+	pub scale: Option<f32>,
+}
+
+/// Output config
+///
+/// Overrides the arguments of a single output, given it's name with `<name>=<window-id>`.
+#[derive(Clone, Default, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+	/// Images directory
+	///
+	/// Replaces any other source of images, and is loaded separately from the other outputs.
+	pub images_dir: Option<PathBuf>,
+
+	/// Duration, such as `30s`
+	pub duration: Option<String>,
+
+	/// Grid, such as `1x2`, or `1x1` for a single image
+	pub grid: Option<String>,
+
+	/// Scale of all overlay widgets without their own scale, instead of the output's scale factor
+	pub overlay_scale: Option<f32>,
+}
+
+impl OutputConfig {
+	/// Applies this config to `args`
+	pub fn apply(&self, args: &mut Args) -> Result<(), anyhow::Error> {
+		if let Some(images_dir) = &self.images_dir {
+			args.images_dir.clone_from(images_dir);
+			args.images_from = None;
+			args.urls.clear();
+			args.images_command = None;
+			args.sync_follow = None;
+		}
+
+		if let Some(duration) = &self.duration {
+			let duration = args::parse_duration(duration).context("Unable to parse duration")?;
+			anyhow::ensure!(duration > Duration::ZERO, "Duration must be positive");
+			args.duration = duration;
+		}
+
+		if let Some(grid) = &self.grid {
+			args.mode = match args::parse_grid(grid)? {
+				Mode::Grid { width: 1, height: 1 } => Mode::Single,
+				mode => mode,
+			};
+			args::check_pinned_cells(&args.mode, &args.pinned_cells)?;
+		}
+
+		if let Some(overlay_scale) = self.overlay_scale {
+			anyhow::ensure!(overlay_scale > 0.0, "Overlay scale must be positive");
+			args.overlay_scale = Some(overlay_scale);
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,46 @@
+//! Current images
+
+// Imports
+use std::{path::PathBuf, sync::Mutex};
+
+/// Images currently shown in each panel
+#[derive(Default, Debug)]
+pub struct CurrentImages {
+	/// Path shown in each panel, if any
+	paths: Mutex<Vec<Option<PathBuf>>>,
+}
+
+impl CurrentImages {
+	/// Sets the path shown in `panel`, returning if it changed
+	pub fn set(&self, panel: usize, path: Option<&PathBuf>) -> bool {
+		let mut paths = self.paths.lock().expect("Current images lock was poisoned");
+		if panel >= paths.len() {
+			paths.resize(panel + 1, None);
+		}
+
+		match paths[panel].as_ref() == path {
+			true => false,
+			false => {
+				paths[panel] = path.cloned();
+				true
+			},
+		}
+	}
+
+	/// Returns the paths shown in each panel, if any
+	pub fn paths(&self) -> Vec<Option<PathBuf>> {
+		self.paths.lock().expect("Current images lock was poisoned").clone()
+	}
+
+	/// Returns the paths shown in each panel, one per line, with panels without one left empty
+	pub fn lines(&self) -> String {
+		self.paths()
+			.iter()
+			.map(|path| {
+				path.as_ref()
+					.map_or_else(String::new, |path| path.display().to_string())
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+}
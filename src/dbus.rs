@@ -0,0 +1,137 @@
+//! D-Bus interface
+//!
+//! Exposes the ipc commands as methods of the `org.zenithsiz.zss` interface, at
+//! `/org/zenithsiz/zss`, under the `org.zenithsiz.zss` name on the session bus.
+
+// Imports
+use crate::{current_images::CurrentImages, ipc::IpcCommand};
+use anyhow::Context;
+use dbus_crossroads::{Crossroads, MethodErr};
+use dbus_rs::blocking::Connection;
+use std::{
+	path::PathBuf,
+	sync::{mpsc, Arc},
+	thread,
+	time::Duration,
+};
+
+/// Bus name and interface name
+pub const NAME: &str = "org.zenithsiz.zss";
+
+/// Object path
+pub const PATH: &str = "/org/zenithsiz/zss";
+
+/// D-Bus server
+pub struct Dbus {
+	/// Receiver end for the commands
+	command_rx: mpsc::Receiver<IpcCommand>,
+}
+
+impl Dbus {
+	/// Starts serving the interface on the session bus
+	pub fn new(current_images: Arc<CurrentImages>) -> Result<Self, anyhow::Error> {
+		let (command_tx, command_rx) = mpsc::channel();
+
+		// Note: The connection can't be sent across threads, so we create it within the server thread
+		let (res_tx, res_rx) = mpsc::sync_channel(1);
+		thread::spawn(move || {
+			let server = Server {
+				command_tx,
+				current_images,
+			};
+			let (connection, crossroads) = match self::connect(server) {
+				Ok(res) => {
+					let _ = res_tx.send(Ok(()));
+					res
+				},
+				Err(err) => {
+					let _ = res_tx.send(Err(err));
+					return;
+				},
+			};
+
+			if let Err(err) = crossroads.serve(&connection) {
+				log::warn!("D-Bus server stopped: {err}");
+			}
+		});
+
+		res_rx.recv().context("D-Bus server thread panicked")??;
+		log::info!("Serving D-Bus interface {NAME:?} at {PATH:?}");
+
+		Ok(Self { command_rx })
+	}
+
+	/// Returns the next command, returning `None` if there are none
+	pub fn try_recv(&self) -> Option<IpcCommand> {
+		self.command_rx.try_recv().ok()
+	}
+}
+
+/// Server state
+struct Server {
+	/// Sender for all commands
+	command_tx: mpsc::Sender<IpcCommand>,
+
+	/// Current images
+	current_images: Arc<CurrentImages>,
+}
+
+impl Server {
+	/// Sends a command
+	fn send(&self, command: IpcCommand) -> Result<(), MethodErr> {
+		self.command_tx
+			.send(command)
+			.map_err(|_| MethodErr::failed("Wallpaper is no longer running"))
+	}
+}
+
+/// Connects to the session bus and creates the interface for `server`
+fn connect(server: Server) -> Result<(Connection, Crossroads), anyhow::Error> {
+	let connection = Connection::new_session().context("Unable to connect to session bus")?;
+	connection
+		.request_name(NAME, false, true, true)
+		.with_context(|| format!("Unable to acquire name {NAME:?}"))?;
+
+	let mut crossroads = Crossroads::new();
+	let iface = crossroads.register(NAME, self::build_iface);
+	crossroads.insert(PATH, &[iface], server);
+
+	Ok((connection, crossroads))
+}
+
+/// Builds the interface
+fn build_iface(builder: &mut dbus_crossroads::IfaceBuilder<Server>) {
+	builder.method("Next", (), (), |_, server, ()| server.send(IpcCommand::Next));
+	builder.method("Prev", (), (), |_, server, ()| server.send(IpcCommand::Prev));
+	builder.method("Pause", (), (), |_, server, ()| server.send(IpcCommand::Pause));
+	builder.method("Resume", (), (), |_, server, ()| server.send(IpcCommand::Resume));
+	builder.method("Reload", (), (), |_, server, ()| server.send(IpcCommand::Reload));
+	builder.method("Quit", (), (), |_, server, ()| server.send(IpcCommand::Quit));
+	builder.method("Ban", ("panel",), (), |_, server, (panel,): (u32,)| {
+		server.send(IpcCommand::Ban(panel as usize))
+	});
+	builder.method("Fav", ("panel",), (), |_, server, (panel,): (u32,)| {
+		server.send(IpcCommand::Fav(panel as usize))
+	});
+	builder.method("Screenshot", ("path",), (), |_, server, (path,): (String,)| {
+		server.send(IpcCommand::Screenshot(PathBuf::from(path)))
+	});
+	builder.method("SetDuration", ("seconds",), (), |_, server, (seconds,): (f64,)| {
+		if !seconds.is_finite() || seconds <= 0.0 || seconds > f64::from(u32::MAX) {
+			return Err(MethodErr::invalid_arg(&seconds));
+		}
+
+		server.send(IpcCommand::SetDuration(Duration::from_secs_f64(seconds)))
+	});
+
+	// Note: Panels without an image yet have an empty path
+	builder.method("CurrentImages", (), ("paths",), |_, server, ()| {
+		let paths = server
+			.current_images
+			.paths()
+			.into_iter()
+			.map(|path| path.map_or_else(String::new, |path| path.to_string_lossy().into_owned()))
+			.collect::<Vec<_>>();
+		Ok((paths,))
+	});
+}
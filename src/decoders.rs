@@ -0,0 +1,121 @@
+//! Optional decoders
+//!
+//! Decoders for formats `image` doesn't support, each enabled by it's own feature:
+//! - `heic`: Heic / Heif, through `libheif`
+//! - `avif`: Avif, through `libheif`
+//! - `jxl`: Jpeg-XL, through `djxl`
+//!
+//! Along with faster decoders for formats it does support:
+//! - `turbojpeg`: Jpeg, through `libjpeg-turbo`. If it fails, `image` is used instead.
+
+// Imports
+#[cfg(any(feature = "heic", feature = "avif", feature = "jxl", feature = "turbojpeg"))]
+use anyhow::Context;
+use image::DynamicImage;
+use std::path::Path;
+
+/// Decodes an image with an optional decoder.
+///
+/// Returns `None` if no enabled decoder supports it.
+#[allow(clippy::match_single_binding)] // Without any decoders enabled, there's only a single arm
+pub fn decode(path: &Path) -> Option<Result<DynamicImage, anyhow::Error>> {
+	let extension = path.extension()?.to_str()?.to_lowercase();
+	match extension.as_str() {
+		#[cfg(feature = "heic")]
+		"heic" | "heif" => Some(self::decode_heif(path)),
+		#[cfg(feature = "avif")]
+		"avif" => Some(self::decode_heif(path)),
+		#[cfg(feature = "jxl")]
+		"jxl" => Some(self::decode_jxl(path)),
+		#[cfg(feature = "turbojpeg")]
+		"jpg" | "jpeg" | "jpe" | "jfif" => match self::decode_turbojpeg(path) {
+			Ok(image) => Some(Ok(image)),
+			Err(err) => {
+				log::debug!("Unable to decode {path:?} with `libjpeg-turbo`, falling back: {err:?}");
+				None
+			},
+		},
+		_ => None,
+	}
+}
+
+/// Decodes a jpeg image with `libjpeg-turbo`
+#[cfg(feature = "turbojpeg")]
+fn decode_turbojpeg(path: &Path) -> Result<DynamicImage, anyhow::Error> {
+	use std::convert::TryInto;
+
+	let data = std::fs::read(path).context("Unable to read image")?;
+	let image = turbojpeg_rs::decompress(&data, turbojpeg_rs::PixelFormat::RGB).context("Unable to decode image")?;
+
+	// Note: Rows may be padded, so we need to copy them without the padding
+	let row_len = 3 * image.width;
+	let data = image
+		.pixels
+		.chunks(image.pitch)
+		.take(image.height)
+		.flat_map(|row| &row[..row_len])
+		.copied()
+		.collect();
+	let width = image.width.try_into().context("Image was too wide")?;
+	let height = image.height.try_into().context("Image was too tall")?;
+	let image = image::RgbImage::from_raw(width, height, data).context("Image had the wrong size")?;
+
+	Ok(DynamicImage::ImageRgb8(image))
+}
+
+/// Decodes a heif image, including heic and avif
+#[cfg(any(feature = "heic", feature = "avif"))]
+fn decode_heif(path: &Path) -> Result<DynamicImage, anyhow::Error> {
+	use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+	let path = path.to_str().context("Path wasn't utf-8")?;
+	let context = HeifContext::read_from_file(path).context("Unable to read image")?;
+	let handle = context.primary_image_handle().context("Unable to get primary image")?;
+	let image = LibHeif::new()
+		.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)
+		.context("Unable to decode image")?;
+
+	// Note: Rows may be padded, so we need to copy them without the padding
+	let plane = image.planes().interleaved.context("Image had no interleaved plane")?;
+	let row_len = 4 * plane.width as usize;
+	let data = plane
+		.data
+		.chunks(plane.stride)
+		.take(plane.height as usize)
+		.flat_map(|row| &row[..row_len])
+		.copied()
+		.collect();
+	let image = image::RgbaImage::from_raw(plane.width, plane.height, data).context("Image had the wrong size")?;
+
+	Ok(DynamicImage::ImageRgba8(image))
+}
+
+/// Decodes a jpeg-xl image
+#[cfg(feature = "jxl")]
+fn decode_jxl(path: &Path) -> Result<DynamicImage, anyhow::Error> {
+	use std::process::Command;
+
+	// Note: `djxl` can only write to files, so we decode to a temporary png, in our cache directory, if
+	//       possible, so it's private. Decodes may run concurrently, so each gets it's own file.
+	let out_dir = crate::storage::cache_dir().map_or_else(std::env::temp_dir, |cache_dir| cache_dir.join("decode"));
+	std::fs::create_dir_all(&out_dir).context("Unable to create decode directory")?;
+	let (_, out_path) =
+		crate::storage::create_unique(&out_dir, "jxl", ".png").context("Unable to create decoded image")?;
+	let image = Command::new("djxl")
+		.arg(path)
+		.arg(&out_path)
+		.output()
+		.context("Unable to run `djxl`")
+		.and_then(|output| {
+			anyhow::ensure!(
+				output.status.success(),
+				"`djxl` failed: {}",
+				String::from_utf8_lossy(&output.stderr).trim()
+			);
+			image::open(&out_path).context("Unable to open decoded image")
+		});
+
+	// Note: We remove it even if decoding failed, so it isn't left behind
+	let _ = std::fs::remove_file(&out_path);
+	image
+}
@@ -0,0 +1,135 @@
+//! Deduplication
+//!
+//! Keeps track of the contents of every loaded file, so copies of the same image
+//! spread across directories aren't shown more often than other images.
+//!
+//! Files are compared by a hash of their contents, and, optionally, by a perceptual
+//! hash of their image, which also matches re-encoded or resized copies.
+
+// Imports
+use crate::{args::DedupMode, images::Image};
+use anyhow::Context;
+use std::{
+	collections::{hash_map::DefaultHasher, HashMap},
+	hash::Hasher,
+	path::{Path, PathBuf},
+};
+
+/// Deduplication
+#[derive(Debug)]
+pub struct Dedup {
+	/// Mode
+	mode: DedupMode,
+
+	/// Path with each content hash
+	content_hashes: HashMap<u64, PathBuf>,
+
+	/// Perceptual hash of each path
+	perceptual_hashes: HashMap<PathBuf, u64>,
+}
+
+impl Dedup {
+	/// Maximum number of differing bits for perceptual hashes to match
+	const MAX_PERCEPTUAL_DISTANCE: u32 = 4;
+
+	/// Creates the deduplication with mode `mode`
+	pub fn new(mode: DedupMode) -> Self {
+		Self {
+			mode,
+			content_hashes: HashMap::new(),
+			perceptual_hashes: HashMap::new(),
+		}
+	}
+
+	/// Checks if the contents of `path`, loaded from `load_path`, are the same as any other path
+	/// for which `is_known` returns `true`, returning it.
+	pub fn check_contents(
+		&mut self, path: &Path, load_path: &Path, is_known: impl Fn(&Path) -> bool,
+	) -> Result<Option<PathBuf>, anyhow::Error> {
+		let contents = std::fs::read(load_path).context("Unable to read file")?;
+		let mut hasher = DefaultHasher::new();
+		hasher.write(&contents);
+		let hash = hasher.finish();
+
+		// Note: If the other path is no longer known, it's no longer a duplicate
+		match self.content_hashes.get(&hash) {
+			Some(other_path) if other_path != path && is_known(other_path) => Ok(Some(other_path.clone())),
+			_ => {
+				self.content_hashes.insert(hash, path.to_path_buf());
+				Ok(None)
+			},
+		}
+	}
+
+	/// Checks if `image`, loaded from `path`, looks the same as the image of any other path
+	/// for which `is_known` returns `true`, returning it.
+	///
+	/// Always returns `None` unless comparing perceptual hashes.
+	pub fn check_image(&mut self, path: &Path, image: &Image, is_known: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+		if self.mode != DedupMode::Perceptual {
+			return None;
+		}
+
+		let hash = self::perceptual_hash(image);
+		self.perceptual_hashes.retain(|other_path, _| is_known(other_path));
+		let other_path = self
+			.perceptual_hashes
+			.iter()
+			.find(|&(other_path, &other_hash)| {
+				other_path != path && (hash ^ other_hash).count_ones() <= Self::MAX_PERCEPTUAL_DISTANCE
+			})
+			.map(|(other_path, _)| other_path.clone());
+		if other_path.is_none() {
+			self.perceptual_hashes.insert(path.to_path_buf(), hash);
+		}
+
+		other_path
+	}
+}
+
+/// Calculates the perceptual hash of an image.
+///
+/// The image is averaged into a 9x8 grayscale grid, and each bit is set if a
+/// cell is brighter than the cell to it's right, so it's unaffected by resizing,
+/// re-encoding, or small changes in brightness.
+#[allow(clippy::suboptimal_flops)] // Keeping the formulas readable is more important
+fn perceptual_hash(image: &Image) -> u64 {
+	/// Grid width
+	const WIDTH: usize = 9;
+
+	/// Grid height
+	const HEIGHT: usize = 8;
+
+	/// Samples per cell, in each direction
+	const SAMPLES: usize = 4;
+
+	let (width, height) = image.dimensions();
+	let (width, height) = (width as usize, height as usize);
+	let bytes_per_pixel = image.bytes_per_pixel() as usize;
+	let raw = image.as_raw();
+	let luma = |x: usize, y: usize| {
+		let idx = (y * width + x) * bytes_per_pixel;
+		0.299 * f64::from(raw[idx]) + 0.587 * f64::from(raw[idx + 1]) + 0.114 * f64::from(raw[idx + 2])
+	};
+
+	// Note: We sample the center of each sample in the grid, instead of every pixel, as it's faster
+	let mut cells = [[0.0; WIDTH]; HEIGHT];
+	for (cell_y, row) in cells.iter_mut().enumerate() {
+		for (cell_x, cell) in row.iter_mut().enumerate() {
+			for sample_y in 0..SAMPLES {
+				for sample_x in 0..SAMPLES {
+					let x = ((cell_x * SAMPLES + sample_x) * 2 + 1) * width / (2 * WIDTH * SAMPLES);
+					let y = ((cell_y * SAMPLES + sample_y) * 2 + 1) * height / (2 * HEIGHT * SAMPLES);
+					*cell += luma(x, y);
+				}
+			}
+		}
+	}
+
+	cells
+		.iter()
+		.flat_map(|row| row.windows(2))
+		.enumerate()
+		.filter(|(_, cells)| cells[0] > cells[1])
+		.fold(0, |hash, (idx, _)| hash | (1 << idx))
+}
@@ -0,0 +1,353 @@
+//! Directory watcher
+//!
+//! Watches the images directory, forwarding it's events along with all existing files.
+//!
+//! If the directory is deleted and recreated, or it's filesystem is remounted, the
+//! watch dies without any further events, so it's re-established, retrying with a
+//! backoff until the directory exists again, and the directory is rescanned.
+//!
+//! On filesystems where events never arrive, such as network mounts, the directory may
+//! instead be periodically rescanned, sending the differences with the files found before.
+//!
+//! When ignore rules change, all files under them are sent again, so any files that are
+//! no longer ignored are picked up.
+//!
+//! Rescans may also be requested at any time, by sending [`DebouncedEvent::Rescan`].
+
+// Imports
+use crate::{ignores::Ignores, library::Library};
+use anyhow::Context;
+use notify::{DebouncedEvent, Watcher};
+use std::{
+	collections::HashSet,
+	os::unix::fs::MetadataExt,
+	path::{Path, PathBuf},
+	sync::mpsc,
+	thread,
+	time::{Duration, Instant},
+};
+
+/// Directory watcher
+pub struct DirWatcher {
+	/// Path being watched
+	path: PathBuf,
+
+	/// Identity of the directory being watched, if it exists
+	path_id: Option<PathId>,
+
+	/// Watch, if watching
+	watch: Option<Watch>,
+
+	/// Sender for the watch's events
+	watch_event_tx: mpsc::Sender<DebouncedEvent>,
+
+	/// Receiver for the watch's events, along with any requested rescans
+	watch_event_rx: mpsc::Receiver<DebouncedEvent>,
+
+	/// Interval between rescans, if any
+	rescan_interval: Option<Duration>,
+
+	/// All files sent
+	files: Library,
+
+	/// Sender for all events
+	event_tx: mpsc::Sender<DebouncedEvent>,
+}
+
+impl DirWatcher {
+	/// Interval to check if the directory was replaced
+	const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+	/// Delay for debouncing events
+	const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+	/// Maximum delay before retrying to watch the directory
+	const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+	/// Minimum delay before retrying to watch the directory
+	const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+	/// Creates a watcher for `path`, sending all events to `event_tx`.
+	///
+	/// If `watch` is `false`, the directory is only scanned, every `rescan_interval`, if any.
+	pub fn new(
+		path: PathBuf, event_tx: mpsc::Sender<DebouncedEvent>, watch: bool, rescan_interval: Option<Duration>,
+	) -> Result<Self, anyhow::Error> {
+		let (watch_event_tx, watch_event_rx) = mpsc::channel();
+		let watch = match watch {
+			true => Some(Watch::new(&path, watch_event_tx.clone())?),
+			false => None,
+		};
+
+		Ok(Self {
+			path_id: PathId::new(&path),
+			path,
+			watch,
+			watch_event_tx,
+			watch_event_rx,
+			rescan_interval,
+			files: Library::default(),
+			event_tx,
+		})
+	}
+
+	/// Returns a sender to request rescans through
+	pub fn rescan_tx(&self) -> mpsc::Sender<DebouncedEvent> {
+		self.watch_event_tx.clone()
+	}
+
+	/// Sends all existing files and then forwards all events, until the receiver is dropped
+	pub fn run(mut self) {
+		match self.run_inner() {
+			Ok(never) => never,
+			Err(_) => log::debug!("Event receiver was dropped, stopping directory watcher"),
+		}
+	}
+
+	/// Sends all existing files and then forwards all events
+	fn run_inner(&mut self) -> Result<!, mpsc::SendError<DebouncedEvent>> {
+		self.rescan()?;
+
+		let mut next_rescan = self
+			.rescan_interval
+			.map(|rescan_interval| Instant::now() + rescan_interval);
+		loop {
+			let timeout = next_rescan.map_or(Self::CHECK_INTERVAL, |next_rescan| {
+				next_rescan
+					.saturating_duration_since(Instant::now())
+					.min(Self::CHECK_INTERVAL)
+			});
+
+			match self.watch_event_rx.recv_timeout(timeout) {
+				Ok(event) => self.handle_event(event)?,
+
+				// Note: The directory might have been replaced without us getting any events,
+				//       such as when it's filesystem is remounted.
+				Err(mpsc::RecvTimeoutError::Timeout) => {
+					if self.watch.is_some() && PathId::new(&self.path) != self.path_id {
+						log::warn!("Directory {:?} was replaced", self.path);
+						self.rewatch()?;
+					}
+				},
+
+				Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!("Watch event sender was dropped"),
+			}
+
+			if next_rescan.map_or(false, |next_rescan| Instant::now() >= next_rescan) {
+				log::debug!("Rescanning directory {:?}", self.path);
+				self.rescan()?;
+				next_rescan = self
+					.rescan_interval
+					.map(|rescan_interval| Instant::now() + rescan_interval);
+			}
+		}
+	}
+
+	/// Handles an event from the watcher
+	fn handle_event(&mut self, event: DebouncedEvent) -> Result<(), mpsc::SendError<DebouncedEvent>> {
+		// If the directory itself was removed, the watch is gone
+		let lost_watch = match &event {
+			DebouncedEvent::Remove(path) | DebouncedEvent::Rename(path, _) => *path == self.path,
+			DebouncedEvent::Error(err, path) => {
+				log::warn!("Receiver error from directory watcher for {path:?}: {err}");
+				PathId::new(&self.path) != self.path_id
+			},
+
+			// Note: The watcher doesn't know what changed, so we need to find out
+			DebouncedEvent::Rescan => {
+				log::info!("Rescanning directory {:?}, as requested", self.path);
+				return self.rescan();
+			},
+			_ => false,
+		};
+
+		// If any ignore rules changed, files that were ignored might not be anymore
+		// Note: On renames, the rules file may have been either moved away or moved in
+		let rules_paths = match &event {
+			DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Remove(path) => vec![path],
+			DebouncedEvent::Rename(from, to) => vec![from, to],
+			_ => vec![],
+		};
+		let rules_dirs = rules_paths
+			.into_iter()
+			.filter(|path| Ignores::is_rules_file(path))
+			.filter_map(|path| path.parent().map(Path::to_path_buf))
+			.collect::<Vec<_>>();
+
+		self.send(event)?;
+		if lost_watch {
+			log::warn!("Lost watch on directory {:?}", self.path);
+			self.rewatch()?;
+		}
+		for rules_dir in rules_dirs {
+			self.resend(&rules_dir)?;
+		}
+
+		Ok(())
+	}
+
+	/// Re-establishes the watch, once the directory exists again, and rescans it
+	fn rewatch(&mut self) -> Result<(), mpsc::SendError<DebouncedEvent>> {
+		// Note: Drop the previous watch first, in case it's still holding onto the directory
+		self.watch = None;
+
+		let mut retry_delay = Self::MIN_RETRY_DELAY;
+		let watch = loop {
+			match Watch::new(&self.path, self.watch_event_tx.clone()) {
+				Ok(watch) => break watch,
+				Err(err) => {
+					log::warn!(
+						"Unable to watch directory {:?}, retrying in {retry_delay:?}: {err:?}",
+						self.path
+					);
+					thread::sleep(retry_delay);
+					retry_delay = (retry_delay * 2).min(Self::MAX_RETRY_DELAY);
+				},
+			}
+		};
+		log::info!("Re-established watch on directory {:?}", self.path);
+		self.watch = Some(watch);
+		self.path_id = PathId::new(&self.path);
+
+		// Note: We don't know what changed while we weren't watching
+		self.rescan()
+	}
+
+	/// Scans the directory, sending any files removed or added since the last scan
+	fn rescan(&mut self) -> Result<(), mpsc::SendError<DebouncedEvent>> {
+		let mut paths = vec![];
+		self::scan_dir(&self.path, &mut paths);
+		let paths_set = paths.iter().collect::<HashSet<_>>();
+
+		// Note: Directories are only sent by the watcher, and we don't want to remove their files
+		let removed_paths = self
+			.files
+			.paths()
+			.iter()
+			.filter(|path| !paths_set.contains(path) && !path.is_dir())
+			.cloned()
+			.collect::<Vec<_>>();
+		let added_paths = paths
+			.iter()
+			.filter(|path| !self.files.contains(path))
+			.cloned()
+			.collect::<Vec<_>>();
+		if !removed_paths.is_empty() || !added_paths.is_empty() {
+			log::info!(
+				"Found {} removed and {} added files in {:?}",
+				removed_paths.len(),
+				added_paths.len(),
+				self.path
+			);
+		}
+
+		for path in removed_paths {
+			self.send(DebouncedEvent::Remove(path))?;
+		}
+		for path in added_paths {
+			self.send(DebouncedEvent::Create(path))?;
+		}
+
+		Ok(())
+	}
+
+	/// Sends all known files within `dir` again
+	fn resend(&self, dir: &Path) -> Result<(), mpsc::SendError<DebouncedEvent>> {
+		// Note: Files already known by the receiver are just ignored
+		self.files
+			.paths()
+			.iter()
+			.filter(|path| path.starts_with(dir) && !Ignores::is_rules_file(path))
+			.try_for_each(|path| self.event_tx.send(DebouncedEvent::Create(path.clone())))
+	}
+
+	/// Sends an event, keeping track of all files sent
+	fn send(&mut self, event: DebouncedEvent) -> Result<(), mpsc::SendError<DebouncedEvent>> {
+		match &event {
+			DebouncedEvent::Create(path) => {
+				self.files.add(path.clone());
+			},
+			DebouncedEvent::Remove(path) => {
+				self.files.remove(path);
+			},
+			DebouncedEvent::Rename(from, to) => {
+				// Note: If we didn't know the path, it was moved in from outside
+				let renamed = self.files.rename(from, to);
+				if renamed.is_empty() {
+					self.files.add(to.clone());
+				}
+			},
+			_ => (),
+		}
+
+		self.event_tx.send(event)
+	}
+}
+
+/// Watch over a directory
+struct Watch {
+	/// Watcher
+	_watcher: notify::RecommendedWatcher,
+}
+
+impl Watch {
+	/// Starts watching `path`, sending all events to `event_tx`
+	fn new(path: &Path, event_tx: mpsc::Sender<DebouncedEvent>) -> Result<Self, anyhow::Error> {
+		let mut watcher =
+			notify::watcher(event_tx, DirWatcher::DEBOUNCE_DELAY).context("Unable to create directory watcher")?;
+		watcher
+			.watch(path, notify::RecursiveMode::Recursive)
+			.context("Unable to start watching directory")?;
+
+		Ok(Self { _watcher: watcher })
+	}
+}
+
+/// Identity of a path, to detect when it's replaced
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+struct PathId {
+	/// Device
+	dev: u64,
+
+	/// Inode
+	ino: u64,
+}
+
+impl PathId {
+	/// Returns the identity of `path`, if it exists
+	fn new(path: &Path) -> Option<Self> {
+		let metadata = std::fs::metadata(path).ok()?;
+		Some(Self {
+			dev: metadata.dev(),
+			ino: metadata.ino(),
+		})
+	}
+}
+
+/// Collects all files in directory `path`, recursively
+fn scan_dir(path: &Path, paths: &mut Vec<PathBuf>) {
+	let entries = match std::fs::read_dir(path) {
+		Ok(entries) => entries,
+		Err(err) => {
+			log::warn!("Unable to read directory {path:?}: {err}");
+			return;
+		},
+	};
+
+	for entry in entries {
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(err) => {
+				log::warn!("Unable to read directory entry in {path:?}: {err}");
+				continue;
+			},
+		};
+
+		match entry.file_type().map(|file_type| file_type.is_dir()) {
+			// Recurse on directories
+			Ok(true) => self::scan_dir(&entry.path(), paths),
+
+			// And collect files + others
+			Ok(false) => paths.push(entry.path()),
+
+			Err(err) => log::warn!("Unable to get file type of {:?}: {err}", entry.path()),
+		}
+	}
+}
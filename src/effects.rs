@@ -0,0 +1,47 @@
+//! Effects
+
+// Imports
+use crate::args::Args;
+
+/// Per-frame effects state.
+///
+/// Applied to every image in the fragment shader.
+#[derive(Clone, Copy, Debug)]
+pub struct Effects {
+	/// Brightness
+	pub brightness: f32,
+
+	/// Contrast
+	pub contrast: f32,
+
+	/// Gamma
+	pub gamma: f32,
+
+	/// Saturation
+	pub saturation: f32,
+
+	/// Color tint
+	pub tint: [f32; 3],
+}
+
+impl Effects {
+	/// Effects that leave images unchanged
+	pub const IDENTITY: Self = Self {
+		brightness: 1.0,
+		contrast:   1.0,
+		gamma:      1.0,
+		saturation: 1.0,
+		tint:       [1.0, 1.0, 1.0],
+	};
+
+	/// Creates the base effects from the arguments
+	pub const fn new(args: &Args) -> Self {
+		Self {
+			brightness: args.brightness,
+			contrast:   args.contrast,
+			gamma:      args.gamma,
+			saturation: args.saturation,
+			tint:       [1.0, 1.0, 1.0],
+		}
+	}
+}
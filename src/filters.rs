@@ -0,0 +1,49 @@
+//! Image filters
+
+// Imports
+use anyhow::Context;
+use std::path::Path;
+
+/// Filters for which images to display
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Filters {
+	/// Minimum resolution
+	pub min_resolution: Option<[u32; 2]>,
+
+	/// Maximum file size, in bytes
+	pub max_file_size: Option<u64>,
+}
+
+impl Filters {
+	/// Checks if `path` passes all filters, returning why it didn't otherwise.
+	///
+	/// Only the file's metadata and image header are read, so this is cheap
+	/// compared to decoding it.
+	pub fn check(&self, path: &Path) -> Result<(), anyhow::Error> {
+		if let Some(max_file_size) = self.max_file_size {
+			let file_size = std::fs::metadata(path).context("Unable to get file metadata")?.len();
+			anyhow::ensure!(
+				file_size <= max_file_size,
+				"File size {} is larger than {}",
+				file_size,
+				max_file_size
+			);
+		}
+
+		// Note: If we can't read the header, the file might not be an image, so we let the handler deal with it
+		if let Some([min_width, min_height]) = self.min_resolution {
+			if let Ok((width, height)) = image::image_dimensions(path) {
+				anyhow::ensure!(
+					width >= min_width && height >= min_height,
+					"Resolution {}x{} is smaller than {}x{}",
+					width,
+					height,
+					min_width,
+					min_height
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
@@ -1,9 +1,48 @@
 #version 330 core
 
 // Uniforms
-uniform sampler2D tex_sampler;
-uniform vec2 tex_offset;
-uniform float alpha;
+uniform sampler2D cur_tex_sampler;
+uniform vec2 cur_tex_start;
+uniform vec2 cur_tex_offset;
+uniform vec2 cur_tex_offset_delta;
+uniform vec2 cur_tex_scale;
+uniform bool cur_is_placeholder;
+uniform vec3 cur_letterbox_color;
+uniform sampler2D next_tex_sampler;
+uniform vec2 next_tex_start;
+uniform vec2 next_tex_offset;
+uniform vec2 next_tex_offset_delta;
+uniform vec2 next_tex_scale;
+uniform bool next_is_placeholder;
+uniform vec3 next_letterbox_color;
+uniform sampler2D exp_cur_tex_sampler;
+uniform vec2 exp_cur_tex_start;
+uniform vec2 exp_cur_tex_offset;
+uniform vec2 exp_cur_tex_offset_delta;
+uniform vec2 exp_cur_tex_scale;
+uniform bool exp_cur_is_placeholder;
+uniform vec3 exp_cur_letterbox_color;
+uniform sampler2D exp_next_tex_sampler;
+uniform vec2 exp_next_tex_start;
+uniform vec2 exp_next_tex_offset;
+uniform vec2 exp_next_tex_offset_delta;
+uniform vec2 exp_next_tex_scale;
+uniform bool exp_next_is_placeholder;
+uniform vec3 exp_next_letterbox_color;
+uniform float exp_fade;
+uniform int blend_mode;
+uniform float exposure_mix;
+uniform int scaling;
+uniform vec2 kaleidoscope;
+uniform vec3 bg_color;
+uniform uint interpolation_samples;
+uniform float fade;
+uniform bool fade_linear;
+uniform float brightness;
+uniform float contrast;
+uniform float gamma;
+uniform float saturation;
+uniform vec3 tint;
 
 // Inputs
 in vec2 frag_pos;
@@ -12,10 +51,145 @@ in vec2 frag_tex;
 // Outputs
 out vec4 color;
 
+// 4x4 bayer matrix for ordered dithering
+const float bayer[16] = float[16](
+	 0.0,  8.0,  2.0, 10.0,
+	12.0,  4.0, 14.0,  6.0,
+	 3.0, 11.0,  1.0,  9.0,
+	15.0,  7.0, 13.0,  5.0
+);
+
+// Converts from srgb to linear
+vec3 srgb_to_linear(vec3 c) {
+	return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+// Converts from linear to srgb
+vec3 linear_to_srgb(vec3 c) {
+	return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+// Samples a texture at `uvs`, with the origin on the bottom-left
+// Note: Images are uploaded with their first row at the top, so we flip them here
+//       instead of flipping them on the cpu when loading.
+vec4 sample_tex(sampler2D tex_sampler, vec2 uvs) {
+	return texture(tex_sampler, vec2(uvs.x, 1.0 - uvs.y));
+}
+
+// Samples a texture at `uvs` and mipmap level `lod`, with the origin on the bottom-left
+vec4 sample_tex_lod(sampler2D tex_sampler, vec2 uvs, float lod) {
+	return textureLod(tex_sampler, vec2(uvs.x, 1.0 - uvs.y), lod);
+}
+
+// Samples an image at `pos` according to the scaling
+vec3 sample_image(vec2 pos, sampler2D tex_sampler, vec2 tex_start, vec2 tex_offset, vec2 tex_offset_delta, vec2 tex_scale, vec3 letterbox_color) {
+	// Fill
+	// Note: We sample across the scroll until the next frame and blend them, so slow
+	//       scrolls look smooth even at low framerates.
+	if (scaling == 0) {
+		vec3 sum = vec3(0.0);
+		for (uint i = 0u; i < interpolation_samples; i++) {
+			vec2 offset = tex_offset + tex_offset_delta * (float(i) / float(interpolation_samples));
+			sum += srgb_to_linear(sample_tex(tex_sampler, pos * tex_start + offset).rgb);
+		}
+		return linear_to_srgb(sum / float(interpolation_samples));
+	}
+
+	// Tile
+	if (scaling == 5) {
+		return sample_tex(tex_sampler, pos * tex_scale).rgb;
+	}
+
+	// Fit, contain-blur, stretch and center
+	vec2 uvs = (pos - 0.5) * tex_scale + 0.5;
+	if (all(greaterThanEqual(uvs, vec2(0.0))) && all(lessThanEqual(uvs, vec2(1.0)))) {
+		return sample_tex(tex_sampler, uvs).rgb;
+	}
+
+	// If we're containing the image, use a blurred, stretched, copy of it outside of it's bounds.
+	if (scaling == 2) {
+		// Note: We blur by sampling a low mipmap level a few times around the pixel
+		vec2 tex_size = vec2(textureSize(tex_sampler, 0));
+		float lod = max(log2(max(tex_size.x, tex_size.y)) - 4.0, 0.0);
+		vec3 blurred = vec3(0.0);
+		for (int y = -1; y <= 1; y++) {
+			for (int x = -1; x <= 1; x++) {
+				blurred += sample_tex_lod(tex_sampler, pos + vec2(x, y) * 0.02, lod).rgb;
+			}
+		}
+
+		// Note: We darken it slightly so the actual image stands out
+		return 0.6 * blurred / 9.0;
+	}
+
+	return letterbox_color;
+}
+
+// Fades from `cur_color` to `next_color` by `fade`
+// Note: Unless requested otherwise, we blend in linear light, to avoid a brightness dip mid-fade
+vec3 fade_colors(vec3 cur_color, vec3 next_color, float fade) {
+	if (fade_linear) {
+		return linear_to_srgb(mix(srgb_to_linear(cur_color), srgb_to_linear(next_color), fade));
+	}
+
+	return mix(cur_color, next_color, fade);
+}
+
+// Blends `top` over `base` according to the blend mode
+vec3 blend_colors(vec3 base, vec3 top) {
+	// Multiply
+	if (blend_mode == 1) {
+		return base * top;
+	}
+
+	// Screen
+	if (blend_mode == 2) {
+		return 1.0 - (1.0 - base) * (1.0 - top);
+	}
+
+	// Overlay
+	return mix(2.0 * base * top, 1.0 - 2.0 * (1.0 - base) * (1.0 - top), step(0.5, base));
+}
+
 void main() {
-	// Get the texture
-	color = texture(tex_sampler, frag_tex + tex_offset);
+	// Mirror the position across the center on each kaleidoscope axis
+	vec2 pos = mix(frag_tex, min(frag_tex, 1.0 - frag_tex), kaleidoscope);
+
+	// Get both textures and blend them
+	// Note: Placeholders are shown as the background color
+	vec3 cur_color = bg_color;
+	if (!cur_is_placeholder) {
+		cur_color = sample_image(pos, cur_tex_sampler, cur_tex_start, cur_tex_offset, cur_tex_offset_delta, cur_tex_scale, cur_letterbox_color);
+	}
+	vec3 next_color = bg_color;
+	if (fade > 0.0 && !next_is_placeholder) {
+		next_color = sample_image(pos, next_tex_sampler, next_tex_start, next_tex_offset, next_tex_offset_delta, next_tex_scale, next_letterbox_color);
+	}
+	color = vec4(fade_colors(cur_color, next_color, fade), 1.0);
+
+	// Then blend the second exposure over it, if any
+	if (blend_mode != 0) {
+		vec3 exp_cur_color = bg_color;
+		if (!exp_cur_is_placeholder) {
+			exp_cur_color = sample_image(pos, exp_cur_tex_sampler, exp_cur_tex_start, exp_cur_tex_offset, exp_cur_tex_offset_delta, exp_cur_tex_scale, exp_cur_letterbox_color);
+		}
+		vec3 exp_next_color = bg_color;
+		if (exp_fade > 0.0 && !exp_next_is_placeholder) {
+			exp_next_color = sample_image(pos, exp_next_tex_sampler, exp_next_tex_start, exp_next_tex_offset, exp_next_tex_offset_delta, exp_next_tex_scale, exp_next_letterbox_color);
+		}
+		vec3 exp_color = fade_colors(exp_cur_color, exp_next_color, exp_fade);
+		color.rgb = mix(color.rgb, blend_colors(color.rgb, exp_color), exposure_mix);
+	}
+
+	// Apply the color adjustments
+	color.rgb = pow(color.rgb, vec3(1.0 / gamma));
+	color.rgb = (color.rgb - 0.5) * contrast + 0.5;
+	color.rgb *= brightness * tint;
+	float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+	color.rgb = clamp(mix(vec3(luma), color.rgb, saturation), 0.0, 1.0);
 
-	// Set alpha mixing
-	color.a = alpha;
+	// Then dither to avoid banding on smooth gradients
+	ivec2 bayer_pos = ivec2(gl_FragCoord.xy) % 4;
+	float threshold = (bayer[bayer_pos.y * 4 + bayer_pos.x] + 0.5) / 16.0 - 0.5;
+	color.rgb = clamp(color.rgb + threshold / 255.0, 0.0, 1.0);
 }
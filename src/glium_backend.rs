@@ -1,53 +1,71 @@
 //! Glium backend
 
 // Imports
-use crate::window::Window;
-use std::{ffi::CString, rc::Rc};
-use x11::glx;
+use std::{ffi::c_void, rc::Rc};
+
+/// Surface drawn to by open-gl, along with it's context
+pub trait GlSurface {
+	/// Surface size
+	fn size(&self) -> [u32; 2];
+
+	/// Scale factor, from the physical size of the output
+	fn scale_factor(&self) -> f32;
+
+	/// Processes all events
+	fn process_events(&self);
+
+	/// Swaps buffers
+	fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError>;
+
+	/// Returns the address of the open-gl function `name`, or null, if it doesn't exist
+	fn get_proc_address(&self, name: &str) -> *const c_void;
+
+	/// Returns if the gl context is current
+	fn is_context_current(&self) -> bool;
+
+	/// Makes the gl context current
+	fn make_context_current(&self) -> Result<(), anyhow::Error>;
+}
 
 /// Glium backend
 pub struct GliumBackend {
-	/// Window
-	window: Rc<Window>,
+	/// Surface
+	surface: Rc<dyn GlSurface>,
 }
 
 impl GliumBackend {
 	/// Creates a new glium backend
 	#[allow(clippy::unnecessary_wraps)] // We might in the future require some initialization here.
-	pub fn new(window: Rc<Window>) -> Result<Self, anyhow::Error> {
-		Ok(Self { window })
+	pub fn new<S: GlSurface + 'static>(surface: Rc<S>) -> Result<Self, anyhow::Error> {
+		Ok(Self { surface })
 	}
 }
 
 // SAFETY: The implementation of each function is safe
 unsafe impl glium::backend::Backend for GliumBackend {
 	fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError> {
-		self.window.swap_buffers();
-		Ok(())
+		self.surface.swap_buffers()
 	}
 
-	unsafe fn get_proc_address(&self, name: &str) -> *const std::ffi::c_void {
-		let name_cstr = CString::new(name).expect("Unable to create c-string from name");
-		// SAFETY: `glXGetProcAddressARB` should be safe to call with any string.
-		match unsafe { glx::glXGetProcAddressARB(name_cstr.as_ptr().cast()) } {
-			Some(f) => f as *const _,
-			None => {
-				log::warn!("Unable to load {name}");
-				std::ptr::null()
-			},
+	unsafe fn get_proc_address(&self, name: &str) -> *const c_void {
+		let f = self.surface.get_proc_address(name);
+		if f.is_null() {
+			log::warn!("Unable to load {name}");
 		}
+		f
 	}
 
 	fn get_framebuffer_dimensions(&self) -> (u32, u32) {
-		(self.window.width(), self.window.height())
+		let [width, height] = self.surface.size();
+		(width, height)
 	}
 
 	fn is_current(&self) -> bool {
-		self.window.is_context_current()
+		self.surface.is_context_current()
 	}
 
 	unsafe fn make_current(&self) {
-		self.window
+		self.surface
 			.make_context_current()
 			.expect("Unable to make context current");
 	}
@@ -6,23 +6,92 @@ use anyhow::Context as _;
 use glium::{
 	backend::{Context, Facade},
 	debug::DebugCallbackBehavior,
+	texture::{MipmapsOption, RawImage2d, TextureCreationError, UncompressedFloatFormat},
+	CapabilitiesSource, Texture2d,
 };
-use std::rc::Rc;
+use image::{imageops, DynamicImage, RgbImage, RgbaImage};
+use std::{cell::RefCell, collections::VecDeque, convert::TryInto, rc::Rc};
 
 /// Glium facade
 pub struct GliumFacade {
 	/// Context
 	context: Rc<Context>,
+
+	/// Unused textures, along with their format, from oldest to newest
+	unused_textures: RefCell<VecDeque<(Texture2d, UncompressedFloatFormat)>>,
 }
 
 impl GliumFacade {
+	/// Maximum number of unused textures kept for reuse
+	const MAX_UNUSED_TEXTURES: usize = 8;
+
 	/// Creates a new display
 	pub fn new(backend: GliumBackend) -> Result<Self, anyhow::Error> {
 		// SAFETY: The backend has a safe implementation.
 		let context = unsafe { Context::new(backend, true, DebugCallbackBehavior::PrintAll) }
 			.context("Unable to create context")?;
 
-		Ok(Self { context })
+		Ok(Self {
+			context,
+			unused_textures: RefCell::new(VecDeque::new()),
+		})
+	}
+
+	/// Creates an empty `width`x`height` texture with `format`, with mipmaps.
+	///
+	/// Reuses an unused texture of the same size and format, if any, instead of allocating a new one.
+	pub fn empty_texture(
+		&self, width: u32, height: u32, format: UncompressedFloatFormat,
+	) -> Result<Texture2d, TextureCreationError> {
+		let mut unused_textures = self.unused_textures.borrow_mut();
+		let idx = unused_textures
+			.iter()
+			.position(|(texture, texture_format)| texture.dimensions() == (width, height) && *texture_format == format);
+		match idx.and_then(|idx| unused_textures.remove(idx)) {
+			Some((texture, _)) => Ok(texture),
+			None => Texture2d::empty_with_format(self, format, MipmapsOption::AutoGeneratedMipmaps, width, height),
+		}
+	}
+
+	/// Returns a texture that's no longer used, so it may be reused.
+	///
+	/// Once there are too many unused textures, the oldest are dropped.
+	pub fn recycle_texture(&self, texture: Texture2d, format: UncompressedFloatFormat) {
+		let mut unused_textures = self.unused_textures.borrow_mut();
+		unused_textures.push_back((texture, format));
+		while unused_textures.len() > Self::MAX_UNUSED_TEXTURES {
+			unused_textures.pop_front();
+		}
+	}
+
+	/// Returns the maximum width and height of a texture
+	pub fn max_texture_size(&self) -> u32 {
+		// Note: The spec requires at least 1024, so it should never be negative
+		self.context
+			.get_capabilities()
+			.max_texture_size
+			.try_into()
+			.unwrap_or(1024)
+	}
+
+	/// Reads the last frame shown
+	pub fn read_front_buffer(&self) -> Result<RgbImage, anyhow::Error> {
+		let raw: RawImage2d<u8> = self
+			.context
+			.read_front_buffer()
+			.context("Unable to read front buffer")?;
+		let image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+			.context("Front buffer had an unexpected size")?;
+
+		// Note: Open-gl's origin is on the bottom-left, so we need to flip it, and
+		//       the alpha channel is never shown, so we drop it
+		let image = imageops::flip_vertical(&image);
+		Ok(DynamicImage::ImageRgba8(image).into_rgb8())
+	}
+
+	/// Returns if the context was lost, such as after a gpu reset, and everything must be recreated
+	pub fn is_context_lost(&self) -> bool {
+		self.context.is_context_lost()
 	}
 
 	/// Starts drawing
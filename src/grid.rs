@@ -0,0 +1,285 @@
+//! Grid
+//!
+//! Splits a window into the cells of a grid, separated by a gap and surrounded by a margin.
+//!
+//! Grids are made of uniform slots, and each cell covers a rectangle of them, so layouts may
+//! have cells of different sizes.
+
+// Imports
+use anyhow::Context;
+use cgmath::{Point2, Vector2};
+use rand::Rng;
+use std::{cmp, collections::BTreeMap, convert::TryFrom};
+
+/// Character of empty slots in a layout
+const EMPTY_SLOT: char = '.';
+
+/// Grid cell
+///
+/// Within a [`Layout`], the position and size are in slots, instead of pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+	/// Position of the bottom-left corner, in pixels, from the bottom-left of the window
+	pub pos: [u32; 2],
+
+	/// Size, in pixels
+	pub size: [u32; 2],
+}
+
+impl Cell {
+	/// Returns the scale and offset of the center of this cell, in normalized device coordinates,
+	/// within a `window_size` window
+	#[allow(clippy::cast_precision_loss)] // Windows are much smaller than 2^24
+	pub fn transform(&self, [window_width, window_height]: [u32; 2]) -> (Vector2<f32>, Point2<f32>) {
+		let [x, y] = self.pos;
+		let [width, height] = self.size;
+		let scale = Vector2::new(width as f32 / window_width as f32, height as f32 / window_height as f32);
+		let offset = Point2::new(
+			-1.0 + (2 * x + width) as f32 / window_width as f32,
+			-1.0 + (2 * y + height) as f32 / window_height as f32,
+		);
+
+		(scale, offset)
+	}
+
+	/// Returns if this cell contains the point `pos`
+	pub const fn contains(&self, [x, y]: [u32; 2]) -> bool {
+		let [cell_x, cell_y] = self.pos;
+		let [width, height] = self.size;
+		x >= cell_x && x < cell_x + width && y >= cell_y && y < cell_y + height
+	}
+
+	/// Returns the position of the top-left corner, in pixels, from the top-left of a `window_height` window
+	pub const fn top_left_pos(&self, window_height: u32) -> [u32; 2] {
+		[self.pos[0], window_height - self.pos[1] - self.size[1]]
+	}
+}
+
+/// Grid layout
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Layout {
+	/// Number of slots, horizontally and vertically
+	pub size: [u32; 2],
+
+	/// All cells, ordered from the bottom-left, by rows
+	pub cells: Vec<Cell>,
+}
+
+impl Layout {
+	/// Creates a `width`x`height` layout, with a cell on each slot
+	pub fn uniform([width, height]: [u32; 2]) -> Self {
+		let cells = (0..height)
+			.flat_map(|y| {
+				(0..width).map(move |x| Cell {
+					pos:  [x, y],
+					size: [1, 1],
+				})
+			})
+			.collect();
+
+		Self {
+			size: [width, height],
+			cells,
+		}
+	}
+
+	/// Creates a random collage on a `width`x`height` grid, with cells spanning different numbers of slots
+	pub fn collage(size: [u32; 2]) -> Self {
+		let mut cells = vec![];
+		self::split_collage(Cell { pos: [0, 0], size }, 0, &mut rand::thread_rng(), &mut cells);
+		cells.sort_by_key(|cell| [cell.pos[1], cell.pos[0]]);
+
+		Self { size, cells }
+	}
+
+	/// Parses an ascii layout.
+	///
+	/// Each line is a row of slots, from the top, and each character a slot. All slots with the same
+	/// character make up a cell, and must form a rectangle. Slots with `.` are left empty.
+	pub fn parse(layout: &str) -> Result<Self, anyhow::Error> {
+		let rows = layout
+			.lines()
+			.map(str::trim)
+			.filter(|row| !row.is_empty())
+			.map(|row| row.chars().collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+		let width = rows.first().map_or(0, Vec::len);
+		anyhow::ensure!(width != 0, "Layout must have at least 1 row");
+		if let Some((row_idx, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != width) {
+			anyhow::bail!(
+				"Layout rows must all have the same width, row {row_idx} has {}, instead of {width}",
+				row.len()
+			);
+		}
+
+		// Get the slots of each cell, with `y` growing upwards
+		let width = u32::try_from(width).context("Layout was too wide")?;
+		let height = u32::try_from(rows.len()).context("Layout was too tall")?;
+		let mut cell_slots = BTreeMap::<char, Vec<[u32; 2]>>::new();
+		for (y, row) in (0..height).rev().zip(&rows) {
+			for (x, &slot) in (0..width).zip(row) {
+				if slot != EMPTY_SLOT {
+					cell_slots.entry(slot).or_default().push([x, y]);
+				}
+			}
+		}
+
+		// Then make sure each cell is a rectangle
+		let mut cells = cell_slots
+			.into_iter()
+			.map(|(name, slots)| {
+				let (min_x, max_x, min_y, max_y) = slots
+					.iter()
+					.fold((u32::MAX, 0, u32::MAX, 0), |(min_x, max_x, min_y, max_y), &[x, y]| {
+						(min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+					});
+				let size = [max_x - min_x + 1, max_y - min_y + 1];
+				anyhow::ensure!(
+					slots.len() == size[0] as usize * size[1] as usize,
+					"Cell {:?} must be a rectangle",
+					name
+				);
+
+				Ok(Cell {
+					pos: [min_x, min_y],
+					size,
+				})
+			})
+			.collect::<Result<Vec<_>, anyhow::Error>>()?;
+		anyhow::ensure!(!cells.is_empty(), "Layout must have at least 1 cell");
+		cells.sort_by_key(|cell| [cell.pos[1], cell.pos[0]]);
+
+		Ok(Self {
+			size: [width, height],
+			cells,
+		})
+	}
+
+	/// Merges all cells within the `size` slots at `pos` into a single cell.
+	///
+	/// All cells within it must cover a single slot, so spans may not overlap.
+	pub fn span(&mut self, pos @ [x, y]: [u32; 2], size @ [width, height]: [u32; 2]) -> Result<(), anyhow::Error> {
+		anyhow::ensure!(width != 0 && height != 0, "Span must cover at least 1 slot");
+		anyhow::ensure!(
+			x.saturating_add(width) <= self.size[0] && y.saturating_add(height) <= self.size[1],
+			"Span {width}x{height} at {x},{y} doesn't fit the {}x{} grid",
+			self.size[0],
+			self.size[1]
+		);
+
+		let overlaps = |cell: &Cell| {
+			let [cell_x, cell_y] = cell.pos;
+			let [cell_width, cell_height] = cell.size;
+			cell_x < x + width && x < cell_x + cell_width && cell_y < y + height && y < cell_y + cell_height
+		};
+		anyhow::ensure!(
+			self.cells
+				.iter()
+				.filter(|cell| overlaps(cell))
+				.all(|cell| cell.size == [1, 1]),
+			"Span {}x{} at {},{} overlaps another span",
+			width,
+			height,
+			x,
+			y
+		);
+
+		self.cells.retain(|cell| !overlaps(cell));
+		self.cells.push(Cell { pos, size });
+		self.cells.sort_by_key(|cell| [cell.pos[1], cell.pos[0]]);
+
+		Ok(())
+	}
+
+	/// Returns the size of each slot on a `window_size` window.
+	///
+	/// Slots are `gap` pixels apart, and `margin` pixels away from the edges of the window. If they
+	/// don't fit, the size will be 0.
+	pub fn slot_size(&self, [window_width, window_height]: [u32; 2], gap: u32, margin: u32) -> [u32; 2] {
+		let [width, height] = self.size;
+		let slot_len = |window_len: u32, len: u32| {
+			window_len
+				.saturating_sub(margin.saturating_mul(2))
+				.saturating_sub((len - 1).saturating_mul(gap)) /
+				len
+		};
+
+		[slot_len(window_width, width), slot_len(window_height, height)]
+	}
+
+	/// Returns all cells, in pixels, on a `window_size` window.
+	///
+	/// See [`Layout::slot_size`] for the gap and margin.
+	pub fn window_cells(&self, window_size: [u32; 2], gap: u32, margin: u32) -> Vec<Cell> {
+		let [slot_width, slot_height] = self.slot_size(window_size, gap, margin);
+
+		self.cells
+			.iter()
+			.map(|cell| {
+				let [x, y] = cell.pos;
+				let [width, height] = cell.size;
+				Cell {
+					pos:  [margin + x * (slot_width + gap), margin + y * (slot_height + gap)],
+					size: [
+						width * slot_width + (width - 1) * gap,
+						height * slot_height + (height - 1) * gap,
+					],
+				}
+			})
+			.collect()
+	}
+}
+
+/// Splits `cell` of a collage, at `depth` splits deep, into `cells`
+///
+/// Cells are split in 2 along their longest side, until they're at most twice as long as they are wide,
+/// and then stop being split more often the smaller they are, so there's a mix of sizes, without any
+/// cell being too thin.
+fn split_collage(cell: Cell, depth: usize, rng: &mut impl Rng, cells: &mut Vec<Cell>) {
+	let [x, y] = cell.pos;
+	let [width, height] = cell.size;
+
+	// Note: The whole grid is always split, so it's not a single cell
+	let area = width * height;
+	let is_thin = width > 2 * height || height > 2 * width;
+	if area == 1 || (depth != 0 && !is_thin && rng.gen_ratio(2, area)) {
+		cells.push(cell);
+		return;
+	}
+
+	let split_width = match width.cmp(&height) {
+		cmp::Ordering::Less => false,
+		cmp::Ordering::Equal => rng.gen(),
+		cmp::Ordering::Greater => true,
+	};
+	let (lhs, rhs) = match split_width {
+		true => {
+			let lhs_width = rng.gen_range(1..width);
+			(
+				Cell {
+					pos:  [x, y],
+					size: [lhs_width, height],
+				},
+				Cell {
+					pos:  [x + lhs_width, y],
+					size: [width - lhs_width, height],
+				},
+			)
+		},
+		false => {
+			let lhs_height = rng.gen_range(1..height);
+			(
+				Cell {
+					pos:  [x, y],
+					size: [width, lhs_height],
+				},
+				Cell {
+					pos:  [x, y + lhs_height],
+					size: [width, height - lhs_height],
+				},
+			)
+		},
+	};
+	self::split_collage(lhs, depth + 1, rng, cells);
+	self::split_collage(rhs, depth + 1, rng, cells);
+}
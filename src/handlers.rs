@@ -0,0 +1,91 @@
+//! File type handlers
+
+// Imports
+use std::{collections::HashMap, path::Path};
+
+/// File type handler
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Handler {
+	/// Decodes the file as an image, playing all of it's frames, if animated
+	#[serde(alias = "animated")]
+	Image,
+
+	/// Decodes the file as an image, showing only it's first frame
+	Static,
+
+	/// Decodes the file as a video, with `ffmpeg`
+	#[cfg(feature = "video")]
+	Video,
+
+	/// Skips the file
+	Skip,
+}
+
+/// File type handlers.
+///
+/// Maps extensions (`gif`), mime types (`image/gif`) and mime type
+/// wildcards (`image/*`) to handlers, tried in that order.
+///
+/// When none match, videos use [`Handler::Video`], if enabled, and
+/// everything else uses [`Handlers::DEFAULT`].
+///
+/// Keys are case-insensitive, and extensions may start with a `.`.
+#[derive(Clone, Default, Debug, serde::Deserialize)]
+#[serde(from = "HashMap<String, Handler>")]
+pub struct Handlers {
+	/// All handlers
+	handlers: HashMap<String, Handler>,
+}
+
+impl Handlers {
+	/// Default handler, when none match
+	pub const DEFAULT: Handler = Handler::Image;
+
+	/// Returns the handler for `path`
+	pub fn get(&self, path: &Path) -> Handler {
+		let extension = path
+			.extension()
+			.and_then(|extension| extension.to_str())
+			.map(str::to_lowercase);
+		let mime = mime_guess::from_path(path).first();
+
+		let keys = [
+			extension,
+			mime.as_ref().map(|mime| mime.essence_str().to_owned()),
+			mime.as_ref().map(|mime| format!("{}/*", mime.type_())),
+		];
+
+		keys.iter()
+			.flatten()
+			.find_map(|key| self.handlers.get(key))
+			.copied()
+			.unwrap_or_else(|| Self::default_handler(mime.as_ref()))
+	}
+
+	/// Returns the default handler for a file with mime type `mime`
+	#[cfg_attr(not(feature = "video"), allow(unused_variables))] // `mime` is only used for videos
+	fn default_handler(mime: Option<&mime_guess::Mime>) -> Handler {
+		#[cfg(feature = "video")]
+		if mime.map_or(false, |mime| mime.type_() == mime_guess::mime::VIDEO) {
+			return Handler::Video;
+		}
+
+		Self::DEFAULT
+	}
+
+	/// Normalizes a key, so it matches regardless of case or a leading `.`
+	fn normalize_key(key: &str) -> String {
+		key.trim_start_matches('.').to_lowercase()
+	}
+}
+
+impl From<HashMap<String, Handler>> for Handlers {
+	fn from(handlers: HashMap<String, Handler>) -> Self {
+		let handlers = handlers
+			.into_iter()
+			.map(|(key, handler)| (Self::normalize_key(&key), handler))
+			.collect();
+		Self { handlers }
+	}
+}
@@ -0,0 +1,141 @@
+//! History
+//!
+//! Keeps track of the most recently loaded paths, optionally persisting
+//! them, so restarting zss doesn't immediately show the same images again,
+//! along with how many times each image was shown.
+
+// Imports
+use crate::storage;
+use std::{
+	collections::{BTreeMap, VecDeque},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+/// History
+#[derive(Debug)]
+pub struct History {
+	/// Path of the history file, if persisted
+	path: Option<PathBuf>,
+
+	/// Maximum number of paths
+	len: usize,
+
+	/// Recently loaded paths, from oldest to newest
+	recent: VecDeque<PathBuf>,
+}
+
+impl History {
+	/// Schema version of the history file
+	const VERSION: u32 = 1;
+
+	/// Creates a history of the last `len` paths, loading it from the state directory, if `persist`
+	pub fn load(len: usize, persist: bool) -> Self {
+		let path = match persist {
+			true => storage::state_dir().map(|state_dir| state_dir.join("history.toml")),
+			false => None,
+		};
+		let mut recent: VecDeque<PathBuf> = path
+			.as_deref()
+			.map(|path| storage::load(path, Self::VERSION))
+			.unwrap_or_default();
+		while recent.len() > len {
+			recent.pop_front();
+		}
+
+		Self { path, len, recent }
+	}
+
+	/// Returns all recent paths, from oldest to newest
+	pub fn recent(&self) -> impl Iterator<Item = &Path> {
+		self.recent.iter().map(PathBuf::as_path)
+	}
+
+	/// Records that `path` was loaded
+	pub fn push(&mut self, path: &Path) {
+		self.recent.retain(|recent_path| recent_path != path);
+		self.recent.push_back(path.to_path_buf());
+		while self.recent.len() > self.len {
+			self.recent.pop_front();
+		}
+
+		self.save();
+	}
+
+	/// Saves the history
+	fn save(&self) {
+		if let Some(path) = &self.path {
+			if let Err(err) = storage::save(path, Self::VERSION, &self.recent) {
+				log::warn!("Unable to save history to {path:?}: {err:?}");
+			}
+		}
+	}
+}
+
+/// Statistics of a shown image
+#[derive(Clone, Copy, Default, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ShowStats {
+	/// Number of times it was shown
+	pub shown: u64,
+
+	/// Total time spent loading it, in seconds
+	pub load_secs: f64,
+}
+
+impl ShowStats {
+	/// Returns the average time spent loading it
+	#[allow(clippy::cast_precision_loss)] // We don't need the exact average
+	pub fn average_load_duration(&self) -> Option<Duration> {
+		match self.shown {
+			0 => None,
+			shown => Some(Duration::from_secs_f64(self.load_secs / shown as f64)),
+		}
+	}
+}
+
+/// Show counts
+///
+/// Persists how many times each image was shown, and how long it took to load, for
+/// `zss stats`. Shared between all image loaders.
+#[derive(Clone, Debug)]
+pub struct ShowCounts {
+	/// Path of the show counts file
+	path: PathBuf,
+
+	/// Statistics of each image
+	stats: Arc<Mutex<BTreeMap<PathBuf, ShowStats>>>,
+}
+
+impl ShowCounts {
+	/// Schema version of the show counts file
+	const VERSION: u32 = 1;
+
+	/// Loads the show counts from the state directory, if any
+	pub fn load() -> Option<Self> {
+		let path = storage::state_dir()?.join("shows.toml");
+		let stats = storage::load(&path, Self::VERSION);
+
+		Some(Self {
+			path,
+			stats: Arc::new(Mutex::new(stats)),
+		})
+	}
+
+	/// Returns the statistics of all images
+	pub fn stats(&self) -> BTreeMap<PathBuf, ShowStats> {
+		self.stats.lock().expect("Show counts lock was poisoned").clone()
+	}
+
+	/// Records that `path` was shown, after taking `load_duration` to load
+	pub fn record(&self, path: &Path, load_duration: Duration) {
+		let mut stats = self.stats.lock().expect("Show counts lock was poisoned");
+		let image_stats = stats.entry(path.to_path_buf()).or_default();
+		image_stats.shown += 1;
+		image_stats.load_secs += load_duration.as_secs_f64();
+
+		if let Err(err) = storage::save(&self.path, Self::VERSION, &*stats) {
+			log::warn!("Unable to save show counts to {:?}: {err:?}", self.path);
+		}
+	}
+}
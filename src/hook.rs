@@ -0,0 +1,48 @@
+//! Hooks
+//!
+//! Commands ran, with `sh`, whenever a new image is shown, with `{path}` and `{panel}`
+//! replaced by the image's path and the index of the panel it's shown in.
+
+// Imports
+use anyhow::Context;
+use std::{path::Path, process::Command, thread};
+
+/// Hook
+#[derive(Clone, Debug)]
+pub struct Hook {
+	/// Script, with the placeholders replaced by it's arguments
+	script: String,
+}
+
+impl Hook {
+	/// Creates a hook for `command`
+	pub fn new(command: &str) -> Self {
+		// Note: The values are passed as arguments, so they don't need to be quoted
+		let script = command.replace("{path}", r#""$1""#).replace("{panel}", r#""$2""#);
+		Self { script }
+	}
+
+	/// Runs the hook for `path`, shown in `panel`, without waiting for it
+	pub fn run(&self, path: &Path, panel: usize) {
+		let res = Command::new("sh")
+			.arg("-c")
+			.arg(&self.script)
+			.arg("zss")
+			.arg(path)
+			.arg(panel.to_string())
+			.spawn()
+			.context("Unable to spawn hook");
+
+		// Note: We still need to wait on it, so it doesn't linger as a zombie
+		match res {
+			Ok(mut child) => {
+				thread::spawn(move || match child.wait() {
+					Ok(status) if !status.success() => log::warn!("Hook failed: {status}"),
+					Ok(_) => (),
+					Err(err) => log::warn!("Unable to wait for hook: {err}"),
+				});
+			},
+			Err(err) => log::warn!("Unable to run hook for {path:?}: {err:?}"),
+		}
+	}
+}
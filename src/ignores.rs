@@ -0,0 +1,135 @@
+//! Ignores
+
+// Imports
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{
+	collections::HashMap,
+	ffi::OsStr,
+	path::{Path, PathBuf},
+};
+
+/// Marker file that ignores it's whole directory
+pub const NO_MEDIA_FILE_NAME: &str = ".nomedia";
+
+/// Gitignore-style file with paths to ignore, relative to it's directory
+pub const ZSS_IGNORE_FILE_NAME: &str = ".zssignore";
+
+/// Ignore rules for the images directory.
+///
+/// Directories with a `.nomedia` file are ignored entirely, while `.zssignore`
+/// files are parsed as gitignore files relative to their directory. Rules
+/// are lazily read per-directory and cached until invalidated.
+#[derive(Debug)]
+pub struct Ignores {
+	/// Root directory
+	root: PathBuf,
+
+	/// Cached rules for each directory
+	dirs: HashMap<PathBuf, DirRules>,
+}
+
+impl Ignores {
+	/// Creates the ignore rules for `root`
+	pub fn new(root: PathBuf) -> Self {
+		Self {
+			root,
+			dirs: HashMap::new(),
+		}
+	}
+
+	/// Returns if `path` is an ignore rules file
+	pub fn is_rules_file(path: &Path) -> bool {
+		matches!(
+			path.file_name().and_then(OsStr::to_str),
+			Some(NO_MEDIA_FILE_NAME | ZSS_IGNORE_FILE_NAME)
+		)
+	}
+
+	/// Invalidates the cached rules of the directory containing the rules file `path`
+	pub fn invalidate(&mut self, path: &Path) {
+		if let Some(dir) = path.parent() {
+			self.dirs.remove(dir);
+		}
+	}
+
+	/// Returns if `path` is ignored
+	pub fn is_ignored(&mut self, path: &Path) -> bool {
+		// Note: The rules files themselves are never images
+		if Self::is_rules_file(path) {
+			return true;
+		}
+
+		// If the path isn't within our root, nothing applies
+		let relative_path = match path.strip_prefix(&self.root) {
+			Ok(relative_path) => relative_path,
+			Err(_) => return false,
+		};
+
+		// Then check every directory from the root to the path's, with deeper rules taking priority
+		let mut ignored = false;
+		let mut dir = self.root.clone();
+		let parent_components = relative_path.parent().into_iter().flat_map(Path::components);
+		for component in std::iter::once(None).chain(parent_components.map(Some)) {
+			if let Some(component) = component {
+				dir.push(component);
+			}
+
+			let rules = self
+				.dirs
+				.entry(dir.clone())
+				.or_insert_with_key(|dir| DirRules::new(dir));
+			if rules.no_media {
+				return true;
+			}
+
+			if let Some(gitignore) = &rules.gitignore {
+				let matched = gitignore.matched_path_or_any_parents(path, false);
+				if matched.is_ignore() {
+					ignored = true;
+				} else if matched.is_whitelist() {
+					ignored = false;
+				}
+			}
+		}
+
+		ignored
+	}
+}
+
+/// Rules for a directory
+#[derive(Debug)]
+struct DirRules {
+	/// If the directory has a `.nomedia` file
+	no_media: bool,
+
+	/// Gitignore from the directory's `.zssignore` file
+	gitignore: Option<Gitignore>,
+}
+
+impl DirRules {
+	/// Reads the rules of `dir`
+	fn new(dir: &Path) -> Self {
+		let no_media = dir.join(NO_MEDIA_FILE_NAME).exists();
+
+		let zss_ignore_path = dir.join(ZSS_IGNORE_FILE_NAME);
+		let gitignore = match zss_ignore_path.exists() {
+			true => {
+				let mut builder = GitignoreBuilder::new(dir);
+				if let Some(err) = builder.add(&zss_ignore_path) {
+					log::warn!("Unable to parse {zss_ignore_path:?}: {err}");
+				}
+
+				match builder.build() {
+					Ok(gitignore) => Some(gitignore),
+					Err(err) => {
+						log::warn!("Unable to build ignore rules from {zss_ignore_path:?}: {err}");
+						None
+					},
+				}
+			},
+			false => None,
+		};
+
+		Self { no_media, gitignore }
+	}
+}
@@ -0,0 +1,172 @@
+//! Image cache
+//!
+//! Caches loaded images on disk, already resized for the window, keyed by their
+//! path, modification time and the options they were loaded with, so restarting
+//! or cycling through a large library doesn't need to decode and resize them again.
+//!
+//! Once the cache grows over it's maximum size, the oldest entries are removed.
+
+// Imports
+use crate::{
+	images::{self, Frame, Image, LoadOptions, LoadedImage},
+	slide_info::{SlideInfo, SlideSource},
+	storage,
+};
+use anyhow::Context;
+use image::{RgbImage, RgbaImage};
+use std::{
+	collections::hash_map::DefaultHasher,
+	convert::TryInto,
+	hash::{Hash, Hasher},
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+/// Image cache
+#[derive(Debug)]
+pub struct ImageCache {
+	/// Path of the cached image
+	path: PathBuf,
+
+	/// Maximum size of the cache, in bytes
+	max_size: u64,
+}
+
+impl ImageCache {
+	/// Schema version of the cached images
+	const VERSION: u32 = 3;
+
+	/// Creates the cache for the image at `path`, loaded with `load_options`.
+	///
+	/// Returns `None` if no cache directory exists, or if the image's modification time is unavailable.
+	pub fn new(path: &Path, load_options: LoadOptions, max_size: u64) -> Option<Self> {
+		let cache_dir = storage::cache_dir()?;
+		let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+
+		let mut hasher = DefaultHasher::new();
+		Self::VERSION.hash(&mut hasher);
+		path.hash(&mut hasher);
+		modified.hash(&mut hasher);
+		load_options.window_size.hash(&mut hasher);
+		load_options.scaling.hash(&mut hasher);
+		load_options.resize_filter.hash(&mut hasher);
+		load_options.gpu_resize.hash(&mut hasher);
+		load_options.rgb.hash(&mut hasher);
+		load_options.max_texture_size.hash(&mut hasher);
+		let key = hasher.finish();
+
+		Some(Self {
+			path: cache_dir.join("images").join(format!("{key:016x}.bin")),
+			max_size,
+		})
+	}
+
+	/// Loads the cached image of `path`, which started loading at `load_start`
+	pub fn load(&self, path: &Path, load_start: Instant) -> Result<LoadedImage, anyhow::Error> {
+		let data = std::fs::read(&self.path).context("Unable to read cached image")?;
+		let mut reader = Reader { data: &data };
+
+		anyhow::ensure!(reader.u32()? == Self::VERSION, "Cached image had a different version");
+		let dimensions = [reader.u32()?, reader.u32()?];
+		let dominant_color = [reader.f32()?, reader.f32()?, reader.f32()?];
+		let frames_len = reader.u32()?;
+		let frames = (0..frames_len)
+			.map(|_| {
+				let (width, height) = (reader.u32()?, reader.u32()?);
+				let bytes_per_pixel = reader.u32()?;
+				let delay = Duration::from_micros(reader.u64()?);
+				let image_len = bytes_per_pixel as usize * width as usize * height as usize;
+				let data = reader.bytes(image_len)?.to_vec();
+				let image = match bytes_per_pixel {
+					4 => RgbaImage::from_raw(width, height, data).map(Image::Rgba),
+					3 => RgbImage::from_raw(width, height, data).map(Image::Rgb),
+					_ => anyhow::bail!("Cached frame had {} bytes per pixel", bytes_per_pixel),
+				}
+				.context("Cached frame had the wrong size")?;
+
+				Ok(Frame { image, delay })
+			})
+			.collect::<Result<Vec<_>, anyhow::Error>>()?;
+		anyhow::ensure!(!frames.is_empty(), "Cached image had no frames");
+		let palette = images::palette(&frames[0].image, images::PALETTE_LEN);
+
+		Ok(LoadedImage {
+			info: SlideInfo::new(path, dimensions, SlideSource::Directory, load_start),
+			frames,
+			dominant_color,
+			palette,
+			swap_dir: false,
+			#[cfg(feature = "video")]
+			video: None,
+		})
+	}
+
+	/// Stores an image in the cache, removing the oldest entries if it grows too large
+	pub fn store(&self, image: &LoadedImage) -> Result<(), anyhow::Error> {
+		let frames_size = image
+			.frames
+			.iter()
+			.map(|frame| 20 + frame.image.as_raw().len())
+			.sum::<usize>();
+		let mut data = Vec::with_capacity(36 + frames_size);
+		data.extend(Self::VERSION.to_le_bytes());
+		data.extend(image.info.dimensions.iter().flat_map(|dim| dim.to_le_bytes()));
+		data.extend(image.dominant_color.iter().flat_map(|color| color.to_le_bytes()));
+		let frames_len: u32 = image.frames.len().try_into().context("Image had too many frames")?;
+		data.extend(frames_len.to_le_bytes());
+		for frame in &image.frames {
+			let (width, height) = frame.image.dimensions();
+			data.extend(width.to_le_bytes());
+			data.extend(height.to_le_bytes());
+			let bytes_per_pixel = frame.image.bytes_per_pixel();
+			data.extend(bytes_per_pixel.to_le_bytes());
+			let delay: u64 = frame.delay.as_micros().try_into().context("Frame delay was too long")?;
+			data.extend(delay.to_le_bytes());
+			data.extend(frame.image.as_raw());
+		}
+
+		// Note: Images larger than the whole cache would just evict everything else
+		anyhow::ensure!(
+			(data.len() as u64) <= self.max_size,
+			"Image is larger than the maximum cache size"
+		);
+		storage::write_atomic(&self.path, &data).context("Unable to write cached image")?;
+
+		let cache_dir = self.path.parent().context("Cached image had no parent directory")?;
+		storage::evict(cache_dir, self.max_size).context("Unable to remove old cached images")
+	}
+}
+
+/// Reader over the cached data
+struct Reader<'a> {
+	/// Remaining data
+	data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	/// Reads `len` bytes
+	fn bytes(&mut self, len: usize) -> Result<&'a [u8], anyhow::Error> {
+		anyhow::ensure!(self.data.len() >= len, "Cached image was too small");
+		let (bytes, rest) = self.data.split_at(len);
+		self.data = rest;
+		Ok(bytes)
+	}
+
+	/// Reads a `u32`
+	fn u32(&mut self) -> Result<u32, anyhow::Error> {
+		let bytes = self.bytes(4)?;
+		Ok(u32::from_le_bytes(bytes.try_into().expect("Slice had wrong length")))
+	}
+
+	/// Reads a `u64`
+	fn u64(&mut self) -> Result<u64, anyhow::Error> {
+		let bytes = self.bytes(8)?;
+		Ok(u64::from_le_bytes(bytes.try_into().expect("Slice had wrong length")))
+	}
+
+	/// Reads a `f32`
+	fn f32(&mut self) -> Result<f32, anyhow::Error> {
+		let bytes = self.bytes(4)?;
+		Ok(f32::from_le_bytes(bytes.try_into().expect("Slice had wrong length")))
+	}
+}
@@ -1,207 +1,1161 @@
 //! Images
 
 // Imports
+use crate::{
+	args::{DedupMode, ResizeFilter, Scaling},
+	dedup::Dedup,
+	dir_watcher::DirWatcher,
+	filters::Filters,
+	handlers::{Handler, Handlers},
+	history::ShowCounts,
+	ignores::Ignores,
+	image_cache::ImageCache,
+	marked_paths::MarkedPaths,
+	online::Online,
+	path_list,
+	provider::{ImageProvider, LibraryProvider, ProvidedImage, ReplayProvider},
+	quarantine::Quarantine,
+	remote,
+	replay::{Recorder, ReplayImage},
+	schedule::Schedule,
+	selection::SelectionStrategy,
+	slide_info::{Shape, SlideInfo, SlideSource},
+};
 use anyhow::Context;
-use image::{imageops::FilterType, GenericImageView, ImageBuffer, Rgba};
-use notify::Watcher;
+use image::{
+	codecs::{gif::GifDecoder, png::PngDecoder},
+	imageops::FilterType,
+	AnimationDecoder, DynamicImage, GenericImageView, ImageFormat, Rgb, RgbImage, Rgba, RgbaImage,
+};
 use num_rational::Ratio;
-use rand::prelude::SliceRandom;
 use std::{
-	cmp::Ordering,
+	cmp::{Ordering, Reverse},
+	collections::{BTreeSet, HashMap, VecDeque},
+	convert::TryInto,
+	error::Error,
+	fmt, io,
 	path::{Path, PathBuf},
-	sync::mpsc::{self, RecvError, SendError},
+	sync::{
+		atomic::{self, AtomicBool},
+		mpsc, Arc, Condvar, Mutex, PoisonError,
+	},
 	thread,
-	time::Duration,
+	time::{Duration, Instant},
 };
 
-/// Image type
-type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+/// Image
+#[derive(Debug)]
+pub enum Image {
+	/// Rgba
+	Rgba(RgbaImage),
+
+	/// Rgb, without alpha
+	Rgb(RgbImage),
+}
+
+impl Image {
+	/// Returns the width and height of this image
+	pub fn dimensions(&self) -> (u32, u32) {
+		match self {
+			Self::Rgba(image) => image.dimensions(),
+			Self::Rgb(image) => image.dimensions(),
+		}
+	}
+
+	/// Returns the number of bytes per pixel
+	pub const fn bytes_per_pixel(&self) -> u32 {
+		match self {
+			Self::Rgba(_) => 4,
+			Self::Rgb(_) => 3,
+		}
+	}
+
+	/// Returns the raw pixel data of this image
+	pub fn as_raw(&self) -> &[u8] {
+		match self {
+			Self::Rgba(image) => image.as_raw(),
+			Self::Rgb(image) => image.as_raw(),
+		}
+	}
+}
+
+/// Image frame
+#[derive(Debug)]
+pub struct Frame {
+	/// Image
+	pub image: Image,
+
+	/// Delay until the next frame
+	pub delay: Duration,
+}
+
+/// Loaded image, along with it's metadata
+#[derive(Debug)]
+pub struct LoadedImage {
+	/// Info
+	pub info: SlideInfo,
+
+	/// All frames
+	///
+	/// Non-animated images only have a single frame, but there
+	/// is always at least 1 frame.
+	pub frames: Vec<Frame>,
+
+	/// Dominant color
+	pub dominant_color: [f32; 3],
+
+	/// Palette, from the most to least common color
+	pub palette: Vec<[f32; 3]>,
+
+	/// If the scroll direction should be swapped
+	///
+	/// Chosen by [`Images`] when the image is retrieved.
+	pub swap_dir: bool,
+
+	/// Video, if any
+	///
+	/// Streams all frames after the first.
+	#[cfg(feature = "video")]
+	pub video: Option<crate::video::Video>,
+}
+
+impl LoadedImage {
+	/// Returns the memory used by all frames, in bytes
+	pub fn memory_size(&self) -> u64 {
+		self.frames.iter().map(|frame| frame.image.as_raw().len() as u64).sum()
+	}
+}
+
+/// Options for loading images
+#[derive(Clone, Copy, Debug)]
+pub struct LoadOptions {
+	/// Window size
+	pub window_size: [u32; 2],
+
+	/// Scaling
+	pub scaling: Scaling,
+
+	/// Resize filter
+	pub resize_filter: ResizeFilter,
+
+	/// If images should be resized on the gpu, instead of here
+	pub gpu_resize: bool,
+
+	/// If images should be kept as rgb, without alpha
+	pub rgb: bool,
+
+	/// Maximum texture width and height, if any
+	pub max_texture_size: Option<u32>,
+
+	/// Maximum size of the image cache, in bytes, or `0` to not cache images
+	pub cache_size: u64,
+}
+
+/// Source of the paths to load
+#[derive(Debug)]
+pub enum PathSource {
+	/// Directory, along with all files within it
+	Dir {
+		/// Path of the directory
+		path: PathBuf,
+
+		/// If the directory should be watched
+		watch: bool,
+
+		/// Interval between rescans of the directory, if any
+		rescan_interval: Option<Duration>,
+
+		/// Schedule of directories to show instead, depending on the time of day
+		schedule: Schedule,
+	},
+
+	/// Newline-separated paths, or urls, read from a file, or `stdin`, if `-`
+	List(PathBuf),
+
+	/// Urls
+	Urls(Vec<String>),
+}
 
 /// Images
 pub struct Images {
 	/// Receiver end for the image loading.
-	image_rx: mpsc::Receiver<Image>,
+	image_rx: mpsc::Receiver<SentImage>,
+
+	/// Memory budget of the backlog, if any
+	budget: Option<Arc<BacklogBudget>>,
+
+	/// Path generations
+	generations: Arc<Generations>,
+
+	/// Number of times we've been polled for an image
+	polls: u64,
+
+	/// If images are routed to panels of the same shape
+	aspect_routing: bool,
+
+	/// Images received, but held back for a panel of their shape
+	routed: VecDeque<LoadedImage>,
+
+	/// Remaining images, if replaying
+	replay: Option<VecDeque<ReplayImage>>,
+
+	/// Recorder, if any
+	recorder: Option<Recorder>,
+
+	/// Show counts, if any
+	show_counts: Option<ShowCounts>,
 
-	/// Watcher
-	_watcher: notify::RecommendedWatcher,
+	/// Sender to request rescans of the images directory, if any
+	rescan_tx: Option<mpsc::Sender<notify::DebouncedEvent>>,
+
+	/// If the loader finished providing images
+	loader_finished: Arc<AtomicBool>,
+
+	/// Error the loader failed with, if any
+	loader_error: Arc<Mutex<Option<anyhow::Error>>>,
+
+	/// Set to interrupt waiting for images, if any
+	interrupt: Option<Arc<AtomicBool>>,
+
+	/// Disconnected once the loader stops
+	loader_stopped_rx: mpsc::Receiver<!>,
+
+	/// If all images were received
+	finished: bool,
+
+	/// If the loader failed
+	failed: bool,
 }
 
 impl Images {
+	/// Interval between checks for interruptions, while waiting for an image
+	const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+	/// Maximum number of images held back while routing
+	// Note: Past this, any panel gets the oldest image held back, so we don't stall if there are only
+	//       images of a single shape.
+	const MAX_ROUTED: usize = 8;
+
 	/// Starts loading images in the background and returns the
 	/// instance to retrieve them from.
-	pub fn new(path: PathBuf, image_backlog: usize, window_size: [u32; 2]) -> Result<Self, anyhow::Error> {
-		// Create the event channel
-		let (event_tx, event_rx) = mpsc::channel();
-		let mut existing_tx = event_tx.clone();
+	#[allow(clippy::too_many_arguments)] // TODO: Refactor
+	pub fn new(
+		source: PathSource, online: Online, image_backlog: usize, max_backlog_memory: Option<u64>,
+		load_options: LoadOptions, handlers: Handlers, filters: Filters, decode_timeout: Duration,
+		quarantine: Quarantine, banned: Arc<MarkedPaths>, only: Option<Arc<MarkedPaths>>,
+		selection: Box<dyn SelectionStrategy>, dedup: Option<DedupMode>, max_passes: Option<usize>,
+	) -> Result<Self, anyhow::Error> {
+		// Note: The watcher always reports absolute paths, so we make sure our path is too
+		let current_dir = std::env::current_dir().context("Unable to get current directory")?;
 
-		// Then start the watcher and start watching the path
-		let mut watcher =
-			notify::watcher(event_tx, Duration::from_secs(2)).context("Unable to create directory watcher")?;
-		watcher
-			.watch(&path, notify::RecursiveMode::Recursive)
-			.context("Unable to start watching directory")?;
+		// Start watching the path and send all existing files, or read the paths from the list
+		let (raw_event_tx, raw_event_rx) = mpsc::channel();
+		if !online.sources.is_empty() {
+			let online_event_tx = raw_event_tx.clone();
+			thread::spawn(move || online.run(&online_event_tx));
+		}
+		let generations = Arc::new(Generations::default());
+		let (path, rescan_tx, active_dir) = match source {
+			PathSource::Dir {
+				path,
+				watch,
+				rescan_interval,
+				schedule,
+			} => {
+				let path = current_dir.join(path);
+				let watcher = DirWatcher::new(path.clone(), raw_event_tx.clone(), watch, rescan_interval)?;
+				let rescan_tx = watcher.rescan_tx();
+				thread::spawn(move || watcher.run());
 
-		// Send existing files over the sender
-		thread::spawn(move || {
-			/// Sends all files in directory `dir`
-			fn send_files_dir(path: &Path, tx: &mut mpsc::Sender<notify::DebouncedEvent>) -> Result<(), anyhow::Error> {
-				for entry in std::fs::read_dir(path).context("Unable to read directory")? {
-					let entry = entry.context("Unable to read directory entry")?;
-					let file_type = entry.file_type().context("Unable to get entry file type")?;
-
-					match file_type.is_dir() {
-						// Recurse on directories
-						true => send_files_dir(&entry.path(), tx).context("Unable to send files for sub-directory")?,
-
-						// And send files + others
-						false => {
-							// Try to send it, or just quit else
-							if tx.send(notify::DebouncedEvent::Create(entry.path())).is_err() {
-								return Ok(());
-							}
-						},
+				// If we have a schedule, also watch all of it's directories, and keep the active one up to date
+				let active_dir = match schedule.is_empty() {
+					true => None,
+					false => {
+						for dir in schedule.dirs() {
+							let watcher =
+								DirWatcher::new(dir.to_path_buf(), raw_event_tx.clone(), watch, rescan_interval)
+									.with_context(|| format!("Unable to watch scheduled directory {dir:?}"))?;
+							thread::spawn(move || watcher.run());
+						}
+
+						let dir = schedule.active_dir(chrono::Local::now().time()).unwrap_or(&path);
+						log::info!("Showing images in {dir:?}");
+						let active_dir = Arc::new(Mutex::new(dir.to_path_buf()));
+						let schedule_active_dir = Arc::clone(&active_dir);
+						let schedule_generations = Arc::clone(&generations);
+						let fallback = path.clone();
+						thread::spawn(move || {
+							schedule.run(&fallback, &schedule_active_dir, &schedule_generations, &raw_event_tx);
+						});
+						Some(active_dir)
+					},
+				};
+
+				(path, Some(rescan_tx), active_dir)
+			},
+			PathSource::List(list_path) => {
+				thread::spawn(move || {
+					match path_list::read(&list_path, &raw_event_tx) {
+						Ok(()) => log::info!("Finished reading paths from {list_path:?}"),
+						Err(err) => log::warn!("Unable to read paths from {list_path:?}: {err:?}"),
 					}
+					self::keep_alive(raw_event_tx)
+				});
+				(current_dir, None, None)
+			},
+			PathSource::Urls(urls) => {
+				thread::spawn(move || {
+					remote::fetch_all(&urls, &raw_event_tx);
+					self::keep_alive(raw_event_tx)
+				});
+				(current_dir, None, None)
+			},
+		};
+
+		// Invalidate any removed paths as soon as we're notified, even while the loader is busy,
+		// so their in-flight and already loaded images are dropped.
+		let (event_tx, event_rx) = mpsc::channel();
+		let watcher_generations = Arc::clone(&generations);
+		thread::spawn(move || {
+			for event in raw_event_rx {
+				match &event {
+					notify::DebouncedEvent::Remove(path) | notify::DebouncedEvent::Rename(path, _) => {
+						watcher_generations.invalidate(path);
+					},
+					_ => (),
 				}
 
-				Ok(())
+				if event_tx.send(event).is_err() {
+					return;
+				}
 			}
-
-			send_files_dir(&path, &mut existing_tx).expect("Unable to load exiting files");
 		});
 
+		// Then load them all
+		let provider = LibraryProvider::new(
+			event_rx,
+			load_options,
+			Ignores::new(path),
+			handlers,
+			filters,
+			decode_timeout,
+			quarantine,
+			banned,
+			only,
+			active_dir,
+			selection,
+			dedup.map(Dedup::new),
+			Arc::clone(&generations),
+		)
+		.with_max_passes(max_passes);
 
-		// Start loading them in a background thread
-		let (image_tx, image_rx) = mpsc::sync_channel(image_backlog);
+		Ok(Self {
+			rescan_tx,
+			..Self::from_provider(provider, generations, image_backlog, max_backlog_memory)
+		})
+	}
+
+	/// Starts loading the images of a replay in the background and returns
+	/// the instance to retrieve them from, in the same order they were recorded.
+	pub fn from_replay(
+		replay_images: VecDeque<ReplayImage>, image_backlog: usize, max_backlog_memory: Option<u64>,
+		load_options: LoadOptions, handlers: Handlers, decode_timeout: Duration,
+	) -> Self {
+		let paths = replay_images.iter().map(|image| image.path.clone()).collect();
+		let provider = ReplayProvider::new(paths, load_options, handlers, decode_timeout);
+
+		Self {
+			replay: Some(replay_images),
+			..Self::from_provider(
+				provider,
+				Arc::new(Generations::default()),
+				image_backlog,
+				max_backlog_memory,
+			)
+		}
+	}
+
+	/// Starts running `provider` in the background and returns the
+	/// instance to retrieve it's images from.
+	///
+	/// Any images whose path was invalidated in `generations` after they
+	/// started loading are dropped.
+	pub fn from_provider(
+		provider: impl ImageProvider + 'static, generations: Arc<Generations>, image_backlog: usize,
+		max_backlog_memory: Option<u64>,
+	) -> Self {
+		let (image_tx, image_rx, budget) = self::image_channel(image_backlog, max_backlog_memory);
+		let loader_finished = Arc::new(AtomicBool::new(false));
+		let provider_finished = Arc::clone(&loader_finished);
+		let loader_error = Arc::new(Mutex::new(None));
+		let provider_error = Arc::clone(&loader_error);
+		let (loader_stopped_tx, loader_stopped_rx) = mpsc::channel();
 		thread::spawn(move || {
-			self::image_loader(event_rx, window_size, image_tx).expect("Background thread returned `Err`")
+			// Note: Dropped once we stop, even if we panic, which disconnects the receiver
+			let _loader_stopped_tx = loader_stopped_tx;
+
+			// Note: On errors, the image sender is dropped once we return, so the receiver
+			//       sees the disconnect and may retrieve the error
+			match self::run_provider(provider, &image_tx) {
+				Ok(()) => {
+					log::info!("Finished loading all images");
+					provider_finished.store(true, atomic::Ordering::Release);
+				},
+				Err(ImageLoaderError::SendImage) => log::debug!("Image receiver was dropped, stopping image loader"),
+				Err(ImageLoaderError::Provider(err)) => {
+					*provider_error.lock().expect("Loader error lock was poisoned") = Some(err);
+				},
+			}
 		});
 
-		Ok(Self {
+		Self {
 			image_rx,
-			_watcher: watcher,
-		})
+			budget,
+			generations,
+			polls: 0,
+			aspect_routing: false,
+			routed: VecDeque::new(),
+			replay: None,
+			recorder: None,
+			show_counts: None,
+			rescan_tx: None,
+			loader_finished,
+			loader_error,
+			interrupt: None,
+			loader_stopped_rx,
+			finished: false,
+			failed: false,
+		}
 	}
 
-	/// Returns the next image, waiting if not yet available
-	pub fn next_image(&mut self) -> Image {
-		self.image_rx.recv().expect("Loading thread panicked")
+	/// Records all images retrieved to `recorder`
+	pub fn set_recorder(&mut self, recorder: Recorder) {
+		self.recorder = Some(recorder);
 	}
 
-	/// Returns the next image, returning `None` if not yet loaded
-	pub fn try_next_image(&mut self) -> Option<Image> {
-		match self.image_rx.try_recv() {
-			// if we got it, return it
-			Ok(image) => Some(image),
+	/// Records how many times each image retrieved is shown to `show_counts`
+	pub fn set_show_counts(&mut self, show_counts: ShowCounts) {
+		self.show_counts = Some(show_counts);
+	}
 
-			// If it wasn't ready, return `None`
-			Err(mpsc::TryRecvError::Empty) => None,
+	/// Routes images to panels of the same shape, holding back images of other shapes
+	///
+	/// Replays are always retrieved in the order they were recorded.
+	pub fn set_aspect_routing(&mut self, aspect_routing: bool) {
+		self.aspect_routing = aspect_routing;
+	}
 
-			// If unable to, wait and increase the timeout
-			Err(mpsc::TryRecvError::Disconnected) => panic!("Loading thread panicked"),
+	/// Rescans the images directory, returning `false` if there isn't one
+	pub fn rescan(&self) -> bool {
+		// Note: If the watcher stopped, there's nothing to rescan anyway
+		match &self.rescan_tx {
+			Some(rescan_tx) => {
+				let _ = rescan_tx.send(notify::DebouncedEvent::Rescan);
+				true
+			},
+			None => false,
 		}
 	}
-}
 
-/// Image loader to run in a background thread
-#[allow(clippy::needless_pass_by_value)] // It's better for this function to own the sender
-fn image_loader(
-	event_rx: mpsc::Receiver<notify::DebouncedEvent>, window_size: [u32; 2], image_tx: mpsc::SyncSender<Image>,
-) -> Result<!, ImageLoaderError> {
-	let mut paths = vec![];
+	/// Stops waiting for images once `interrupt` is set, returning an error instead
+	pub fn set_interrupt(&mut self, interrupt: Arc<AtomicBool>) {
+		self.interrupt = Some(interrupt);
+	}
 
-	loop {
-		// Receives the next event, waiting if we're empty
-		let next_event = |is_empty| match is_empty {
-			true => {
-				log::warn!("No images found, waiting for new files");
-				Ok(Some(event_rx.recv()?))
-			},
-			false => match event_rx.try_recv() {
-				Ok(path) => Ok(Some(path)),
-				Err(mpsc::TryRecvError::Disconnected) => Err(mpsc::RecvError),
-				Err(mpsc::TryRecvError::Empty) => Ok(None),
-			},
-		};
+	/// Returns if the loader finished and all of it's images were retrieved
+	pub fn is_finished(&self) -> bool {
+		self.finished && self.routed.is_empty()
+	}
 
-		// Check for new paths, or, if we're out, wait
-		while let Some(event) = next_event(paths.is_empty()).map_err(ImageLoaderError::ReceiveEvent)? {
-			// Note: No need to match `Remove`, the `drain_filter` below will remove it.
-			// Note: On `Rename`, the original path will be removed by the `drain_filter` below
-			match event {
-				notify::DebouncedEvent::Create(path) | notify::DebouncedEvent::Rename(_, path) => {
-					log::info!("Adding {path:?}");
-					paths.push(path);
-				},
-				notify::DebouncedEvent::Error(err, path) => {
-					log::warn!("Receiver error from directory watcher for {path:?}: {err}");
-				},
-				_ => (),
+	/// Returns if the loader failed, so no more images will arrive
+	pub const fn has_failed(&self) -> bool {
+		self.failed
+	}
+
+	/// Returns if we're replaying and have no more images
+	pub fn is_replay_finished(&self) -> bool {
+		self.replay.as_ref().map_or(false, VecDeque::is_empty)
+	}
+
+	/// Stops loading images, waiting up to `timeout` for the loader to stop.
+	///
+	/// Returns if the loader stopped in time. If not, it's left running in the background.
+	pub fn stop(self, timeout: Duration) -> bool {
+		// Note: The loader stops once it fails to send it's next image, so we drop the receiver and
+		//       release the whole budget, in case it's waiting for room in the backlog.
+		drop(self.image_rx);
+		if let Some(budget) = &self.budget {
+			budget.release(u64::MAX);
+		}
+
+		matches!(
+			self.loader_stopped_rx.recv_timeout(timeout),
+			Err(mpsc::RecvTimeoutError::Disconnected)
+		)
+	}
+
+	/// Returns the next image, for a panel of `shape`, waiting if not yet available
+	pub fn next_image(&mut self, shape: Shape) -> Result<LoadedImage, anyhow::Error> {
+		self.polls += 1;
+		if let Some(replay) = &mut self.replay {
+			let replay_image = replay.pop_front().context("Replay ran out of images")?;
+			if replay_image.poll != self.polls {
+				log::warn!(
+					"Replayed image was retrieved on poll {}, but was recorded on poll {}",
+					self.polls,
+					replay_image.poll
+				);
 			}
+
+			let mut image = self.recv_image()?;
+			image.swap_dir = replay_image.swap_dir;
+			return Ok(image);
 		}
 
-		// Shuffles all paths
-		log::info!("Shuffling all files");
-		paths.shuffle(&mut rand::thread_rng());
-		log::info!("Shuffled {} files", paths.len());
-
-		// Then load them all and send them
-		let mut send_err = None;
-		paths.drain_filter(|path| {
-			// If we have a sending error, just return
-			if send_err.is_some() {
-				return false;
+		let image = loop {
+			if let Some(image) = self.take_routed(shape) {
+				break image;
 			}
 
-			// ELse try to load it
-			let image = match self::load_img(path, window_size) {
-				Ok(value) => value,
-				Err(err) => {
-					log::info!("Unable to load {path:?}: {err}");
-					return true;
+			let image = self.recv_image()?;
+			match self.fits(&image, shape) {
+				true => break image,
+				false => self.routed.push_back(image),
+			}
+		};
+		Ok(self.retrieved(image))
+	}
+
+	/// Returns the next image, for a panel of `shape`, returning `None` if not yet loaded
+	pub fn try_next_image(&mut self, shape: Shape) -> Result<Option<LoadedImage>, anyhow::Error> {
+		self.polls += 1;
+
+		// Note: When replaying, we wait for the image on the same poll it was recorded on,
+		//       regardless of whether it's loaded, so the replay doesn't depend on loading times.
+		if let Some(replay) = &mut self.replay {
+			let replay_image = match replay.front() {
+				Some(replay_image) if replay_image.poll <= self.polls => replay.pop_front(),
+				_ => None,
+			};
+			return match replay_image {
+				Some(replay_image) => {
+					let mut image = self.recv_image()?;
+					image.swap_dir = replay_image.swap_dir;
+					Ok(Some(image))
 				},
+				None => Ok(None),
 			};
+		}
 
-			// Then try to send it
-			if let Err(err) = image_tx.send(image) {
-				send_err = Some(err);
+		let image = loop {
+			if let Some(image) = self.take_routed(shape) {
+				break image;
 			}
 
-			false
-		});
+			let image = match self.try_recv_image()? {
+				Some(image) => image,
+				// Note: Once the loader finishes, we take any images held back, regardless of their shape
+				None if self.finished && !self.routed.is_empty() => continue,
+				None => return Ok(None),
+			};
+			match self.fits(&image, shape) {
+				true => break image,
+				false => self.routed.push_back(image),
+			}
+		};
+		Ok(Some(self.retrieved(image)))
+	}
+
+	/// Returns if `image` may be shown on a panel of `shape`
+	fn fits(&self, image: &LoadedImage, shape: Shape) -> bool {
+		!self.aspect_routing || image.info.shape == shape
+	}
+
+	/// Takes the oldest image held back that fits a panel of `shape`.
+	///
+	/// If there are too many images held back, or the loader finished, takes the oldest one instead.
+	fn take_routed(&mut self, shape: Shape) -> Option<LoadedImage> {
+		let idx = match self.routed.iter().position(|image| self.fits(image, shape)) {
+			Some(idx) => idx,
+			None if self.routed.len() >= Self::MAX_ROUTED || self.finished => 0,
+			None => return None,
+		};
+
+		self.routed.remove(idx)
+	}
+
+	/// Receives the next image, waiting if not yet available.
+	///
+	/// Returns an error if the loader stopped, or if interrupted while waiting.
+	fn recv_image(&mut self) -> Result<LoadedImage, anyhow::Error> {
+		loop {
+			let is_interrupted = self
+				.interrupt
+				.as_ref()
+				.is_some_and(|interrupt| interrupt.load(atomic::Ordering::Acquire));
+			if is_interrupted {
+				anyhow::bail!("Interrupted while waiting for an image");
+			}
+
+			match self.image_rx.recv_timeout(Self::INTERRUPT_CHECK_INTERVAL) {
+				Ok(image) => {
+					if let Some(image) = self.received(image) {
+						return Ok(image);
+					}
+				},
+				Err(mpsc::RecvTimeoutError::Timeout) => (),
+				Err(mpsc::RecvTimeoutError::Disconnected) => match self.loader_finished.load(atomic::Ordering::Acquire)
+				{
+					true => anyhow::bail!("Image loader finished, no more images are available"),
+					false => return Err(self.loader_error()),
+				},
+			}
+		}
+	}
+
+	/// Receives the next image, returning `None` if not yet available, or if the loader finished
+	fn try_recv_image(&mut self) -> Result<Option<LoadedImage>, anyhow::Error> {
+		loop {
+			match self.image_rx.try_recv() {
+				Ok(image) => {
+					if let Some(image) = self.received(image) {
+						return Ok(Some(image));
+					}
+				},
+				Err(mpsc::TryRecvError::Empty) => return Ok(None),
+				Err(mpsc::TryRecvError::Disconnected) => match self.loader_finished.load(atomic::Ordering::Acquire) {
+					true => {
+						self.finished = true;
+						return Ok(None);
+					},
+					false => return Err(self.loader_error()),
+				},
+			}
+		}
+	}
+
+	/// Returns the error the loader stopped with
+	fn loader_error(&mut self) -> anyhow::Error {
+		// Note: The error is only returned once, after which we just report that the loader failed
+		self.failed = true;
+		let err = self.loader_error.lock().expect("Loader error lock was poisoned").take();
+		err.map_or_else(
+			|| anyhow::anyhow!("Image loader stopped unexpectedly"),
+			|err| err.context("Image loader failed"),
+		)
+	}
+
+	/// Releases the memory of a received image from the budget and returns it,
+	/// unless it's path was removed after it was loaded
+	fn received(&self, SentImage { image, generation }: SentImage) -> Option<LoadedImage> {
+		if let Some(budget) = &self.budget {
+			budget.release(image.memory_size());
+		}
+
+		match self.generations.is_stale(&image.info.path, generation) {
+			true => {
+				log::info!("Dropping {:?}, as it was removed after being loaded", image.info.path);
+				None
+			},
+			false => Some(image),
+		}
+	}
+
+	/// Chooses the scroll direction of a retrieved image and records it
+	fn retrieved(&mut self, mut image: LoadedImage) -> LoadedImage {
+		image.swap_dir = rand::random();
+		if let Some(recorder) = &self.recorder {
+			recorder.image(self.polls, image.swap_dir, &image.info.path);
+		}
+		if let Some(show_counts) = &self.show_counts {
+			show_counts.record(&image.info.path, image.info.load_duration);
+		}
+
+		image
+	}
+}
+
+/// Creates the channel to send loaded images through, with room for `image_backlog` images
+/// and at most `max_backlog_memory` bytes, if any
+fn image_channel(
+	image_backlog: usize, max_backlog_memory: Option<u64>,
+) -> (ImageSender, mpsc::Receiver<SentImage>, Option<Arc<BacklogBudget>>) {
+	let (image_tx, image_rx) = mpsc::sync_channel(image_backlog);
+	let budget = max_backlog_memory.map(|max_memory| {
+		Arc::new(BacklogBudget {
+			max_memory,
+			memory: Mutex::new(0),
+			released: Condvar::new(),
+		})
+	});
+	let image_tx = ImageSender {
+		image_tx,
+		budget: budget.clone(),
+	};
+
+	(image_tx, image_rx, budget)
+}
+
+/// Image sent by the loader
+#[derive(Debug)]
+struct SentImage {
+	/// Image
+	image: LoadedImage,
+
+	/// Generation it started loading in
+	generation: u64,
+}
+
+/// Sender of loaded images
+struct ImageSender {
+	/// Sender
+	image_tx: mpsc::SyncSender<SentImage>,
+
+	/// Memory budget of the backlog, if any
+	budget: Option<Arc<BacklogBudget>>,
+}
 
-		// If we got a send error, return Err
-		if let Some(err) = send_err {
-			return Err(ImageLoaderError::SendImage(err));
+impl ImageSender {
+	/// Sends an image that started loading in `generation`, waiting until there's room for it in the backlog
+	fn send(&self, image: LoadedImage, generation: u64) -> Result<(), ImageLoaderError> {
+		if let Some(budget) = &self.budget {
+			budget.reserve(image.memory_size());
 		}
+
+		self.image_tx
+			.send(SentImage { image, generation })
+			.map_err(|_| ImageLoaderError::SendImage)
+	}
+}
+
+/// Path generations.
+///
+/// Each removal bumps the generation, and records the generation the path was removed in,
+/// so anything that started loading before it, in an earlier generation, may be dropped.
+#[derive(Default, Debug)]
+pub struct Generations {
+	/// Inner
+	inner: Mutex<GenerationsInner>,
+}
+
+/// Path generations inner
+#[derive(Default, Debug)]
+struct GenerationsInner {
+	/// Current generation
+	generation: u64,
+
+	/// Generation each path was last removed in
+	removed: HashMap<PathBuf, u64>,
+}
+
+impl Generations {
+	/// Returns the current generation
+	pub fn current(&self) -> u64 {
+		self.inner.lock().expect("Generations lock was poisoned").generation
+	}
+
+	/// Invalidates `path`, along with anything within it, if a directory
+	pub fn invalidate(&self, path: &Path) {
+		let mut inner = self.inner.lock().expect("Generations lock was poisoned");
+		inner.generation += 1;
+		let generation = inner.generation;
+		inner.removed.insert(path.to_path_buf(), generation);
+	}
+
+	/// Returns if `path`, which started loading in `generation`, has since been invalidated
+	pub fn is_stale(&self, path: &Path, generation: u64) -> bool {
+		let inner = self.inner.lock().expect("Generations lock was poisoned");
+		path.ancestors()
+			.filter_map(|path| inner.removed.get(path))
+			.any(|&removed_generation| removed_generation > generation)
 	}
 }
 
+/// Memory budget of the backlog
+#[derive(Debug)]
+struct BacklogBudget {
+	/// Maximum memory, in bytes
+	max_memory: u64,
+
+	/// Memory of all images sent, but not yet received, in bytes
+	memory: Mutex<u64>,
+
+	/// Signaled whenever memory is released
+	released: Condvar,
+}
+
+impl BacklogBudget {
+	/// Waits until `size` bytes fit within the budget and reserves them.
+	///
+	/// Note: Images larger than the whole budget still fit once nothing else is
+	///       reserved, so they don't block the loader forever.
+	fn reserve(&self, size: u64) {
+		let mut memory = self.memory.lock().expect("Backlog budget lock was poisoned");
+		while *memory != 0 && memory.saturating_add(size) > self.max_memory {
+			memory = self.released.wait(memory).expect("Backlog budget lock was poisoned");
+		}
+		*memory += size;
+	}
+
+	/// Releases `size` bytes
+	fn release(&self, size: u64) {
+		let mut memory = self.memory.lock().expect("Backlog budget lock was poisoned");
+		*memory = memory.saturating_sub(size);
+		drop(memory);
+
+		self.released.notify_all();
+	}
+}
+
+/// Runs `provider`, sending all of it's images through `image_tx`
+fn run_provider(mut provider: impl ImageProvider, image_tx: &ImageSender) -> Result<(), ImageLoaderError> {
+	while !provider.is_finished() {
+		provider.poll_events().map_err(ImageLoaderError::Provider)?;
+		while let Some(ProvidedImage { image, generation }) = provider.next().map_err(ImageLoaderError::Provider)? {
+			image_tx.send(image, generation)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Keeps the event sender alive, forever.
+///
+/// Note: The loader stops once it's event sender is dropped, so sources that
+///       finish sending their paths need to keep it alive.
+#[allow(clippy::needless_pass_by_value)] // We need to own the sender to keep it alive
+fn keep_alive(_event_tx: mpsc::Sender<notify::DebouncedEvent>) -> ! {
+	loop {
+		thread::park();
+	}
+}
+
+/// Image loader error
 #[derive(Debug)]
 enum ImageLoaderError {
-	/// Unable to send image
-	SendImage(SendError<Image>),
+	/// Unable to send image, as the receiver was dropped
+	SendImage,
 
-	/// Unable to receive fs event
-	ReceiveEvent(RecvError),
+	/// Provider failed
+	Provider(anyhow::Error),
 }
 
-/// Loads an image from a path
-fn load_img(path: &Path, [window_width, window_height]: [u32; 2]) -> Result<Image, anyhow::Error> {
-	// Try to open the image by guessing it's format
-	let image_reader = image::io::Reader::open(&path)
-		.context("Unable to open image")?
-		.with_guessed_format()
-		.context("Unable to parse image")?;
-	let image = image_reader.decode().context("Unable to decode image")?;
+/// Image load error
+#[derive(Debug)]
+pub enum ImageLoadError {
+	/// Unable to open the image
+	Open(io::Error),
+
+	/// Unable to guess the image's format
+	GuessFormat(io::Error),
+
+	/// Unable to decode the image
+	Decode(image::ImageError),
+
+	/// Image had no frames
+	NoFrames,
+
+	/// An external decoder, such as for videos, failed
+	Decoder(anyhow::Error),
+
+	/// Loading timed out
+	Timeout(Duration),
+
+	/// A previous, timed out, load of the same file is still running
+	StillLoading,
+
+	/// Loading thread panicked
+	Panicked,
+}
+
+impl fmt::Display for ImageLoadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Open(_) => write!(f, "Unable to open image"),
+			Self::GuessFormat(_) => write!(f, "Unable to parse image"),
+			Self::Decode(_) => write!(f, "Unable to decode image"),
+			Self::NoFrames => write!(f, "Image had no frames"),
+			Self::Decoder(_) => write!(f, "Decoder failed"),
+			Self::Timeout(timeout) => write!(f, "Timed out after {timeout:.2?}"),
+			Self::StillLoading => write!(f, "A previous load is still running"),
+			Self::Panicked => write!(f, "Loading thread panicked"),
+		}
+	}
+}
+
+impl Error for ImageLoadError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Open(err) | Self::GuessFormat(err) => Some(err),
+			Self::Decode(err) => Some(err),
+			Self::Decoder(err) => Some(err.as_ref()),
+			Self::NoFrames | Self::Timeout(_) | Self::StillLoading | Self::Panicked => None,
+		}
+	}
+}
+
+/// Loads a file with `handler`, failing if it takes longer than `timeout`.
+///
+/// Note: Decoding can't be cancelled, so on a timeout it's left to finish in the
+///       background, but the loader can move on to other files.
+///       While it's still running, loading the same file again fails, so a file
+///       that always times out doesn't keep piling up decoding threads.
+pub fn load_with_timeout(
+	path: &Path, handler: Handler, load_options: LoadOptions, timeout: Duration,
+) -> Result<LoadedImage, ImageLoadError> {
+	let guard = InFlightGuard::new(path).ok_or(ImageLoadError::StillLoading)?;
+
+	let (res_tx, res_rx) = mpsc::sync_channel(1);
+	let path = path.to_path_buf();
+	thread::spawn(move || {
+		// Note: The guard is only dropped once we're done, even if we panic
+		let _guard = guard;
+
+		let res = self::load_with_handler(&path, handler, load_options);
+
+		// Note: If we timed out, the receiver will have been dropped, so we ignore any errors
+		let _ = res_tx.send(res);
+	});
+
+	match res_rx.recv_timeout(timeout) {
+		Ok(res) => res,
+		Err(mpsc::RecvTimeoutError::Timeout) => Err(ImageLoadError::Timeout(timeout)),
+		Err(mpsc::RecvTimeoutError::Disconnected) => Err(ImageLoadError::Panicked),
+	}
+}
+
+/// Loads a file with `handler`.
+///
+/// Panics if `handler` is `Handler::Skip`.
+pub fn load_with_handler(
+	path: &Path, handler: Handler, load_options: LoadOptions,
+) -> Result<LoadedImage, ImageLoadError> {
+	match handler {
+		Handler::Image => self::load_img(path, load_options),
+		// Note: Only the first frame is kept, so the image isn't animated
+		Handler::Static => self::load_img(path, load_options).map(|mut image| {
+			image.frames.truncate(1);
+			image
+		}),
+		#[cfg(feature = "video")]
+		Handler::Video => crate::video::load_video(path, load_options).map_err(ImageLoadError::Decoder),
+		Handler::Skip => unreachable!("Skipped files shouldn't be loaded"),
+	}
+}
+
+/// All paths currently being loaded by `load_with_timeout`
+static IN_FLIGHT: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Guard for a path in `IN_FLIGHT`, removing it on drop
+struct InFlightGuard {
+	/// Path
+	path: PathBuf,
+}
+
+impl InFlightGuard {
+	/// Marks `path` as in flight, returning `None` if it already was
+	fn new(path: &Path) -> Option<Self> {
+		let mut in_flight = IN_FLIGHT.lock().expect("In flight lock was poisoned");
+		match in_flight.insert(path.to_path_buf()) {
+			true => Some(Self {
+				path: path.to_path_buf(),
+			}),
+			false => None,
+		}
+	}
+}
+
+impl Drop for InFlightGuard {
+	fn drop(&mut self) {
+		// Note: We don't panic on poison here, as we might already be panicking
+		let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(PoisonError::into_inner);
+		in_flight.remove(&self.path);
+	}
+}
+
+/// Loads an image from a path, or from the image cache, if cached
+pub fn load_img(path: &Path, load_options: LoadOptions) -> Result<LoadedImage, ImageLoadError> {
+	let load_start = Instant::now();
+
+	// If we have it cached, use it
+	let cache = match load_options.cache_size {
+		0 => None,
+		cache_size => ImageCache::new(path, load_options, cache_size),
+	};
+	if let Some(cache) = &cache {
+		match cache.load(path, load_start) {
+			Ok(image) => {
+				log::info!("Loaded {path:?} from cache");
+				return Ok(image);
+			},
+			Err(err) => log::debug!("Unable to load {path:?} from cache: {err:?}"),
+		}
+	}
+
+	// Else load it and cache it
+	// Note: The cache is only an optimization, so we don't fail if we can't store it
+	let image = self::load_img_uncached(path, load_options, load_start)?;
+	if let Some(cache) = &cache {
+		if let Err(err) = cache.store(&image) {
+			log::warn!("Unable to cache {path:?}: {err:?}");
+		}
+	}
+
+	Ok(image)
+}
+
+/// Loads an image from a path, which started loading at `load_start`
+fn load_img_uncached(
+	path: &Path, load_options: LoadOptions, load_start: Instant,
+) -> Result<LoadedImage, ImageLoadError> {
+	let frames = self::decode(path)?;
+	self::process_frames(path, frames, load_options, SlideSource::Directory, load_start)
+}
 
-	// Get it's width and aspect ratio
+/// Decodes all frames of the image at `path`, without processing them
+pub fn decode(path: &Path) -> Result<Vec<(DynamicImage, Duration)>, ImageLoadError> {
+	// If any optional decoder supports it, use it
+	match crate::decoders::decode(path) {
+		Some(image) => Ok(vec![(image.map_err(ImageLoadError::Decoder)?, Duration::ZERO)]),
+		None => self::decode_img(path),
+	}
+}
+
+/// Processes all decoded frames of an image at `path`, from `source`, which started loading at `load_start`
+pub fn process_frames(
+	path: &Path, frames: Vec<(DynamicImage, Duration)>, load_options: LoadOptions, source: SlideSource,
+	load_start: Instant,
+) -> Result<LoadedImage, ImageLoadError> {
+	let LoadOptions {
+		window_size: [window_width, window_height],
+		scaling,
+		resize_filter,
+		gpu_resize,
+		max_texture_size,
+		rgb,
+		..
+	} = load_options;
+	let (image, _) = frames.first().ok_or(ImageLoadError::NoFrames)?;
+
+	// Get it's width
 	let (image_width, image_height) = (image.width(), image.height());
+	match frames.len() {
+		1 => log::info!("Loaded {path:?} ({image_width}x{image_height})"),
+		frames_len => log::info!("Loaded {path:?} ({image_width}x{image_height}, {frames_len} frames)"),
+	}
+
+	// Then get the size we'll be resizing to, if any, and with which filter
+	let filter_type = match resize_filter {
+		ResizeFilter::Nearest => FilterType::Nearest,
+		ResizeFilter::Triangle => FilterType::Triangle,
+		ResizeFilter::CatmullRom => FilterType::CatmullRom,
+		ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+	};
+	// Note: When resizing on the gpu, the texture's mipmaps take care of it while sampling
+	let mut resize_size = match gpu_resize {
+		true => None,
+		false => self::resize_size((image_width, image_height), [window_width, window_height], scaling),
+	};
+
+	// If we're centering the image, crop away anything outside of the window
+	let crop_size = match scaling {
+		Scaling::Center if image_width > window_width || image_height > window_height => {
+			let (crop_width, crop_height) = (image_width.min(window_width), image_height.min(window_height));
+			log::debug!("Cropping from {image_width}x{image_height} to {crop_width}x{crop_height}");
+			Some((crop_width, crop_height))
+		},
+		_ => None,
+	};
+
+	// Make sure the gpu can fit it in a texture
+	if let Some(max_texture_size) = max_texture_size {
+		let size = resize_size.or(crop_size).unwrap_or((image_width, image_height));
+		if let Some(clamped_size) = self::clamp_texture_size(size, max_texture_size) {
+			log::warn!(
+				"{path:?} is larger than the maximum texture size ({max_texture_size}), downscaling to {}x{}",
+				clamped_size.0,
+				clamped_size.1
+			);
+			resize_size = Some(clamped_size);
+		}
+	}
+
+	// And resize if necessary
+	match resize_size {
+		Some((resize_width, resize_height)) => {
+			let reduction = 100.0 * (f64::from(resize_width) * f64::from(resize_height)) /
+				(f64::from(image_width) * f64::from(image_height));
+
+			log::debug!(
+				"Resizing from {image_width}x{image_height} to {resize_width}x{resize_height} ({reduction:.2}%)",
+			);
+		},
+		None => log::debug!("Not resizing"),
+	}
+
+	// Then process all frames
+	let frames = frames
+		.into_iter()
+		.map(|(image, delay)| {
+			let image = match crop_size {
+				Some((crop_width, crop_height)) => image.crop_imm(
+					(image_width - crop_width) / 2,
+					(image_height - crop_height) / 2,
+					crop_width,
+					crop_height,
+				),
+				None => image,
+			};
+
+			let image = match resize_size {
+				Some((resize_width, resize_height)) => image.resize_exact(resize_width, resize_height, filter_type),
+				None => image,
+			};
+
+			// Note: If the image is already in the right format, this doesn't copy it
+			let image = match rgb {
+				true => Image::Rgb(image.into_rgb8()),
+				false => Image::Rgba(image.into_rgba8()),
+			};
+			Frame { image, delay }
+		})
+		.collect::<Vec<_>>();
+
+	let palette = self::palette(&frames[0].image, PALETTE_LEN);
+	let dominant_color = palette.first().copied().unwrap_or_default();
+	Ok(LoadedImage {
+		info: SlideInfo::new(path, [image_width, image_height], source, load_start),
+		frames,
+		dominant_color,
+		palette,
+		swap_dir: false,
+		#[cfg(feature = "video")]
+		video: None,
+	})
+}
+
+/// Returns the size to downscale `size` to, keeping it's aspect ratio, so it's no
+/// larger than `max_texture_size`, if it's larger
+pub fn clamp_texture_size((width, height): (u32, u32), max_texture_size: u32) -> Option<(u32, u32)> {
+	let longest_side = width.max(height);
+	if longest_side <= max_texture_size {
+		return None;
+	}
+
+	let clamp = |side: u32| {
+		let side = u64::from(side) * u64::from(max_texture_size) / u64::from(longest_side);
+		side.try_into().map_or(max_texture_size, |side: u32| side.max(1))
+	};
+	Some((clamp(width), clamp(height)))
+}
+
+/// Returns the size to resize an image of size `image_size` to, if any
+pub fn resize_size(
+	(image_width, image_height): (u32, u32), [window_width, window_height]: [u32; 2], scaling: Scaling,
+) -> Option<(u32, u32)> {
 	let image_aspect_ratio = Ratio::new(image_width, image_height);
 	let window_aspect_ratio = Ratio::new(window_width, window_height);
 
-	log::info!("Loaded {path:?} ({image_width}x{image_height})");
-
-	// Then check what direction we'll be scrolling the image
+	// Check what direction we'll be scrolling the image
 	let scroll_dir = match (image_width.cmp(&image_height), window_width.cmp(&window_height)) {
 		// If they're both square, no scrolling occurs
 		(Ordering::Equal, Ordering::Equal) => ScrollDir::None,
@@ -227,51 +1181,146 @@ fn load_img(path: &Path, [window_width, window_height]: [u32; 2]) -> Result<Imag
 		},
 	};
 	match scroll_dir {
-		ScrollDir::Vertically => log::info!("Scrolling image vertically"),
-		ScrollDir::Horizontally => log::info!("Scrolling image horizontally"),
-		ScrollDir::None => log::info!("Not scrolling image"),
+		ScrollDir::Vertically => log::debug!("Scrolling image vertically"),
+		ScrollDir::Horizontally => log::debug!("Scrolling image horizontally"),
+		ScrollDir::None => log::debug!("Not scrolling image"),
 	}
 
 	// Then get the size we'll be resizing to, if any
-	let resize_size = match scroll_dir {
+	match (scaling, scroll_dir) {
+		// If we're fitting the image, resize it to fit within the window if it's larger
+		(Scaling::Fit | Scaling::ContainBlur, _) if image_width > window_width || image_height > window_height => {
+			match image_aspect_ratio >= window_aspect_ratio {
+				true => Some((window_width, (window_width * image_height) / image_width)),
+				false => Some(((window_height * image_width) / image_height, window_height)),
+			}
+		},
+
+		// If we're stretching the image, shrink any dimension larger than the window's
+		(Scaling::Stretch, _) if image_width > window_width || image_height > window_height => {
+			Some((image_width.min(window_width), image_height.min(window_height)))
+		},
+
 		// If we're scrolling vertically, resize if the image width is larger than the window width
-		ScrollDir::Vertically if image_width > window_width => {
+		(Scaling::Fill, ScrollDir::Vertically) if image_width > window_width => {
 			Some((window_width, (window_width * image_height) / image_width))
 		},
 
 		// If we're scrolling horizontally, resize if the image height is larger than the window height
-		ScrollDir::Horizontally if image_height > window_height => {
+		(Scaling::Fill, ScrollDir::Horizontally) if image_height > window_height => {
 			Some(((window_height * image_width) / image_height, window_height))
 		},
 
 		// If we're not doing any scrolling and the window is smaller, resize the image to screen size
 		// Note: Since we're not scrolling, we know aspect ratio is the same and so
 		//       we only need to check the width.
-		ScrollDir::None if image_width > window_width => Some((window_width, window_height)),
+		(Scaling::Fill, ScrollDir::None) if image_width > window_width => Some((window_width, window_height)),
 
 		// Else don't do any scrolling
 		_ => None,
-	};
-
-	// And resize if necessary
-	let image = match resize_size {
-		Some((resize_width, resize_height)) => {
-			let reduction = 100.0 * (f64::from(resize_width) * f64::from(resize_height)) /
-				(f64::from(image_width) * f64::from(image_height));
+	}
+}
 
-			log::info!(
-				"Resizing from {image_width}x{image_height} to {resize_width}x{resize_height} ({reduction:.2}%)",
-			);
-			image.resize_exact(resize_width, resize_height, FilterType::Lanczos3)
+/// Decodes an image, and all it's frames, if animated
+fn decode_img(path: &Path) -> Result<Vec<(DynamicImage, Duration)>, ImageLoadError> {
+	// Try to open the image by guessing it's format
+	let image_reader = image::io::Reader::open(&path)
+		.map_err(ImageLoadError::Open)?
+		.with_guessed_format()
+		.map_err(ImageLoadError::GuessFormat)?;
+	let frames = match image_reader.format() {
+		// If it's possibly animated, decode all frames
+		Some(ImageFormat::Gif) => {
+			let decoder = GifDecoder::new(image_reader.into_inner()).map_err(ImageLoadError::Decode)?;
+			self::decode_frames(decoder)?
 		},
-		None => {
-			log::info!("Not resizing");
-			image
+		Some(ImageFormat::Png) => {
+			let decoder = PngDecoder::new(image_reader.into_inner()).map_err(ImageLoadError::Decode)?;
+			match decoder.is_apng() {
+				true => self::decode_frames(decoder.apng())?,
+				false => vec![(
+					DynamicImage::from_decoder(decoder).map_err(ImageLoadError::Decode)?,
+					Duration::ZERO,
+				)],
+			}
 		},
+
+		// Else just decode it
+		_ => vec![(image_reader.decode().map_err(ImageLoadError::Decode)?, Duration::ZERO)],
 	};
 
-	let image = image.flipv().to_rgba8();
-	Ok(image)
+	Ok(frames)
+}
+
+/// Decodes all frames of an animated image
+fn decode_frames<'a>(decoder: impl AnimationDecoder<'a>) -> Result<Vec<(DynamicImage, Duration)>, ImageLoadError> {
+	decoder
+		.into_frames()
+		.map(|frame| {
+			let frame = frame?;
+			let delay = Duration::from(frame.delay());
+			Ok((DynamicImage::ImageRgba8(frame.into_buffer()), delay))
+		})
+		.collect::<Result<_, image::ImageError>>()
+		.map_err(ImageLoadError::Decode)
+}
+
+/// Number of colors in the palette of each image
+pub const PALETTE_LEN: usize = 8;
+
+/// Calculates the palette of an image, with at most `len` colors, from the most to least common.
+///
+/// Colors are quantized to 4 bits per channel, and the average of each of the most
+/// common buckets is returned, skipping any too similar to a more common one. Only a
+/// subset of the pixels are sampled.
+pub fn palette(image: &Image, len: usize) -> Vec<[f32; 3]> {
+	/// Maximum pixels to sample
+	const MAX_SAMPLES: usize = 16384;
+
+	/// Minimum distance between colors
+	const MIN_DISTANCE: f32 = 0.15;
+
+	let mut buckets = vec![(0_u32, [0_u32; 3]); 16 * 16 * 16];
+	let (width, height) = image.dimensions();
+	let pixels_len = (width as usize) * (height as usize);
+	let step = (pixels_len / MAX_SAMPLES).max(1);
+	let pixels: Box<dyn Iterator<Item = [u8; 3]>> = match image {
+		Image::Rgba(image) => Box::new(image.pixels().map(|&Rgba([red, green, blue, _])| [red, green, blue])),
+		Image::Rgb(image) => Box::new(image.pixels().map(|&Rgb(pixel)| pixel)),
+	};
+	for [red, green, blue] in pixels.step_by(step) {
+		let bucket_idx = (usize::from(red >> 4) << 8) | (usize::from(green >> 4) << 4) | usize::from(blue >> 4);
+		let (count, sum) = &mut buckets[bucket_idx];
+		*count += 1;
+		for (sum, value) in sum.iter_mut().zip([red, green, blue]) {
+			*sum += u32::from(value);
+		}
+	}
+
+	// Note: Buckets with the same count are kept in order, so the palette is deterministic
+	buckets.sort_by_key(|&(count, _)| Reverse(count));
+	let mut palette = Vec::<[f32; 3]>::with_capacity(len);
+	for &(count, sum) in buckets.iter().take_while(|&&(count, _)| count != 0) {
+		if palette.len() >= len {
+			break;
+		}
+
+		#[allow(clippy::cast_precision_loss)] // Sums are small enough to not matter
+		let color = sum.map(|sum| (sum as f32 / count as f32) / 255.0);
+		let is_distinct = palette.iter().all(|other| {
+			let distance_squared = color
+				.iter()
+				.zip(other)
+				.map(|(lhs, rhs)| (lhs - rhs).powi(2))
+				.sum::<f32>();
+			distance_squared >= MIN_DISTANCE * MIN_DISTANCE
+		});
+		if is_distinct {
+			palette.push(color);
+		}
+	}
+
+	palette
 }
 
 /// Image scrolling direction
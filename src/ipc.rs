@@ -0,0 +1,202 @@
+//! Ipc
+
+// Imports
+use crate::{args, current_images::CurrentImages};
+use anyhow::Context;
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::Shutdown,
+	os::unix::net::{UnixListener, UnixStream},
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::{mpsc, Arc},
+	thread,
+	time::Duration,
+};
+
+/// Ipc command
+#[derive(Clone, Debug)]
+pub enum IpcCommand {
+	/// Taps the tempo
+	Tap,
+
+	/// Skips to the next image
+	Next,
+
+	/// Goes back to the previous image
+	Prev,
+
+	/// Pauses the slideshow
+	Pause,
+
+	/// Resumes the slideshow
+	Resume,
+
+	/// Pauses the slideshow, if playing, else resumes it
+	TogglePause,
+
+	/// Sets the duration of each image
+	SetDuration(Duration),
+
+	/// Rescans the images directory
+	Reload,
+
+	/// Bans the image shown in a panel
+	Ban(usize),
+
+	/// Marks the image shown in a panel as a favorite
+	Fav(usize),
+
+	/// Saves the last frame shown to a path
+	Screenshot(PathBuf),
+
+	/// Quits, clearing the window
+	Quit,
+}
+
+impl FromStr for IpcCommand {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		// Note: Paths may contain whitespace, so we can't split them
+		// Note: Relative paths would be relative to our working directory, instead of the sender's
+		if let Some(path) = s.trim().strip_prefix("screenshot ") {
+			let path = PathBuf::from(path.trim_start());
+			anyhow::ensure!(path.is_absolute(), "Screenshot path {:?} must be absolute", path);
+			return Ok(Self::Screenshot(path));
+		}
+
+		match *s.split_whitespace().collect::<Vec<_>>().as_slice() {
+			["tap"] => Ok(Self::Tap),
+			["next"] => Ok(Self::Next),
+			["prev"] => Ok(Self::Prev),
+			// Note: Holding the current image is the same as pausing, it's just kept for convenience
+			["pause" | "hold"] => Ok(Self::Pause),
+			["resume"] => Ok(Self::Resume),
+			["toggle-pause"] => Ok(Self::TogglePause),
+			["set-duration", duration] => {
+				let duration = args::parse_duration(duration).context("Unable to parse duration")?;
+				anyhow::ensure!(duration > Duration::ZERO, "Duration must be positive");
+				Ok(Self::SetDuration(duration))
+			},
+			["reload"] => Ok(Self::Reload),
+			["ban"] => Ok(Self::Ban(0)),
+			["ban", panel] => Ok(Self::Ban(panel.parse().context("Unable to parse panel")?)),
+			["fav"] => Ok(Self::Fav(0)),
+			["fav", panel] => Ok(Self::Fav(panel.parse().context("Unable to parse panel")?)),
+			["quit"] => Ok(Self::Quit),
+			_ => Err(anyhow::anyhow!("Unknown command {:?}", s.trim())),
+		}
+	}
+}
+
+/// Ipc server.
+///
+/// Listens on a unix socket for newline-separated commands.
+///
+/// Queries, such as `current`, are answered directly, instead of being received as commands.
+pub struct Ipc {
+	/// Receiver end for the commands
+	command_rx: mpsc::Receiver<IpcCommand>,
+
+	/// Socket path
+	path: PathBuf,
+}
+
+impl Ipc {
+	/// Starts listening for commands on the socket at `path`
+	pub fn new(path: PathBuf, current_images: Arc<CurrentImages>) -> Result<Self, anyhow::Error> {
+		// Remove any stale socket from a previous run, unless another instance is still listening on it
+		// Note: If removing fails, binding will fail below with a better error
+		match UnixStream::connect(&path) {
+			Ok(_) => anyhow::bail!("Another instance is already listening on {:?}", path),
+			Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+				log::debug!("Removing stale socket {path:?}");
+				let _ = std::fs::remove_file(&path);
+			},
+			Err(_) => (),
+		}
+		let listener = UnixListener::bind(&path).context("Unable to bind socket")?;
+		log::info!("Listening for commands on {path:?}");
+
+		let (command_tx, command_rx) = mpsc::channel();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let stream = match stream {
+					Ok(stream) => stream,
+					Err(err) => {
+						log::warn!("Unable to accept ipc connection: {err}");
+						continue;
+					},
+				};
+
+				// Note: Each connection is handled on it's own thread, so a client that
+				//       never finishes it's commands doesn't block all others.
+				let command_tx = command_tx.clone();
+				let current_images = Arc::clone(&current_images);
+				thread::spawn(move || {
+					if let Err(err) = self::handle_connection(stream, &command_tx, &current_images) {
+						log::warn!("Unable to handle ipc connection: {err:?}");
+					}
+				});
+			}
+		});
+
+		Ok(Self { command_rx, path })
+	}
+
+	/// Returns the next command, returning `None` if there are none
+	pub fn try_recv(&self) -> Option<IpcCommand> {
+		self.command_rx.try_recv().ok()
+	}
+}
+
+impl Drop for Ipc {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.path);
+	}
+}
+
+/// Handles all commands of a connection
+fn handle_connection(
+	stream: UnixStream, command_tx: &mpsc::Sender<IpcCommand>, current_images: &CurrentImages,
+) -> Result<(), anyhow::Error> {
+	let mut writer = stream.try_clone().context("Unable to clone stream")?;
+	for line in BufReader::new(stream).lines() {
+		let line = line.context("Unable to read command")?;
+		let response = match line.trim() {
+			"current" => current_images.lines(),
+			_ => match line.parse::<IpcCommand>() {
+				Ok(command) => {
+					command_tx.send(command).context("Unable to send command")?;
+					"ok".to_owned()
+				},
+				Err(err) => format!("error: {err}"),
+			},
+		};
+
+		writeln!(writer, "{response}").context("Unable to write response")?;
+	}
+
+	Ok(())
+}
+
+/// Sends `command` to the ipc server listening on the socket at `path` and returns it's response
+pub fn send(path: &Path, command: &str) -> Result<String, anyhow::Error> {
+	let mut stream = UnixStream::connect(path).context("Unable to connect to socket")?;
+	writeln!(stream, "{command}").context("Unable to write command")?;
+	stream
+		.shutdown(Shutdown::Write)
+		.context("Unable to finish writing command")?;
+
+	// Note: Responses may span multiple lines, but the server closes the connection after it
+	let mut response = String::new();
+	stream
+		.read_to_string(&mut response)
+		.context("Unable to read response")?;
+	let response = response.trim_end();
+	match response.strip_prefix("error: ") {
+		Some(err) => Err(anyhow::anyhow!("{}", err)),
+		None => Ok(response.to_owned()),
+	}
+}
@@ -0,0 +1,745 @@
+//! Kms
+//!
+//! Shows frames directly on the display connected to a drm device, without `X`, such as for a
+//! kiosk on a console. The gl context is created with `EGL`, on a `gbm` surface, and each frame
+//! is flipped onto the display with kernel mode setting.
+
+// Imports
+use crate::{glium_backend::GlSurface, overlay};
+use anyhow::Context;
+use std::{
+	cell::Cell,
+	convert::TryInto,
+	ffi::{c_void, CString},
+	fs::{File, OpenOptions},
+	os::{
+		raw::{c_char, c_int, c_uint},
+		unix::io::AsRawFd,
+	},
+	path::Path,
+	ptr, slice,
+};
+
+/// Kms window
+pub struct KmsWindow {
+	/// Drm device
+	device: File,
+
+	/// Connector id
+	connector_id: u32,
+
+	/// Crtc id
+	crtc_id: u32,
+
+	/// Mode
+	mode: ffi::ModeInfo,
+
+	/// Crtc before we changed it, to restore once we're done
+	prev_crtc: *mut ffi::Crtc,
+
+	/// Gbm device
+	gbm_device: *mut ffi::GbmDevice,
+
+	/// Gbm surface
+	gbm_surface: *mut ffi::GbmSurface,
+
+	/// Egl display
+	egl_display: ffi::EglDisplay,
+
+	/// Egl context
+	egl_context: ffi::EglContext,
+
+	/// Egl surface
+	egl_surface: ffi::EglSurface,
+
+	/// Buffer being shown, along with it's framebuffer, if any
+	front: Cell<Option<(*mut ffi::GbmBo, u32)>>,
+
+	/// Size
+	size: [u32; 2],
+
+	/// Scale factor
+	scale_factor: f32,
+}
+
+impl KmsWindow {
+	/// Frame buffer configuration attributes
+	#[rustfmt::skip]
+	const EGL_CONFIG_ATTRIBUTES: [i32; 13] = [
+		ffi::EGL_SURFACE_TYPE   , ffi::EGL_WINDOW_BIT,
+		ffi::EGL_RENDERABLE_TYPE, ffi::EGL_OPENGL_BIT,
+		ffi::EGL_RED_SIZE       , 8,
+		ffi::EGL_GREEN_SIZE     , 8,
+		ffi::EGL_BLUE_SIZE      , 8,
+		ffi::EGL_ALPHA_SIZE     , 0,
+		ffi::EGL_NONE,
+	];
+	/// Opengl configuration attributes
+	#[rustfmt::skip]
+	const EGL_CONTEXT_ATTRIBUTES: [i32; 9] = [
+		ffi::EGL_CONTEXT_MAJOR_VERSION, 3,
+		ffi::EGL_CONTEXT_MINOR_VERSION, 0,
+		ffi::EGL_CONTEXT_OPENGL_FORWARD_COMPATIBLE, ffi::EGL_TRUE,
+		ffi::EGL_CONTEXT_OPENGL_PROFILE_MASK, ffi::EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+		ffi::EGL_NONE,
+	];
+
+	/// Creates a window on the first connected display of the drm device at `path`
+	#[allow(clippy::too_many_lines)] // TODO: Refactor
+	pub fn open(path: &Path) -> Result<Self, anyhow::Error> {
+		let device = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open(path)
+			.context("Unable to open drm device")?;
+		let fd = device.as_raw_fd();
+
+		// Find a connected display and the crtc to drive it with
+		let (connector_id, crtc_id, mode, width_mm) =
+			self::find_display(fd).context("Unable to find a connected display")?;
+		let size = [u32::from(mode.hdisplay), u32::from(mode.vdisplay)];
+		log::info!(
+			"Found display on connector {connector_id} with crtc {crtc_id}, at {}x{}@{}",
+			size[0],
+			size[1],
+			mode.vrefresh
+		);
+
+		// SAFETY: The device is valid.
+		let prev_crtc = unsafe { ffi::drmModeGetCrtc(fd, crtc_id) };
+
+		// Create the gbm device and surface
+		// SAFETY: The device is valid and outlives the gbm device.
+		let gbm_device = unsafe { ffi::gbm_create_device(fd) };
+		anyhow::ensure!(!gbm_device.is_null(), "Unable to create gbm device");
+		let mut window = Self {
+			device,
+			connector_id,
+			crtc_id,
+			mode,
+			prev_crtc,
+			gbm_device,
+			gbm_surface: std::ptr::null_mut(),
+			egl_display: std::ptr::null_mut(),
+			egl_context: std::ptr::null_mut(),
+			egl_surface: std::ptr::null_mut(),
+			front: Cell::new(None),
+			size,
+			scale_factor: overlay::scale_factor(size[0], width_mm),
+		};
+
+		// SAFETY: The gbm device is valid.
+		window.gbm_surface = unsafe {
+			ffi::gbm_surface_create(
+				gbm_device,
+				size[0],
+				size[1],
+				ffi::GBM_FORMAT_XRGB8888,
+				ffi::GBM_BO_USE_SCANOUT | ffi::GBM_BO_USE_RENDERING,
+			)
+		};
+		anyhow::ensure!(!window.gbm_surface.is_null(), "Unable to create gbm surface");
+
+		// Then initialize egl on it
+		// SAFETY: The gbm device is valid, and we don't pass any attributes.
+		window.egl_display =
+			unsafe { ffi::eglGetPlatformDisplay(ffi::EGL_PLATFORM_GBM_KHR, gbm_device.cast(), std::ptr::null()) };
+		anyhow::ensure!(!window.egl_display.is_null(), "Unable to get egl display");
+		let (mut major, mut minor) = (0, 0);
+		// SAFETY: The display is valid and the version outlives the call.
+		if unsafe { ffi::eglInitialize(window.egl_display, ptr::addr_of_mut!(major), ptr::addr_of_mut!(minor)) } == 0 {
+			window.egl_display = std::ptr::null_mut();
+			anyhow::bail!("Unable to initialize egl: {:#x}", self::egl_error());
+		}
+		log::info!("Initialized egl {major}.{minor}");
+		// SAFETY: Binding the api has no preconditions.
+		if unsafe { ffi::eglBindAPI(ffi::EGL_OPENGL_API) } == 0 {
+			anyhow::bail!("Unable to bind open-gl api: {:#x}", self::egl_error());
+		}
+
+		// Find a config that matches the gbm surface's format
+		let mut configs = [std::ptr::null_mut(); 64];
+		let mut configs_len = 0;
+		// SAFETY: We null-terminate the attributes, and `configs` has room for `configs.len()` configs.
+		let res = unsafe {
+			ffi::eglChooseConfig(
+				window.egl_display,
+				Self::EGL_CONFIG_ATTRIBUTES.as_ptr(),
+				configs.as_mut_ptr(),
+				configs.len().try_into().expect("Config count didn't fit into an `i32`"),
+				ptr::addr_of_mut!(configs_len),
+			)
+		};
+		anyhow::ensure!(res != 0, "Unable to choose egl config: {:#x}", self::egl_error());
+		let configs_len = configs_len.try_into().context("Egl config count was negative")?;
+		let config = configs[..configs_len]
+			.iter()
+			.copied()
+			.find(|&config| {
+				let mut visual_id = 0;
+				// SAFETY: The display and config are valid.
+				let res = unsafe {
+					ffi::eglGetConfigAttrib(
+						window.egl_display,
+						config,
+						ffi::EGL_NATIVE_VISUAL_ID,
+						ptr::addr_of_mut!(visual_id),
+					)
+				};
+				#[allow(clippy::cast_sign_loss)] // Formats are fourcc codes, which we just compare
+				let is_xrgb8888 = visual_id as u32 == ffi::GBM_FORMAT_XRGB8888;
+				res != 0 && is_xrgb8888
+			})
+			.context("No egl config matched the gbm surface's format")?;
+
+		// Create the context and the surface
+		// SAFETY: The display and config are valid and we null-terminate the attributes.
+		window.egl_context = unsafe {
+			ffi::eglCreateContext(
+				window.egl_display,
+				config,
+				std::ptr::null_mut(),
+				Self::EGL_CONTEXT_ATTRIBUTES.as_ptr(),
+			)
+		};
+		anyhow::ensure!(
+			!window.egl_context.is_null(),
+			"Unable to create egl context: {:#x}",
+			self::egl_error()
+		);
+		// SAFETY: The display, config and gbm surface are valid and we don't pass any attributes.
+		window.egl_surface = unsafe {
+			ffi::eglCreateWindowSurface(window.egl_display, config, window.gbm_surface.cast(), std::ptr::null())
+		};
+		anyhow::ensure!(
+			!window.egl_surface.is_null(),
+			"Unable to create egl surface: {:#x}",
+			self::egl_error()
+		);
+
+		Ok(window)
+	}
+
+	/// Shows the buffer just drawn, waiting until it's shown
+	fn flip(&self) -> Result<(), anyhow::Error> {
+		let fd = self.device.as_raw_fd();
+
+		// SAFETY: The gbm surface is valid and a buffer was just swapped onto it.
+		let bo = unsafe { ffi::gbm_surface_lock_front_buffer(self.gbm_surface) };
+		anyhow::ensure!(!bo.is_null(), "Unable to lock front buffer");
+
+		// SAFETY: The buffer is valid and it's handle is an integer.
+		let (stride, handle) = unsafe { (ffi::gbm_bo_get_stride(bo), ffi::gbm_bo_get_handle(bo).u32_) };
+		let mut fb = 0;
+		// SAFETY: The device is valid and the buffer is `width`x`height`.
+		let res = unsafe {
+			ffi::drmModeAddFB(
+				fd,
+				self.size[0],
+				self.size[1],
+				24,
+				32,
+				stride,
+				handle,
+				ptr::addr_of_mut!(fb),
+			)
+		};
+		if res != 0 {
+			// SAFETY: The buffer was locked above.
+			unsafe { ffi::gbm_surface_release_buffer(self.gbm_surface, bo) };
+			anyhow::bail!("Unable to add framebuffer");
+		}
+
+		// Note: The first frame sets the mode, while the rest are flipped on the next vertical blank
+		let prev_front = self.front.replace(Some((bo, fb)));
+		match prev_front {
+			None => {
+				let mut connector_id = self.connector_id;
+				let mut mode = self.mode;
+				// SAFETY: The device, crtc, framebuffer and connector are valid.
+				let res = unsafe {
+					ffi::drmModeSetCrtc(
+						fd,
+						self.crtc_id,
+						fb,
+						0,
+						0,
+						ptr::addr_of_mut!(connector_id),
+						1,
+						ptr::addr_of_mut!(mode),
+					)
+				};
+				anyhow::ensure!(res == 0, "Unable to set crtc");
+			},
+			Some((prev_bo, prev_fb)) => {
+				let is_flipped = Cell::new(false);
+				// SAFETY: The device, crtc and framebuffer are valid, and `is_flipped` outlives the flip, as
+				//         we wait for it below.
+				let res = unsafe {
+					ffi::drmModePageFlip(
+						fd,
+						self.crtc_id,
+						fb,
+						ffi::DRM_MODE_PAGE_FLIP_EVENT,
+						ptr::addr_of!(is_flipped) as *mut c_void,
+					)
+				};
+				if res == 0 {
+					let mut event_ctx = ffi::EventContext {
+						version:           2,
+						vblank_handler:    None,
+						page_flip_handler: Some(self::on_page_flip),
+					};
+					while !is_flipped.get() {
+						// SAFETY: The device and event context are valid.
+						if unsafe { ffi::drmHandleEvent(fd, ptr::addr_of_mut!(event_ctx)) } != 0 {
+							log::warn!("Unable to wait for page flip");
+							break;
+						}
+					}
+				}
+
+				// SAFETY: The previous buffer is no longer shown.
+				unsafe {
+					ffi::drmModeRmFB(fd, prev_fb);
+					ffi::gbm_surface_release_buffer(self.gbm_surface, prev_bo);
+				}
+				anyhow::ensure!(res == 0, "Unable to flip page");
+			},
+		}
+
+		Ok(())
+	}
+}
+
+impl GlSurface for KmsWindow {
+	fn size(&self) -> [u32; 2] {
+		self.size
+	}
+
+	fn scale_factor(&self) -> f32 {
+		self.scale_factor
+	}
+
+	fn process_events(&self) {}
+
+	fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError> {
+		// SAFETY: The display and surface are valid.
+		if unsafe { ffi::eglSwapBuffers(self.egl_display, self.egl_surface) } == 0 {
+			match self::egl_error() {
+				ffi::EGL_CONTEXT_LOST => return Err(glium::SwapBuffersError::ContextLost),
+				err => log::warn!("Unable to swap buffers: {err:#x}"),
+			}
+		}
+
+		if let Err(err) = self.flip() {
+			log::warn!("Unable to show frame: {err:?}");
+		}
+		Ok(())
+	}
+
+	fn get_proc_address(&self, name: &str) -> *const c_void {
+		let name_cstr = CString::new(name).expect("Unable to create c-string from name");
+		// SAFETY: `eglGetProcAddress` should be safe to call with any string.
+		unsafe { ffi::eglGetProcAddress(name_cstr.as_ptr()) }
+	}
+
+	fn is_context_current(&self) -> bool {
+		// SAFETY: No arguments are involved, call should be inherently safe.
+		let egl_context = unsafe { ffi::eglGetCurrentContext() };
+		egl_context == self.egl_context
+	}
+
+	fn make_context_current(&self) -> Result<(), anyhow::Error> {
+		// SAFETY: The display, surface and context are valid.
+		let res =
+			unsafe { ffi::eglMakeCurrent(self.egl_display, self.egl_surface, self.egl_surface, self.egl_context) };
+		anyhow::ensure!(res != 0, "Unable to make context current: {:#x}", self::egl_error());
+		Ok(())
+	}
+}
+
+impl Drop for KmsWindow {
+	fn drop(&mut self) {
+		let fd = self.device.as_raw_fd();
+
+		// SAFETY: Everything is valid, if not null, and no longer used after this.
+		unsafe {
+			// Restore the previous crtc, so the console is shown again
+			if !self.prev_crtc.is_null() {
+				let prev_crtc = &mut *self.prev_crtc;
+				let mut connector_id = self.connector_id;
+				ffi::drmModeSetCrtc(
+					fd,
+					prev_crtc.id,
+					prev_crtc.buffer_id,
+					prev_crtc.x,
+					prev_crtc.y,
+					ptr::addr_of_mut!(connector_id),
+					1,
+					ptr::addr_of_mut!(prev_crtc.mode),
+				);
+				ffi::drmModeFreeCrtc(self.prev_crtc);
+			}
+
+			if let Some((bo, fb)) = self.front.take() {
+				ffi::drmModeRmFB(fd, fb);
+				ffi::gbm_surface_release_buffer(self.gbm_surface, bo);
+			}
+
+			if !self.egl_display.is_null() {
+				ffi::eglMakeCurrent(
+					self.egl_display,
+					std::ptr::null_mut(),
+					std::ptr::null_mut(),
+					std::ptr::null_mut(),
+				);
+				if !self.egl_surface.is_null() {
+					ffi::eglDestroySurface(self.egl_display, self.egl_surface);
+				}
+				if !self.egl_context.is_null() {
+					ffi::eglDestroyContext(self.egl_display, self.egl_context);
+				}
+				ffi::eglTerminate(self.egl_display);
+			}
+
+			if !self.gbm_surface.is_null() {
+				ffi::gbm_surface_destroy(self.gbm_surface);
+			}
+			ffi::gbm_device_destroy(self.gbm_device);
+		}
+	}
+}
+
+/// Finds the first connected display, returning it's connector, the crtc to drive it with, it's mode and
+/// it's physical width, in millimeters
+fn find_display(fd: c_int) -> Result<(u32, u32, ffi::ModeInfo, u32), anyhow::Error> {
+	// SAFETY: The device is valid.
+	let resources = unsafe { ffi::drmModeGetResources(fd) };
+	anyhow::ensure!(!resources.is_null(), "Unable to get resources, is this a drm device?");
+
+	// SAFETY: The resources are valid until freed below, and each array has it's count of elements.
+	let res = unsafe {
+		let resources_ref = &*resources;
+		let connectors = self::ffi_slice(resources_ref.connectors, resources_ref.count_connectors);
+		let crtcs = self::ffi_slice(resources_ref.crtcs, resources_ref.count_crtcs);
+		connectors
+			.iter()
+			.find_map(|&connector_id| {
+				let connector = ffi::drmModeGetConnector(fd, connector_id);
+				if connector.is_null() {
+					return None;
+				}
+
+				let connector_ref = &*connector;
+				let res = match connector_ref.connection == ffi::DRM_MODE_CONNECTED {
+					true => {
+						let modes = self::ffi_slice(connector_ref.modes, connector_ref.count_modes);
+						let mode = modes
+							.iter()
+							.find(|mode| mode.type_ & ffi::DRM_MODE_TYPE_PREFERRED != 0)
+							.or_else(|| modes.first())
+							.copied();
+						let crtc_id = self::find_crtc(fd, connector_ref, crtcs);
+						let width_mm = connector_ref.mm_width;
+						mode.zip(crtc_id)
+							.map(|(mode, crtc_id)| (connector_id, crtc_id, mode, width_mm))
+					},
+					false => None,
+				};
+				ffi::drmModeFreeConnector(connector);
+				res
+			})
+			.context("No connected display with a mode and crtc was found")
+	};
+	// SAFETY: The resources are no longer used.
+	unsafe { ffi::drmModeFreeResources(resources) };
+
+	res
+}
+
+/// Finds the crtc to drive `connector` with.
+///
+/// Uses the crtc currently driving it, if any, else the first one any of it's encoders supports.
+///
+/// # Safety
+/// `connector` must have been returned by `drmModeGetConnector` on `fd`.
+unsafe fn find_crtc(fd: c_int, connector: &ffi::Connector, crtcs: &[u32]) -> Option<u32> {
+	let encoder_crtc = |encoder_id: u32| {
+		// SAFETY: The device is valid.
+		let encoder = unsafe { ffi::drmModeGetEncoder(fd, encoder_id) };
+		if encoder.is_null() {
+			return None;
+		}
+
+		// SAFETY: The encoder was just checked to be valid.
+		let (crtc_id, possible_crtcs) = unsafe { ((*encoder).crtc_id, (*encoder).possible_crtcs) };
+		unsafe { ffi::drmModeFreeEncoder(encoder) };
+		Some((crtc_id, possible_crtcs))
+	};
+
+	// If it's already driven by a crtc, use it
+	if let Some((crtc_id, _)) = encoder_crtc(connector.encoder_id) {
+		if crtc_id != 0 {
+			return Some(crtc_id);
+		}
+	}
+
+	// SAFETY: The connector is valid, as required by the caller.
+	let encoders = unsafe { self::ffi_slice(connector.encoders, connector.count_encoders) };
+	encoders.iter().find_map(|&encoder_id| {
+		let (_, possible_crtcs) = encoder_crtc(encoder_id)?;
+		crtcs
+			.iter()
+			.enumerate()
+			.find(|&(idx, _)| possible_crtcs & (1 << idx) != 0)
+			.map(|(_, &crtc_id)| crtc_id)
+	})
+}
+
+/// Creates a slice from a pointer and length returned by `libdrm`
+///
+/// # Safety
+/// `ptr` must point to at least `len` elements, if non-null and `len` is positive
+unsafe fn ffi_slice<'a, T>(ptr: *const T, len: c_int) -> &'a [T] {
+	match (ptr.is_null(), len.try_into()) {
+		// SAFETY: The caller guarantees `ptr` points to `len` elements
+		(false, Ok(len)) => unsafe { slice::from_raw_parts(ptr, len) },
+		_ => &[],
+	}
+}
+
+/// Returns the last egl error
+fn egl_error() -> i32 {
+	// SAFETY: No arguments are involved, call should be inherently safe.
+	unsafe { ffi::eglGetError() }
+}
+
+/// Page flip handler
+extern "C" fn on_page_flip(_fd: c_int, _sequence: c_uint, _tv_sec: c_uint, _tv_usec: c_uint, user_data: *mut c_void) {
+	// SAFETY: We pass a pointer to a `Cell<bool>` when flipping the page, which outlives the event.
+	unsafe { (*user_data.cast::<Cell<bool>>()).set(true) };
+}
+
+/// Bindings to `libEGL`, `libgbm` and `libdrm`
+#[allow(non_snake_case, non_camel_case_types)] // We keep the C names
+mod ffi {
+	// Imports
+	use super::{c_char, c_int, c_uint, c_void};
+
+	pub type EglDisplay = *mut c_void;
+	pub type EglConfig = *mut c_void;
+	pub type EglContext = *mut c_void;
+	pub type EglSurface = *mut c_void;
+
+	pub const EGL_TRUE: i32 = 1;
+	pub const EGL_NONE: i32 = 0x3038;
+	pub const EGL_ALPHA_SIZE: i32 = 0x3021;
+	pub const EGL_BLUE_SIZE: i32 = 0x3022;
+	pub const EGL_GREEN_SIZE: i32 = 0x3023;
+	pub const EGL_RED_SIZE: i32 = 0x3024;
+	pub const EGL_NATIVE_VISUAL_ID: i32 = 0x302e;
+	pub const EGL_SURFACE_TYPE: i32 = 0x3033;
+	pub const EGL_RENDERABLE_TYPE: i32 = 0x3040;
+	pub const EGL_WINDOW_BIT: i32 = 0x4;
+	pub const EGL_OPENGL_BIT: i32 = 0x8;
+	pub const EGL_CONTEXT_LOST: i32 = 0x300e;
+	pub const EGL_OPENGL_API: c_uint = 0x30a2;
+	pub const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+	pub const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30fb;
+	pub const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30fd;
+	pub const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x1;
+	pub const EGL_CONTEXT_OPENGL_FORWARD_COMPATIBLE: i32 = 0x31b1;
+	pub const EGL_PLATFORM_GBM_KHR: c_uint = 0x31d7;
+
+	#[link(name = "EGL")]
+	extern "C" {
+		pub fn eglGetPlatformDisplay(platform: c_uint, native_display: *mut c_void, attrs: *const isize) -> EglDisplay;
+		pub fn eglInitialize(display: EglDisplay, major: *mut i32, minor: *mut i32) -> c_uint;
+		pub fn eglTerminate(display: EglDisplay) -> c_uint;
+		pub fn eglBindAPI(api: c_uint) -> c_uint;
+		pub fn eglChooseConfig(
+			display: EglDisplay, attrs: *const i32, configs: *mut EglConfig, configs_size: i32, configs_len: *mut i32,
+		) -> c_uint;
+		pub fn eglGetConfigAttrib(display: EglDisplay, config: EglConfig, attr: i32, value: *mut i32) -> c_uint;
+		pub fn eglCreateContext(
+			display: EglDisplay, config: EglConfig, share_context: EglContext, attrs: *const i32,
+		) -> EglContext;
+		pub fn eglDestroyContext(display: EglDisplay, context: EglContext) -> c_uint;
+		pub fn eglCreateWindowSurface(
+			display: EglDisplay, config: EglConfig, window: *mut c_void, attrs: *const i32,
+		) -> EglSurface;
+		pub fn eglDestroySurface(display: EglDisplay, surface: EglSurface) -> c_uint;
+		pub fn eglMakeCurrent(display: EglDisplay, draw: EglSurface, read: EglSurface, context: EglContext) -> c_uint;
+		pub fn eglGetCurrentContext() -> EglContext;
+		pub fn eglSwapBuffers(display: EglDisplay, surface: EglSurface) -> c_uint;
+		pub fn eglGetProcAddress(name: *const c_char) -> *const c_void;
+		pub fn eglGetError() -> i32;
+	}
+
+	/// Gbm device
+	#[repr(C)]
+	pub struct GbmDevice {
+		_private: [u8; 0],
+	}
+
+	/// Gbm surface
+	#[repr(C)]
+	pub struct GbmSurface {
+		_private: [u8; 0],
+	}
+
+	/// Gbm buffer
+	#[repr(C)]
+	pub struct GbmBo {
+		_private: [u8; 0],
+	}
+
+	/// Buffer handle
+	#[repr(C)]
+	#[derive(Clone, Copy)]
+	pub union GbmBoHandle {
+		pub ptr:  *mut c_void,
+		pub s32:  i32,
+		pub u32_: u32,
+		pub s64:  i64,
+		pub u64_: u64,
+	}
+
+	pub const GBM_FORMAT_XRGB8888: u32 = u32::from_le_bytes(*b"XR24");
+	pub const GBM_BO_USE_SCANOUT: u32 = 1 << 0;
+	pub const GBM_BO_USE_RENDERING: u32 = 1 << 2;
+
+	#[link(name = "gbm")]
+	extern "C" {
+		pub fn gbm_create_device(fd: c_int) -> *mut GbmDevice;
+		pub fn gbm_device_destroy(device: *mut GbmDevice);
+		pub fn gbm_surface_create(
+			device: *mut GbmDevice, width: u32, height: u32, format: u32, flags: u32,
+		) -> *mut GbmSurface;
+		pub fn gbm_surface_destroy(surface: *mut GbmSurface);
+		pub fn gbm_surface_lock_front_buffer(surface: *mut GbmSurface) -> *mut GbmBo;
+		pub fn gbm_surface_release_buffer(surface: *mut GbmSurface, bo: *mut GbmBo);
+		pub fn gbm_bo_get_stride(bo: *mut GbmBo) -> u32;
+		pub fn gbm_bo_get_handle(bo: *mut GbmBo) -> GbmBoHandle;
+	}
+
+	pub const DRM_MODE_CONNECTED: c_int = 1;
+	pub const DRM_MODE_TYPE_PREFERRED: u32 = 1 << 3;
+	pub const DRM_MODE_PAGE_FLIP_EVENT: u32 = 1;
+
+	/// Resources
+	#[repr(C)]
+	pub struct Resources {
+		pub count_fbs:        c_int,
+		pub fbs:              *mut u32,
+		pub count_crtcs:      c_int,
+		pub crtcs:            *mut u32,
+		pub count_connectors: c_int,
+		pub connectors:       *mut u32,
+		pub count_encoders:   c_int,
+		pub encoders:         *mut u32,
+		pub min_width:        u32,
+		pub max_width:        u32,
+		pub min_height:       u32,
+		pub max_height:       u32,
+	}
+
+	/// Mode info
+	#[repr(C)]
+	#[derive(Clone, Copy)]
+	pub struct ModeInfo {
+		pub clock:       u32,
+		pub hdisplay:    u16,
+		pub hsync_start: u16,
+		pub hsync_end:   u16,
+		pub htotal:      u16,
+		pub hskew:       u16,
+		pub vdisplay:    u16,
+		pub vsync_start: u16,
+		pub vsync_end:   u16,
+		pub vtotal:      u16,
+		pub vscan:       u16,
+		pub vrefresh:    u32,
+		pub flags:       u32,
+		pub type_:       u32,
+		pub name:        [c_char; 32],
+	}
+
+	/// Connector
+	#[repr(C)]
+	pub struct Connector {
+		pub id:             u32,
+		pub encoder_id:     u32,
+		pub type_:          u32,
+		pub type_id:        u32,
+		pub connection:     c_int,
+		pub mm_width:       u32,
+		pub mm_height:      u32,
+		pub subpixel:       c_int,
+		pub count_modes:    c_int,
+		pub modes:          *mut ModeInfo,
+		pub count_props:    c_int,
+		pub props:          *mut u32,
+		pub prop_values:    *mut u64,
+		pub count_encoders: c_int,
+		pub encoders:       *mut u32,
+	}
+
+	/// Encoder
+	#[repr(C)]
+	pub struct Encoder {
+		pub id:              u32,
+		pub type_:           u32,
+		pub crtc_id:         u32,
+		pub possible_crtcs:  u32,
+		pub possible_clones: u32,
+	}
+
+	/// Crtc
+	#[repr(C)]
+	pub struct Crtc {
+		pub id:         u32,
+		pub buffer_id:  u32,
+		pub x:          u32,
+		pub y:          u32,
+		pub width:      u32,
+		pub height:     u32,
+		pub mode_valid: c_int,
+		pub mode:       ModeInfo,
+		pub gamma_size: c_int,
+	}
+
+	/// Page flip, or vertical blank, handler
+	pub type EventHandler = extern "C" fn(c_int, c_uint, c_uint, c_uint, *mut c_void);
+
+	/// Event context, version 2
+	#[repr(C)]
+	pub struct EventContext {
+		pub version:           c_int,
+		pub vblank_handler:    Option<EventHandler>,
+		pub page_flip_handler: Option<EventHandler>,
+	}
+
+	#[link(name = "drm")]
+	extern "C" {
+		pub fn drmModeGetResources(fd: c_int) -> *mut Resources;
+		pub fn drmModeFreeResources(resources: *mut Resources);
+		pub fn drmModeGetConnector(fd: c_int, connector_id: u32) -> *mut Connector;
+		pub fn drmModeFreeConnector(connector: *mut Connector);
+		pub fn drmModeGetEncoder(fd: c_int, encoder_id: u32) -> *mut Encoder;
+		pub fn drmModeFreeEncoder(encoder: *mut Encoder);
+		pub fn drmModeGetCrtc(fd: c_int, crtc_id: u32) -> *mut Crtc;
+		pub fn drmModeFreeCrtc(crtc: *mut Crtc);
+		pub fn drmModeSetCrtc(
+			fd: c_int, crtc_id: u32, buffer_id: u32, x: u32, y: u32, connectors: *mut u32, count: c_int,
+			mode: *mut ModeInfo,
+		) -> c_int;
+		pub fn drmModeAddFB(
+			fd: c_int, width: u32, height: u32, depth: u8, bpp: u8, pitch: u32, bo_handle: u32, buffer_id: *mut u32,
+		) -> c_int;
+		pub fn drmModeRmFB(fd: c_int, buffer_id: u32) -> c_int;
+		pub fn drmModePageFlip(fd: c_int, crtc_id: u32, buffer_id: u32, flags: u32, user_data: *mut c_void) -> c_int;
+		pub fn drmHandleEvent(fd: c_int, event_ctx: *mut EventContext) -> c_int;
+	}
+}
@@ -0,0 +1,207 @@
+//! Layout preview
+//!
+//! Renders a grid layout with numbered placeholder cells, drawing them through the same
+//! renderer as the wallpaper, so the preview matches what's shown.
+
+// Imports
+use anyhow::Context;
+use image::{imageops, Rgba, RgbaImage};
+use std::{
+	convert::TryFrom,
+	path::Path,
+	rc::Rc,
+	time::{Duration, Instant},
+};
+use zss::{
+	args::{FadeSpace, Scaling},
+	effects::Effects,
+	glium_backend::GliumBackend,
+	glium_facade::GliumFacade,
+	grid::Layout,
+	images::{self, Frame, LoadedImage},
+	panel::Panel,
+	renderer::{DrawParams, Renderer},
+	slide_info::{SlideInfo, SlideSource},
+	window::Window,
+	Image,
+};
+
+/// Cell colors, alternated in a checkerboard pattern
+const CELL_COLORS: [Rgba<u8>; 2] = [Rgba([0x30, 0x30, 0x40, 0xff]), Rgba([0x50, 0x50, 0x68, 0xff])];
+
+/// Cell border color
+const BORDER_COLOR: Rgba<u8> = Rgba([0x90, 0x90, 0xa0, 0xff]);
+
+/// Digit color
+const DIGIT_COLOR: Rgba<u8> = Rgba([0xff, 0xff, 0xff, 0xff]);
+
+/// Digit glyphs, 3x5, with each row's bits from left to right
+#[rustfmt::skip]
+const DIGITS: [[u8; 5]; 10] = [
+	[0b111, 0b101, 0b101, 0b101, 0b111],
+	[0b010, 0b110, 0b010, 0b010, 0b111],
+	[0b111, 0b001, 0b111, 0b100, 0b111],
+	[0b111, 0b001, 0b111, 0b001, 0b111],
+	[0b101, 0b101, 0b111, 0b001, 0b001],
+	[0b111, 0b100, 0b111, 0b001, 0b111],
+	[0b111, 0b100, 0b111, 0b101, 0b111],
+	[0b111, 0b001, 0b001, 0b001, 0b001],
+	[0b111, 0b101, 0b111, 0b101, 0b111],
+	[0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Renders a `grid_width`x`grid_height` grid layout with numbered cells to `out`.
+///
+/// Cells are numbered in the same order the wallpaper fills them, starting
+/// from the bottom-left cell.
+pub fn render(grid_width: usize, grid_height: usize, size: [u32; 2], out: &Path) -> Result<(), anyhow::Error> {
+	anyhow::ensure!(grid_width != 0 && grid_height != 0, "Grid must have at least 1 cell");
+	let grid_width = u32::try_from(grid_width).context("Grid was too wide")?;
+	let grid_height = u32::try_from(grid_height).context("Grid was too tall")?;
+
+	let layout = Layout::uniform([grid_width, grid_height]);
+	let window_cells = layout.window_cells(size, 0, 0);
+	anyhow::ensure!(
+		window_cells.iter().all(|cell| cell.size[0] != 0 && cell.size[1] != 0),
+		"Grid cells must be at least 1 pixel wide"
+	);
+
+	// Create an offscreen window to render with
+	let window = Window::headless(size)
+		.map(Rc::new)
+		.context("Unable to create headless window")?;
+	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+
+	let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+
+	// Create a panel for each cell, as the wallpaper does
+	// Note: Panels always have a next image, so we use a copy, as when setting a single image
+	let panels = layout
+		.cells
+		.iter()
+		.zip(&window_cells)
+		.enumerate()
+		.map(|(idx, (slot_cell, cell))| {
+			let [x, y] = slot_cell.pos;
+			let color = CELL_COLORS[((x + y) % 2) as usize];
+			let cell_image = || {
+				self::cell_image(&facade, cell.size, color, idx)
+					.with_context(|| format!("Unable to create image for cell {idx}"))
+			};
+
+			let (scale, offset) = cell.transform(size);
+			Ok(Panel::new(cell_image()?, cell_image()?, scale, offset))
+		})
+		.collect::<Result<Vec<_>, anyhow::Error>>()?;
+	let params = DrawParams {
+		fade:                  1.0,
+		fade_space:            FadeSpace::Linear,
+		scaling:               Scaling::Stretch,
+		kaleidoscope:          None,
+		double_exposure:       None,
+		exposure_mix:          0.0,
+		bg_color:              [0.0; 3],
+		border_color:          [0.0; 3],
+		dominant_bg:           false,
+		interpolation_samples: 1,
+		effects:               Effects::IDENTITY,
+	};
+
+	let [width, height] = size;
+	let texture = glium::Texture2d::empty(&facade, width, height).context("Unable to create texture")?;
+	let mut surface =
+		glium::framebuffer::SimpleFrameBuffer::new(&facade, &texture).context("Unable to create framebuffer")?;
+	renderer
+		.draw(&mut surface, &panels, &params)
+		.context("Unable to draw frame")?;
+
+	// Note: Open-gl's origin is on the bottom-left, so we need to flip it
+	let raw: glium::texture::RawImage2d<u8> = texture.read();
+	let image =
+		RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).context("Frame had an unexpected size")?;
+	let image = imageops::flip_vertical(&image);
+
+	image.save(out).context("Unable to save preview")
+}
+
+/// Creates and uploads the placeholder image of a `size` cell, with `number`
+fn cell_image(
+	facade: &GliumFacade, size @ [width, height]: [u32; 2], color: Rgba<u8>, number: usize,
+) -> Result<Image, anyhow::Error> {
+	let mut image = RgbaImage::new(width, height);
+	self::draw_cell(&mut image, color);
+	self::draw_number(&mut image, number);
+
+	let Rgba([red, green, blue, _]) = color;
+	let color = [red, green, blue].map(|value| f32::from(value) / 255.0);
+	let image = LoadedImage {
+		frames: vec![Frame {
+			image: images::Image::Rgba(image),
+			delay: Duration::ZERO,
+		}],
+		dominant_color: color,
+		palette: vec![color],
+		info: SlideInfo::new(
+			Path::new(&format!("cell #{number}")),
+			[width, height],
+			SlideSource::Directory,
+			Instant::now(),
+		),
+		swap_dir: false,
+		#[cfg(feature = "video")]
+		video: None,
+	};
+
+	let mut image = Image::from_image(facade, image, size, Scaling::Stretch, None)?;
+	image.finish_upload();
+	Ok(image)
+}
+
+/// Fills a cell image with `color`, with a border
+fn draw_cell(image: &mut RgbaImage, color: Rgba<u8>) {
+	let (width, height) = image.dimensions();
+	for (x, y, pixel) in image.enumerate_pixels_mut() {
+		let is_border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+		*pixel = match is_border {
+			true => BORDER_COLOR,
+			false => color,
+		};
+	}
+}
+
+/// Draws `number` centered on a cell image
+fn draw_number(image: &mut RgbaImage, number: usize) {
+	let (width, height) = image.dimensions();
+	let digits = number.to_string();
+
+	// Make the digits take up about a quarter of the cell, with a gap of 1 scaled pixel between them
+	#[allow(clippy::cast_possible_truncation)] // Digit count will be small
+	let digits_len = digits.len() as u32;
+	let scale = (height / 4 / 5).min(width / 2 / (4 * digits_len)).max(1);
+	let text_width = (4 * digits_len - 1) * scale;
+	let text_height = 5 * scale;
+	let start_x = width.saturating_sub(text_width) / 2;
+	let start_y = height.saturating_sub(text_height) / 2;
+
+	for (digit_idx, digit) in (0..).zip(digits.bytes()) {
+		let glyph = DIGITS[usize::from(digit - b'0')];
+		for (row_idx, row) in (0..).zip(glyph) {
+			for col_idx in 0..3 {
+				if row & (0b100 >> col_idx) == 0 {
+					continue;
+				}
+
+				for pixel_y in 0..scale {
+					for pixel_x in 0..scale {
+						let pixel_x = start_x + (digit_idx * 4 + col_idx) * scale + pixel_x;
+						let pixel_y = start_y + row_idx * scale + pixel_y;
+						if pixel_x < width && pixel_y < height {
+							image.put_pixel(pixel_x, pixel_y, DIGIT_COLOR);
+						}
+					}
+				}
+			}
+		}
+	}
+}
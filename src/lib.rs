@@ -0,0 +1,371 @@
+//! Zss
+//!
+//! Slideshow engine, which loads images and renders them, scrolling through and fading between them.
+//!
+//! The `zss` binary is a thin command line interface over this library.
+
+// Features
+#![feature(format_args_capture, never_type)]
+// Warnings
+#![warn(
+	clippy::correctness,
+	clippy::perf,
+	clippy::style,
+	clippy::pedantic,
+	clippy::complexity,
+	clippy::cargo,
+	clippy::nursery
+)]
+#![warn(unsafe_op_in_unsafe_fn)]
+// `match` can look better than `if` + `else`
+#![allow(clippy::single_match_else, clippy::match_bool)]
+// Some false positives
+#![allow(clippy::cargo_common_metadata)]
+// Our module organization makes this happen a lot, but struct names should be consistent
+#![allow(clippy::module_name_repetitions)]
+// We can't super control this, and it shouldn't be a big issue
+#![allow(clippy::multiple_crate_versions)]
+// Most functions would need these, which would just add noise
+#![allow(
+	clippy::missing_errors_doc,
+	clippy::missing_panics_doc,
+	clippy::must_use_candidate,
+	clippy::return_self_not_must_use
+)]
+
+// Modules
+pub mod archive;
+pub mod args;
+pub mod clock;
+pub mod config;
+pub mod current_images;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+pub mod decoders;
+pub mod dedup;
+pub mod dir_watcher;
+pub mod effects;
+pub mod filters;
+pub mod glium_backend;
+pub mod glium_facade;
+pub mod grid;
+pub mod handlers;
+pub mod history;
+pub mod hook;
+pub mod ignores;
+pub mod image_cache;
+pub mod images;
+pub mod ipc;
+#[cfg(feature = "kms")]
+pub mod kms;
+pub mod library;
+pub mod marked_paths;
+pub mod net_sync;
+pub mod night_light;
+pub mod online;
+pub mod overlay;
+pub mod palette;
+pub mod panel;
+pub mod path_list;
+pub mod program_cache;
+pub mod provider;
+pub mod quarantine;
+pub mod remote;
+pub mod renderer;
+pub mod replay;
+pub mod schedule;
+pub mod selection;
+pub mod signals;
+pub mod slide_info;
+pub mod software_renderer;
+pub mod software_window;
+pub mod storage;
+pub mod systemd;
+pub mod tempo;
+pub mod texture;
+pub mod texture_upload;
+pub mod uvs;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod window;
+
+
+// Imports
+use crate::{
+	args::{Scaling, TextureCompression},
+	glium_facade::GliumFacade,
+	images::Images,
+	slide_info::{Shape, SlideInfo},
+	texture::ImageTexture,
+	texture_upload::TextureUpload,
+	uvs::ImageUvs,
+};
+use anyhow::Context;
+use std::{collections::VecDeque, mem, time::Duration};
+
+/// Image
+#[derive(Debug)]
+pub struct Image {
+	/// Info
+	pub info: SlideInfo,
+
+	/// Frames
+	frames: Vec<ImageFrame>,
+
+	/// Current frame
+	cur_frame: usize,
+
+	/// Time elapsed on the current frame
+	frame_elapsed: Duration,
+
+	/// Video, if any
+	#[cfg(feature = "video")]
+	video: Option<video::Video>,
+
+	/// Uvs
+	uvs: ImageUvs,
+
+	/// Window size
+	window_size: [u32; 2],
+
+	/// Scaling
+	scaling: Scaling,
+
+	/// Texture compression, if any
+	compression: Option<TextureCompression>,
+
+	/// If this image is a placeholder
+	pub is_placeholder: bool,
+
+	/// Dominant color
+	dominant_color: [f32; 3],
+
+	/// Palette
+	pub palette: Vec<[f32; 3]>,
+
+	/// If this image was shown
+	pub was_shown: bool,
+}
+
+impl Image {
+	/// Minimum delay between frames
+	// Note: Some animations use a `0` delay, which browsers treat as a small delay, so we do the same
+	const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+
+	/// Creates a new image
+	pub fn new(
+		facade: &GliumFacade, images: &mut Images, window_size: [u32; 2], scaling: Scaling,
+		compression: Option<TextureCompression>,
+	) -> Result<Self, anyhow::Error> {
+		let image = images
+			.next_image(Shape::of(window_size))
+			.context("Unable to get next image")?;
+		let mut image = Self::from_image(facade, image, window_size, scaling, compression)?;
+		image.finish_upload();
+
+		Ok(image)
+	}
+
+	/// Creates a new image from an already loaded image.
+	///
+	/// It's textures are only uploaded once [`Self::upload`] finishes.
+	pub fn from_image(
+		facade: &GliumFacade, image: images::LoadedImage, window_size @ [window_width, window_height]: [u32; 2],
+		scaling: Scaling, compression: Option<TextureCompression>,
+	) -> Result<Self, anyhow::Error> {
+		let images::LoadedImage {
+			info,
+			frames,
+			dominant_color,
+			palette,
+			swap_dir,
+			#[cfg(feature = "video")]
+			video,
+		} = image;
+		let image_dims = frames[0].image.dimensions();
+		let frames = frames
+			.into_iter()
+			.map(|images::Frame { image, delay }| {
+				let (texture, upload) =
+					TextureUpload::new(facade, image, compression).context("Unable to start texture upload")?;
+
+				Ok(ImageFrame {
+					texture,
+					delay,
+					upload: Some(upload),
+				})
+			})
+			.collect::<Result<_, anyhow::Error>>()?;
+
+		#[allow(clippy::cast_precision_loss)] // Image and window sizes are likely much lower than 2^24
+		let uvs = ImageUvs::new(
+			image_dims.0 as f32,
+			image_dims.1 as f32,
+			window_width as f32,
+			window_height as f32,
+			swap_dir,
+			scaling,
+		);
+
+		Ok(Self {
+			info,
+			frames,
+			cur_frame: 0,
+			frame_elapsed: Duration::ZERO,
+			#[cfg(feature = "video")]
+			video,
+			uvs,
+			window_size,
+			scaling,
+			compression,
+			is_placeholder: false,
+			dominant_color,
+			palette,
+			was_shown: false,
+		})
+	}
+
+	/// Tries to update this image and returns if actually updated.
+	///
+	/// Uses the standby image first, if any, so it may be replenished.
+	///
+	/// If this image was shown, it's kept in `history`, which keeps at most `history_len` images.
+	pub fn try_update(
+		&mut self, facade: &GliumFacade, images: &mut Images, standby: &mut Option<Self>, history: &mut VecDeque<Self>,
+		history_len: usize, force_wait: bool,
+	) -> Result<bool, anyhow::Error> {
+		let new_image = match standby.take() {
+			Some(image) => image,
+			None => {
+				let shape = Shape::of(self.window_size);
+				let image = match images.try_next_image(shape).context("Unable to get next image")? {
+					Some(image) => image,
+					None if force_wait && !images.is_finished() => {
+						images.next_image(shape).context("Unable to get next image")?
+					},
+					None => return Ok(false),
+				};
+
+				// Note: The old textures are only recycled after, so they're still intact if we fail
+				Self::from_image(facade, image, self.window_size, self.scaling, self.compression)?
+			},
+		};
+		let old_image = mem::replace(self, new_image);
+		match old_image.was_shown && !old_image.is_placeholder && history_len != 0 {
+			true => {
+				history.push_back(old_image);
+				while history.len() > history_len {
+					if let Some(image) = history.pop_front() {
+						image.recycle(facade);
+					}
+				}
+			},
+			false => old_image.recycle(facade),
+		}
+
+		Ok(true)
+	}
+
+	/// Recycles this image's textures
+	pub fn recycle(self, facade: &GliumFacade) {
+		for frame in self.frames {
+			if let ImageTexture::Uncompressed(texture, format) = frame.texture {
+				facade.recycle_texture(texture, format);
+			}
+		}
+	}
+
+	/// Uploads at most `max_bytes` more of this image's textures and returns if they're all uploaded
+	pub fn upload(&mut self, mut max_bytes: usize) -> bool {
+		for frame in &mut self.frames {
+			if let Some(upload) = &mut frame.upload {
+				max_bytes = max_bytes.saturating_sub(upload.step(&frame.texture, max_bytes));
+				match upload.is_finished() {
+					true => frame.upload = None,
+					false => return false,
+				}
+			}
+		}
+
+		true
+	}
+
+	/// Finishes uploading this image's textures
+	pub fn finish_upload(&mut self) {
+		self.upload(usize::MAX);
+	}
+
+	/// Returns the texture of the current frame
+	pub fn texture(&self) -> &ImageTexture {
+		&self.frames[self.cur_frame].texture
+	}
+
+	/// Advances the animation of this image, if any
+	pub fn advance(&mut self, elapsed: Duration) {
+		#[cfg(feature = "video")]
+		if self.advance_video(elapsed) {
+			return;
+		}
+
+		if self.frames.len() <= 1 {
+			return;
+		}
+
+		self.frame_elapsed += elapsed;
+		loop {
+			let delay = self.frames[self.cur_frame].delay.max(Self::MIN_FRAME_DELAY);
+			if self.frame_elapsed < delay {
+				break;
+			}
+
+			self.frame_elapsed -= delay;
+			self.cur_frame = (self.cur_frame + 1) % self.frames.len();
+		}
+	}
+
+	/// Advances the video of this image, returning if it was a video
+	///
+	/// Skips any frames we're late on and only uploads the latest one.
+	#[cfg(feature = "video")]
+	fn advance_video(&mut self, elapsed: Duration) -> bool {
+		let video = match &self.video {
+			Some(video) => video,
+			None => return false,
+		};
+
+		self.frame_elapsed += elapsed;
+
+		let mut frame = None;
+		while self.frame_elapsed >= video.frame_delay() {
+			self.frame_elapsed -= video.frame_delay();
+
+			// Note: If the decoder can't keep up, just keep the current frame
+			match video.try_next_frame() {
+				Some(next_frame) => frame = Some(next_frame),
+				None => {
+					self.frame_elapsed = Duration::ZERO;
+					break;
+				},
+			}
+		}
+
+		if let Some(frame) = frame {
+			self.frames[0].texture.write(&frame);
+		}
+
+		true
+	}
+}
+
+/// Image frame
+#[derive(Debug)]
+pub struct ImageFrame {
+	/// Texture
+	texture: ImageTexture,
+
+	/// Delay until the next frame
+	delay: Duration,
+
+	/// Upload of the texture, if unfinished
+	upload: Option<TextureUpload>,
+}
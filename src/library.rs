@@ -0,0 +1,90 @@
+//! Library
+//!
+//! All paths known to the image loader, kept in sync with the directory watcher's
+//! events, so removed and renamed files are no longer selected.
+
+// Imports
+use std::{
+	collections::HashSet,
+	path::{Path, PathBuf},
+};
+
+/// Library
+#[derive(Debug, Default)]
+pub struct Library {
+	/// All paths, in the order they were added
+	paths: Vec<PathBuf>,
+
+	/// All paths, for fast lookups
+	paths_set: HashSet<PathBuf>,
+}
+
+impl Library {
+	/// Returns all paths
+	pub fn paths(&self) -> &[PathBuf] {
+		&self.paths
+	}
+
+	/// Returns if there are no paths
+	pub fn is_empty(&self) -> bool {
+		self.paths.is_empty()
+	}
+
+	/// Returns if `path` was added
+	pub fn contains(&self, path: &Path) -> bool {
+		self.paths_set.contains(path)
+	}
+
+	/// Adds `path`, returning if it wasn't already added
+	pub fn add(&mut self, path: PathBuf) -> bool {
+		if !self.paths_set.insert(path.clone()) {
+			return false;
+		}
+
+		self.paths.push(path);
+		true
+	}
+
+	/// Removes `path`, along with any paths within it, if a directory.
+	///
+	/// Returns the number of paths removed.
+	pub fn remove(&mut self, path: &Path) -> usize {
+		let prev_len = self.paths.len();
+		self.retain(|cur_path| !cur_path.starts_with(path));
+		prev_len - self.paths.len()
+	}
+
+	/// Renames `from` to `to`, along with any paths within it, if a directory.
+	///
+	/// Returns the renamed paths.
+	pub fn rename(&mut self, from: &Path, to: &Path) -> Vec<PathBuf> {
+		let renamed = self
+			.paths
+			.iter()
+			.filter_map(|path| path.strip_prefix(from).ok())
+			.map(|rest| match rest.as_os_str().is_empty() {
+				true => to.to_path_buf(),
+				false => to.join(rest),
+			})
+			.collect::<Vec<_>>();
+
+		self.remove(from);
+		for path in &renamed {
+			self.add(path.clone());
+		}
+
+		renamed
+	}
+
+	/// Retains only the paths for which `f` returns `true`
+	pub fn retain(&mut self, mut f: impl FnMut(&Path) -> bool) {
+		let paths_set = &mut self.paths_set;
+		self.paths.retain(|path| match f(path) {
+			true => true,
+			false => {
+				paths_set.remove(path);
+				false
+			},
+		});
+	}
+}
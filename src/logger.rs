@@ -0,0 +1,228 @@
+//! Logger
+//!
+//! Filters records by their module before writing them to `stderr` or the log file, as `simplelog`
+//! only supports a single level for all modules.
+
+// Imports
+use anyhow::Context;
+use log::{LevelFilter, Log, Metadata, Record};
+use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
+use std::{
+	fs::{self, File},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+use zss::args::{LogArgs, LogFormat};
+
+/// Number of rotated log files kept
+const ROTATED_FILES: usize = 3;
+
+/// Initializes the logger
+pub fn init(args: &LogArgs) -> Result<(), anyhow::Error> {
+	// Note: The terminal logger accepts everything, we filter the records before them reaching it.
+	let output = match (&args.file, args.format) {
+		(Some(path), _) => {
+			let file = LogFile::open(path.clone(), args.max_file_size)
+				.with_context(|| format!("Unable to open log file {}", path.display()))?;
+			Output::File(Mutex::new(file))
+		},
+		(None, LogFormat::Text) => Output::Term(TermLogger::new(
+			LevelFilter::Trace,
+			Config::default(),
+			TerminalMode::Stderr,
+			ColorChoice::Auto,
+		)),
+		(None, LogFormat::Json) => Output::Stderr,
+	};
+	let logger = Logger {
+		level: args.level,
+		module_levels: args.module_levels.clone(),
+		format: args.format,
+		output,
+	};
+	let max_level = args
+		.module_levels
+		.iter()
+		.map(|&(_, level)| level)
+		.fold(args.level, Ord::max);
+
+	log::set_boxed_logger(Box::new(logger)).context("Unable to set logger")?;
+	log::set_max_level(max_level);
+	Ok(())
+}
+
+/// Logger
+struct Logger {
+	/// Default level
+	level: LevelFilter,
+
+	/// Levels of specific modules
+	module_levels: Vec<(String, LevelFilter)>,
+
+	/// Format
+	format: LogFormat,
+
+	/// Output
+	output: Output,
+}
+
+impl Logger {
+	/// Returns the level of the module `target`
+	///
+	/// Uses the level of the most specific module containing `target`, if any.
+	fn level(&self, target: &str) -> LevelFilter {
+		self.module_levels
+			.iter()
+			.filter(|(module, _)| {
+				target
+					.strip_prefix(module.as_str())
+					.map_or(false, |rest| rest.is_empty() || rest.starts_with("::"))
+			})
+			.max_by_key(|(module, _)| module.len())
+			.map_or(self.level, |&(_, level)| level)
+	}
+
+	/// Formats `record` as a single line
+	fn format(&self, record: &Record) -> String {
+		let time = chrono::Local::now();
+		match self.format {
+			LogFormat::Text => format!(
+				"{} [{}] {}: {}",
+				time.format("%Y-%m-%d %H:%M:%S%.3f"),
+				record.level(),
+				record.target(),
+				record.args()
+			),
+			LogFormat::Json => serde_json::json!({
+				"time": time.to_rfc3339(),
+				"level": record.level().as_str(),
+				"target": record.target(),
+				"message": record.args().to_string(),
+			})
+			.to_string(),
+		}
+	}
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= self.level(metadata.target())
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+
+		// Note: We can't log any errors, so we just print them
+		let res = match &self.output {
+			Output::Term(logger) => {
+				logger.log(record);
+				Ok(())
+			},
+			Output::Stderr => writeln!(io::stderr(), "{}", self.format(record)),
+			Output::File(file) => file
+				.lock()
+				.expect("Log file lock was poisoned")
+				.write_line(&self.format(record)),
+		};
+		if let Err(err) = res {
+			eprintln!("Unable to log: {err}");
+		}
+	}
+
+	fn flush(&self) {
+		match &self.output {
+			Output::Term(logger) => logger.flush(),
+			Output::Stderr => (),
+			Output::File(file) => {
+				let mut file = file.lock().expect("Log file lock was poisoned");
+				if let Err(err) = file.file.flush() {
+					eprintln!("Unable to flush log file: {err}");
+				}
+			},
+		}
+	}
+}
+
+/// Logger output
+enum Output {
+	/// Terminal, through `simplelog`
+	Term(Box<TermLogger>),
+
+	/// `stderr`, without any colors
+	Stderr,
+
+	/// Log file
+	File(Mutex<LogFile>),
+}
+
+/// Log file, rotated once too large
+struct LogFile {
+	/// Path
+	path: PathBuf,
+
+	/// File
+	file: File,
+
+	/// Current size
+	size: u64,
+
+	/// Maximum size
+	max_size: u64,
+}
+
+impl LogFile {
+	/// Opens the log file at `path`, appending to it
+	fn open(path: PathBuf, max_size: u64) -> Result<Self, io::Error> {
+		let file = self::open_append(&path)?;
+		let size = file.metadata()?.len();
+
+		Ok(Self {
+			path,
+			file,
+			size,
+			max_size,
+		})
+	}
+
+	/// Writes a line to the log file, rotating it first if it'd grow too large
+	fn write_line(&mut self, line: &str) -> Result<(), io::Error> {
+		let line_len = line.len() as u64 + 1;
+		if self.size != 0 && self.size + line_len > self.max_size {
+			self.rotate()?;
+		}
+
+		writeln!(self.file, "{line}")?;
+		self.size += line_len;
+		Ok(())
+	}
+
+	/// Rotates the log file, moving each file to the next index and deleting the oldest
+	fn rotate(&mut self) -> Result<(), io::Error> {
+		for idx in (1..ROTATED_FILES).rev() {
+			match fs::rename(self.rotated_path(idx), self.rotated_path(idx + 1)) {
+				Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+				_ => (),
+			}
+		}
+		fs::rename(&self.path, self.rotated_path(1))?;
+
+		self.file = self::open_append(&self.path)?;
+		self.size = 0;
+		Ok(())
+	}
+
+	/// Returns the path of the `idx`th rotated file
+	fn rotated_path(&self, idx: usize) -> PathBuf {
+		let mut path = self.path.clone().into_os_string();
+		path.push(format!(".{idx}"));
+		PathBuf::from(path)
+	}
+}
+
+/// Opens `path` for appending, creating it if it doesn't exist
+fn open_append(path: &Path) -> Result<File, io::Error> {
+	fs::OpenOptions::new().create(true).append(true).open(path)
+}
@@ -1,7 +1,7 @@
 //! Zss
 
 // Features
-#![feature(format_args_capture, try_blocks, drain_filter, never_type)]
+#![feature(format_args_capture)]
 // Warnings
 #![warn(
 	clippy::correctness,
@@ -23,401 +23,1135 @@
 #![allow(clippy::multiple_crate_versions)]
 
 // Modules
-mod args;
-mod glium_backend;
-mod glium_facade;
-mod images;
-mod uvs;
-mod window;
+mod bench;
+mod check;
+mod layout_preview;
+mod logger;
+mod preview;
+mod render;
+mod set;
+mod software;
+mod stats;
+mod verify;
 
 // Imports
-use crate::{glium_backend::GliumBackend, glium_facade::GliumFacade, images::Images, uvs::ImageUvs};
 use anyhow::Context;
-use args::Args;
-use cgmath::{Matrix4, Point2, Vector2, Vector3};
-use glium::Surface;
-use std::{mem, rc::Rc};
-use window::Window;
+use glium::SwapBuffersError;
+use std::{
+	mem,
+	path::Path,
+	rc::Rc,
+	sync::{mpsc, Arc},
+	thread,
+	time::{Duration, Instant},
+};
+#[cfg(feature = "dbus")]
+use zss::dbus;
+use zss::{
+	args::{self, Args, Command, TextureCompression},
+	config::{Config, OutputConfig},
+	current_images::CurrentImages,
+	effects::Effects,
+	filters::Filters,
+	glium_backend::{GlSurface, GliumBackend},
+	glium_facade::GliumFacade,
+	history::{History, ShowCounts},
+	hook::Hook,
+	images::{Images, LoadOptions, PathSource},
+	ipc::{self, Ipc, IpcCommand},
+	marked_paths::MarkedPaths,
+	net_sync::{self, SyncLeader},
+	overlay::{Overlays, WidgetState},
+	palette::PaletteWriter,
+	panel::{self, Mirror, Panel, UpdateContext},
+	provider::{CommandProvider, FollowProvider},
+	quarantine::Quarantine,
+	renderer::{DrawParams, Renderer},
+	replay::{Recorder, Replay},
+	schedule::Schedule,
+	selection::{ClockSync, NoRepeat},
+	signals::Signals,
+	software_window::SoftwareWindow,
+	storage,
+	systemd::Systemd,
+	tempo::Tempo,
+	texture_upload::TextureUpload,
+	window::Window,
+	Image,
+};
+
+/// Maximum time to wait for the image loader to stop when quitting
+const LOADER_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Number of attempts to rebuild everything after the gl context is lost
+const CONTEXT_REBUILD_ATTEMPTS: usize = 5;
+
+/// Delay between attempts to rebuild everything after the gl context is lost
+// Note: The gpu might still be resetting, so we give it some time
+const CONTEXT_REBUILD_DELAY: Duration = Duration::from_secs(1);
+
+/// Duration of each frame
+// Note: We assume the display is synchronized to 60hz
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
 #[allow(clippy::too_many_lines)] // TODO: Refactor
 fn main() -> Result<(), anyhow::Error> {
-	// Initialize logger
-	simplelog::TermLogger::init(
-		log::LevelFilter::Info,
-		simplelog::Config::default(),
-		simplelog::TerminalMode::Stderr,
-		simplelog::ColorChoice::Auto,
-	)
-	.expect("Unable to initialize logger");
-
-	// Get arguments
-	let args = Args::new().context("Unable to retrieve arguments")?;
-
-	// Then create the window
-	let window = Window::from_window_id(args.window_id)
-		.map(Rc::new)
-		.context("Unable to create window")?;
-
-	// Load images
-	let mut images = Images::new(args.images_dir.clone(), args.image_backlog, window.size())
-		.with_context(|| format!("Unable to start loading images from {}", args.images_dir.display()))?;
-
-	// Create the backend
-	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
-
-	// And then create the glium facade
-	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+	// Get arguments and initialize the logger
+	let (command, log_args) = Command::new().context("Unable to retrieve arguments")?;
+	logger::init(&log_args).context("Unable to initialize logger")?;
 
-	// Create the indices buffer
-	let indices =
-		glium::IndexBuffer::<u32>::new(&facade, glium::index::PrimitiveType::TrianglesList, &[0, 1, 3, 0, 3, 2])
-			.context("Unable to create index buffer")?;
-
-	// Create the program
-	let program = {
-		glium::Program::new(&facade, glium::program::ProgramCreationInput::SourceCode {
-			vertex_shader:                  include_str!("vertex.glsl"),
-			fragment_shader:                include_str!("frag.glsl"),
-			geometry_shader:                None,
-			tessellation_control_shader:    None,
-			tessellation_evaluation_shader: None,
-			transform_feedback_varyings:    None,
-			outputs_srgb:                   true,
-			uses_point_size:                false,
-		})
+	let (mut args, mut replay) = match command {
+		Command::Run(args) => (args, None),
+		Command::Set(args) => {
+			return set::run(&args).with_context(|| format!("Unable to set {}", args.image.display()));
+		},
+		Command::LayoutPreview(args) => {
+			return layout_preview::render(args.grid_width, args.grid_height, args.size, &args.out)
+				.with_context(|| format!("Unable to render layout preview to {}", args.out.display()));
+		},
+		Command::Verify(args) => return verify::run(&args).context("Unable to verify frames"),
+		Command::Preview(args) => {
+			return preview::run(&args).with_context(|| format!("Unable to render preview to {}", args.out.display()));
+		},
+		Command::Render(args) => {
+			return render::run(&args).with_context(|| format!("Unable to render video to {}", args.out.display()));
+		},
+		Command::Stats(args) => return stats::run(&args).context("Unable to print stats"),
+		Command::Check(args) => return check::run(&args).context("Unable to check images"),
+		Command::Bench(args) => return bench::run(&args).context("Unable to benchmark images"),
+		Command::Ctl(args) => {
+			// Note: The daemon likely has a different working directory, so we make screenshot paths absolute
+			let command = match args.command.strip_prefix("screenshot ").map(Path::new) {
+				Some(path) if path.is_relative() => {
+					let cur_dir = std::env::current_dir().context("Unable to get current directory")?;
+					let path = cur_dir.join(path);
+					let path = path.to_str().context("Screenshot path must be utf-8")?;
+					format!("screenshot {path}")
+				},
+				_ => args.command.clone(),
+			};
+
+			let response = ipc::send(&args.ipc_socket, &command)
+				.with_context(|| format!("Unable to send command to {}", args.ipc_socket.display()))?;
+			println!("{response}");
+			return Ok(());
+		},
+		Command::Replay(replay_args) => {
+			let replay = Replay::load(&replay_args.path)
+				.with_context(|| format!("Unable to load replay from {}", replay_args.path.display()))?;
+			let mut args = match Command::from_args(replay.args.clone()).context("Unable to parse replay arguments")? {
+				(Command::Run(args), _) => args,
+				_ => anyhow::bail!("Replay wasn't recorded from a running wallpaper"),
+			};
+
+			// Note: The recorded window is likely gone by now, and we shouldn't overwrite the replay we're reading
+			let name = args.outputs.swap_remove(0).name;
+			args.outputs = vec![args::Output {
+				name,
+				kind: args::OutputKind::Window(replay_args.window_id),
+			}];
+			args.record = None;
+			(args, Some(replay))
+		},
+	};
+
+	for output in &args.outputs {
+		log::info!("Showing on {output}");
+	}
+
+	// Load the config, if any
+	let config = match &args.config_path {
+		Some(config_path) => Config::load(config_path)
+			.with_context(|| format!("Unable to load config from {}", config_path.display()))?,
+		None => Config::default(),
+	};
+	config.draw.apply(&mut args).context("Unable to apply draw config")?;
+	config
+		.overlay
+		.apply(&mut args)
+		.context("Unable to apply overlay config")?;
+
+	// Get the arguments of each output, with it's section of the config applied, if any
+	for name in config.outputs.keys() {
+		if !args.outputs.iter().any(|output| output.name.as_ref() == Some(name)) {
+			log::warn!("Ignoring config of output {name:?}, no output has that name");
+		}
 	}
-	.context("Unable to build program")?;
+	let mut outputs_args = args
+		.outputs
+		.iter()
+		.map(|output| {
+			let mut output_args = args.clone();
+			if let Some(output_config) = self::output_config(&config, output) {
+				output_config
+					.apply(&mut output_args)
+					.with_context(|| format!("Unable to apply config of {output}"))?;
+			}
+			Ok(output_args)
+		})
+		.collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+	// Load the banned and favorite images
+	let banned = Arc::new(MarkedPaths::load(args.ban_list.clone()));
+	let favorites = Arc::new(MarkedPaths::load(args.favorites_list.clone()));
 
-	// All images
-	let mut images_data = Vec::new();
+	// Note: The images directory must be absolute, to compare with the paths of the images
+	let images_dir = std::env::current_dir()
+		.context("Unable to get current directory")?
+		.join(&args.images_dir);
 
-	match args.mode {
-		args::Mode::Single => {
-			let cur_image = Image::new(&facade, &mut images, window.size()).context("Unable to create image")?;
-			let next_image = Image::new(&facade, &mut images, window.size()).context("Unable to create image")?;
-			images_data.push((cur_image, next_image, 0.0, false));
+	// Then create all windows, falling back to software rendering if open-gl is unavailable
+	let gl = match args.software_rendering {
+		true => None,
+		false => match args.outputs.iter().map(self::create_gl).collect::<Result<Vec<_>, _>>() {
+			Ok(gl) => Some(gl),
+			Err(err) => match args.outputs.as_slice() {
+				[args::Output {
+					kind: args::OutputKind::Window(_),
+					..
+				}] => {
+					log::warn!("Unable to use open-gl, falling back to software rendering: {err:?}");
+					None
+				},
+				// Note: Without open-gl, we can't draw anything on a drm device, and software rendering only
+				//       supports a single window
+				_ => return Err(err),
+			},
+		},
+	};
+	let gl = match (gl, args.outputs.as_slice()) {
+		(Some(gl), _) => gl,
+		(
+			None,
+			[output @ args::Output {
+				kind: args::OutputKind::Window(window_id),
+				..
+			}],
+		) => {
+			let window = SoftwareWindow::from_window_id(*window_id).context("Unable to create software window")?;
+			let args = outputs_args.swap_remove(0);
+			self::check_window_size(&args, output, window.size())?;
+
+			// Note: Images are sampled at their nearest pixel, so they must always be resized beforehand
+			let load_options = LoadOptions {
+				window_size:      window.size(),
+				scaling:          args.scaling,
+				resize_filter:    args.resize_filter,
+				gpu_resize:       false,
+				rgb:              args.rgb,
+				max_texture_size: None,
+				cache_size:       args.image_cache_size,
+			};
+			let images = self::create_images(
+				&args,
+				config,
+				&mut replay,
+				load_options,
+				&images_dir,
+				&banned,
+				&favorites,
+			)?;
+			return software::run(args, window, images);
 		},
-		args::Mode::Grid { width, height } => {
-			let [window_width, window_height] = window.size();
+		(None, _) => anyhow::bail!("Software rendering only supports a single window, and not drm devices"),
+	};
+	for ((output, output_args), (window, _)) in args.outputs.iter().zip(&outputs_args).zip(&gl) {
+		self::check_window_size(output_args, output, window.size())?;
+	}
 
-			#[allow(clippy::cast_possible_truncation)] // Widths and heights will be small enough for this to not matter
-			let window_size = [window_width / width as u32, window_height / height as u32];
+	// Note: If the gpu can't compress textures, we just leave them uncompressed
+	let compress_textures = args.compress_textures.filter(|&compression| {
+		let is_supported = gl
+			.iter()
+			.all(|(_, facade)| TextureUpload::supports_compression(facade, compression));
+		if !is_supported {
+			log::warn!("Gpu doesn't support {compression:?} texture compression, leaving textures uncompressed");
+		}
+		is_supported
+	});
 
-			for _y in 0..height {
-				for _x in 0..width {
-					let cur_image = Image::new(&facade, &mut images, window_size).context("Unable to create image")?;
-					let next_image = Image::new(&facade, &mut images, window_size).context("Unable to create image")?;
+	// Load images, either from the replay or the images directory
+	// Note: Windows share the same images, so they're loaded for the largest one
+	let largest_window_size = gl
+		.iter()
+		.map(|(window, _)| window.size())
+		.fold([0, 0], |[max_width, max_height], [width, height]| {
+			[max_width.max(width), max_height.max(height)]
+		});
+	let load_options = LoadOptions {
+		window_size:      largest_window_size,
+		scaling:          args.scaling,
+		resize_filter:    args.resize_filter,
+		gpu_resize:       args.gpu_resize,
+		rgb:              args.rgb,
+		max_texture_size: gl.iter().map(|(_, facade)| facade.max_texture_size()).min(),
+		cache_size:       args.image_cache_size,
+	};
+	// Note: Outputs with their own images directory are loaded separately, and only the rest are replayed
+	let mut images = vec![];
+	let mut images_dirs = vec![];
+	let mut outputs_images = vec![];
+	for (output, output_args) in args.outputs.iter().zip(&outputs_args) {
+		let own_images_dir = self::output_config(&config, output).and_then(|config| config.images_dir.as_ref());
+		let idx = match images_dirs.iter().position(|&dir| dir == own_images_dir) {
+			Some(idx) => idx,
+			None => {
+				let replay = match own_images_dir {
+					Some(_) => &mut None,
+					None => &mut replay,
+				};
+				images.push(self::create_images(
+					output_args,
+					config.clone(),
+					replay,
+					load_options,
+					&images_dir,
+					&banned,
+					&favorites,
+				)?);
+				images_dirs.push(own_images_dir);
+				images.len() - 1
+			},
+		};
+		outputs_images.push(idx);
+	}
 
-					let progress = rand::random();
+	// Start leading, if requested
+	let sync_leader = args
+		.sync_lead
+		.as_deref()
+		.map(|addr| SyncLeader::bind(addr, images_dir.clone()))
+		.transpose()
+		.context("Unable to start leading")?;
 
-					images_data.push((cur_image, next_image, progress, true));
-				}
+	// Start recording, if requested
+	let recorder = match &args.record {
+		Some(record_path) => {
+			let recorder = Recorder::create(record_path, std::env::args_os())
+				.with_context(|| format!("Unable to start recording to {}", record_path.display()))?;
+			// Note: Only the images of outputs without their own images directory are recorded
+			if let Some(idx) = images_dirs.iter().position(Option::is_none) {
+				images[idx].set_recorder(recorder.clone());
 			}
+			Some(recorder)
+		},
+		None => None,
+	};
+
+	// Count how many times each image is shown, if persisting the history
+	if args.persist_history {
+		match ShowCounts::load() {
+			Some(show_counts) => {
+				for images in &mut images {
+					images.set_show_counts(show_counts.clone());
+				}
+			},
+			None => log::warn!("Unable to count how many times each image is shown, no state directory"),
+		}
+	}
+
+	// Start listening for commands
+	// Note: Ipc isn't required, so we just warn if we can't start it
+	let current_images = Arc::new(CurrentImages::default());
+	let ipc = match Ipc::new(args.ipc_socket.clone(), Arc::clone(&current_images)) {
+		Ok(ipc) => Some(ipc),
+		Err(err) => {
+			log::warn!("Unable to start ipc at {}: {err:?}", args.ipc_socket.display());
+			None
+		},
+	};
+
+	// Note: Same for signals and D-Bus
+	let signals = match Signals::new() {
+		Ok(signals) => Some(signals),
+		Err(err) => {
+			log::warn!("Unable to handle signals: {err:?}");
+			None
 		},
+	};
+
+	// Note: We might block waiting for images, so quitting must interrupt the wait, else we'd never quit
+	if let Some(signals) = &signals {
+		for images in &mut images {
+			images.set_interrupt(signals.quit_flag());
+		}
 	}
+	#[cfg(feature = "dbus")]
+	let dbus = match dbus::Dbus::new(Arc::clone(&current_images)) {
+		Ok(dbus) => Some(dbus),
+		Err(err) => {
+			log::warn!("Unable to start D-Bus interface: {err:?}");
+			None
+		},
+	};
+
+	// Note: Same for systemd, which we're usually not running under
+	let mut systemd = Systemd::from_env().unwrap_or_else(|err| {
+		log::warn!("Unable to notify systemd: {err:?}");
+		None
+	});
+
+	// Create the tempo, if any
+	let mut tempo = args.bpm.map(|bpm| Tempo::new(bpm, args.beats_per_image));
+
+	// Create the hook, if any
+	let on_change = args.on_change.as_deref().map(Hook::new);
+
+	// Create the palette writer, if any
+	let palette_writer = args
+		.palette_out
+		.as_ref()
+		.map(|out| PaletteWriter::new(out.clone(), args.palette_template.as_deref()))
+		.transpose()
+		.context("Unable to create palette writer")?;
+
+	// If we're paused
+	let mut paused = args.start_held;
+
+	// Create the renderer and all panels of each window
+	let screens = args
+		.outputs
+		.iter()
+		.zip(outputs_args)
+		.zip(outputs_images)
+		.zip(gl)
+		.map(|(((output, output_args), output_images), (window, facade))| {
+			let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+			let panels = self::create_panels(
+				&output_args,
+				&facade,
+				&mut images[output_images],
+				window.size(),
+				compress_textures,
+				|| {
+					let progress = match &mut replay {
+						Some(replay) => replay.progresses.pop_front().unwrap_or_default(),
+						None => rand::random(),
+					};
+					if let Some(recorder) = &recorder {
+						recorder.progress(progress);
+					}
+					progress
+				},
+			)?;
+			let overlays = self::create_overlays(&output_args, &*window);
 
+			Ok(Screen {
+				output: output.clone(),
+				args: output_args,
+				images: output_images,
+				window,
+				facade,
+				renderer,
+				panels,
+				overlays,
+				arranged_at: Instant::now(),
+			})
+		})
+		.collect::<Result<Vec<_>, anyhow::Error>>();
+	let mut screens = match screens {
+		Ok(screens) => screens,
+		Err(err) if signals.as_ref().map_or(false, Signals::quit_requested) => {
+			log::info!("Quitting before showing any images: {err:?}");
+			self::stop_images(images);
+			return Ok(());
+		},
+		Err(err) => return Err(err),
+	};
+
+	let start_time = Instant::now();
+	'frames: for frame in 0_u64.. {
+		// If we're done replaying, quit
+		if images.iter().any(Images::is_replay_finished) {
+			log::info!("Finished replaying");
+			break;
+		}
+
+		// If we've ran for long enough, quit
+		if args
+			.exit_after
+			.map_or(false, |exit_after| start_time.elapsed() >= exit_after)
+		{
+			log::info!("Exiting after {:?}", start_time.elapsed());
+			break;
+		}
 
-	loop {
 		// Process events
-		window.process_events();
-
-		// Draw
-		let mut target = facade.draw();
-
-		// Clear the screen
-		target.clear_color(0.0, 0.0, 0.0, 1.0);
-
-		match args.mode {
-			args::Mode::Single => {
-				let (cur_image, next_image, progress, next_image_is_loaded) = &mut images_data[0];
-
-				self::draw_update(
-					&mut target,
-					progress,
-					&args,
-					cur_image,
-					next_image,
-					&indices,
-					&program,
-					next_image_is_loaded,
-					&facade,
-					&mut images,
-					Vector2::new(1.0, 1.0),
-					Point2::new(0.0, 0.0),
-				);
-			},
-			#[allow(clippy::cast_precision_loss)] // Grids will be less than `2^23`
-			args::Mode::Grid { width, height } => {
-				for y in 0..height {
-					for x in 0..width {
-						let (cur_image, next_image, progress, next_image_is_loaded) = &mut images_data[width * y + x];
-
-						let scale = Vector2::new(1.0 / (width as f32), 1.0 / (height as f32));
-						//let offset = Point2::new((2.0 * x as f32 * scale.x) - 1.0, (2.0 * y as f32 * scale.y) - 1.0);
-						//let offset = Point2::new(x as f32 * scale.x, y as f32 * scale.y);
-						#[allow(clippy::suboptimal_flops)] // This isn't calculated very often.
-						let offset = Point2::new(
-							-1.0 + scale.x + 2.0 * scale.x * x as f32,
-							-1.0 + scale.y + 2.0 * scale.y * y as f32,
-						);
-
-						self::draw_update(
-							&mut target,
-							progress,
-							&args,
+		for screen in &screens {
+			screen.window.process_events();
+		}
+
+		// Process all ipc, signal and D-Bus commands
+		loop {
+			let command = ipc
+				.as_ref()
+				.and_then(Ipc::try_recv)
+				.or_else(|| signals.as_ref().and_then(Signals::try_recv));
+			#[cfg(feature = "dbus")]
+			let command = command.or_else(|| dbus.as_ref().and_then(dbus::Dbus::try_recv));
+			let command = match command {
+				Some(command) => command,
+				None => break,
+			};
+
+			match command {
+				IpcCommand::Tap => match &mut tempo {
+					Some(tempo) => {
+						tempo.tap(Instant::now());
+						if let Some(recorder) = &recorder {
+							recorder.tap(frame);
+						}
+					},
+					None => log::warn!("Ignoring tap, no tempo was set with `--bpm`"),
+				},
+
+				// Note: Skipping just starts fading into the next image
+				IpcCommand::Next => {
+					log::info!("Skipping to the next image");
+					for panel in screens
+						.iter_mut()
+						.flat_map(|screen| &mut screen.panels)
+						.filter(|panel| !panel.pinned)
+					{
+						panel.progress = panel.progress.max(args.fade);
+					}
+				},
+				IpcCommand::Prev => {
+					log::info!("Going back to the previous image");
+					for Screen { facade, panels, .. } in &mut screens {
+						for Panel {
 							cur_image,
 							next_image,
-							&indices,
-							&program,
+							progress,
 							next_image_is_loaded,
-							&facade,
-							&mut images,
-							scale,
-							offset,
-						);
+							standby,
+							history,
+							..
+						} in panels
+						{
+							// Note: Going back while already fading would skip over the image being faded into
+							if *progress >= args.fade {
+								log::debug!("Ignoring going back, already fading");
+								continue;
+							}
+
+							// Note: If the next image wasn't replaced yet, it's the one previously shown, so we don't
+							//       need to take it from the history.
+							let next_is_prev =
+								!*next_image_is_loaded && next_image.was_shown && !next_image.is_placeholder;
+							if !next_is_prev {
+								let prev_image = match history.pop_back() {
+									Some(image) => image,
+									None => {
+										log::debug!("No previous images to go back to");
+										continue;
+									},
+								};
+
+								// Note: If the next image was already loaded, we keep it to show after
+								let next = mem::replace(next_image, prev_image);
+								match *next_image_is_loaded && standby.is_none() {
+									true => *standby = Some(next),
+									false => next.recycle(facade),
+								}
+							}
+							*next_image_is_loaded = true;
+
+							// Note: The current image is skipped over when going back, so it isn't kept in the history
+							cur_image.was_shown = false;
+							*progress = args.fade;
+						}
 					}
-				}
-			},
+				},
+				IpcCommand::Pause => {
+					log::info!("Pausing");
+					paused = true;
+				},
+				IpcCommand::Resume => {
+					log::info!("Resuming");
+					paused = false;
+				},
+				IpcCommand::TogglePause => {
+					paused = !paused;
+					match paused {
+						true => log::info!("Pausing"),
+						false => log::info!("Resuming"),
+					}
+				},
+				IpcCommand::SetDuration(duration) => {
+					log::info!("Setting duration to {duration:?}");
+					args.duration = duration;
+					for screen in &mut screens {
+						screen.args.duration = duration;
+					}
+				},
+				// Note: We rescan all images, without stopping at the first that's showing an images directory
+				IpcCommand::Reload => match images.iter().map(Images::rescan).fold(false, |lhs, rhs| lhs | rhs) {
+					true => log::info!("Rescanning images directory"),
+					false => log::warn!("Ignoring reload, not showing an images directory"),
+				},
+
+				// Note: Any copies of the image already loaded are still shown, only new loads skip it
+				IpcCommand::Ban(panel) => match screens.iter_mut().flat_map(|screen| &mut screen.panels).nth(panel) {
+					Some(Panel {
+						cur_image, progress, ..
+					}) if !cur_image.is_placeholder => {
+						let path = &cur_image.info.path;
+						match banned.add(path) {
+							Ok(_) => log::info!("Banned {path:?}"),
+							Err(err) => log::warn!("Unable to save ban of {path:?}: {err:?}"),
+						}
+						*progress = progress.max(args.fade);
+					},
+					Some(_) => log::warn!("Ignoring ban, panel {panel} isn't showing an image"),
+					None => log::warn!("Ignoring ban, panel {panel} doesn't exist"),
+				},
+				IpcCommand::Fav(panel) => match screens.iter().flat_map(|screen| &screen.panels).nth(panel) {
+					Some(Panel { cur_image, .. }) if !cur_image.is_placeholder => {
+						let path = &cur_image.info.path;
+						match favorites.add(path) {
+							Ok(true) => log::info!("Added {path:?} to favorites"),
+							Ok(false) => log::info!("{path:?} was already a favorite"),
+							Err(err) => log::warn!("Unable to save favorite {path:?}: {err:?}"),
+						}
+					},
+					Some(_) => log::warn!("Ignoring favorite, panel {panel} isn't showing an image"),
+					None => log::warn!("Ignoring favorite, panel {panel} doesn't exist"),
+				},
+				// Note: Only the first window is saved, as there's only a single path
+				IpcCommand::Screenshot(path) => {
+					let res = screens[0]
+						.facade
+						.read_front_buffer()
+						.and_then(|image| image.save(&path).context("Unable to save image"));
+					match res {
+						Ok(()) => log::info!("Saved screenshot to {path:?}"),
+						Err(err) => log::warn!("Unable to save screenshot to {path:?}: {err:?}"),
+					}
+				},
+				IpcCommand::Quit => {
+					log::info!("Quitting");
+
+					// Clear all windows, so we don't leave a stale frame behind, such as after logging out
+					for Screen { facade, renderer, .. } in &screens {
+						let mut target = facade.draw();
+						if let Err(err) = renderer.draw(&mut target, &[], &DrawParams::new(&args, Effects::new(&args)))
+						{
+							log::warn!("Unable to clear window: {err:?}");
+						}
+						target.finish().context("Unable to finish drawing")?;
+					}
+					break 'frames;
+				},
+			}
 		}
 
-		// Finish drawing
-		target.finish().context("Unable to finish drawing")?;
-	}
-}
+		// Replay any taps on this frame
+		if let (Some(replay), Some(tempo)) = (&mut replay, &mut tempo) {
+			while replay.taps.front().map_or(false, |&tap_frame| tap_frame <= frame) {
+				replay.taps.pop_front();
+				tempo.tap(Instant::now());
+			}
+		}
 
-/// Draws and updates
-#[allow(clippy::too_many_arguments)] // TODO: Refactor, closure doesn't work, though
-fn draw_update(
-	target: &mut glium::Frame, progress: &mut f32, args: &args::Args, cur_image: &mut Image, next_image: &mut Image,
-	indices: &glium::IndexBuffer<u32>, program: &glium::Program, next_image_is_loaded: &mut bool, facade: &GliumFacade,
-	images: &mut Images, scale: Vector2<f32>, offset: Point2<f32>,
-) {
-	if let Err(err) = self::draw(
-		target, *progress, args, cur_image, next_image, indices, program, scale, offset,
-	) {
-		// Note: We just want to ensure we don't get a panic by dropping an unwrapped target
-		let _ = target.set_finish();
-		log::warn!("Unable to draw: {err:?}");
-	}
+		// Check if we're on a beat that should trigger a transition
+		let beat = tempo.as_mut().map_or(false, |tempo| tempo.tick(Instant::now()));
 
-	if let Err(err) = self::update(
-		progress,
-		next_image_is_loaded,
-		args,
-		cur_image,
-		next_image,
-		facade,
-		images,
-	) {
-		log::warn!("Unable to update: {err:?}");
-	}
-}
+		// Get this frame's effects
+		let mut effects = Effects::new(&args);
+		if let Some(night_light) = &args.night_light {
+			night_light.apply(&mut effects, chrono::Local::now().time());
+		}
 
-/// Updates
-#[allow(clippy::too_many_arguments)] // It's a binary function, not library
-fn update(
-	progress: &mut f32, next_image_is_loaded: &mut bool, args: &Args, cur_image: &mut Image, next_image: &mut Image,
-	facade: &GliumFacade, images: &mut Images,
-) -> Result<(), anyhow::Error> {
-	// Increase the progress
-	*progress += (1.0 / 60.0) / args.duration.as_secs_f32();
+		// Draw each window
+		let mut draw_params = DrawParams::new(&args, effects);
+		draw_params.exposure_mix = panel::exposure_mix(&args, start_time.elapsed());
+		for screen in &mut screens {
+			let state = WidgetState {
+				path: screen
+					.panels
+					.first()
+					.filter(|panel| !panel.cur_image.is_placeholder)
+					.map(|panel| panel.cur_image.info.path.as_path()),
+				bpm: tempo.as_ref().map(Tempo::bpm),
+				paused,
+			};
+			if let Err(err) = screen.overlays.update(&screen.facade, &state) {
+				log::warn!("Unable to update overlays: {err:?}");
+			}
 
-	// If the next image isn't loaded, try to load it
-	if !*next_image_is_loaded {
-		// If our progress is >= fade start, then we have to force wait for the image.
-		let force_wait = *progress >= args.fade;
+			let mut target = screen.facade.draw();
+			if let Err(err) = screen.renderer.draw(&mut target, &screen.panels, &draw_params) {
+				log::warn!("Unable to draw: {err:?}");
+			}
+			if let Err(err) = screen.renderer.draw_overlays(&mut target, &screen.overlays) {
+				log::warn!("Unable to draw overlays: {err:?}");
+			}
+			let is_context_lost = match target.finish() {
+				Ok(()) => screen.facade.is_context_lost(),
+				Err(SwapBuffersError::ContextLost) => true,
+				Err(err) => return Err(err).context("Unable to finish drawing"),
+			};
 
-		if force_wait {
-			log::info!("Next image hasn't arrived yet at the end of current image, waiting for it");
+			// If the context was lost, such as after a gpu reset, rebuild everything on a new one
+			if is_context_lost {
+				log::warn!("Gl context of {} was lost, rebuilding everything", screen.output);
+				let mut attempt = 1;
+				*screen = loop {
+					match self::rebuild(screen, &mut images[screen.images], compress_textures) {
+						Ok(screen) => break screen,
+						Err(err) if attempt < CONTEXT_REBUILD_ATTEMPTS => {
+							log::warn!("Unable to rebuild after losing the gl context (attempt {attempt}): {err:?}");
+							attempt += 1;
+							thread::sleep(CONTEXT_REBUILD_DELAY);
+						},
+						Err(err) => return Err(err).context("Unable to rebuild after losing the gl context"),
+					}
+				};
+				log::info!("Rebuilt everything after losing the gl context");
+			}
 		}
 
-		// Then try to load it
-		*next_image_is_loaded ^= next_image
-			.try_update(facade, images, force_wait)
-			.context("Unable to update image")?;
+		// Once the first frame is drawn, we're ready, and after each frame, we're still alive
+		if let Some(systemd) = &mut systemd {
+			if frame == 0 {
+				if let Err(err) = systemd.ready() {
+					log::warn!("Unable to notify systemd: {err:?}");
+				}
+			}
+			if let Err(err) = systemd.ping_watchdog() {
+				log::warn!("Unable to ping systemd watchdog: {err:?}");
+			}
+		}
 
-		// If we force waited but the next image isn't loaded, return Err
-		if force_wait && !*next_image_is_loaded {
-			return Err(anyhow::anyhow!("Unable to load next image even while force-waiting"));
+		// Then update all panels
+		for screen in &mut screens {
+			let update_ctx = UpdateContext {
+				args: &screen.args,
+				tempo: tempo.as_ref(),
+				beat,
+				paused,
+			};
+			for panel in &mut screen.panels {
+				let images = &mut images[screen.images];
+				if let Err(err) = panel.update(FRAME_DURATION, &update_ctx, &screen.facade, images) {
+					// Note: If the loader failed, no more images will arrive, so we quit
+					match images.has_failed() {
+						true => return Err(err).context("Unable to update"),
+						false => log::warn!("Unable to update: {err:?}"),
+					}
+				}
+			}
 		}
-	}
 
-	// If we reached the end, swap the next to current and try to load the next
-	if *progress >= 1.0 {
-		// Reset the progress to where we where during the fade
-		*progress = 1.0 - args.fade;
+		// Re-arrange any collages
+		// Note: New panels start with new images, so the previous ones are cut off
+		for screen in &mut screens {
+			if let args::Mode::Collage { interval, .. } = screen.args.mode {
+				if screen.arranged_at.elapsed() >= interval {
+					log::info!("Re-arranging collage of {}", screen.output);
+					let panels = self::create_panels(
+						&screen.args,
+						&screen.facade,
+						&mut images[screen.images],
+						screen.window.size(),
+						compress_textures,
+						rand::random,
+					);
+					match panels {
+						Ok(panels) => screen.panels = panels,
+						Err(err) if images[screen.images].has_failed() => {
+							return Err(err).context("Unable to re-arrange collage");
+						},
+						Err(err) => log::warn!("Unable to re-arrange collage: {err:?}"),
+					}
+					screen.arranged_at = Instant::now();
+				}
+			}
+		}
 
-		// Swap the images
-		mem::swap(cur_image, next_image);
-		*next_image_is_loaded = false;
+		// If we're out of images and all panels finished showing their last one, quit
+		let all_shown = screens.iter().flat_map(|screen| &screen.panels).all(|panel| {
+			panel.pinned ||
+				(!panel.next_image_is_loaded &&
+					panel.standby.is_none() &&
+					panel.progress >= args.fade - f32::EPSILON)
+		});
+		if images.iter().all(Images::is_finished) && all_shown {
+			log::info!("Finished showing all images");
+			break;
+		}
 
-		// And try to update the next image
-		*next_image_is_loaded ^= next_image
-			.try_update(facade, images, false)
-			.context("Unable to update image")?;
+		// And update the current images
+		let mut current_changed = false;
+		for (panel, Panel { cur_image, .. }) in screens.iter().flat_map(|screen| &screen.panels).enumerate() {
+			let path = match cur_image.is_placeholder {
+				true => None,
+				false => Some(&cur_image.info.path),
+			};
+			let changed = current_images.set(panel, path);
+			if let (true, 0, Some(path), Some(sync_leader)) = (changed, panel, path, &sync_leader) {
+				sync_leader.show(path);
+			}
+			// Note: Only the first panel's palette is written, as there's only a single file
+			if let (true, 0, Some(path), Some(palette_writer)) = (changed, panel, path, &palette_writer) {
+				if let Err(err) = palette_writer.write(path, &cur_image.palette) {
+					log::warn!("Unable to write palette of {path:?}: {err:?}");
+				}
+			}
+			if let (true, Some(path), Some(on_change)) = (changed, path, &on_change) {
+				on_change.run(path, panel);
+			}
+			current_changed |= changed;
+		}
+		if let (true, Some(current_file)) = (current_changed, &args.current_file) {
+			let mut contents = current_images.lines();
+			contents.push('\n');
+			if let Err(err) = storage::write_atomic(current_file, contents.as_bytes()) {
+				log::warn!("Unable to write current images to {}: {err:?}", current_file.display());
+			}
+		}
 	}
 
+	// Stop loading images
+	self::stop_images(images);
+
+	if let Some(systemd) = &systemd {
+		if let Err(err) = systemd.stopping() {
+			log::warn!("Unable to notify systemd: {err:?}");
+		}
+	}
 
 	Ok(())
 }
 
-/// Draws
-#[allow(clippy::too_many_arguments)] // TODO: Refactor
-fn draw(
-	target: &mut glium::Frame, progress: f32, args: &Args, cur_image: &Image, next_image: &Image,
-	indices: &glium::IndexBuffer<u32>, program: &glium::Program, scale: Vector2<f32>, offset: Point2<f32>,
-) -> Result<(), anyhow::Error> {
-	// Calculate the base alpha and progress to apply to the images
-	let (base_alpha, next_progress) = match progress {
-		f if f >= args.fade => ((progress - args.fade) / (1.0 - args.fade), progress - args.fade),
-		_ => (0.0, 0.0),
-	};
-
-	// Then draw
-	for (image, alpha, progress) in [
-		(cur_image, 1.0 - base_alpha, progress),
-		(next_image, base_alpha, next_progress),
-	] {
-		// If alpha is 0, don't render
-		if alpha == 0.0 {
-			continue;
+/// Stops loading all images
+fn stop_images(images: Vec<Images>) {
+	for images in images {
+		if !images.stop(LOADER_STOP_TIMEOUT) {
+			log::warn!("Image loader didn't stop within {LOADER_STOP_TIMEOUT:?}, exiting anyway");
 		}
+	}
+}
 
-		let mat = Matrix4::from_translation(Vector3::new(offset.x, offset.y, 0.0)) *
-			Matrix4::from_nonuniform_scale(scale.x, scale.y, 1.0);
+/// Creates all panels, with images from `images`.
+///
+/// On a grid, each panel starts at the progress returned by `progress`.
+fn create_panels(
+	args: &Args, facade: &GliumFacade, images: &mut Images, window_size: [u32; 2],
+	compress_textures: Option<TextureCompression>, mut progress: impl FnMut() -> f32,
+) -> Result<Vec<Panel>, anyhow::Error> {
+	let mut panels = Vec::new();
 
-		let sampler = image.texture.sampled();
-		let tex_offset = image.uvs.offset(progress);
-		let uniforms = glium::uniform! {
-			mat: *<_ as AsRef<[[f32; 4]; 4]>>::as_ref(&mat),
-			tex_sampler: sampler,
-			tex_offset: tex_offset,
-			alpha: alpha,
-		};
-		let draw_parameters = glium::DrawParameters {
-			blend: glium::Blend::alpha_blending(),
-			..glium::DrawParameters::default()
-		};
-		target
-			.draw(&image.vertex_buffer, indices, program, &uniforms, &draw_parameters)
-			.context("Unable to draw")?;
+	match args.mode.layout() {
+		None => {
+			let mut cur_image = Image::new(facade, images, window_size, args.scaling, compress_textures)
+				.context("Unable to create image")?;
+			cur_image.was_shown = true;
+			let next_image = Image::new(facade, images, window_size, args.scaling, compress_textures)
+				.context("Unable to create image")?;
+			let mut panel = Panel::full(cur_image, next_image);
+			panel.exposure =
+				self::create_exposure(args, facade, images, window_size, compress_textures, &mut progress)?;
+			panels.push(panel);
+		},
+		Some(layout) => {
+			let window_cells = layout.window_cells(window_size, args.grid_gap, args.grid_margin);
+			for (idx, (slots, cell)) in layout.cells.iter().zip(window_cells).enumerate() {
+				// Note: When mirroring, the first panel is drawn on all other cells, randomly flipped, so
+				//       each image is only loaded once.
+				let (scale, offset) = cell.transform(window_size);
+				if let (true, Some(panel)) = (args.grid_mirror, panels.first_mut()) {
+					panel.mirrors.push(Mirror {
+						scale,
+						offset,
+						flip: [rand::random(), rand::random()],
+					});
+					continue;
+				}
+
+				// If the cell is pinned, always show it's image
+				// Note: Panels always have a next image, so we use a copy, as when setting a single image
+				if let Some((_, path)) = args.pinned_cells.iter().find(|&&(slot, _)| slots.contains(slot)) {
+					let load_image = || {
+						preview::load_image(facade, path, cell.size, args.scaling)
+							.with_context(|| format!("Unable to load pinned image {}", path.display()))
+					};
+					let mut panel = Panel::new(load_image()?, load_image()?, scale, offset);
+					panel.pinned = true;
+					panels.push(panel);
+					continue;
+				}
+
+				let mut cur_image = Image::new(facade, images, cell.size, args.scaling, compress_textures)
+					.context("Unable to create image")?;
+				cur_image.was_shown = true;
+				let next_image = Image::new(facade, images, cell.size, args.scaling, compress_textures)
+					.context("Unable to create image")?;
+
+				let mut panel = Panel::new(cur_image, next_image, scale, offset);
+				panel.progress = panel::grid_progress(args, idx).unwrap_or_else(&mut progress);
+				panel.duration_scale = panel::duration_scale(args);
+				panel.next_image_is_loaded = true;
+				panel.exposure =
+					self::create_exposure(args, facade, images, cell.size, compress_textures, &mut progress)?;
+				panels.push(panel);
+			}
+		},
 	}
 
-	Ok(())
+	Ok(panels)
 }
 
-/// Image
-#[derive(Debug)]
-struct Image {
-	/// Texture
-	texture: glium::Texture2d,
+/// Creates the second exposure of a `size` panel, if any, with images from `images`.
+///
+/// It starts at the progress returned by `progress`, so it changes images at different times than the panel.
+fn create_exposure(
+	args: &Args, facade: &GliumFacade, images: &mut Images, size: [u32; 2],
+	compress_textures: Option<TextureCompression>, progress: impl FnOnce() -> f32,
+) -> Result<Option<Box<Panel>>, anyhow::Error> {
+	if args.double_exposure.is_none() {
+		return Ok(None);
+	}
 
-	/// Uvs
-	uvs: ImageUvs,
+	let mut cur_image =
+		Image::new(facade, images, size, args.scaling, compress_textures).context("Unable to create image")?;
+	cur_image.was_shown = true;
+	let next_image =
+		Image::new(facade, images, size, args.scaling, compress_textures).context("Unable to create image")?;
 
-	/// Vertex buffer
-	vertex_buffer: glium::VertexBuffer<Vertex>,
+	let mut exposure = Panel::full(cur_image, next_image);
+	exposure.progress = progress();
+	exposure.duration_scale = panel::duration_scale(args);
+	exposure.next_image_is_loaded = true;
 
-	/// Window size
-	window_size: [u32; 2],
+	Ok(Some(Box::new(exposure)))
 }
 
-impl Image {
-	/// Creates a new image
-	pub fn new(
-		facade: &GliumFacade, images: &mut Images, window_size @ [window_width, window_height]: [u32; 2],
-	) -> Result<Self, anyhow::Error> {
-		let image = images.next_image();
-
-		let image_dims = image.dimensions();
-		let texture = glium::texture::Texture2d::new(
-			facade,
-			glium::texture::RawImage2d::from_raw_rgba(image.into_raw(), image_dims),
-		)
-		.context("Unable to create texture")?;
-
-		#[allow(clippy::cast_precision_loss)] // Image and window sizes are likely much lower than 2^24
-		let uvs = ImageUvs::new(
-			image_dims.0 as f32,
-			image_dims.1 as f32,
-			window_width as f32,
-			window_height as f32,
-			rand::random(),
-		);
+/// Output being shown on, along with everything drawn to it
+struct Screen {
+	/// Output
+	output: args::Output,
 
-		let vertex_buffer = glium::VertexBuffer::dynamic(facade, &Self::vertices(uvs.start()))
-			.context("Unable to create vertex buffer")?;
-		Ok(Self {
-			texture,
-			uvs,
-			vertex_buffer,
-			window_size,
-		})
+	/// Arguments, with the output's config applied
+	args: Args,
+
+	/// Index of the images shown
+	images: usize,
+
+	/// Window
+	window: Rc<dyn GlSurface>,
+
+	/// Facade
+	facade: GliumFacade,
+
+	/// Renderer
+	renderer: Renderer,
+
+	/// Panels
+	panels: Vec<Panel>,
+
+	/// Overlays
+	overlays: Overlays,
+
+	/// When the panels were arranged
+	arranged_at: Instant,
+}
+
+/// Rebuilds the window, facade, renderer and all panels of `screen` after the gl context was lost.
+///
+/// The images shown were only kept on the gpu, so each panel continues with the next images,
+/// but keeps it's progress.
+fn rebuild(
+	screen: &Screen, images: &mut Images, compress_textures: Option<TextureCompression>,
+) -> Result<Screen, anyhow::Error> {
+	let (window, facade) = self::create_gl(&screen.output)?;
+	let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+
+	let mut new_panels = self::create_panels(
+		&screen.args,
+		&facade,
+		images,
+		window.size(),
+		compress_textures,
+		f32::default,
+	)?;
+	for (new_panel, panel) in new_panels.iter_mut().zip(&screen.panels) {
+		new_panel.progress = panel.progress;
+		if let (Some(new_exposure), Some(exposure)) = (&mut new_panel.exposure, &panel.exposure) {
+			new_exposure.progress = exposure.progress;
+		}
 	}
 
-	/// Tries to update this image and returns if actually updated
-	pub fn try_update(
-		&mut self, facade: &GliumFacade, images: &mut Images, force_wait: bool,
-	) -> Result<bool, anyhow::Error> {
-		let image = match images.try_next_image() {
-			Some(image) => image,
-			None if force_wait => images.next_image(),
-			None => return Ok(false),
-		};
+	let overlays = self::create_overlays(&screen.args, &*window);
 
-		let image_dims = image.dimensions();
-		self.texture = glium::texture::Texture2d::new(
-			facade,
-			glium::texture::RawImage2d::from_raw_rgba(image.into_raw(), image_dims),
-		)
-		.context("Unable to create texture")?;
-
-		#[allow(clippy::cast_precision_loss)] // Image and window sizes are likely much lower than 2^24
-		let uvs = ImageUvs::new(
-			image_dims.0 as f32,
-			image_dims.1 as f32,
-			self.window_size[0] as f32,
-			self.window_size[1] as f32,
-			rand::random(),
-		);
-		self.uvs = uvs;
+	Ok(Screen {
+		output: screen.output.clone(),
+		args: screen.args.clone(),
+		images: screen.images,
+		window,
+		facade,
+		renderer,
+		panels: new_panels,
+		overlays,
+		arranged_at: screen.arranged_at,
+	})
+}
 
-		self.vertex_buffer
-			.as_mut_slice()
-			.write(&Self::vertices(self.uvs.start()));
+/// Creates the overlays of a window
+fn create_overlays(args: &Args, window: &dyn GlSurface) -> Overlays {
+	let scale_factor = args.overlay_scale.unwrap_or_else(|| window.scale_factor());
+	if !args.overlays.is_empty() {
+		log::debug!("Drawing overlays with a scale factor of {scale_factor}");
+	}
+
+	Overlays::new(&args.overlays, scale_factor)
+}
 
-		Ok(true)
+/// Returns the section of the config of `output`, if any
+fn output_config<'a>(config: &'a Config, output: &args::Output) -> Option<&'a OutputConfig> {
+	output.name.as_ref().and_then(|name| config.outputs.get(name))
+}
+
+/// Creates the window, backend and facade
+fn create_gl(output: &args::Output) -> Result<(Rc<dyn GlSurface>, GliumFacade), anyhow::Error> {
+	let (window, backend): (Rc<dyn GlSurface>, _) = match &output.kind {
+		&args::OutputKind::Window(window_id) => {
+			let window = Window::from_window_id(window_id)
+				.map(Rc::new)
+				.context("Unable to create window")?;
+			let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+			(window, backend)
+		},
+		#[cfg(feature = "kms")]
+		args::OutputKind::Kms(device) => {
+			let window = zss::kms::KmsWindow::open(device)
+				.map(Rc::new)
+				.with_context(|| format!("Unable to create window on {}", device.display()))?;
+			let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+			(window, backend)
+		},
+		#[cfg(not(feature = "kms"))]
+		args::OutputKind::Kms(_) => anyhow::bail!("Unable to show on a drm device, `zss` was built without `kms`"),
+	};
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+
+	Ok((window, facade))
+}
+
+/// Makes sure the window of `output` isn't empty and the grid fits it
+fn check_window_size(
+	args: &Args, output: &args::Output, [window_width, window_height]: [u32; 2],
+) -> Result<(), anyhow::Error> {
+	anyhow::ensure!(
+		window_width != 0 && window_height != 0,
+		"Output {} has no area ({}x{}), is it mapped?",
+		output,
+		window_width,
+		window_height
+	);
+	if let Some(layout) = args.mode.layout() {
+		let [width, height] = layout.size;
+		let [slot_width, slot_height] =
+			layout.slot_size([window_width, window_height], args.grid_gap, args.grid_margin);
+		anyhow::ensure!(
+			slot_width != 0 && slot_height != 0,
+			"Grid {}x{} doesn't fit the {}x{} window, use a smaller `--grid` or `--layout`, `--grid-gap` or \
+			 `--grid-margin`",
+			width,
+			height,
+			window_width,
+			window_height
+		);
 	}
 
-	/// Creates the vertices for uvs
-	const fn vertices(uvs_start: [f32; 2]) -> [Vertex; 4] {
-		[
-			Vertex {
-				vertex_pos: [-1.0, -1.0],
-				vertex_tex: [0.0, 0.0],
+	Ok(())
+}
+
+/// Creates the image loader, either from the replay or the images directory
+fn create_images(
+	args: &Args, config: Config, replay: &mut Option<Replay>, load_options: LoadOptions, images_dir: &Path,
+	banned: &Arc<MarkedPaths>, favorites: &Arc<MarkedPaths>,
+) -> Result<Images, anyhow::Error> {
+	let mut images = match (replay, &args.images_command, &args.sync_follow) {
+		(Some(replay), ..) => Images::from_replay(
+			mem::take(&mut replay.images),
+			args.image_backlog,
+			args.max_backlog_memory,
+			load_options,
+			config.handlers,
+			args.decode_timeout,
+		),
+		(None, Some(images_command), _) => Images::from_provider(
+			CommandProvider::spawn(images_command.clone(), load_options)
+				.with_context(|| format!("Unable to read images from command {images_command:?}"))?,
+			Arc::default(),
+			args.image_backlog,
+			args.max_backlog_memory,
+		),
+		(None, None, Some(leader_addr)) => {
+			let (path_tx, path_rx) = mpsc::channel();
+			let leader_addr = leader_addr.clone();
+			let follow_images_dir = images_dir.to_path_buf();
+			thread::spawn(move || net_sync::follow(&leader_addr, &follow_images_dir, &path_tx));
+
+			Images::from_provider(
+				FollowProvider::new(path_rx, load_options, config.handlers, args.decode_timeout),
+				Arc::default(),
+				args.image_backlog,
+				args.max_backlog_memory,
+			)
+		},
+		(None, None, None) => Images::new(
+			match &args.images_from {
+				Some(images_from) => PathSource::List(images_from.clone()),
+				None if !args.urls.is_empty() => PathSource::Urls(args.urls.clone()),
+				None => PathSource::Dir {
+					path:            args.images_dir.clone(),
+					watch:           !args.no_watch,
+					rescan_interval: args.rescan_interval,
+					schedule:        Schedule::new(config.schedule).context("Unable to parse schedule")?,
+				},
 			},
-			Vertex {
-				vertex_pos: [1.0, -1.0],
-				vertex_tex: [uvs_start[0], 0.0],
+			config.online,
+			args.image_backlog,
+			args.max_backlog_memory,
+			load_options,
+			config.handlers,
+			Filters {
+				min_resolution: args.min_resolution,
+				max_file_size:  args.max_file_size,
 			},
-			Vertex {
-				vertex_pos: [-1.0, 1.0],
-				vertex_tex: [0.0, uvs_start[1]],
+			args.decode_timeout,
+			Quarantine::load(args.quarantine_log.clone()),
+			Arc::clone(banned),
+			match args.only_favorites {
+				true => Some(Arc::clone(favorites)),
+				false => None,
 			},
-			Vertex {
-				vertex_pos: [1.0, 1.0],
-				vertex_tex: uvs_start,
+			{
+				let selection = config
+					.selection
+					.with_weights(args.weights.clone())
+					.context("Unable to add weights to selection strategy")?
+					.into_strategy(&args.images_dir)
+					.context("Unable to create selection strategy")?;
+				match (args.clock_sync, args.no_repeat_last) {
+					// Note: When synchronized to the clock, the clock alone must choose the images
+					(Some(seed), _) => Box::new(ClockSync::new(seed, panel::slot_duration(args))),
+					(None, Some(no_repeat_last)) => Box::new(NoRepeat::new(
+						selection,
+						History::load(no_repeat_last, args.persist_history),
+					)),
+					(None, None) => selection,
+				}
 			},
-		]
-	}
-}
-
+			args.dedup,
+			args.cycles,
+		)
+		.with_context(|| format!("Unable to start loading images from {}", args.images_dir.display()))?,
+	};
+	images.set_aspect_routing(args.aspect_routing);
 
-/// Vertex
-#[derive(Clone, Copy, Debug)]
-struct Vertex {
-	vertex_pos: [f32; 2],
-	vertex_tex: [f32; 2],
+	Ok(images)
 }
-
-glium::implement_vertex!(Vertex, vertex_pos, vertex_tex);
@@ -0,0 +1,84 @@
+//! Marked paths
+//!
+//! Keeps a list of paths, such as banned images, persisted as a file with one
+//! path per line, so it may also be edited by hand.
+
+// Imports
+use anyhow::Context;
+use std::{
+	collections::HashSet,
+	fs::{self, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::RwLock,
+};
+
+/// Marked paths
+#[derive(Debug)]
+pub struct MarkedPaths {
+	/// Path of the list file, if persisted
+	path: Option<PathBuf>,
+
+	/// All marked paths
+	paths: RwLock<HashSet<PathBuf>>,
+}
+
+impl MarkedPaths {
+	/// Loads the marked paths from `path`, if any
+	pub fn load(path: Option<PathBuf>) -> Self {
+		let paths = match path.as_deref().map(fs::read_to_string) {
+			Some(Ok(contents)) => contents
+				.lines()
+				.map(str::trim)
+				.filter(|line| !line.is_empty())
+				.map(PathBuf::from)
+				.collect(),
+			Some(Err(err)) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+			Some(Err(err)) => {
+				log::warn!("Unable to read {path:?}: {err}");
+				HashSet::new()
+			},
+			None => HashSet::new(),
+		};
+
+		Self {
+			path,
+			paths: RwLock::new(paths),
+		}
+	}
+
+	/// Returns if `path` is marked
+	pub fn contains(&self, path: &Path) -> bool {
+		self.paths
+			.read()
+			.expect("Marked paths lock was poisoned")
+			.contains(path)
+	}
+
+	/// Marks `path`, returning if it wasn't already marked
+	pub fn add(&self, path: &Path) -> Result<bool, anyhow::Error> {
+		if !self
+			.paths
+			.write()
+			.expect("Marked paths lock was poisoned")
+			.insert(path.to_path_buf())
+		{
+			return Ok(false);
+		}
+
+		// Note: We only append to the file, so any changes made by hand since we loaded it are kept
+		if let Some(list_path) = &self.path {
+			if let Some(parent) = list_path.parent() {
+				fs::create_dir_all(parent).context("Unable to create parent directory")?;
+			}
+			let mut file = OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(list_path)
+				.with_context(|| format!("Unable to open {list_path:?}"))?;
+			writeln!(file, "{}", path.display()).with_context(|| format!("Unable to write to {list_path:?}"))?;
+		}
+
+		Ok(true)
+	}
+}
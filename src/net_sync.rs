@@ -0,0 +1,163 @@
+//! Network sync
+//!
+//! A leader sends the path of each new image it shows, relative to it's images directory,
+//! to all followers connected over tcp, which then show the same image from their own
+//! images directory.
+//!
+//! The protocol is line-based, with each line being `show {path}`. Once a follower connects,
+//! it's immediately sent the image currently shown.
+
+// Imports
+use anyhow::Context;
+use std::{
+	io::{BufRead, BufReader, Write},
+	net::{TcpListener, TcpStream},
+	path::{Component, Path, PathBuf},
+	sync::{mpsc, Arc, Mutex},
+	thread,
+	time::Duration,
+};
+
+/// Leader state
+#[derive(Default, Debug)]
+struct LeaderState {
+	/// Line for the current image, if any
+	current: Option<String>,
+
+	/// All followers
+	followers: Vec<TcpStream>,
+}
+
+/// Sync leader
+#[derive(Debug)]
+pub struct SyncLeader {
+	/// Images directory
+	images_dir: PathBuf,
+
+	/// Line sender
+	line_tx: mpsc::Sender<String>,
+}
+
+impl SyncLeader {
+	/// Maximum time to wait on a follower before dropping it
+	const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+	/// Starts listening for followers on `addr`
+	pub fn bind(addr: &str, images_dir: PathBuf) -> Result<Self, anyhow::Error> {
+		let listener = TcpListener::bind(addr).with_context(|| format!("Unable to bind to {addr:?}"))?;
+		log::info!("Listening for followers on {addr:?}");
+
+		// Note: Followers are accepted and sent lines in separate threads, so neither blocks the other,
+		//       and a slow follower never blocks the slideshow.
+		let state = Arc::new(Mutex::new(LeaderState::default()));
+		let accept_state = Arc::clone(&state);
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let mut stream = match stream {
+					Ok(stream) => stream,
+					Err(err) => {
+						log::warn!("Unable to accept follower: {err}");
+						continue;
+					},
+				};
+				if let Err(err) = stream.set_write_timeout(Some(Self::WRITE_TIMEOUT)) {
+					log::warn!("Unable to set follower write timeout: {err}");
+					continue;
+				}
+				log::info!("Follower {:?} connected", stream.peer_addr().ok());
+
+				let mut state = accept_state.lock().expect("Leader state lock was poisoned");
+				if let Some(line) = &state.current {
+					if let Err(err) = stream.write_all(line.as_bytes()) {
+						log::warn!("Unable to send current image to follower: {err}");
+						continue;
+					}
+				}
+				state.followers.push(stream);
+			}
+		});
+
+		let (line_tx, line_rx) = mpsc::channel::<String>();
+		thread::spawn(move || {
+			for line in line_rx {
+				let mut state = state.lock().expect("Leader state lock was poisoned");
+				state
+					.followers
+					.retain(|mut stream| match stream.write_all(line.as_bytes()) {
+						Ok(()) => true,
+						Err(err) => {
+							log::info!("Follower {:?} disconnected: {err}", stream.peer_addr().ok());
+							false
+						},
+					});
+				state.current = Some(line);
+			}
+		});
+
+		Ok(Self { images_dir, line_tx })
+	}
+
+	/// Sends `path` to all followers
+	pub fn show(&self, path: &Path) {
+		let path = path.strip_prefix(&self.images_dir).unwrap_or(path);
+		match path.to_str() {
+			Some(path) if !path.contains('\n') => {
+				// Note: The sender thread never stops, so this can't fail
+				let _ = self.line_tx.send(format!("show {path}\n"));
+			},
+			_ => log::warn!("Unable to send {path:?} to followers, only utf-8 paths without newlines are supported"),
+		}
+	}
+}
+
+/// Follows the leader at `addr`, sending the path of each image it shows, within `images_dir`, to `path_tx`.
+///
+/// Reconnects to the leader whenever the connection is lost.
+pub fn follow(addr: &str, images_dir: &Path, path_tx: &mpsc::Sender<PathBuf>) {
+	/// Delay between connection attempts
+	const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+	loop {
+		match self::follow_connection(addr, images_dir, path_tx) {
+			Ok(()) => log::warn!("Leader {addr:?} disconnected, reconnecting"),
+			Err(err) => log::warn!("Unable to follow leader {addr:?}, reconnecting: {err:?}"),
+		}
+
+		thread::sleep(RECONNECT_DELAY);
+	}
+}
+
+/// Follows a single connection to the leader at `addr`, until it disconnects
+fn follow_connection(addr: &str, images_dir: &Path, path_tx: &mpsc::Sender<PathBuf>) -> Result<(), anyhow::Error> {
+	let stream = TcpStream::connect(addr).context("Unable to connect")?;
+	log::info!("Following leader {addr:?}");
+
+	for line in BufReader::new(stream).lines() {
+		let line = line.context("Unable to read line")?;
+		let path = match line.split_once(' ') {
+			// Note: The leader's paths must stay within our images directory
+			Some(("show", path))
+				if Path::new(path)
+					.components()
+					.all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) =>
+			{
+				images_dir.join(path)
+			},
+			Some(("show", path)) => {
+				log::warn!("Ignoring path outside of the images directory from leader: {path:?}");
+				continue;
+			},
+			_ => {
+				log::warn!("Ignoring unknown line from leader: {line:?}");
+				continue;
+			},
+		};
+
+		// Note: If the receiver was dropped, we're quitting anyway
+		if path_tx.send(path).is_err() {
+			return Ok(());
+		}
+	}
+
+	Ok(())
+}
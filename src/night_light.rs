@@ -0,0 +1,94 @@
+//! Night light
+
+// Imports
+use crate::effects::Effects;
+use chrono::{NaiveTime, Timelike};
+use std::time::Duration;
+
+/// Seconds in a day
+const DAY_SECS: f32 = 24.0 * 60.0 * 60.0;
+
+/// Night light schedule.
+///
+/// Dims and warms the wallpaper during the night, ramping in and out
+/// over the transition duration.
+#[derive(Clone, Copy, Debug)]
+pub struct NightLight {
+	/// Start of the night
+	pub start: NaiveTime,
+
+	/// End of the night
+	pub end: NaiveTime,
+
+	/// Transition duration
+	pub transition: Duration,
+
+	/// Brightness during the night
+	pub brightness: f32,
+
+	/// Color temperature during the night, in kelvin
+	pub temperature: f32,
+}
+
+impl NightLight {
+	/// Returns the intensity of the night light at `time`, from `0.0` (day) to `1.0` (night)
+	#[allow(clippy::cast_precision_loss)] // Seconds in a day are less than `2^23`
+	pub fn intensity(&self, time: NaiveTime) -> f32 {
+		let secs = |time: NaiveTime| time.num_seconds_from_midnight() as f32;
+
+		// Get how long since the night started and how long it lasts, accounting for midnight
+		let since_start = (secs(time) - secs(self.start)).rem_euclid(DAY_SECS);
+		let night_len = (secs(self.end) - secs(self.start)).rem_euclid(DAY_SECS);
+		if since_start >= night_len {
+			return 0.0;
+		}
+
+		// Then ramp in at the start and out at the end
+		let transition = self.transition.as_secs_f32();
+		match transition {
+			_ if transition <= 0.0 => 1.0,
+			_ => (since_start / transition)
+				.min((night_len - since_start) / transition)
+				.min(1.0),
+		}
+	}
+
+	/// Applies the night light at `time` to `effects`
+	#[allow(clippy::suboptimal_flops)] // This isn't performance sensitive
+	pub fn apply(&self, effects: &mut Effects, time: NaiveTime) {
+		let intensity = self.intensity(time);
+		if intensity == 0.0 {
+			return;
+		}
+
+		let lerp = |from: f32, to: f32| from + (to - from) * intensity;
+		let tint = self::temperature_tint(self.temperature);
+
+		effects.brightness *= lerp(1.0, self.brightness);
+		for (effect_tint, tint) in effects.tint.iter_mut().zip(tint) {
+			*effect_tint *= lerp(1.0, tint);
+		}
+	}
+}
+
+/// Returns the rgb tint for a color temperature, in kelvin, relative to daylight (`6500K`)
+#[allow(clippy::suboptimal_flops)] // Keeping the fit's formulas readable is more important
+fn temperature_tint(temperature: f32) -> [f32; 3] {
+	// Approximation of the black-body color, from Tanner Helland's fit
+	let temperature = temperature.clamp(1000.0, 40000.0) / 100.0;
+	let red = match temperature {
+		t if t <= 66.0 => 255.0,
+		t => 329.698_73 * (t - 60.0).powf(-0.133_204_76),
+	};
+	let green = match temperature {
+		t if t <= 66.0 => 99.470_8 * t.ln() - 161.119_57,
+		t => 288.122_16 * (t - 60.0).powf(-0.075_514_85),
+	};
+	let blue = match temperature {
+		t if t >= 66.0 => 255.0,
+		t if t <= 19.0 => 0.0,
+		t => 138.517_73 * (t - 10.0).ln() - 305.044_8,
+	};
+
+	[red, green, blue].map(|value: f32| value.clamp(0.0, 255.0) / 255.0)
+}
@@ -0,0 +1,228 @@
+//! Online sources
+//!
+//! Periodically pulls images from online providers, such as wallhaven searches,
+//! subreddits or rss / atom feeds, downloading them like any other url, and then
+//! loading them along with all other images.
+
+// Imports
+use crate::remote;
+use anyhow::Context;
+use notify::DebouncedEvent;
+use std::{collections::HashSet, sync::mpsc, thread, time::Duration};
+
+/// Online sources config
+#[derive(Clone, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Online {
+	/// Sources
+	pub sources: Vec<OnlineSource>,
+
+	/// Interval between pulls, in seconds
+	pub interval: u64,
+
+	/// Maximum number of images pulled from each source, every pull
+	pub limit: usize,
+
+	/// If nsfw images are allowed
+	pub nsfw: bool,
+
+	/// Minimum resolution, for sources that report it
+	pub min_resolution: Option<[u32; 2]>,
+}
+
+impl Default for Online {
+	fn default() -> Self {
+		Self {
+			sources:        vec![],
+			interval:       3600,
+			limit:          24,
+			nsfw:           false,
+			min_resolution: None,
+		}
+	}
+}
+
+/// Online source
+#[derive(Clone, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(tag = "provider", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum OnlineSource {
+	/// Wallhaven search
+	Wallhaven {
+		/// Search query
+		query: String,
+	},
+
+	/// Subreddit, by it's hot posts
+	Reddit {
+		/// Subreddit name, without the `r/`
+		subreddit: String,
+	},
+
+	/// Rss or atom feed
+	Feed {
+		/// Feed url
+		url: String,
+	},
+}
+
+impl Online {
+	/// Image extensions accepted from sources that don't report if a url is an image
+	const IMAGE_EXTENSIONS: &'static [&'static str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+	/// Minimum interval between pulls, in seconds
+	pub const MIN_INTERVAL: u64 = 60;
+
+	/// Pulls images from all sources every interval, sending them to `event_tx`, until the receiver is dropped
+	pub fn run(&self, event_tx: &mpsc::Sender<DebouncedEvent>) {
+		loop {
+			for source in &self.sources {
+				let urls = match self.image_urls(source) {
+					Ok(urls) => urls,
+					Err(err) => {
+						log::warn!("Unable to pull images from {source:?}: {err:?}");
+						continue;
+					},
+				};
+				log::info!("Pulled {} images from {source:?}", urls.len());
+
+				for url in urls.into_iter().take(self.limit) {
+					let path = match remote::fetch(&url) {
+						Ok(path) => path,
+						Err(err) => {
+							log::warn!("Unable to download {url:?}: {err:?}");
+							continue;
+						},
+					};
+
+					if event_tx.send(DebouncedEvent::Create(path)).is_err() {
+						return;
+					}
+				}
+			}
+
+			thread::sleep(Duration::from_secs(self.interval));
+		}
+	}
+
+	/// Returns the urls of the images of `source`
+	fn image_urls(&self, source: &OnlineSource) -> Result<Vec<String>, anyhow::Error> {
+		let urls = match source {
+			OnlineSource::Wallhaven { query } => {
+				let purity = match self.nsfw {
+					true => "110",
+					false => "100",
+				};
+				let atleast = match self.min_resolution {
+					Some([min_width, min_height]) => format!("&atleast={min_width}x{min_height}"),
+					None => String::new(),
+				};
+				let url = format!(
+					"https://wallhaven.cc/api/v1/search?q={}&purity={purity}{atleast}",
+					self::encode(query)
+				);
+
+				let response = self::get_json(&url)?;
+				let images = response["data"].as_array().context("Response had no images")?;
+				images
+					.iter()
+					.filter_map(|image| image["path"].as_str())
+					.filter(|url| remote::is_url(url))
+					.map(str::to_owned)
+					.collect()
+			},
+
+			OnlineSource::Reddit { subreddit } => {
+				let url = format!(
+					"https://www.reddit.com/r/{}/hot.json?limit=100",
+					self::encode(subreddit)
+				);
+				let response = self::get_json(&url)?;
+				let posts = response["data"]["children"]
+					.as_array()
+					.context("Response had no posts")?;
+				posts
+					.iter()
+					.map(|post| &post["data"])
+					.filter(|post| self.nsfw || !post["over_18"].as_bool().unwrap_or(false))
+					.filter(|post| {
+						// Note: Posts without a preview don't report their resolution, so we let the loader filter them
+						let resolution = &post["preview"]["images"][0]["source"];
+						match (
+							self.min_resolution,
+							resolution["width"].as_u64(),
+							resolution["height"].as_u64(),
+						) {
+							(Some([min_width, min_height]), Some(width), Some(height)) => {
+								width >= u64::from(min_width) && height >= u64::from(min_height)
+							},
+							_ => true,
+						}
+					})
+					.filter_map(|post| post["url"].as_str())
+					.filter(|url| Self::is_image_url(url))
+					.map(str::to_owned)
+					.collect()
+			},
+
+			OnlineSource::Feed { url } => {
+				let feed = remote::get(url).context("Unable to get feed")?;
+				let feed = String::from_utf8_lossy(&feed);
+				self::feed_urls(&feed)
+					.into_iter()
+					.filter(|url| Self::is_image_url(url))
+					.collect()
+			},
+		};
+
+		Ok(urls)
+	}
+
+	/// Returns if `url` is of an image, by it's extension
+	fn is_image_url(url: &str) -> bool {
+		remote::extension(url).map_or(false, |extension| {
+			Self::IMAGE_EXTENSIONS
+				.iter()
+				.any(|image_extension| extension.eq_ignore_ascii_case(image_extension))
+		})
+	}
+}
+
+/// Gets and parses the json at `url`
+fn get_json(url: &str) -> Result<serde_json::Value, anyhow::Error> {
+	let response = remote::get(url).context("Unable to get response")?;
+	serde_json::from_slice(&response).context("Unable to parse response")
+}
+
+/// Returns all urls in the `url` and `href` attributes of a feed, in order, without duplicates.
+///
+/// Note: Feeds reference their images in many different elements, such as `enclosure`,
+///       `media:content` or `link`, so we just check every attribute that may hold one.
+fn feed_urls(feed: &str) -> Vec<String> {
+	let mut urls = vec![];
+	let mut seen_urls = HashSet::new();
+	for attribute in ["url=\"", "href=\""] {
+		for (start, _) in feed.match_indices(attribute) {
+			let value = &feed[start + attribute.len()..];
+			let url = match value.split_once('"') {
+				Some((url, _)) => url.replace("&amp;", "&"),
+				None => continue,
+			};
+
+			if remote::is_url(&url) && seen_urls.insert(url.clone()) {
+				urls.push(url);
+			}
+		}
+	}
+
+	urls
+}
+
+/// Percent-encodes `value`, for use in a url
+fn encode(value: &str) -> String {
+	value
+		.bytes()
+		.map(|byte| match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => char::from(byte).to_string(),
+			_ => format!("%{byte:02X}"),
+		})
+		.collect()
+}
@@ -0,0 +1,252 @@
+//! Overlays
+//!
+//! Widgets drawn over the slideshow, such as a clock. Each widget is a line of text, drawn
+//! with a bitmap font onto a texture, only re-drawn once it's text changes.
+//!
+//! All widgets are scaled by their output's scale factor, taken from it's physical size, so
+//! they have about the same physical size on every output, unless overridden.
+
+// Imports
+use crate::glium_facade::GliumFacade;
+use anyhow::Context;
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+
+/// Dots per inch of a scale factor of `1.0`
+const BASE_DPI: f32 = 96.0;
+
+/// Size of each pixel of the font, at a scale factor of `1.0`, in pixels
+const FONT_PIXEL_SIZE: f32 = 3.0;
+
+/// Padding around the text, in font pixels
+const PADDING: u32 = 2;
+
+/// Margin between each widget and the edges of the output, at a scale factor of `1.0`, in pixels
+pub const MARGIN: f32 = 12.0;
+
+/// Maximum number of characters shown by a widget
+const MAX_TEXT_LEN: usize = 64;
+
+/// Text color
+const TEXT_COLOR: Rgba<u8> = Rgba([0xff, 0xff, 0xff, 0xff]);
+
+/// Background color
+const BG_COLOR: Rgba<u8> = Rgba([0x00, 0x00, 0x00, 0x80]);
+
+/// Digit glyphs, 5x7, with each row's bits from left to right
+#[rustfmt::skip]
+const DIGITS: [[u8; 7]; 10] = [
+	[0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+	[0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+	[0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+	[0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+	[0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+	[0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+	[0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+	[0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+	[0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+	[0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+];
+
+/// Letter glyphs, 5x7, with each row's bits from left to right
+#[rustfmt::skip]
+const LETTERS: [[u8; 7]; 26] = [
+	[0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+	[0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+	[0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+	[0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+	[0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+	[0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+	[0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+	[0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+	[0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+	[0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+	[0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+	[0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+	[0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+	[0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+	[0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+	[0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+	[0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+	[0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+	[0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+	[0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+	[0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+	[0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+	[0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+	[0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+	[0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+	[0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+];
+
+/// Returns the scale factor of an output `width` pixels and `width_mm` millimeters wide.
+///
+/// Returns `1.0` if the physical size is unknown, as some outputs, such as projectors, report `0`.
+#[allow(clippy::cast_precision_loss)] // Outputs are much smaller than 2^24
+pub fn scale_factor(width: u32, width_mm: u32) -> f32 {
+	match width_mm {
+		0 => 1.0,
+		_ => (width as f32 * 25.4 / width_mm as f32) / BASE_DPI,
+	}
+}
+
+/// Overlay widget kind
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WidgetKind {
+	/// Clock, showing the time, on the bottom-right
+	Clock,
+
+	/// Caption, showing the file name of the first panel's image, on the bottom-left
+	Caption,
+
+	/// Hud, showing the slideshow's state, on the top-left
+	Hud,
+}
+
+impl WidgetKind {
+	/// Parses a widget kind
+	pub fn parse(kind: &str) -> Result<Self, anyhow::Error> {
+		match kind {
+			"clock" => Ok(Self::Clock),
+			"caption" => Ok(Self::Caption),
+			"hud" => Ok(Self::Hud),
+			kind => anyhow::bail!("Unknown overlay {:?}, expected `clock`, `caption` or `hud`", kind),
+		}
+	}
+}
+
+/// Overlay widget
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Widget {
+	/// Kind
+	pub kind: WidgetKind,
+
+	/// Scale, overriding the output's scale factor, if any
+	pub scale: Option<f32>,
+}
+
+/// State shown by the widgets
+#[derive(Clone, Copy, Debug)]
+pub struct WidgetState<'a> {
+	/// Path of the first panel's image, if any
+	pub path: Option<&'a Path>,
+
+	/// Beats per minute of the tempo, if following one
+	pub bpm: Option<f32>,
+
+	/// If we're paused
+	pub paused: bool,
+}
+
+/// Overlays of an output
+#[derive(Debug)]
+pub struct Overlays {
+	/// All widgets, with their scale, text and it's texture, once drawn
+	widgets: Vec<(Widget, f32, String, Option<glium::Texture2d>)>,
+}
+
+impl Overlays {
+	/// Creates the overlays with `widgets`, on an output with `scale_factor`
+	pub fn new(widgets: &[Widget], scale_factor: f32) -> Self {
+		let widgets = widgets
+			.iter()
+			.map(|&widget| (widget, widget.scale.unwrap_or(scale_factor), String::new(), None))
+			.collect();
+
+		Self { widgets }
+	}
+
+	/// Updates the text of all widgets from `state`, re-drawing any that changed
+	pub fn update(&mut self, facade: &GliumFacade, state: &WidgetState) -> Result<(), anyhow::Error> {
+		for (widget, scale, text, texture) in &mut self.widgets {
+			let new_text = match widget.kind {
+				WidgetKind::Clock => chrono::Local::now().format("%H:%M").to_string(),
+				WidgetKind::Caption => state
+					.path
+					.and_then(Path::file_name)
+					.map(|file_name| file_name.to_string_lossy().into_owned())
+					.unwrap_or_default(),
+				WidgetKind::Hud => match (state.paused, state.bpm) {
+					(true, _) => "Paused".to_owned(),
+					(false, Some(bpm)) => format!("{bpm:.0} BPM"),
+					(false, None) => String::new(),
+				},
+			};
+			if *text == new_text {
+				continue;
+			}
+
+			*texture = match new_text.is_empty() {
+				true => None,
+				false => {
+					let image = self::draw_text(&new_text, *scale);
+					let image = glium::texture::RawImage2d::from_raw_rgba_reversed(&image, image.dimensions());
+					let new_texture = glium::Texture2d::new(facade, image)
+						.with_context(|| format!("Unable to create texture for {:?} overlay", widget.kind))?;
+					Some(new_texture)
+				},
+			};
+			*text = new_text;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the kind, scale and texture of all widgets with any text
+	pub fn textures(&self) -> impl Iterator<Item = (WidgetKind, f32, &glium::Texture2d)> {
+		self.widgets
+			.iter()
+			.filter_map(|(widget, scale, _, texture)| texture.as_ref().map(|texture| (widget.kind, *scale, texture)))
+	}
+}
+
+/// Draws `text` onto an image, with a background, at `scale`
+fn draw_text(text: &str, scale: f32) -> RgbaImage {
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Scales will be small and positive
+	let pixel_size = (FONT_PIXEL_SIZE * scale).round().max(1.0) as u32;
+
+	// Note: Each glyph is followed by a column of space, except the last
+	let chars = text.chars().take(MAX_TEXT_LEN).collect::<Vec<_>>();
+	#[allow(clippy::cast_possible_truncation)] // We only take a few characters
+	let chars_len = chars.len() as u32;
+	let width = (chars_len * 6 - 1 + 2 * PADDING) * pixel_size;
+	let height = (7 + 2 * PADDING) * pixel_size;
+
+	let mut image = RgbaImage::from_pixel(width, height, BG_COLOR);
+	for (char_idx, &ch) in (0..).zip(&chars) {
+		for (row_idx, row) in (0..).zip(self::glyph(ch)) {
+			for col_idx in 0..5 {
+				if row & (0b10000 >> col_idx) == 0 {
+					continue;
+				}
+
+				let x = (PADDING + char_idx * 6 + col_idx) * pixel_size;
+				let y = (PADDING + row_idx) * pixel_size;
+				for pixel_y in y..y + pixel_size {
+					for pixel_x in x..x + pixel_size {
+						image.put_pixel(pixel_x, pixel_y, TEXT_COLOR);
+					}
+				}
+			}
+		}
+	}
+
+	image
+}
+
+/// Returns the glyph of `ch`.
+///
+/// Lowercase letters use the uppercase glyphs, and any characters without a glyph are shown as `?`.
+#[rustfmt::skip]
+fn glyph(ch: char) -> [u8; 7] {
+	match ch.to_ascii_uppercase() {
+		ch @ '0'..='9' => DIGITS[usize::from(ch as u8 - b'0')],
+		ch @ 'A'..='Z' => LETTERS[usize::from(ch as u8 - b'A')],
+		' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+		':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+		'.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+		'-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+		'_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+		_   => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+	}
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+// Uniforms
+uniform sampler2D tex_sampler;
+
+// Inputs
+in vec2 frag_tex;
+
+// Outputs
+out vec4 color;
+
+void main() {
+	// Note: The texture is already in srgb, like our output, so we don't convert it
+	color = texture(tex_sampler, frag_tex);
+}
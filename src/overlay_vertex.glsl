@@ -0,0 +1,19 @@
+#version 330 core
+
+// Uniforms
+uniform vec4 rect;
+
+// Inputs
+in vec2 vertex_pos;
+in vec2 vertex_tex;
+
+// Outputs
+out vec2 frag_tex;
+
+void main() {
+	frag_tex = vertex_tex;
+
+	// Note: The quad covers the whole screen, so we move it onto `rect`, with the bottom-left
+	//       corner on `rect.xy` and the top-right corner on `rect.zw`.
+	gl_Position = vec4(mix(rect.xy, rect.zw, vertex_tex), 0.0, 1.0);
+}
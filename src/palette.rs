@@ -0,0 +1,101 @@
+//! Palette
+//!
+//! Writes the palette of the current image to a file, either as Xresources or
+//! using a template, so other programs may be themed after the wallpaper.
+
+// Imports
+use crate::{images, storage};
+use anyhow::Context;
+use std::{
+	cmp::Ordering,
+	fmt::Write,
+	fs,
+	path::{Path, PathBuf},
+};
+
+/// Palette writer
+#[derive(Clone, Debug)]
+pub struct PaletteWriter {
+	/// Output path
+	out: PathBuf,
+
+	/// Template, if any
+	template: Option<String>,
+}
+
+impl PaletteWriter {
+	/// Creates a palette writer to `out`, reading the template from `template`, if any
+	pub fn new(out: PathBuf, template: Option<&Path>) -> Result<Self, anyhow::Error> {
+		let template = template
+			.map(|template| {
+				fs::read_to_string(template).with_context(|| format!("Unable to read template {template:?}"))
+			})
+			.transpose()?;
+
+		Ok(Self { out, template })
+	}
+
+	/// Writes the palette of the image at `path`
+	pub fn write(&self, path: &Path, palette: &[[f32; 3]]) -> Result<(), anyhow::Error> {
+		let colors = self::colors(palette);
+		let background = &colors[0];
+		let foreground = &colors[colors.len() - 1];
+
+		let contents = match &self.template {
+			Some(template) => {
+				let contents = colors
+					.iter()
+					.enumerate()
+					.fold(template.clone(), |contents, (idx, color)| {
+						contents.replace(&format!("{{color{idx}}}"), color)
+					});
+				contents
+					.replace("{background}", background)
+					.replace("{foreground}", foreground)
+					.replace("{path}", &path.to_string_lossy())
+			},
+			None => {
+				let mut contents = String::new();
+				for (idx, color) in colors.iter().enumerate() {
+					writeln!(contents, "*.color{idx}: {color}").expect("Unable to write string");
+				}
+				writeln!(contents, "*.background: {background}").expect("Unable to write string");
+				writeln!(contents, "*.foreground: {foreground}").expect("Unable to write string");
+				contents
+			},
+		};
+
+		storage::write_atomic(&self.out, contents.as_bytes())
+			.with_context(|| format!("Unable to write palette to {:?}", self.out))
+	}
+}
+
+/// Returns the colors of a palette as `#rrggbb`, from darkest to lightest.
+///
+/// If the palette has less colors than [`images::PALETTE_LEN`], the lightest one is repeated.
+fn colors(palette: &[[f32; 3]]) -> Vec<String> {
+	let mut palette = palette.to_vec();
+	palette.sort_by(|lhs, rhs| {
+		self::luminance(*lhs)
+			.partial_cmp(&self::luminance(*rhs))
+			.unwrap_or(Ordering::Equal)
+	});
+
+	let last = palette.last().copied().unwrap_or_default();
+	palette.resize(images::PALETTE_LEN, last);
+
+	palette
+		.into_iter()
+		.map(|color| {
+			#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // We clamp it to `0..=255`
+			let [r, g, b] = color.map(|value| (value * 255.0).round().clamp(0.0, 255.0) as u8);
+			format!("#{r:02x}{g:02x}{b:02x}")
+		})
+		.collect()
+}
+
+/// Returns the relative luminance of a color
+#[allow(clippy::suboptimal_flops)] // This isn't performance sensitive
+fn luminance([r, g, b]: [f32; 3]) -> f32 {
+	0.2126 * r + 0.7152 * g + 0.0722 * b
+}
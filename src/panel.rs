@@ -0,0 +1,374 @@
+//! Panel
+//!
+//! A panel shows a single image at a time, scrolling through it and then fading into the
+//! next. Without a grid, there's a single panel covering the whole window.
+
+// Imports
+use crate::{
+	args::{Args, GridTiming, LatePolicy},
+	clock,
+	glium_facade::GliumFacade,
+	images::Images,
+	slide_info::Shape,
+	tempo::Tempo,
+	texture_upload::TextureUpload,
+	Image,
+};
+use anyhow::Context;
+use cgmath::{Point2, Vector2};
+use rand::Rng;
+use std::{
+	collections::VecDeque,
+	mem,
+	time::{Duration, SystemTime},
+};
+
+/// Update context, shared by all panels
+#[derive(Clone, Copy)]
+pub struct UpdateContext<'a> {
+	/// Arguments
+	pub args: &'a Args,
+
+	/// Tempo, if any
+	pub tempo: Option<&'a Tempo>,
+
+	/// If we're on a beat
+	pub beat: bool,
+
+	/// If we're paused
+	pub paused: bool,
+}
+
+/// Panel
+#[derive(Debug)]
+pub struct Panel {
+	/// Current image
+	pub cur_image: Image,
+
+	/// Next image
+	pub next_image: Image,
+
+	/// Progress through the current image
+	pub progress: f32,
+
+	/// How much the progress advances on the next update
+	pub progress_step: f32,
+
+	/// Scale of the duration of the current image
+	pub duration_scale: f32,
+
+	/// If the next image is loaded
+	pub next_image_is_loaded: bool,
+
+	/// Standby image, if any
+	pub standby: Option<Image>,
+
+	/// Previously shown images
+	pub history: VecDeque<Image>,
+
+	/// Scale, relative to the window
+	pub scale: Vector2<f32>,
+
+	/// Offset of the center, in normalized device coordinates
+	pub offset: Point2<f32>,
+
+	/// Mirrors, drawing this panel elsewhere
+	pub mirrors: Vec<Mirror>,
+
+	/// If the panel always shows the current image
+	pub pinned: bool,
+
+	/// Second exposure, blended over this panel, if any
+	pub exposure: Option<Box<Self>>,
+}
+
+/// Mirror of a panel, drawing it elsewhere in the window
+#[derive(Clone, Copy, Debug)]
+pub struct Mirror {
+	/// Scale, relative to the window
+	pub scale: Vector2<f32>,
+
+	/// Offset of the center, in normalized device coordinates
+	pub offset: Point2<f32>,
+
+	/// If the panel is flipped horizontally and vertically
+	pub flip: [bool; 2],
+}
+
+impl Panel {
+	/// Creates a new panel, scaled by `scale` and offset by `offset` within the window
+	pub fn new(cur_image: Image, next_image: Image, scale: Vector2<f32>, offset: Point2<f32>) -> Self {
+		Self {
+			cur_image,
+			next_image,
+			progress: 0.0,
+			progress_step: 0.0,
+			duration_scale: 1.0,
+			next_image_is_loaded: false,
+			standby: None,
+			history: VecDeque::new(),
+			scale,
+			offset,
+			mirrors: vec![],
+			pinned: false,
+			exposure: None,
+		}
+	}
+
+	/// Creates a new panel covering the whole window
+	pub fn full(cur_image: Image, next_image: Image) -> Self {
+		Self::new(cur_image, next_image, Vector2::new(1.0, 1.0), Point2::new(0.0, 0.0))
+	}
+
+	/// Updates this panel after `dt` has elapsed
+	#[allow(clippy::too_many_lines)] // TODO: Refactor
+	pub fn update(
+		&mut self, dt: Duration, ctx: &UpdateContext, facade: &GliumFacade, images: &mut Images,
+	) -> Result<(), anyhow::Error> {
+		let UpdateContext {
+			args,
+			tempo,
+			beat,
+			paused,
+		} = *ctx;
+
+		// Update the second exposure on it's own, so it changes images at different times
+		if let Some(exposure) = &mut self.exposure {
+			exposure
+				.update(dt, ctx, facade, images)
+				.context("Unable to update second exposure")?;
+		}
+
+		// If we're pinned, stay at the start of the current image
+		// Note: Skipping may still have advanced us
+		if self.pinned {
+			self.progress = 0.0;
+			self.cur_image.advance(dt);
+			return Ok(());
+		}
+
+		// Increase the progress
+		// Note: When paused, we still finish any fade, so we don't stay stuck between images
+		let was_fading = self.progress >= args.fade;
+		match args.clock_sync {
+			// Note: If the clock moved on to the next slot, we finish the current image
+			Some(_) => {
+				let clock_progress = self::clock_progress(args);
+				self.progress = match self.progress - clock_progress > args.fade / 2.0 {
+					true => 1.0,
+					false => clock_progress,
+				};
+			},
+			// Note: When following, we only fade once the leader's next image is loaded
+			None => {
+				if (!paused && args.sync_follow.is_none()) || was_fading {
+					self.progress += self::progress_step(self.progress, dt, self.duration_scale, args, tempo);
+				}
+			},
+		}
+
+		// Advance any animations
+		// Note: The next image only starts playing once it's fading in
+		self.cur_image.advance(dt);
+		if was_fading {
+			self.next_image.advance(dt);
+		}
+
+		// If we're following a tempo, only start fading on a beat
+		if tempo.is_some() && !was_fading && !paused {
+			self.progress = match beat {
+				true => self.progress.max(args.fade),
+				false => self.progress.min(args.fade - f32::EPSILON),
+			};
+		}
+
+		// If the next image isn't loaded, try to load it
+		if !self.next_image_is_loaded {
+			// If our progress is >= fade start, the next image is late.
+			// Note: Only when waiting do we block until the next image arrives
+			let is_late = self.progress >= args.fade;
+			let force_wait = is_late && args.late_policy == LatePolicy::Wait && !images.is_finished();
+
+			if force_wait {
+				log::info!("Next image hasn't arrived yet at the end of current image, waiting for it");
+			}
+
+			// Then try to load it
+			self.next_image_is_loaded ^= self
+				.next_image
+				.try_update(
+					facade,
+					images,
+					&mut self.standby,
+					&mut self.history,
+					args.prev_images,
+					force_wait,
+				)
+				.context("Unable to update image")?;
+
+			// If it's still late, act according to the policy
+			if is_late && !self.next_image_is_loaded {
+				match args.late_policy {
+					// Note: If there are no more images, we just stay on the current one until we quit
+					_ if images.is_finished() => self.progress = self.progress.min(args.fade - f32::EPSILON),
+
+					LatePolicy::Wait => {
+						return Err(anyhow::anyhow!("Unable to load next image even while force-waiting"));
+					},
+
+					// Hold the current image right before the fade, until the next arrives
+					LatePolicy::Extend => {
+						log::debug!("Next image is late, extending current image");
+						self.progress = self.progress.min(args.fade - f32::EPSILON);
+					},
+
+					// Scroll back through the current image, from the same position
+					LatePolicy::Replay => {
+						log::debug!("Next image is late, replaying current image");
+						self.cur_image.uvs.reverse();
+						self.progress = 1.0 - self.progress;
+					},
+
+					// Fade into a placeholder
+					LatePolicy::Placeholder => {
+						log::debug!("Next image is late, fading into placeholder");
+						self.next_image.is_placeholder = true;
+						self.next_image_is_loaded = true;
+					},
+				}
+			}
+		}
+
+		// If we're following and the leader's next image is loaded, start fading into it
+		if args.sync_follow.is_some() && self.next_image_is_loaded {
+			self.progress = self.progress.max(args.fade);
+		}
+
+		// If we reached the end, swap the next to current and try to load the next
+		if self.progress >= 1.0 {
+			// Reset the progress to where we where during the fade
+			self.progress = 1.0 - args.fade;
+
+			// Swap the images
+			mem::swap(&mut self.cur_image, &mut self.next_image);
+			self.cur_image.was_shown = true;
+			self.duration_scale = self::duration_scale(args);
+			self.next_image_is_loaded = false;
+			match self.cur_image.is_placeholder {
+				true => log::info!("Showing placeholder"),
+				false => log::info!("Showing {}", self.cur_image.info),
+			}
+
+			// And try to update the next image
+			self.next_image_is_loaded ^= self
+				.next_image
+				.try_update(
+					facade,
+					images,
+					&mut self.standby,
+					&mut self.history,
+					args.prev_images,
+					false,
+				)
+				.context("Unable to update image")?;
+		}
+
+		// If we don't have a standby image, try to get one
+		if self.standby.is_none() {
+			let image = images
+				.try_next_image(Shape::of(self.next_image.window_size))
+				.context("Unable to get standby image")?;
+			if let Some(image) = image {
+				let image = Image::from_image(
+					facade,
+					image,
+					self.next_image.window_size,
+					self.next_image.scaling,
+					self.next_image.compression,
+				)
+				.context("Unable to create standby image")?;
+				self.standby = Some(image);
+			}
+		}
+
+		// Keep uploading the next image, finishing it once it's being shown.
+		// Note: Once the next image is uploaded, we pre-upload the standby image, so it's
+		//       ready before it's needed, even if it only arrived right before the fade.
+		let next_image_is_uploaded = match self.progress >= args.fade {
+			true => {
+				self.next_image.finish_upload();
+				true
+			},
+			false => self.next_image.upload(TextureUpload::FRAME_BYTES),
+		};
+		if let (true, Some(standby)) = (next_image_is_uploaded, &mut self.standby) {
+			standby.upload(TextureUpload::FRAME_BYTES);
+		}
+
+		// Finally get how much we'll advance next, for interpolating
+		self.progress_step = self::progress_step(self.progress, dt, self.duration_scale, args, tempo);
+
+		Ok(())
+	}
+}
+
+/// Returns the initial progress of the `idx`-th panel of a grid, or `None` if it should be random.
+///
+/// When staggered, each panel starts fading `interval` after the previous one, wrapping around
+/// after each image.
+pub fn grid_progress(args: &Args, idx: usize) -> Option<f32> {
+	match args.grid_timing {
+		GridTiming::Random => None,
+		GridTiming::Sync => Some(0.0),
+		GridTiming::Stagger(interval) => {
+			// Note: Panels start fading every `fade` of the duration, after the previous fade
+			let period = args.duration.as_secs_f32() * args.fade;
+			#[allow(clippy::cast_precision_loss)] // Grids will be less than `2^23`
+			let delay = (interval.as_secs_f32() * (idx + 1) as f32).rem_euclid(period);
+			Some(args.fade - delay / args.duration.as_secs_f32())
+		},
+	}
+}
+
+/// Returns the duration of each wall clock slot, between image changes
+pub fn slot_duration(args: &Args) -> Duration {
+	args.duration.mul_f32(args.fade)
+}
+
+/// Returns the progress of the current image, when synchronized to the wall clock
+#[allow(clippy::suboptimal_flops)] // Keeping the formula readable is more important
+fn clock_progress(args: &Args) -> f32 {
+	let (_, slot_progress) = clock::slot(SystemTime::now(), self::slot_duration(args));
+
+	#[allow(clippy::cast_possible_truncation)] // `slot_progress` is within `0.0 .. 1.0`
+	let slot_progress = slot_progress as f32;
+	1.0 - args.fade + args.fade * slot_progress
+}
+
+/// Returns a random scale for the duration of an image, according to the duration jitter
+pub fn duration_scale(args: &Args) -> f32 {
+	match args.duration_jitter {
+		jitter if jitter > 0.0 => rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter),
+		_ => 1.0,
+	}
+}
+
+/// Returns the mix factor of the second exposure, `elapsed` since the start
+///
+/// Goes back and forth between faint and strong every double exposure period.
+// Note: It never goes below `0.2`, so the second exposure is always visible
+pub fn exposure_mix(args: &Args, elapsed: Duration) -> f32 {
+	let phase = elapsed.as_secs_f32() / args.double_exposure_period.as_secs_f32();
+	0.4f32.mul_add(-(phase * std::f32::consts::TAU).cos(), 0.6)
+}
+
+/// Returns how much the progress advances after `dt`, with the duration scaled by `duration_scale`
+// Note: When following a tempo, the fade is sped up to last a single beat
+fn progress_step(progress: f32, dt: Duration, duration_scale: f32, args: &Args, tempo: Option<&Tempo>) -> f32 {
+	match tempo {
+		Some(tempo) if progress >= args.fade => {
+			dt.as_secs_f32() * (1.0 - args.fade) / tempo.beat_period().as_secs_f32()
+		},
+		_ => dt.as_secs_f32() / (args.duration.as_secs_f32() * duration_scale),
+	}
+}
@@ -0,0 +1,51 @@
+//! Path list
+//!
+//! Reads newline-separated paths from a file, or `stdin`, as they're written,
+//! so other programs, such as `find` or a picker, may choose the images to load.
+//!
+//! Any `http` or `https` urls are downloaded first.
+
+// Imports
+use crate::remote;
+use anyhow::Context;
+use notify::DebouncedEvent;
+use std::{
+	fs::File,
+	io::{self, BufRead, BufReader},
+	path::Path,
+	sync::mpsc,
+};
+
+/// Reads all paths from `path`, or `stdin`, if `-`, sending them to `event_tx`
+pub fn read(path: &Path, event_tx: &mpsc::Sender<DebouncedEvent>) -> Result<(), anyhow::Error> {
+	let reader: Box<dyn BufRead> = match path == Path::new("-") {
+		true => Box::new(BufReader::new(io::stdin())),
+		false => Box::new(BufReader::new(File::open(path).context("Unable to open file")?)),
+	};
+
+	// Note: The loader always uses absolute paths, so we make sure ours are too
+	let current_dir = std::env::current_dir().context("Unable to get current directory")?;
+	for line in reader.lines() {
+		let line = line.context("Unable to read line")?;
+		let line = line.trim();
+		if line.is_empty() {
+			continue;
+		}
+
+		let path = match remote::is_url(line) {
+			true => match remote::fetch(line) {
+				Ok(path) => path,
+				Err(err) => {
+					log::warn!("Unable to download {line:?}: {err:?}");
+					continue;
+				},
+			},
+			false => current_dir.join(line),
+		};
+		if event_tx.send(DebouncedEvent::Create(path)).is_err() {
+			return Ok(());
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,85 @@
+//! Preview
+
+// Imports
+use anyhow::Context;
+use image::{imageops, RgbaImage};
+use std::{path::Path, rc::Rc, slice};
+use zss::{
+	args::{PreviewArgs, ResizeFilter, Scaling},
+	effects::Effects,
+	glium_backend::GliumBackend,
+	glium_facade::GliumFacade,
+	images::{self, LoadOptions},
+	panel::Panel,
+	renderer::{DrawParams, Renderer},
+	window::Window,
+	Image,
+};
+
+/// Renders a single frame of the slideshow to an image
+pub fn run(args: &PreviewArgs) -> Result<(), anyhow::Error> {
+	// Create an offscreen window to render with
+	let window = Window::headless(args.size)
+		.map(Rc::new)
+		.context("Unable to create headless window")?;
+	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+
+	let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+
+	// Note: Without a next image, we just fade into the same image
+	let cur_image = self::load_image(&facade, &args.image, args.size, args.scaling)
+		.with_context(|| format!("Unable to load {}", args.image.display()))?;
+	let next_path = args.next_image.as_deref().unwrap_or(&args.image);
+	let next_image = self::load_image(&facade, next_path, args.size, args.scaling)
+		.with_context(|| format!("Unable to load {}", next_path.display()))?;
+
+	let mut panel = Panel::full(cur_image, next_image);
+	panel.progress = args.at;
+	let params = DrawParams {
+		fade:                  args.fade,
+		fade_space:            args.fade_space,
+		scaling:               args.scaling,
+		kaleidoscope:          None,
+		double_exposure:       None,
+		exposure_mix:          0.0,
+		bg_color:              args.bg_color,
+		border_color:          args.bg_color,
+		dominant_bg:           false,
+		interpolation_samples: 1,
+		effects:               Effects::IDENTITY,
+	};
+
+	let texture = glium::Texture2d::empty(&facade, args.size[0], args.size[1]).context("Unable to create texture")?;
+	let mut surface =
+		glium::framebuffer::SimpleFrameBuffer::new(&facade, &texture).context("Unable to create framebuffer")?;
+	renderer
+		.draw(&mut surface, slice::from_ref(&panel), &params)
+		.context("Unable to draw frame")?;
+
+	// Note: Open-gl's origin is on the bottom-left, so we need to flip it
+	let raw: glium::texture::RawImage2d<u8> = texture.read();
+	let image =
+		RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).context("Frame had an unexpected size")?;
+	let image = imageops::flip_vertical(&image);
+
+	image.save(&args.out).context("Unable to save frame")
+}
+
+/// Loads and uploads the image at `path`, for a `size` frame
+pub fn load_image(facade: &GliumFacade, path: &Path, size: [u32; 2], scaling: Scaling) -> Result<Image, anyhow::Error> {
+	let image = images::load_img(path, LoadOptions {
+		window_size: size,
+		scaling,
+		resize_filter: ResizeFilter::Lanczos3,
+		gpu_resize: false,
+		rgb: false,
+		max_texture_size: Some(facade.max_texture_size()),
+		cache_size: 0,
+	})
+	.context("Unable to load image")?;
+
+	let mut image = Image::from_image(facade, image, size, scaling, None)?;
+	image.finish_upload();
+	Ok(image)
+}
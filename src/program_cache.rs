@@ -0,0 +1,61 @@
+//! Program binary cache
+//!
+//! Caches the compiled program binaries on disk, keyed by their shader sources,
+//! so they don't need to be compiled on every start-up.
+
+// Imports
+use crate::storage;
+use anyhow::Context;
+use std::{
+	collections::hash_map::DefaultHasher,
+	convert::TryInto,
+	hash::{Hash, Hasher},
+	path::PathBuf,
+};
+
+/// Program cache
+#[derive(Debug)]
+pub struct ProgramCache {
+	/// Path of the cached binary
+	path: PathBuf,
+}
+
+impl ProgramCache {
+	/// Creates the cache for a program with shader sources `sources`
+	///
+	/// Returns `None` if no cache directory exists
+	pub fn new(sources: &[&str]) -> Option<Self> {
+		let cache_dir = storage::cache_dir()?;
+
+		let mut hasher = DefaultHasher::new();
+		sources.hash(&mut hasher);
+		let key = hasher.finish();
+
+		Some(Self {
+			path: cache_dir.join(format!("program-{key:016x}.bin")),
+		})
+	}
+
+	/// Loads the cached binary
+	pub fn load(&self) -> Result<glium::program::Binary, anyhow::Error> {
+		let data = std::fs::read(&self.path).context("Unable to read cached binary")?;
+
+		// Note: The format is stored in the first 4 bytes
+		anyhow::ensure!(data.len() >= 4, "Cached binary was too small");
+		let (format, content) = data.split_at(4);
+		let format = u32::from_le_bytes(format.try_into().expect("Slice had wrong length"));
+
+		Ok(glium::program::Binary {
+			format,
+			content: content.to_vec(),
+		})
+	}
+
+	/// Stores a binary in the cache
+	pub fn store(&self, binary: &glium::program::Binary) -> Result<(), anyhow::Error> {
+		let mut data = Vec::with_capacity(4 + binary.content.len());
+		data.extend(binary.format.to_le_bytes());
+		data.extend(&binary.content);
+		storage::write_atomic(&self.path, &data).context("Unable to write cached binary")
+	}
+}
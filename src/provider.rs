@@ -0,0 +1,740 @@
+//! Image providers
+//!
+//! Providers supply the images retrieved from [`Images`], and are run in a background
+//! thread, with every image they provide sent to the backlog.
+
+// Imports
+use crate::{
+	archive,
+	dedup::Dedup,
+	filters::Filters,
+	handlers::{Handler, Handlers},
+	ignores::Ignores,
+	images::{self, Generations, LoadOptions, LoadedImage},
+	library::Library,
+	marked_paths::MarkedPaths,
+	quarantine::Quarantine,
+	selection::SelectionStrategy,
+	slide_info::SlideSource,
+};
+use anyhow::Context;
+use image::{DynamicImage, RgbaImage};
+use std::{
+	collections::{HashSet, VecDeque},
+	io::{BufRead, BufReader, Read},
+	path::PathBuf,
+	process::{Child, ChildStdout, Command, Stdio},
+	sync::{mpsc, Arc, Mutex},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// Image provider
+pub trait ImageProvider: Send {
+	/// Handles all pending events, such as new or removed files.
+	///
+	/// If there are no images to provide, waits until there are.
+	fn poll_events(&mut self) -> Result<(), anyhow::Error>;
+
+	/// Returns the next image, if any.
+	///
+	/// Once this returns `None`, events should be polled before retrieving any more images.
+	fn next(&mut self) -> Result<Option<ProvidedImage>, anyhow::Error>;
+
+	/// Returns if this provider won't provide any more images
+	fn is_finished(&self) -> bool {
+		false
+	}
+}
+
+/// Provided image
+#[derive(Debug)]
+pub struct ProvidedImage {
+	/// Image
+	pub image: LoadedImage,
+
+	/// Generation it started loading in
+	pub generation: u64,
+}
+
+/// Library provider.
+///
+/// Keeps a library of all paths received from it's events, and loads them in
+/// the order chosen by a selection strategy.
+pub struct LibraryProvider {
+	/// Receiver for all events
+	event_rx: mpsc::Receiver<notify::DebouncedEvent>,
+
+	/// Load options
+	load_options: LoadOptions,
+
+	/// Ignore rules
+	ignores: Ignores,
+
+	/// Handlers
+	handlers: Handlers,
+
+	/// Filters
+	filters: Filters,
+
+	/// Timeout for decoding each image
+	decode_timeout: Duration,
+
+	/// Quarantine
+	quarantine: Quarantine,
+
+	/// Banned paths
+	banned: Arc<MarkedPaths>,
+
+	/// Only paths to load, if any
+	only: Option<Arc<MarkedPaths>>,
+
+	/// Directory to only select paths within, if any
+	active_dir: Option<Arc<Mutex<PathBuf>>>,
+
+	/// Selection strategy
+	selection: Box<dyn SelectionStrategy>,
+
+	/// Deduplication, if any
+	dedup: Option<Dedup>,
+
+	/// Path generations
+	generations: Arc<Generations>,
+
+	/// Library
+	library: Library,
+
+	/// Remaining paths selected in this pass
+	selected: VecDeque<PathBuf>,
+
+	/// Generation the paths were selected in
+	selection_generation: u64,
+
+	/// Paths that failed in this pass
+	removed: HashSet<PathBuf>,
+
+	/// Maximum number of passes, if any
+	max_passes: Option<usize>,
+
+	/// Number of passes finished
+	passes: usize,
+}
+
+impl LibraryProvider {
+	/// Creates a library provider, receiving paths from `event_rx`
+	#[allow(clippy::too_many_arguments)] // They're all needed by the loader
+	pub fn new(
+		event_rx: mpsc::Receiver<notify::DebouncedEvent>, load_options: LoadOptions, ignores: Ignores,
+		handlers: Handlers, filters: Filters, decode_timeout: Duration, quarantine: Quarantine,
+		banned: Arc<MarkedPaths>, only: Option<Arc<MarkedPaths>>, active_dir: Option<Arc<Mutex<PathBuf>>>,
+		selection: Box<dyn SelectionStrategy>, dedup: Option<Dedup>, generations: Arc<Generations>,
+	) -> Self {
+		Self {
+			event_rx,
+			load_options,
+			ignores,
+			handlers,
+			filters,
+			decode_timeout,
+			quarantine,
+			banned,
+			only,
+			active_dir,
+			selection,
+			dedup,
+			generations,
+			library: Library::default(),
+			selected: VecDeque::new(),
+			selection_generation: 0,
+			removed: HashSet::new(),
+			max_passes: None,
+			passes: 0,
+		}
+	}
+
+	/// Stops providing images after `max_passes` passes through the library
+	pub fn with_max_passes(self, max_passes: Option<usize>) -> Self {
+		Self { max_passes, ..self }
+	}
+}
+
+impl ImageProvider for LibraryProvider {
+	fn poll_events(&mut self) -> Result<(), anyhow::Error> {
+		loop {
+			// Check for new paths, or, if we're out, wait
+			loop {
+				let event = match self.library.is_empty() {
+					true => {
+						log::warn!("No images found, waiting for new files");
+						self.event_rx.recv().context("Unable to receive event")?
+					},
+					false => match self.event_rx.try_recv() {
+						Ok(event) => event,
+						Err(mpsc::TryRecvError::Empty) => break,
+						Err(mpsc::TryRecvError::Disconnected) => anyhow::bail!("Event sender was dropped"),
+					},
+				};
+
+				self::handle_event(event, &mut self.library, &mut self.ignores, &self.quarantine);
+			}
+
+			// Then get the paths within the active directory, if any
+			self.selection_generation = self.generations.current();
+			let active_paths;
+			let paths = match &self.active_dir {
+				Some(active_dir) => {
+					let active_dir = active_dir.lock().expect("Active directory lock was poisoned").clone();
+					active_paths = self
+						.library
+						.paths()
+						.iter()
+						.filter(|path| path.starts_with(&active_dir))
+						.cloned()
+						.collect::<Vec<_>>();
+
+					// Note: The schedule sends an event whenever the active directory changes, so we just wait for one
+					if active_paths.is_empty() {
+						log::warn!("No images found in {active_dir:?}, waiting for new files");
+						let event = self.event_rx.recv().context("Unable to receive event")?;
+						self::handle_event(event, &mut self.library, &mut self.ignores, &self.quarantine);
+						continue;
+					}
+
+					&active_paths
+				},
+				None => self.library.paths(),
+			};
+
+			// And select the ones to load
+			self.selected = self.selection.select(paths).into();
+			log::info!("Selected {} out of {} files", self.selected.len(), paths.len());
+
+			return Ok(());
+		}
+	}
+
+	fn next(&mut self) -> Result<Option<ProvidedImage>, anyhow::Error> {
+		let Self {
+			load_options,
+			handlers,
+			filters,
+			decode_timeout,
+			quarantine,
+			banned,
+			only,
+			selection,
+			dedup,
+			generations,
+			library,
+			selected,
+			selection_generation,
+			removed,
+			passes,
+			..
+		} = self;
+
+		while let Some(path) = selected.pop_front() {
+			// If it was already removed, skip it
+			if removed.contains(&path) {
+				continue;
+			}
+
+			// If it was removed since we selected it, skip it
+			if generations.is_stale(&path, *selection_generation) {
+				log::debug!("Skipping {path:?}, as it was removed");
+				continue;
+			}
+
+			// If it was banned, remove it
+			if banned.contains(&path) {
+				log::info!("Skipping {path:?}, as it's banned");
+				removed.insert(path);
+				continue;
+			}
+
+			// If we only load some paths and it isn't one of them, remove it
+			if only.as_ref().map_or(false, |only| !only.contains(&path)) {
+				log::debug!("Skipping {path:?}, as it isn't a favorite");
+				removed.insert(path);
+				continue;
+			}
+
+			// Else, if it's within an archive, extract it
+			let load_path = match archive::resolve(&path) {
+				Ok(load_path) => load_path,
+				Err(err) => {
+					log::info!("Unable to extract {path:?}: {err:?}");
+					quarantine.record_failure(&path, &err);
+					removed.insert(path);
+					continue;
+				},
+			};
+
+			// Else, if it doesn't pass the filters, remove it
+			if let Err(err) = filters.check(&load_path) {
+				log::info!("Skipping {path:?}: {err}");
+				removed.insert(path);
+				continue;
+			}
+
+			// Else, if it's a copy of another file, remove it
+			if let Some(dedup) = dedup {
+				match dedup.check_contents(&path, &load_path, |path| library.contains(path)) {
+					Ok(Some(other_path)) => {
+						log::info!("Skipping {path:?}, as it's a copy of {other_path:?}");
+						removed.insert(path);
+						continue;
+					},
+					Ok(None) => (),
+					Err(err) => log::debug!("Unable to check if {path:?} is a copy: {err:?}"),
+				}
+			}
+
+			// Else try to load it with it's handler
+			let handler = handlers.get(&path);
+			if handler == Handler::Skip {
+				log::debug!("Skipping {path:?}");
+				removed.insert(path);
+				continue;
+			}
+			let generation = generations.current();
+			let mut image = match images::load_with_timeout(&load_path, handler, *load_options, *decode_timeout) {
+				Ok(value) => value,
+				Err(err) => {
+					log::info!("Unable to load {path:?}: {err}");
+					quarantine.record_failure(&path, &err);
+					removed.insert(path);
+					continue;
+				},
+			};
+			quarantine.record_success(&path);
+			image.info.path.clone_from(&path);
+
+			// If it looks like another image, remove it
+			let other_path = dedup
+				.as_mut()
+				.zip(image.frames.first())
+				.and_then(|(dedup, frame)| dedup.check_image(&path, &frame.image, |path| library.contains(path)));
+			if let Some(other_path) = other_path {
+				log::info!("Skipping {path:?}, as it looks like {other_path:?}");
+				removed.insert(path);
+				continue;
+			}
+
+			// If it was removed while loading it, drop it
+			if generations.is_stale(&path, generation) {
+				log::info!("Dropping {path:?}, as it was removed while loading it");
+				continue;
+			}
+			selection.loaded(&path);
+
+			return Ok(Some(ProvidedImage { image, generation }));
+		}
+
+		// Once we're out, remove any paths that failed
+		library.retain(|path| !removed.contains(path));
+		removed.clear();
+		*passes += 1;
+
+		Ok(None)
+	}
+
+	fn is_finished(&self) -> bool {
+		self.max_passes.map_or(false, |max_passes| self.passes >= max_passes)
+	}
+}
+
+/// Replay provider.
+///
+/// Loads the images of a replay, in the same order they were recorded.
+pub struct ReplayProvider {
+	/// Remaining paths
+	paths: VecDeque<PathBuf>,
+
+	/// Load options
+	load_options: LoadOptions,
+
+	/// Handlers
+	handlers: Handlers,
+
+	/// Timeout for decoding each image
+	decode_timeout: Duration,
+}
+
+impl ReplayProvider {
+	/// Creates a replay provider for `paths`
+	pub fn new(
+		paths: VecDeque<PathBuf>, load_options: LoadOptions, handlers: Handlers, decode_timeout: Duration,
+	) -> Self {
+		Self {
+			paths,
+			load_options,
+			handlers,
+			decode_timeout,
+		}
+	}
+}
+
+impl ImageProvider for ReplayProvider {
+	fn poll_events(&mut self) -> Result<(), anyhow::Error> {
+		// Note: Replays never receive any events, so once we're out of paths, we have nothing left to provide
+		if self.paths.is_empty() {
+			log::info!("Finished loading all replayed images");
+			loop {
+				thread::park();
+			}
+		}
+
+		Ok(())
+	}
+
+	fn next(&mut self) -> Result<Option<ProvidedImage>, anyhow::Error> {
+		let path = match self.paths.pop_front() {
+			Some(path) => path,
+			None => return Ok(None),
+		};
+
+		// Note: Any image we can't load would desync the replay, so we stop on the first error
+		let image = images::load_with_timeout(&path, self.handlers.get(&path), self.load_options, self.decode_timeout)
+			.with_context(|| format!("Unable to load replayed image {path:?}"))?;
+
+		Ok(Some(ProvidedImage { image, generation: 0 }))
+	}
+}
+
+/// Command provider.
+///
+/// Spawns a command and reads the images it writes to it's `stdout`, each
+/// preceded by a header line, either `rgba <width> <height>`, followed by
+/// the raw pixels, or `png <length>`, followed by the encoded image.
+pub struct CommandProvider {
+	/// Command
+	command: String,
+
+	/// Process
+	child: Child,
+
+	/// Process' output
+	stdout: BufReader<ChildStdout>,
+
+	/// Load options
+	load_options: LoadOptions,
+
+	/// Number of images read
+	images_read: u64,
+
+	/// If the process' output ended
+	finished: bool,
+}
+
+impl CommandProvider {
+	/// Maximum size of each frame's data, in bytes
+	// Note: Same as the default allocation limit of `image` when decoding, so a bad header
+	//       can't make us allocate arbitrarily much
+	const MAX_FRAME_SIZE: usize = 512 * 1024 * 1024;
+
+	/// Spawns `command` with `sh`
+	pub fn spawn(command: String, load_options: LoadOptions) -> Result<Self, anyhow::Error> {
+		let mut child = Command::new("sh")
+			.arg("-c")
+			.arg(&command)
+			.stdin(Stdio::null())
+			.stdout(Stdio::piped())
+			.spawn()
+			.context("Unable to spawn command")?;
+		let stdout = child.stdout.take().context("Command had no stdout")?;
+
+		Ok(Self {
+			command,
+			child,
+			stdout: BufReader::new(stdout),
+			load_options,
+			images_read: 0,
+			finished: false,
+		})
+	}
+
+	/// Reads the next image's frame from the command, if it's output hasn't ended.
+	///
+	/// Returns an inner error if the frame was read, but couldn't be decoded, in which
+	/// case the next frame may still be read, else an outer error.
+	fn read_frame(&mut self) -> Result<Option<Result<DynamicImage, anyhow::Error>>, anyhow::Error> {
+		let mut header = String::new();
+		if self.stdout.read_line(&mut header).context("Unable to read header")? == 0 {
+			return Ok(None);
+		}
+
+		let mut read_data = |len: usize| {
+			anyhow::ensure!(
+				len <= Self::MAX_FRAME_SIZE,
+				"Frame size {} exceeds maximum of {}",
+				len,
+				Self::MAX_FRAME_SIZE
+			);
+			let mut data = vec![0; len];
+			self.stdout
+				.read_exact(&mut data)
+				.map(|()| data)
+				.context("Unable to read data")
+		};
+		let header = header.split_whitespace().collect::<Vec<_>>();
+		let image = match *header.as_slice() {
+			["rgba", width, height] => {
+				let width = width.parse::<u32>().context("Unable to parse width")?;
+				let height = height.parse::<u32>().context("Unable to parse height")?;
+				let len = (width as usize)
+					.checked_mul(height as usize)
+					.and_then(|len| len.checked_mul(4))
+					.context("Image was too large")?;
+				let data = read_data(len).context("Unable to read pixels")?;
+				RgbaImage::from_raw(width, height, data)
+					.map(DynamicImage::ImageRgba8)
+					.context("Pixels didn't match image size")
+			},
+			["png", len] => {
+				let len = len.parse::<usize>().context("Unable to parse length")?;
+				let data = read_data(len).context("Unable to read image")?;
+				image::load_from_memory(&data).context("Unable to decode image")
+			},
+			_ => anyhow::bail!("Unknown header {:?}", header),
+		};
+
+		Ok(Some(image))
+	}
+}
+
+impl Drop for CommandProvider {
+	fn drop(&mut self) {
+		// Note: The command may not have finished, so we kill it and reap it, so it doesn't stay behind
+		if let Err(err) = self.child.kill() {
+			log::warn!("Unable to kill command {:?}: {err:?}", self.command);
+		}
+		if let Err(err) = self.child.wait() {
+			log::warn!("Unable to wait for command {:?}: {err:?}", self.command);
+		}
+	}
+}
+
+impl ImageProvider for CommandProvider {
+	fn poll_events(&mut self) -> Result<(), anyhow::Error> {
+		// Note: Commands don't have any events, so once their output ends, we have nothing left to provide
+		if self.finished {
+			log::warn!("Command {:?} finished, no more images will be shown", self.command);
+			loop {
+				thread::park();
+			}
+		}
+
+		Ok(())
+	}
+
+	fn next(&mut self) -> Result<Option<ProvidedImage>, anyhow::Error> {
+		let (image, path, load_start) = loop {
+			let load_start = Instant::now();
+			let image = match self.read_frame().context("Unable to read image from command")? {
+				Some(image) => image,
+				None => {
+					self.finished = true;
+					return Ok(None);
+				},
+			};
+
+			// Note: The images don't have a path, so we just number them
+			let path = PathBuf::from(format!("{}#{}", self.command, self.images_read));
+			self.images_read += 1;
+
+			// Note: If we couldn't decode it, we already read all of it's data, so we can just skip it
+			match image {
+				Ok(image) => break (image, path, load_start),
+				Err(err) => log::warn!("Unable to decode {path:?}: {err:?}"),
+			}
+		};
+		let image = images::process_frames(
+			&path,
+			vec![(image, Duration::ZERO)],
+			self.load_options,
+			SlideSource::Command,
+			load_start,
+		)?;
+
+		Ok(Some(ProvidedImage { image, generation: 0 }))
+	}
+}
+
+/// Follow provider.
+///
+/// Loads the images shown by a sync leader, as they're shown.
+pub struct FollowProvider {
+	/// Receiver for all paths
+	path_rx: mpsc::Receiver<PathBuf>,
+
+	/// Paths received, but not yet loaded
+	pending: VecDeque<PathBuf>,
+
+	/// Load options
+	load_options: LoadOptions,
+
+	/// Handlers
+	handlers: Handlers,
+
+	/// Timeout for decoding each image
+	decode_timeout: Duration,
+
+	/// If we've provided any images yet
+	provided_any: bool,
+}
+
+impl FollowProvider {
+	/// Creates a follow provider, receiving paths from `path_rx`
+	pub fn new(
+		path_rx: mpsc::Receiver<PathBuf>, load_options: LoadOptions, handlers: Handlers, decode_timeout: Duration,
+	) -> Self {
+		Self {
+			path_rx,
+			pending: VecDeque::new(),
+			load_options,
+			handlers,
+			decode_timeout,
+			provided_any: false,
+		}
+	}
+}
+
+impl ImageProvider for FollowProvider {
+	fn poll_events(&mut self) -> Result<(), anyhow::Error> {
+		if self.pending.is_empty() {
+			let path = self.path_rx.recv().context("Unable to receive path")?;
+			self.pending.push_back(path);
+		}
+		self.pending.extend(self.path_rx.try_iter());
+
+		Ok(())
+	}
+
+	fn next(&mut self) -> Result<Option<ProvidedImage>, anyhow::Error> {
+		while let Some(path) = self.pending.pop_front() {
+			let load_path = match archive::resolve(&path) {
+				Ok(load_path) => load_path,
+				Err(err) => {
+					log::warn!("Unable to extract followed image {path:?}: {err:?}");
+					continue;
+				},
+			};
+
+			let mut image = match images::load_with_timeout(
+				&load_path,
+				self.handlers.get(&path),
+				self.load_options,
+				self.decode_timeout,
+			) {
+				Ok(image) => image,
+				Err(err) => {
+					log::warn!("Unable to load followed image {path:?}: {err}");
+					continue;
+				},
+			};
+			image.info.path.clone_from(&path);
+
+			// Note: At the start, both the current and next image are retrieved, so we provide the first
+			//       image twice, with the next being replaced once the leader shows another image.
+			if !self.provided_any {
+				self.provided_any = true;
+				self.pending.push_front(path);
+			}
+
+			return Ok(Some(ProvidedImage { image, generation: 0 }));
+		}
+
+		Ok(None)
+	}
+}
+
+/// Handles a directory watcher event, updating the library
+fn handle_event(event: notify::DebouncedEvent, library: &mut Library, ignores: &mut Ignores, quarantine: &Quarantine) {
+	// Note: Any images of removed or renamed paths that were already loaded are
+	//       dropped through their generation, we just need to update the library.
+	match event {
+		// If any ignore rules changed, re-check all paths
+		notify::DebouncedEvent::Create(path) |
+		notify::DebouncedEvent::Write(path) |
+		notify::DebouncedEvent::Remove(path)
+			if Ignores::is_rules_file(&path) =>
+		{
+			log::info!("Ignore rules {path:?} changed, re-checking all files");
+			ignores.invalidate(&path);
+			library.retain(|path| !ignores.is_ignored(path));
+		},
+		// Note: The rules file may have been either moved away or moved in
+		notify::DebouncedEvent::Rename(from, to) if Ignores::is_rules_file(&from) || Ignores::is_rules_file(&to) => {
+			log::info!("Ignore rules {from:?} renamed to {to:?}, re-checking all files");
+			ignores.invalidate(&from);
+			ignores.invalidate(&to);
+			library.retain(|path| !ignores.is_ignored(path));
+		},
+		notify::DebouncedEvent::Create(path) => {
+			if ignores.is_ignored(&path) {
+				log::debug!("Ignoring {path:?}");
+				return;
+			}
+
+			for path in self::expand_archive(path) {
+				if quarantine.is_quarantined(&path) {
+					log::debug!("Ignoring quarantined {path:?}");
+					continue;
+				}
+
+				if library.add(path.clone()) {
+					log::info!("Adding {path:?}");
+				}
+			}
+		},
+		notify::DebouncedEvent::Remove(path) => match library.remove(&path) {
+			0 => (),
+			removed => log::info!("Removing {path:?} ({removed} files)"),
+		},
+		notify::DebouncedEvent::Rename(from, to) => {
+			let renamed = library.rename(&from, &to);
+			let renamed = match renamed.is_empty() {
+				// Note: If we didn't know the path, it was moved in from outside, so add it
+				true => {
+					log::info!("Adding {to:?}");
+					self::expand_archive(to)
+				},
+				false => {
+					log::info!("Renaming {from:?} to {to:?} ({} files)", renamed.len());
+					renamed
+				},
+			};
+
+			// Note: The new paths might now be ignored
+			for path in renamed {
+				if ignores.is_ignored(&path) || quarantine.is_quarantined(&path) {
+					log::debug!("Ignoring {path:?}");
+					library.remove(&path);
+					continue;
+				}
+
+				library.add(path);
+			}
+		},
+		_ => (),
+	}
+}
+
+/// Returns all files within `path`, if an archive, else `path` itself
+fn expand_archive(path: PathBuf) -> Vec<PathBuf> {
+	if !archive::is_archive(&path) {
+		return vec![path];
+	}
+
+	match archive::entries(&path) {
+		Ok(entries) => {
+			log::info!("Found {} files in archive {path:?}", entries.len());
+			entries
+		},
+		Err(err) => {
+			log::warn!("Unable to read archive {path:?}: {err:?}");
+			vec![]
+		},
+	}
+}
@@ -0,0 +1,94 @@
+//! Quarantine
+//!
+//! Keeps track of files that failed to load, so files that repeatedly
+//! fail aren't retried every time zss is started.
+
+// Imports
+use crate::storage;
+use std::{
+	collections::HashMap,
+	fmt,
+	fs::OpenOptions,
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+/// Quarantine
+#[derive(Debug)]
+pub struct Quarantine {
+	/// Path of the quarantine file, if any
+	path: Option<PathBuf>,
+
+	/// Path of the log file, if any
+	log_path: Option<PathBuf>,
+
+	/// Number of times each file failed to load
+	failures: HashMap<PathBuf, u32>,
+}
+
+impl Quarantine {
+	/// Number of failures after which a file is quarantined
+	const MAX_FAILURES: u32 = 3;
+	/// Schema version of the quarantine file
+	const VERSION: u32 = 1;
+
+	/// Loads the quarantine from the state directory
+	pub fn load(log_path: Option<PathBuf>) -> Self {
+		let path = storage::state_dir().map(|state_dir| state_dir.join("quarantine.toml"));
+		let failures = path
+			.as_deref()
+			.map(|path| storage::load(path, Self::VERSION))
+			.unwrap_or_default();
+
+		Self {
+			path,
+			log_path,
+			failures,
+		}
+	}
+
+	/// Returns if `path` is quarantined
+	pub fn is_quarantined(&self, path: &Path) -> bool {
+		self.failures
+			.get(path)
+			.map_or(false, |&failures| failures >= Self::MAX_FAILURES)
+	}
+
+	/// Records that `path` failed to load with `err`
+	pub fn record_failure(&mut self, path: &Path, err: &impl fmt::Display) {
+		let failures = self.failures.entry(path.to_path_buf()).or_default();
+		*failures += 1;
+
+		if *failures == Self::MAX_FAILURES {
+			log::warn!("Quarantining {path:?} after {failures} failures");
+			if let Some(log_path) = &self.log_path {
+				let res = OpenOptions::new()
+					.create(true)
+					.append(true)
+					.open(log_path)
+					.and_then(|mut file| writeln!(file, "{}: {err}", path.display()));
+				if let Err(err) = res {
+					log::warn!("Unable to write to quarantine log {log_path:?}: {err}");
+				}
+			}
+		}
+
+		self.save();
+	}
+
+	/// Records that `path` loaded successfully
+	pub fn record_success(&mut self, path: &Path) {
+		if self.failures.remove(path).is_some() {
+			self.save();
+		}
+	}
+
+	/// Saves the quarantine
+	fn save(&self) {
+		if let Some(path) = &self.path {
+			if let Err(err) = storage::save(path, Self::VERSION, &self.failures) {
+				log::warn!("Unable to save quarantine to {path:?}: {err:?}");
+			}
+		}
+	}
+}
@@ -0,0 +1,128 @@
+//! Remote images
+//!
+//! Images may be given as `http` or `https` urls, which are downloaded with `curl`
+//! into `$XDG_CACHE_HOME/zss/remote`, and then loaded like any other file.
+//!
+//! Downloaded images are kept, so they're only downloaded once, until they grow
+//! over `MAX_CACHE_SIZE`, when the oldest are removed.
+
+// Imports
+use crate::storage;
+use anyhow::Context;
+use notify::DebouncedEvent;
+use std::{
+	ffi::OsStr,
+	path::{Path, PathBuf},
+	process::Command,
+	sync::mpsc,
+};
+
+/// Maximum time, in seconds, a single download may take
+const MAX_TIME_SECS: u32 = 120;
+
+/// Maximum size, in bytes, of a single download
+const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Maximum size, in bytes, of all downloads
+const MAX_CACHE_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Returns if `value` is a remote url
+pub fn is_url(value: &str) -> bool {
+	value.starts_with("http://") || value.starts_with("https://")
+}
+
+/// Returns the extension of the path of `url`, if any
+pub fn extension(url: &str) -> Option<&str> {
+	url.split(&['?', '#'][..])
+		.next()
+		.and_then(|url| url.split_once("://"))
+		.and_then(|(_, url)| url.split_once('/'))
+		.and_then(|(_, url_path)| Path::new(url_path).extension())
+		.and_then(|extension| extension.to_str())
+}
+
+/// Downloads the image at `url`, if not already downloaded, and returns it's path
+pub fn fetch(url: &str) -> Result<PathBuf, anyhow::Error> {
+	let remote_dir = storage::cache_dir()
+		.context("Unable to get cache directory")?
+		.join("remote");
+
+	// Note: We keep the extension, so the file's handler may still be selected by it
+	let key = storage::stable_hash(&[url.as_bytes()]);
+	let file_name = match self::extension(url) {
+		Some(extension) => format!("{key:016x}.{extension}"),
+		None => format!("{key:016x}"),
+	};
+	let path = remote_dir.join(file_name);
+	if path.exists() {
+		log::debug!("Using downloaded {url:?} from {path:?}");
+		return Ok(path);
+	}
+
+	// Note: We download to a temporary file, so an interrupted download isn't mistaken for a finished one,
+	//       which is unique, so other instances downloading the same url don't write to it at the same time.
+	std::fs::create_dir_all(&remote_dir).context("Unable to create remote images directory")?;
+	let (_, tmp_path) = storage::create_unique(&remote_dir, &format!("{key:016x}"), ".tmp")
+		.context("Unable to create temporary file")?;
+	log::info!("Downloading {url:?}");
+	let res = self::curl(url, &["--output".as_ref(), tmp_path.as_os_str()])
+		.and_then(|_| std::fs::rename(&tmp_path, &path).context("Unable to move downloaded image"));
+	if res.is_err() {
+		let _ = std::fs::remove_file(&tmp_path);
+	}
+	res?;
+	if let Err(err) = storage::evict(&remote_dir, MAX_CACHE_SIZE) {
+		log::warn!("Unable to remove old downloads: {err:?}");
+	}
+
+	Ok(path)
+}
+
+/// Returns the contents of `url`
+pub fn get(url: &str) -> Result<Vec<u8>, anyhow::Error> {
+	self::curl(url, &[])
+}
+
+/// Runs `curl` over `url` with extra arguments `args`, returning it's output
+fn curl(url: &str, args: &[&OsStr]) -> Result<Vec<u8>, anyhow::Error> {
+	// Note: Some sites reject requests without a user agent.
+	//       We also only allow `http` and `https`, even on redirects, and limit
+	//       how long and how big downloads may be, so a bad url can't stall us.
+	let output = Command::new("curl")
+		.args(&["--fail", "--silent", "--show-error", "--location", "--user-agent"])
+		.arg(concat!("zss/", env!("CARGO_PKG_VERSION")))
+		.args(["--proto", "=http,https", "--proto-redir", "=http,https"])
+		.arg("--max-time")
+		.arg(MAX_TIME_SECS.to_string())
+		.arg("--max-filesize")
+		.arg(MAX_FILE_SIZE.to_string())
+		.args(args)
+		.arg("--")
+		.arg(url)
+		.output()
+		.context("Unable to run `curl`")?;
+	anyhow::ensure!(
+		output.status.success(),
+		"`curl` failed: {}",
+		String::from_utf8_lossy(&output.stderr).trim()
+	);
+
+	Ok(output.stdout)
+}
+
+/// Downloads all images in `urls`, sending their paths to `event_tx`
+pub fn fetch_all(urls: &[String], event_tx: &mpsc::Sender<DebouncedEvent>) {
+	for url in urls {
+		let path = match self::fetch(url) {
+			Ok(path) => path,
+			Err(err) => {
+				log::warn!("Unable to download {url:?}: {err:?}");
+				continue;
+			},
+		};
+
+		if event_tx.send(DebouncedEvent::Create(path)).is_err() {
+			return;
+		}
+	}
+}
@@ -0,0 +1,142 @@
+//! Render
+//!
+//! Renders the slideshow to a video, by drawing each frame to an offscreen framebuffer
+//! and streaming it's raw pixels to an `ffmpeg` process to encode.
+
+// Imports
+use crate::{preview, stats};
+use anyhow::Context;
+use rand::seq::SliceRandom;
+use std::{
+	io::Write,
+	mem,
+	path::PathBuf,
+	process::{Command, Stdio},
+	rc::Rc,
+	slice,
+};
+use zss::{
+	args::RenderArgs,
+	effects::Effects,
+	glium_backend::GliumBackend,
+	glium_facade::GliumFacade,
+	ignores::Ignores,
+	panel::Panel,
+	renderer::{DrawParams, Renderer},
+	window::Window,
+	Image,
+};
+
+/// Renders the slideshow to a video
+pub fn run(args: &RenderArgs) -> Result<(), anyhow::Error> {
+	// Create an offscreen window to render with
+	let window = Window::headless(args.size)
+		.map(Rc::new)
+		.context("Unable to create headless window")?;
+	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+
+	let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+
+	let images_dir = std::env::current_dir()
+		.context("Unable to get current directory")?
+		.join(&args.images_dir);
+	let mut ignores = Ignores::new(images_dir.clone());
+	let mut paths = vec![];
+	stats::collect_paths(&images_dir, &mut ignores, &mut paths).context("Unable to read images directory")?;
+	anyhow::ensure!(!paths.is_empty(), "No images found");
+	paths.shuffle(&mut rand::thread_rng());
+
+	let mut loader = Loader { paths, next_idx: 0 };
+	let cur_image = loader.next(&facade, args)?;
+	let next_image = loader.next(&facade, args)?;
+
+	// Note: `ffmpeg` flips the frames for us, as open-gl's origin is on the bottom-left
+	let [width, height] = args.size;
+	let mut encoder = Command::new("ffmpeg")
+		.args(&["-v", "error", "-y", "-f", "rawvideo", "-pix_fmt", "rgba"])
+		.args(&["-s", &format!("{width}x{height}")])
+		.args(&["-r", &args.fps.to_string(), "-i", "-"])
+		.args(&["-vf", "vflip", "-pix_fmt", "yuv420p"])
+		.arg(&args.out)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::null())
+		.spawn()
+		.context("Unable to run `ffmpeg`")?;
+	let mut stdin = encoder.stdin.take().expect("Encoder stdin was piped");
+
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Frame counts will be small and positive
+	let frames = (args.length.as_secs_f64() * f64::from(args.fps)).round() as usize;
+	#[allow(clippy::cast_possible_truncation)] // We don't need the full precision
+	let progress_step = (1.0 / (args.duration.as_secs_f64() * f64::from(args.fps))) as f32;
+
+	let mut panel = Panel::full(cur_image, next_image);
+	panel.progress_step = progress_step;
+	let params = DrawParams {
+		fade:                  args.fade,
+		fade_space:            args.fade_space,
+		scaling:               args.scaling,
+		kaleidoscope:          None,
+		double_exposure:       None,
+		exposure_mix:          0.0,
+		bg_color:              args.bg_color,
+		border_color:          args.bg_color,
+		dominant_bg:           false,
+		interpolation_samples: 1,
+		effects:               Effects::IDENTITY,
+	};
+
+	let texture = glium::Texture2d::empty(&facade, width, height).context("Unable to create texture")?;
+	for frame in 0..frames {
+		let mut surface =
+			glium::framebuffer::SimpleFrameBuffer::new(&facade, &texture).context("Unable to create framebuffer")?;
+		renderer
+			.draw(&mut surface, slice::from_ref(&panel), &params)
+			.context("Unable to draw frame")?;
+
+		let raw: glium::texture::RawImage2d<u8> = texture.read();
+		stdin
+			.write_all(&raw.data)
+			.with_context(|| format!("Unable to send frame {frame} to `ffmpeg`"))?;
+
+		// Once we finish an image, move onto the next
+		panel.progress += progress_step;
+		if panel.progress >= 1.0 {
+			panel.progress -= 1.0;
+			panel.cur_image = mem::replace(&mut panel.next_image, loader.next(&facade, args)?);
+		}
+	}
+
+	// Note: Closing stdin lets `ffmpeg` know there are no more frames
+	drop(stdin);
+	let status = encoder.wait().context("Unable to wait for `ffmpeg`")?;
+	anyhow::ensure!(status.success(), "`ffmpeg` exited with {}", status);
+
+	Ok(())
+}
+
+/// Image loader
+struct Loader {
+	/// All paths
+	paths: Vec<PathBuf>,
+
+	/// Index of the next path to load
+	next_idx: usize,
+}
+
+impl Loader {
+	/// Loads the next image, cycling through all paths and skipping any that fail to load
+	fn next(&mut self, facade: &GliumFacade, args: &RenderArgs) -> Result<Image, anyhow::Error> {
+		for _ in 0..self.paths.len() {
+			let path = &self.paths[self.next_idx];
+			self.next_idx = (self.next_idx + 1) % self.paths.len();
+
+			match preview::load_image(facade, path, args.size, args.scaling) {
+				Ok(image) => return Ok(image),
+				Err(err) => log::info!("Unable to load {path:?}: {err:?}"),
+			}
+		}
+
+		anyhow::bail!("No images could be loaded")
+	}
+}
@@ -0,0 +1,416 @@
+//! Renderer
+
+// Imports
+use crate::{
+	args::{Args, BlendMode, FadeSpace, Kaleidoscope, Scaling},
+	effects::Effects,
+	glium_facade::GliumFacade,
+	overlay::{self, Overlays, WidgetKind},
+	panel::Panel,
+	program_cache::ProgramCache,
+	Image,
+};
+use anyhow::Context;
+use cgmath::{Matrix4, Vector3};
+use glium::Surface;
+use std::iter;
+
+/// Draw parameters, shared by all panels
+#[derive(Clone, Copy, Debug)]
+pub struct DrawParams {
+	/// Fade
+	pub fade: f32,
+
+	/// Fade space
+	pub fade_space: FadeSpace,
+
+	/// Scaling
+	pub scaling: Scaling,
+
+	/// Axes to mirror images across, if any
+	pub kaleidoscope: Option<Kaleidoscope>,
+
+	/// Blend mode of each panel's second exposure, if any
+	pub double_exposure: Option<BlendMode>,
+
+	/// Mix factor of each panel's second exposure
+	pub exposure_mix: f32,
+
+	/// Background color
+	pub bg_color: [f32; 3],
+
+	/// Border color, shown between and around grid cells
+	pub border_color: [f32; 3],
+
+	/// If each image's dominant color should be used as it's background
+	pub dominant_bg: bool,
+
+	/// Number of samples to interpolate the scrolling with
+	pub interpolation_samples: u32,
+
+	/// Effects
+	pub effects: Effects,
+}
+
+impl DrawParams {
+	/// Creates the draw parameters from the arguments, with `effects`
+	pub const fn new(args: &Args, effects: Effects) -> Self {
+		Self {
+			fade: args.fade,
+			fade_space: args.fade_space,
+			scaling: args.scaling,
+			kaleidoscope: args.kaleidoscope,
+			double_exposure: args.double_exposure,
+			exposure_mix: 0.0,
+			bg_color: args.bg_color,
+			border_color: match args.grid_border_color {
+				Some(border_color) => border_color,
+				None => args.bg_color,
+			},
+			dominant_bg: args.dominant_bg,
+			interpolation_samples: args.interpolation_samples,
+			effects,
+		}
+	}
+}
+
+/// Renderer
+#[derive(Debug)]
+pub struct Renderer {
+	/// Vertex buffer
+	vertex_buffer: glium::VertexBuffer<Vertex>,
+
+	/// Indices
+	indices: glium::IndexBuffer<u32>,
+
+	/// Program
+	program: glium::Program,
+
+	/// Overlay program
+	overlay_program: glium::Program,
+}
+
+impl Renderer {
+	/// Creates a renderer
+	pub fn new(facade: &GliumFacade) -> Result<Self, anyhow::Error> {
+		let vertex_buffer =
+			glium::VertexBuffer::new(facade, &Vertex::QUAD).context("Unable to create vertex buffer")?;
+		let indices =
+			glium::IndexBuffer::<u32>::new(facade, glium::index::PrimitiveType::TrianglesList, &[0, 1, 3, 0, 3, 2])
+				.context("Unable to create index buffer")?;
+		let program = self::create_program(facade).context("Unable to build program")?;
+		let overlay_program = self::create_overlay_program(facade).context("Unable to build overlay program")?;
+
+		Ok(Self {
+			vertex_buffer,
+			indices,
+			program,
+			overlay_program,
+		})
+	}
+
+	/// Clears `target` and draws all `panels` to it
+	pub fn draw(&self, target: &mut impl Surface, panels: &[Panel], params: &DrawParams) -> Result<(), anyhow::Error> {
+		let [border_red, border_green, border_blue] = params.border_color;
+		target.clear_color(border_red, border_green, border_blue, 1.0);
+
+		for panel in panels {
+			self.draw_panel(target, panel, params).context("Unable to draw panel")?;
+		}
+
+		Ok(())
+	}
+
+	/// Draws all widgets of `overlays` over `target`
+	#[allow(clippy::cast_precision_loss)] // Outputs and widgets are much smaller than 2^24
+	pub fn draw_overlays(&self, target: &mut impl Surface, overlays: &Overlays) -> Result<(), anyhow::Error> {
+		let (target_width, target_height) = target.get_dimensions();
+		let (target_width, target_height) = (target_width as f32, target_height as f32);
+
+		for (kind, scale, texture) in overlays.textures() {
+			// Get the widget's rectangle in pixels, from the bottom-left, on it's corner
+			let margin = (overlay::MARGIN * scale).round();
+			let (width, height) = (texture.width() as f32, texture.height() as f32);
+			let (left, bottom) = match kind {
+				WidgetKind::Clock => (target_width - margin - width, margin),
+				WidgetKind::Caption => (margin, margin),
+				WidgetKind::Hud => (margin, target_height - margin - height),
+			};
+
+			// Then convert it to normalized device coordinates
+			let to_ndc = |pos: f32, len: f32| 2.0 * pos / len - 1.0;
+			let rect = [
+				to_ndc(left, target_width),
+				to_ndc(bottom, target_height),
+				to_ndc(left + width, target_width),
+				to_ndc(bottom + height, target_height),
+			];
+
+			// Note: Widgets are drawn pixel for pixel, so we don't need any filtering
+			let uniforms = glium::uniform! {
+				rect: rect,
+				tex_sampler: texture
+					.sampled()
+					.magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+					.minify_filter(glium::uniforms::MinifySamplerFilter::Nearest),
+			};
+			let draw_parameters = glium::DrawParameters {
+				blend: glium::Blend::alpha_blending(),
+				..glium::DrawParameters::default()
+			};
+			target
+				.draw(
+					&self.vertex_buffer,
+					&self.indices,
+					&self.overlay_program,
+					&uniforms,
+					&draw_parameters,
+				)
+				.with_context(|| format!("Unable to draw {kind:?} overlay"))?;
+		}
+
+		Ok(())
+	}
+
+	/// Draws a single panel
+	#[allow(clippy::too_many_lines)] // TODO: Refactor
+	fn draw_panel(&self, target: &mut impl Surface, panel: &Panel, params: &DrawParams) -> Result<(), anyhow::Error> {
+		let Panel {
+			cur_image,
+			next_image,
+			progress,
+			progress_step,
+			scale,
+			offset,
+			mirrors,
+			..
+		} = panel;
+		let (progress, progress_step) = (*progress, *progress_step);
+
+		// Get the second exposure and how to blend it, if any
+		// Note: Without a second exposure, we just pass the panel itself, as it's never sampled
+		let (exposure, blend_mode) = match (params.double_exposure, &panel.exposure) {
+			(Some(blend_mode), Some(exposure)) => (&**exposure, match blend_mode {
+				BlendMode::Multiply => 1,
+				BlendMode::Screen => 2,
+				BlendMode::Overlay => 3,
+			}),
+			_ => (panel, 0),
+		};
+
+		// Calculate the base alpha and progress to apply to the images
+		let fade = params.fade;
+		let fade_progress = |progress: f32| match progress {
+			f if f >= fade => ((progress - fade) / (1.0 - fade), progress - fade),
+			_ => (0.0, 0.0),
+		};
+		let (base_alpha, next_progress) = fade_progress(progress);
+		let (exposure_alpha, exposure_next_progress) = fade_progress(exposure.progress);
+
+		// Note: When tiling, the image must be repeated
+		let wrap_function = match params.scaling {
+			Scaling::Tile => glium::uniforms::SamplerWrapFunction::Repeat,
+			_ => glium::uniforms::SamplerWrapFunction::Mirror,
+		};
+		// Get how much each image scrolls until the next frame, for interpolating
+		// Note: Without interpolation we don't need to calculate it
+		let offset_delta = |image: &Image, progress: f32, progress_step: f32| match params.interpolation_samples {
+			1 => [0.0, 0.0],
+			_ => {
+				let [cur_x, cur_y] = image.uvs.offset(progress);
+				let [next_x, next_y] = image.uvs.offset(progress + progress_step);
+				[next_x - cur_x, next_y - cur_y]
+			},
+		};
+
+		// Get the letterbox color of each image
+		let letterbox_color = |image: &Image| match params.dominant_bg {
+			true => image.dominant_color,
+			false => params.bg_color,
+		};
+
+		// Then draw both images at once, so the shader can blend them, on the panel and each mirror
+		let effects = &params.effects;
+		let placements = iter::once((*scale, *offset, [false, false]))
+			.chain(mirrors.iter().map(|mirror| (mirror.scale, mirror.offset, mirror.flip)));
+		for (scale, offset, [flip_x, flip_y]) in placements {
+			let mat = Matrix4::from_translation(Vector3::new(offset.x, offset.y, 0.0)) *
+				Matrix4::from_nonuniform_scale(scale.x, scale.y, 1.0);
+			let uniforms = glium::uniform! {
+				mat: *<_ as AsRef<[[f32; 4]; 4]>>::as_ref(&mat),
+				tex_flip: [f32::from(u8::from(flip_x)), f32::from(u8::from(flip_y))],
+				cur_tex_sampler: cur_image.texture().sampled(wrap_function),
+				cur_tex_start: cur_image.uvs.start(),
+				cur_tex_offset: cur_image.uvs.offset(progress),
+				cur_tex_offset_delta: offset_delta(cur_image, progress, progress_step),
+				cur_tex_scale: cur_image.uvs.scale(),
+				cur_is_placeholder: cur_image.is_placeholder,
+				cur_letterbox_color: letterbox_color(cur_image),
+				next_tex_sampler: next_image.texture().sampled(wrap_function),
+				next_tex_start: next_image.uvs.start(),
+				next_tex_offset: next_image.uvs.offset(next_progress),
+				next_tex_offset_delta: offset_delta(next_image, next_progress, progress_step),
+				next_tex_scale: next_image.uvs.scale(),
+				next_is_placeholder: next_image.is_placeholder,
+				next_letterbox_color: letterbox_color(next_image),
+				exp_cur_tex_sampler: exposure.cur_image.texture().sampled(wrap_function),
+				exp_cur_tex_start: exposure.cur_image.uvs.start(),
+				exp_cur_tex_offset: exposure.cur_image.uvs.offset(exposure.progress),
+				exp_cur_tex_offset_delta: offset_delta(&exposure.cur_image, exposure.progress, exposure.progress_step),
+				exp_cur_tex_scale: exposure.cur_image.uvs.scale(),
+				exp_cur_is_placeholder: exposure.cur_image.is_placeholder,
+				exp_cur_letterbox_color: letterbox_color(&exposure.cur_image),
+				exp_next_tex_sampler: exposure.next_image.texture().sampled(wrap_function),
+				exp_next_tex_start: exposure.next_image.uvs.start(),
+				exp_next_tex_offset: exposure.next_image.uvs.offset(exposure_next_progress),
+				exp_next_tex_offset_delta: offset_delta(
+					&exposure.next_image,
+					exposure_next_progress,
+					exposure.progress_step
+				),
+				exp_next_tex_scale: exposure.next_image.uvs.scale(),
+				exp_next_is_placeholder: exposure.next_image.is_placeholder,
+				exp_next_letterbox_color: letterbox_color(&exposure.next_image),
+				exp_fade: exposure_alpha,
+				blend_mode: blend_mode,
+				exposure_mix: params.exposure_mix,
+				scaling: match params.scaling {
+					Scaling::Fill => 0,
+					Scaling::Fit => 1,
+					Scaling::ContainBlur => 2,
+					Scaling::Stretch => 3,
+					Scaling::Center => 4,
+					Scaling::Tile => 5,
+				},
+				kaleidoscope: match params.kaleidoscope {
+					None => [0.0, 0.0],
+					Some(Kaleidoscope::Horizontal) => [1.0, 0.0],
+					Some(Kaleidoscope::Vertical) => [0.0, 1.0],
+					Some(Kaleidoscope::Both) => [1.0, 1.0],
+				},
+				bg_color: params.bg_color,
+				interpolation_samples: params.interpolation_samples,
+				fade: base_alpha,
+				fade_linear: params.fade_space == FadeSpace::Linear,
+				brightness: effects.brightness,
+				contrast: effects.contrast,
+				gamma: effects.gamma,
+				saturation: effects.saturation,
+				tint: effects.tint,
+			};
+			target
+				.draw(
+					&self.vertex_buffer,
+					&self.indices,
+					&self.program,
+					&uniforms,
+					&glium::DrawParameters::default(),
+				)
+				.context("Unable to draw")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Creates the program
+///
+/// Uses a cached program binary if available, else compiles it and
+/// caches it, so we don't need to compile it on each start-up.
+fn create_program(facade: &GliumFacade) -> Result<glium::Program, glium::ProgramCreationError> {
+	// Note: The shader already outputs srgb, so glium mustn't enable `GL_FRAMEBUFFER_SRGB` for
+	//       either the window or offscreen framebuffers, else the fade would be converted twice.
+	const OUTPUTS_SRGB: bool = true;
+	const VERTEX_SHADER: &str = include_str!("vertex.glsl");
+	const FRAGMENT_SHADER: &str = include_str!("frag.glsl");
+
+	// Try to use the cached binary first
+	// Note: The binary may be rejected by the driver (e.g. after an update), so we recompile on any error
+	let cache = ProgramCache::new(&[VERTEX_SHADER, FRAGMENT_SHADER]);
+	if let Some(cache) = &cache {
+		let program = cache.load().and_then(|binary| {
+			glium::Program::new(facade, glium::program::ProgramCreationInput::Binary {
+				data:            binary,
+				outputs_srgb:    OUTPUTS_SRGB,
+				uses_point_size: false,
+			})
+			.context("Unable to create program from binary")
+		});
+
+		match program {
+			Ok(program) => return Ok(program),
+			Err(err) => log::debug!("Unable to use cached program binary: {err:?}"),
+		}
+	}
+
+	let program = glium::Program::new(facade, glium::program::ProgramCreationInput::SourceCode {
+		vertex_shader:                  VERTEX_SHADER,
+		fragment_shader:                FRAGMENT_SHADER,
+		geometry_shader:                None,
+		tessellation_control_shader:    None,
+		tessellation_evaluation_shader: None,
+		transform_feedback_varyings:    None,
+		outputs_srgb:                   OUTPUTS_SRGB,
+		uses_point_size:                false,
+	})?;
+
+	// Then cache it, if we can
+	if let Some(cache) = &cache {
+		match program.get_binary() {
+			Ok(binary) => {
+				if let Err(err) = cache.store(&binary) {
+					log::warn!("Unable to cache program binary: {err:?}");
+				}
+			},
+			Err(err) => log::debug!("Unable to get program binary: {err:?}"),
+		}
+	}
+
+	Ok(program)
+}
+
+/// Creates the overlay program
+fn create_overlay_program(facade: &GliumFacade) -> Result<glium::Program, glium::ProgramCreationError> {
+	// Note: The program is small enough that we don't bother caching it
+	glium::Program::new(facade, glium::program::ProgramCreationInput::SourceCode {
+		vertex_shader:                  include_str!("overlay_vertex.glsl"),
+		fragment_shader:                include_str!("overlay_frag.glsl"),
+		geometry_shader:                None,
+		tessellation_control_shader:    None,
+		tessellation_evaluation_shader: None,
+		transform_feedback_varyings:    None,
+		outputs_srgb:                   true,
+		uses_point_size:                false,
+	})
+}
+
+/// Vertex
+#[derive(Clone, Copy, Debug)]
+struct Vertex {
+	vertex_pos: [f32; 2],
+	vertex_tex: [f32; 2],
+}
+
+impl Vertex {
+	/// Quad covering the whole screen
+	const QUAD: [Self; 4] = [
+		Self {
+			vertex_pos: [-1.0, -1.0],
+			vertex_tex: [0.0, 0.0],
+		},
+		Self {
+			vertex_pos: [1.0, -1.0],
+			vertex_tex: [1.0, 0.0],
+		},
+		Self {
+			vertex_pos: [-1.0, 1.0],
+			vertex_tex: [0.0, 1.0],
+		},
+		Self {
+			vertex_pos: [1.0, 1.0],
+			vertex_tex: [1.0, 1.0],
+		},
+	];
+}
+
+glium::implement_vertex!(Vertex, vertex_pos, vertex_tex);
@@ -0,0 +1,176 @@
+//! Replays
+//!
+//! Sessions may be recorded to a replay file, which stores the arguments they were run with,
+//! along with everything that was chosen while running, so they may be replayed exactly.
+//!
+//! The file is line-based, starting with a `zss-replay {version}` header, followed by:
+//! - `arg {arg}`: An argument, including the binary name.
+//! - `image {poll} {swap_dir} {path}`: An image was received on the `poll`th poll for images.
+//! - `progress {progress}`: A grid cell started at `progress`.
+//! - `tap {frame}`: The tempo was tapped on frame `frame`.
+//!
+//! Note: Sessions following a tempo or a night light still depend on the clock, so
+//!       they're only replayed approximately.
+
+// Imports
+use anyhow::Context;
+use std::{
+	collections::VecDeque,
+	ffi::OsString,
+	fs::File,
+	io::{BufRead, BufReader, Write},
+	path::{Path, PathBuf},
+	sync::mpsc,
+	thread,
+};
+
+/// Replay file version
+const VERSION: u32 = 1;
+
+/// Recorded image
+#[derive(Clone, Debug)]
+pub struct ReplayImage {
+	/// Poll the image was received on
+	pub poll: u64,
+
+	/// If the scroll direction was swapped
+	pub swap_dir: bool,
+
+	/// Path
+	pub path: PathBuf,
+}
+
+/// Replay
+#[derive(Clone, Debug)]
+pub struct Replay {
+	/// Arguments, including the binary name
+	pub args: Vec<OsString>,
+
+	/// All images, in order
+	pub images: VecDeque<ReplayImage>,
+
+	/// Starting progress of each grid cell
+	pub progresses: VecDeque<f32>,
+
+	/// Frames the tempo was tapped on
+	pub taps: VecDeque<u64>,
+}
+
+impl Replay {
+	/// Loads a replay from `path`
+	pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+		let file = File::open(path).context("Unable to open file")?;
+		let mut lines = BufReader::new(file).lines();
+
+		let header = lines
+			.next()
+			.context("File was empty")?
+			.context("Unable to read header")?;
+		anyhow::ensure!(
+			header == format!("zss-replay {VERSION}"),
+			"Expected header `zss-replay {}`, found {:?}",
+			VERSION,
+			header
+		);
+
+		let mut replay = Self {
+			args:       vec![],
+			images:     VecDeque::new(),
+			progresses: VecDeque::new(),
+			taps:       VecDeque::new(),
+		};
+		for (idx, line) in lines.enumerate() {
+			let line = line.context("Unable to read line")?;
+			replay
+				.parse_line(&line)
+				.with_context(|| format!("Unable to parse line {}: {line:?}", idx + 2))?;
+		}
+
+		Ok(replay)
+	}
+
+	/// Parses a line into this replay
+	fn parse_line(&mut self, line: &str) -> Result<(), anyhow::Error> {
+		let (kind, value) = line.split_once(' ').context("Line had no value")?;
+		match kind {
+			"arg" => self.args.push(value.into()),
+			"image" => {
+				let mut parts = value.splitn(3, ' ');
+				let mut next_part = || parts.next().context("Image was missing fields");
+				let poll = next_part()?.parse().context("Unable to parse poll")?;
+				let swap_dir = next_part()?.parse().context("Unable to parse swap direction")?;
+				let path = PathBuf::from(next_part()?);
+				self.images.push_back(ReplayImage { poll, swap_dir, path });
+			},
+			"progress" => self
+				.progresses
+				.push_back(value.parse().context("Unable to parse progress")?),
+			"tap" => self.taps.push_back(value.parse().context("Unable to parse frame")?),
+			_ => anyhow::bail!("Unknown line kind {:?}", kind),
+		}
+
+		Ok(())
+	}
+}
+
+/// Recorder.
+///
+/// Lines are written to the file in a background thread.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+	/// Line sender
+	line_tx: mpsc::Sender<String>,
+}
+
+impl Recorder {
+	/// Starts recording to `path` a session with arguments `args`
+	pub fn create(path: &Path, args: impl IntoIterator<Item = OsString>) -> Result<Self, anyhow::Error> {
+		let mut file = File::create(path).context("Unable to create file")?;
+		writeln!(file, "zss-replay {VERSION}").context("Unable to write header")?;
+		for arg in args {
+			let arg = arg
+				.into_string()
+				.map_err(|arg| anyhow::anyhow!("Argument {:?} wasn't utf-8", arg))?;
+			anyhow::ensure!(!arg.contains('\n'), "Argument {:?} contained a newline", arg);
+			writeln!(file, "arg {arg}").context("Unable to write argument")?;
+		}
+
+		// Note: Each line is written as soon as it's received, so nothing is lost if we're killed
+		let (line_tx, line_rx) = mpsc::channel::<String>();
+		let path = path.to_path_buf();
+		thread::spawn(move || {
+			for line in line_rx {
+				if let Err(err) = writeln!(file, "{line}") {
+					log::warn!("Unable to write to replay file {path:?}, stopping recording: {err}");
+					return;
+				}
+			}
+		});
+
+		Ok(Self { line_tx })
+	}
+
+	/// Records an image
+	pub fn image(&self, poll: u64, swap_dir: bool, path: &Path) {
+		match path.to_str() {
+			Some(path) if !path.contains('\n') => self.record(format!("image {poll} {swap_dir} {path}")),
+			_ => log::warn!("Unable to record {path:?}, only utf-8 paths without newlines are supported"),
+		}
+	}
+
+	/// Records a grid cell's starting progress
+	pub fn progress(&self, progress: f32) {
+		self.record(format!("progress {progress}"));
+	}
+
+	/// Records a tap
+	pub fn tap(&self, frame: u64) {
+		self.record(format!("tap {frame}"));
+	}
+
+	/// Records a line
+	fn record(&self, line: String) {
+		// Note: If the writer stopped, it already warned about it
+		let _ = self.line_tx.send(line);
+	}
+}
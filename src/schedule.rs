@@ -0,0 +1,122 @@
+//! Schedule
+//!
+//! Chooses the directory images are shown from depending on the time of day, such
+//! as showing `~/walls/day` during the day and `~/walls/night` during the night.
+
+// Imports
+use crate::images::Generations;
+use anyhow::Context;
+use chrono::NaiveTime;
+use notify::DebouncedEvent;
+use std::{
+	path::{Path, PathBuf},
+	sync::{mpsc, Mutex},
+	thread,
+	time::Duration,
+};
+
+/// Schedule rule config
+#[derive(Clone, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleRule {
+	/// Time of day, as `{start}-{end}`, such as `06:00-18:00`
+	pub time: String,
+
+	/// Directory
+	pub dir: PathBuf,
+}
+
+/// Schedule
+#[derive(Clone, Default, Debug)]
+pub struct Schedule {
+	/// All rules, as their start, end and directory
+	rules: Vec<(NaiveTime, NaiveTime, PathBuf)>,
+}
+
+impl Schedule {
+	/// Interval between checking if the active directory changed
+	const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+	/// Creates a schedule from it's rules.
+	///
+	/// Any directories starting with `~` are relative to the home directory, and any
+	/// other relative directories are relative to the current directory.
+	pub fn new(rules: Vec<ScheduleRule>) -> Result<Self, anyhow::Error> {
+		// Note: The loader always uses absolute paths, so we make sure ours are too
+		let current_dir = std::env::current_dir().context("Unable to get current directory")?;
+		let rules = rules
+			.into_iter()
+			.map(|rule| {
+				let (start, end) = rule
+					.time
+					.split_once('-')
+					.context("Time must be of the format `{start}-{end}`")?;
+				let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").context("Unable to parse start")?;
+				let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").context("Unable to parse end")?;
+
+				let dir = match rule.dir.strip_prefix("~") {
+					Ok(dir) => std::env::var_os("HOME")
+						.map(PathBuf::from)
+						.context("Unable to get home directory")?
+						.join(dir),
+					Err(_) => current_dir.join(rule.dir),
+				};
+
+				Ok((start, end, dir))
+			})
+			.collect::<Result<_, anyhow::Error>>()?;
+
+		Ok(Self { rules })
+	}
+
+	/// Returns if there are no rules
+	pub fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+
+	/// Returns the directories of all rules
+	pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+		self.rules.iter().map(|(_, _, dir)| dir.as_path())
+	}
+
+	/// Returns the directory of the first rule active at `time`, if any
+	pub fn active_dir(&self, time: NaiveTime) -> Option<&Path> {
+		// Note: Rules may span midnight, such as `18:00-06:00`
+		self.rules
+			.iter()
+			.find(|&&(start, end, _)| match start <= end {
+				true => start <= time && time < end,
+				false => time >= start || time < end,
+			})
+			.map(|(_, _, dir)| dir.as_path())
+	}
+
+	/// Keeps `active_dir` up to date, using `fallback` if no rules are active.
+	///
+	/// Whenever it changes, the previous directory is invalidated in `generations`, so
+	/// it's images are dropped, and a rescan is sent to `event_tx`, so the loader
+	/// picks the new directory up.
+	pub fn run(
+		&self, fallback: &Path, active_dir: &Mutex<PathBuf>, generations: &Generations,
+		event_tx: &mpsc::Sender<DebouncedEvent>,
+	) {
+		loop {
+			let dir = self.active_dir(chrono::Local::now().time()).unwrap_or(fallback);
+
+			let mut cur_dir = active_dir.lock().expect("Active directory lock was poisoned");
+			if *cur_dir != dir {
+				log::info!("Switching to images in {dir:?}");
+				generations.invalidate(&cur_dir);
+				*cur_dir = dir.to_path_buf();
+				drop(cur_dir);
+
+				if event_tx.send(DebouncedEvent::Rescan).is_err() {
+					log::debug!("Event receiver was dropped, stopping schedule");
+					return;
+				}
+			}
+
+			thread::sleep(Self::CHECK_INTERVAL);
+		}
+	}
+}
@@ -0,0 +1,458 @@
+//! Image selection
+//!
+//! Every pass, the image loader asks it's [`SelectionStrategy`] which of the known
+//! paths to load, and in which order, and then loads them in that order.
+
+// Imports
+use crate::{clock, history::History};
+use anyhow::Context;
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom};
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	io::Write,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	time::{Duration, SystemTime},
+};
+
+/// Selection strategy
+pub trait SelectionStrategy: Send {
+	/// Selects the paths to load in the next pass, in order, out of all known `paths`.
+	///
+	/// `paths` is never empty. Paths may be returned more than once.
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf>;
+
+	/// Called after `path` is loaded
+	fn loaded(&mut self, _path: &Path) {}
+}
+
+/// Selection strategy config
+#[derive(Clone, PartialEq, Debug, serde::Deserialize)]
+#[serde(tag = "strategy", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Selection {
+	/// Shuffles all paths every pass
+	Shuffle,
+
+	/// Orders all paths by their path
+	Ordered,
+
+	/// Randomly selects paths, biased by the weight of the directory they're in
+	Weighted {
+		/// Weight of each image in each directory, relative to the images directory.
+		///
+		/// Images outside of all directories have a weight of `1`.
+		weights: HashMap<PathBuf, f64>,
+	},
+
+	/// Shuffles all paths every pass, but avoids the most recently loaded ones
+	Cooldown {
+		/// Number of recently loaded paths to avoid
+		cooldown: usize,
+	},
+
+	/// Plays all images of each directory, as an album, before moving on to the next one
+	Album {
+		/// Order of the albums and of the images within them
+		#[serde(default)]
+		order: AlbumOrder,
+	},
+
+	/// Has a command select the paths.
+	///
+	/// The command receives all paths on `stdin`, one per line, and outputs
+	/// the paths to load, in order, one per line.
+	Scripted {
+		/// Command, along with it's arguments
+		command: Vec<String>,
+	},
+}
+
+impl Default for Selection {
+	fn default() -> Self {
+		Self::Shuffle
+	}
+}
+
+/// Album order
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AlbumOrder {
+	/// Shuffled
+	Shuffle,
+
+	/// Ordered by path
+	Ordered,
+}
+
+impl Default for AlbumOrder {
+	fn default() -> Self {
+		Self::Shuffle
+	}
+}
+
+impl Selection {
+	/// Adds directory weights to the strategy, overriding any existing ones.
+	///
+	/// The shuffle strategy becomes the weighted strategy, while the other strategies don't support weights.
+	pub fn with_weights(self, weights: HashMap<PathBuf, f64>) -> Result<Self, anyhow::Error> {
+		if weights.is_empty() {
+			return Ok(self);
+		}
+
+		match self {
+			Self::Shuffle => Ok(Self::Weighted { weights }),
+			Self::Weighted {
+				weights: mut cur_weights,
+			} => {
+				cur_weights.extend(weights);
+				Ok(Self::Weighted { weights: cur_weights })
+			},
+			selection => anyhow::bail!("Weights aren't supported by the {:?} strategy", selection),
+		}
+	}
+
+	/// Creates the strategy for images within `images_dir`
+	pub fn into_strategy(self, images_dir: &Path) -> Result<Box<dyn SelectionStrategy>, anyhow::Error> {
+		let strategy: Box<dyn SelectionStrategy> = match self {
+			Self::Shuffle => Box::new(Shuffle),
+			Self::Ordered => Box::new(Ordered),
+			Self::Weighted { weights } => {
+				for (dir, &weight) in &weights {
+					anyhow::ensure!(
+						weight.is_finite() && weight >= 0.0,
+						"Weight of {:?} must be non-negative, found {}",
+						dir,
+						weight
+					);
+				}
+
+				// Note: The loader always uses absolute paths, so we make sure our path is too
+				let images_dir = std::env::current_dir()
+					.context("Unable to get current directory")?
+					.join(images_dir);
+
+				Box::new(Weighted { images_dir, weights })
+			},
+			Self::Cooldown { cooldown } => Box::new(Cooldown {
+				cooldown,
+				recent: VecDeque::new(),
+			}),
+			Self::Album { order } => Box::new(Album {
+				order,
+				albums: VecDeque::new(),
+			}),
+			Self::Scripted { command } => {
+				anyhow::ensure!(!command.is_empty(), "Command must not be empty");
+				Box::new(Scripted { command })
+			},
+		};
+
+		Ok(strategy)
+	}
+}
+
+/// Shuffle strategy
+#[derive(Debug)]
+pub struct Shuffle;
+
+impl SelectionStrategy for Shuffle {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		let mut paths = paths.to_vec();
+		paths.shuffle(&mut rand::thread_rng());
+		paths
+	}
+}
+
+/// Ordered strategy
+#[derive(Debug)]
+pub struct Ordered;
+
+impl SelectionStrategy for Ordered {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		let mut paths = paths.to_vec();
+		paths.sort();
+		paths
+	}
+}
+
+/// Weighted strategy
+#[derive(Debug)]
+pub struct Weighted {
+	/// Images directory
+	images_dir: PathBuf,
+
+	/// Weights of each directory
+	weights: HashMap<PathBuf, f64>,
+}
+
+impl Weighted {
+	/// Returns the weight of `path`, from it's closest weighted directory
+	fn weight(&self, path: &Path) -> f64 {
+		let relative_path = path.strip_prefix(&self.images_dir).unwrap_or(path);
+		relative_path
+			.ancestors()
+			.skip(1)
+			.find_map(|dir| self.weights.get(dir))
+			.copied()
+			.unwrap_or(1.0)
+	}
+}
+
+impl SelectionStrategy for Weighted {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		let weights = paths.iter().map(|path| self.weight(path));
+		let dist = match WeightedIndex::new(weights) {
+			Ok(dist) => dist,
+			Err(err) => {
+				log::warn!("Unable to weigh paths, shuffling them instead: {err}");
+				return Shuffle.select(paths);
+			},
+		};
+
+		// Note: We select as many as there are paths, so passes are as long as with the other strategies
+		let mut rng = rand::thread_rng();
+		(0..paths.len()).map(|_| paths[dist.sample(&mut rng)].clone()).collect()
+	}
+}
+
+/// Cooldown strategy
+#[derive(Debug)]
+pub struct Cooldown {
+	/// Number of recently loaded paths to avoid
+	cooldown: usize,
+
+	/// Recently loaded paths, from oldest to newest
+	recent: VecDeque<PathBuf>,
+}
+
+impl SelectionStrategy for Cooldown {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		// Note: Recent paths are moved to the end, from oldest to newest, instead of being
+		//       removed, so we still have something to show if there are few paths.
+		let recent = self.recent.iter().collect::<HashSet<_>>();
+		let mut selected = paths
+			.iter()
+			.filter(|path| !recent.contains(path))
+			.cloned()
+			.collect::<Vec<_>>();
+		selected.shuffle(&mut rand::thread_rng());
+
+		let paths = paths.iter().collect::<HashSet<_>>();
+		selected.extend(self.recent.iter().filter(|path| paths.contains(path)).cloned());
+		selected
+	}
+
+	fn loaded(&mut self, path: &Path) {
+		self.recent.retain(|recent_path| recent_path != path);
+		self.recent.push_back(path.to_path_buf());
+		while self.recent.len() > self.cooldown {
+			self.recent.pop_front();
+		}
+	}
+}
+
+/// Album strategy
+#[derive(Debug)]
+pub struct Album {
+	/// Order of the albums and of the images within them
+	order: AlbumOrder,
+
+	/// Remaining albums
+	albums: VecDeque<PathBuf>,
+}
+
+impl Album {
+	/// Returns the album of `path`
+	fn album(path: &Path) -> &Path {
+		path.parent().unwrap_or(path)
+	}
+}
+
+impl SelectionStrategy for Album {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		let mut album_paths = HashMap::<_, Vec<_>>::new();
+		for path in paths {
+			album_paths.entry(Self::album(path)).or_default().push(path.clone());
+		}
+
+		// Note: Albums might have been removed since we queued them, so skip those
+		let album = loop {
+			match self.albums.pop_front() {
+				Some(album) if album_paths.contains_key(album.as_path()) => break album,
+				Some(_) => (),
+				None => {
+					let mut albums = album_paths.keys().map(|album| album.to_path_buf()).collect::<Vec<_>>();
+					match self.order {
+						AlbumOrder::Shuffle => albums.shuffle(&mut rand::thread_rng()),
+						AlbumOrder::Ordered => albums.sort(),
+					}
+					self.albums.extend(albums);
+				},
+			}
+		};
+
+		log::info!("Playing album {album:?}");
+		let mut paths = album_paths.remove(album.as_path()).expect("Album had no paths");
+		match self.order {
+			AlbumOrder::Shuffle => paths.shuffle(&mut rand::thread_rng()),
+			AlbumOrder::Ordered => paths.sort(),
+		}
+		paths
+	}
+}
+
+/// Scripted strategy
+#[derive(Debug)]
+pub struct Scripted {
+	/// Command, along with it's arguments
+	command: Vec<String>,
+}
+
+impl Scripted {
+	/// Runs the command over `paths`
+	fn run(&self, paths: &[PathBuf]) -> Result<Vec<PathBuf>, anyhow::Error> {
+		let mut child = Command::new(&self.command[0])
+			.args(&self.command[1..])
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.spawn()
+			.context("Unable to spawn command")?;
+
+		// Note: We write from another thread, so the command may write to `stdout` while we write to it's `stdin`
+		let mut stdin = child.stdin.take().context("Unable to get command stdin")?;
+		let mut input = vec![];
+		for path in paths {
+			writeln!(input, "{}", path.display()).expect("Writing to a `Vec` can't fail");
+		}
+		let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+		let output = child.wait_with_output().context("Unable to wait for command")?;
+		writer
+			.join()
+			.expect("Writer thread panicked")
+			.context("Unable to write paths to command")?;
+		anyhow::ensure!(output.status.success(), "Command failed with {}", output.status);
+
+		// Note: We only accept known paths, so the command can't make us load anything else
+		let known_paths = paths.iter().collect::<HashSet<_>>();
+		let output = String::from_utf8(output.stdout).context("Command output wasn't utf-8")?;
+		let selected = output
+			.lines()
+			.map(PathBuf::from)
+			.filter(|path| match known_paths.contains(path) {
+				true => true,
+				false => {
+					log::warn!("Ignoring unknown path {path:?} selected by command");
+					false
+				},
+			})
+			.collect::<Vec<_>>();
+		anyhow::ensure!(!selected.is_empty(), "Command selected no paths");
+
+		Ok(selected)
+	}
+}
+
+impl SelectionStrategy for Scripted {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		match self.run(paths) {
+			Ok(paths) => paths,
+			Err(err) => {
+				log::warn!(
+					"Unable to select paths with {:?}, shuffling them instead: {err:?}",
+					self.command
+				);
+				Shuffle.select(paths)
+			},
+		}
+	}
+}
+
+/// Avoids repeating the most recently loaded paths of another strategy, even across passes
+pub struct NoRepeat {
+	/// Inner strategy
+	inner: Box<dyn SelectionStrategy>,
+
+	/// Recently loaded paths
+	history: History,
+}
+
+impl NoRepeat {
+	/// Creates the strategy, avoiding the paths in `history`
+	pub fn new(inner: Box<dyn SelectionStrategy>, history: History) -> Self {
+		Self { inner, history }
+	}
+}
+
+impl SelectionStrategy for NoRepeat {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		// Note: Like with the cooldown strategy, recent paths are moved to the end, from oldest
+		//       to newest, so we still have something to show if there are few paths.
+		let recent = self.history.recent().collect::<HashSet<_>>();
+		let (mut selected, selected_recent): (Vec<_>, Vec<_>) = self
+			.inner
+			.select(paths)
+			.into_iter()
+			.partition(|path| !recent.contains(path.as_path()));
+		let selected_recent = selected_recent.into_iter().collect::<HashSet<_>>();
+		selected.extend(
+			self.history
+				.recent()
+				.filter(|path| selected_recent.contains(*path))
+				.map(Path::to_path_buf),
+		);
+		selected
+	}
+
+	fn loaded(&mut self, path: &Path) {
+		self.inner.loaded(path);
+		self.history.push(path);
+	}
+}
+
+/// Selects the path of each wall clock slot, so all instances with the same seed and
+/// paths select the same path at the same time.
+#[derive(Debug)]
+pub struct ClockSync {
+	/// Seed
+	seed: u64,
+
+	/// Duration of each slot
+	slot_duration: Duration,
+
+	/// Next slot to select, if any yet
+	next_slot: Option<u64>,
+}
+
+impl ClockSync {
+	/// Number of slots selected in each pass
+	const SLOTS_PER_PASS: u64 = 16;
+
+	/// Creates the strategy
+	pub fn new(seed: u64, slot_duration: Duration) -> Self {
+		Self {
+			seed,
+			slot_duration,
+			next_slot: None,
+		}
+	}
+}
+
+impl SelectionStrategy for ClockSync {
+	fn select(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		// Note: The paths must be in the same order on all instances
+		let mut paths = paths.to_vec();
+		paths.sort();
+
+		// Note: The first image is for the current slot, all others are for the following ones
+		let first_slot = self
+			.next_slot
+			.unwrap_or_else(|| clock::slot(SystemTime::now(), self.slot_duration).0);
+		let slots = first_slot..first_slot + Self::SLOTS_PER_PASS;
+		self.next_slot = Some(slots.end);
+
+		slots
+			.map(|slot| paths[clock::slot_index(self.seed, slot, paths.len())].clone())
+			.collect()
+	}
+}
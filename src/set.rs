@@ -0,0 +1,58 @@
+//! Set
+//!
+//! Shows a single image on a window, such as for a static wallpaper, and exits.
+
+// Imports
+use crate::preview;
+use anyhow::Context;
+use std::{rc::Rc, slice};
+use zss::{
+	args::{FadeSpace, SetArgs},
+	effects::Effects,
+	glium_backend::GliumBackend,
+	glium_facade::GliumFacade,
+	panel::Panel,
+	renderer::{DrawParams, Renderer},
+	window::Window,
+};
+
+/// Shows the image on the window
+pub fn run(args: &SetArgs) -> Result<(), anyhow::Error> {
+	let window = Window::from_window_id(args.window_id)
+		.map(Rc::new)
+		.context("Unable to create window")?;
+	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+
+	let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+
+	// Note: We never fade, but the panel still needs a next image, so we just use the same image
+	let size = window.size();
+	let cur_image = preview::load_image(&facade, &args.image, size, args.scaling)
+		.with_context(|| format!("Unable to load {}", args.image.display()))?;
+	let next_image = preview::load_image(&facade, &args.image, size, args.scaling)
+		.with_context(|| format!("Unable to load {}", args.image.display()))?;
+
+	// Note: We show the middle of the image's scroll, as it's the most representative part of it
+	let mut panel = Panel::full(cur_image, next_image);
+	panel.progress = 0.5;
+	let params = DrawParams {
+		fade:                  1.0,
+		fade_space:            FadeSpace::Linear,
+		scaling:               args.scaling,
+		kaleidoscope:          None,
+		double_exposure:       None,
+		exposure_mix:          0.0,
+		bg_color:              args.bg_color,
+		border_color:          args.bg_color,
+		dominant_bg:           false,
+		interpolation_samples: 1,
+		effects:               Effects::IDENTITY,
+	};
+
+	let mut target = facade.draw();
+	renderer
+		.draw(&mut target, slice::from_ref(&panel), &params)
+		.context("Unable to draw image")?;
+	target.finish().context("Unable to swap buffers")
+}
@@ -0,0 +1,74 @@
+//! Signals
+//!
+//! `SIGUSR1` skips to the next image and `SIGUSR2` toggles pausing, so the slideshow
+//! may be controlled with just `pkill`.
+//!
+//! `SIGTERM` and `SIGINT` quit gracefully, such as when logging out. As we might be
+//! waiting for images when they arrive, they also set a flag the waits check.
+
+// Imports
+use crate::ipc::IpcCommand;
+use anyhow::Context;
+use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR1, SIGUSR2};
+use std::{
+	sync::{
+		atomic::{self, AtomicBool},
+		mpsc, Arc,
+	},
+	thread,
+};
+
+/// Signal handler
+pub struct Signals {
+	/// Receiver end for the commands
+	command_rx: mpsc::Receiver<IpcCommand>,
+
+	/// Set once we're asked to quit
+	quit: Arc<AtomicBool>,
+}
+
+impl Signals {
+	/// Starts handling all signals
+	pub fn new() -> Result<Self, anyhow::Error> {
+		let mut signals = signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2, SIGTERM, SIGINT])
+			.context("Unable to register signals")?;
+
+		let (command_tx, command_rx) = mpsc::channel();
+		let quit = Arc::new(AtomicBool::new(false));
+		let signals_quit = Arc::clone(&quit);
+		thread::spawn(move || {
+			for signal in signals.forever() {
+				let command = match signal {
+					SIGUSR1 => IpcCommand::Next,
+					SIGUSR2 => IpcCommand::TogglePause,
+					SIGTERM | SIGINT => {
+						signals_quit.store(true, atomic::Ordering::Release);
+						IpcCommand::Quit
+					},
+					_ => unreachable!("Unknown signal {:?}", signal),
+				};
+
+				if command_tx.send(command).is_err() {
+					return;
+				}
+			}
+		});
+
+		Ok(Self { command_rx, quit })
+	}
+
+	/// Returns a flag set once we're asked to quit
+	pub fn quit_flag(&self) -> Arc<AtomicBool> {
+		Arc::clone(&self.quit)
+	}
+
+	/// Returns if we were asked to quit
+	pub fn quit_requested(&self) -> bool {
+		self.quit.load(atomic::Ordering::Acquire)
+	}
+
+	/// Returns the next command, returning `None` if there are none
+	pub fn try_recv(&self) -> Option<IpcCommand> {
+		self.command_rx.try_recv().ok()
+	}
+}
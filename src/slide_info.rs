@@ -0,0 +1,104 @@
+//! Slide info
+
+// Imports
+use std::{
+	fmt,
+	path::{Path, PathBuf},
+	time::{Duration, Instant, SystemTime},
+};
+
+/// Information about where an image came from
+#[derive(Clone, Debug)]
+pub struct SlideInfo {
+	/// Path
+	pub path: PathBuf,
+
+	/// Original dimensions, before any resizing
+	pub dimensions: [u32; 2],
+
+	/// Shape, from the original dimensions
+	pub shape: Shape,
+
+	/// Source
+	pub source: SlideSource,
+
+	/// File size
+	pub file_size: Option<u64>,
+
+	/// File modification time
+	pub modified: Option<SystemTime>,
+
+	/// How long it took to load
+	pub load_duration: Duration,
+}
+
+impl SlideInfo {
+	/// Creates the info of an image at `path`, which started loading at `load_start`.
+	pub fn new(path: &Path, dimensions: [u32; 2], source: SlideSource, load_start: Instant) -> Self {
+		// Note: The metadata is only informative, so we don't fail if we can't get it
+		let metadata = std::fs::metadata(path).ok();
+
+		Self {
+			path: path.to_path_buf(),
+			dimensions,
+			shape: Shape::of(dimensions),
+			source,
+			file_size: metadata.as_ref().map(std::fs::Metadata::len),
+			modified: metadata.and_then(|metadata| metadata.modified().ok()),
+			load_duration: load_start.elapsed(),
+		}
+	}
+}
+
+impl fmt::Display for SlideInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let [width, height] = self.dimensions;
+		write!(f, "{:?} ({width}x{height}, from {}", self.path, self.source)?;
+		if let Some(file_size) = self.file_size {
+			write!(f, ", {file_size} bytes")?;
+		}
+		if let Some(modified) = self.modified.and_then(|modified| modified.elapsed().ok()) {
+			write!(f, ", modified {}s ago", modified.as_secs())?;
+		}
+		write!(f, ", loaded in {:.2?})", self.load_duration)
+	}
+}
+
+/// Shape of an image, or of where it's shown
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+	/// Wider than it's tall, or square
+	Landscape,
+
+	/// Taller than it's wide
+	Portrait,
+}
+
+impl Shape {
+	/// Returns the shape of something with `width` and `height`
+	pub const fn of([width, height]: [u32; 2]) -> Self {
+		match height > width {
+			true => Self::Portrait,
+			false => Self::Landscape,
+		}
+	}
+}
+
+/// Source of an image
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlideSource {
+	/// Images directory
+	Directory,
+
+	/// Command's output
+	Command,
+}
+
+impl fmt::Display for SlideSource {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Directory => write!(f, "images directory"),
+			Self::Command => write!(f, "command"),
+		}
+	}
+}
@@ -0,0 +1,212 @@
+//! Software rendering
+//!
+//! Runs the slideshow drawing each frame on the cpu, for when open-gl is unavailable, such as in
+//! virtual machines. Only scrolling and fading are supported.
+
+// Imports
+use anyhow::Context;
+use std::{sync::Arc, thread, time::Instant};
+use zss::{
+	args::{self, Args},
+	current_images::CurrentImages,
+	effects::Effects,
+	images::Images,
+	ipc::{Ipc, IpcCommand},
+	panel,
+	renderer::DrawParams,
+	signals::Signals,
+	slide_info::Shape,
+	software_renderer::{SoftwareImage, SoftwarePanel, SoftwareRenderer},
+	software_window::SoftwareWindow,
+};
+
+/// Runs the slideshow on `window`
+#[allow(clippy::too_many_lines)] // TODO: Refactor
+pub fn run(mut args: Args, mut window: SoftwareWindow, mut images: Images) -> Result<(), anyhow::Error> {
+	log::info!("Drawing frames on the cpu");
+	if !args.overlays.is_empty() {
+		log::warn!("Ignoring `--overlay`, unsupported with software rendering");
+	}
+	if args.grid_mirror {
+		log::warn!("Ignoring `--grid-mirror`, unsupported with software rendering");
+	}
+	if !args.pinned_cells.is_empty() {
+		log::warn!("Ignoring `--cell`, unsupported with software rendering");
+	}
+	if args.double_exposure.is_some() {
+		log::warn!("Ignoring `--double-exposure`, unsupported with software rendering");
+	}
+	let renderer = SoftwareRenderer::new();
+
+	// Create all panels
+	let mut panels = self::create_panels(&args, window.size(), &mut images).context("Unable to create panels")?;
+	let mut arranged_at = Instant::now();
+
+	// Note: Ipc and signals aren't required, so we just warn if we can't start them
+	let current_images = Arc::new(CurrentImages::default());
+	let ipc = match Ipc::new(args.ipc_socket.clone(), Arc::clone(&current_images)) {
+		Ok(ipc) => Some(ipc),
+		Err(err) => {
+			log::warn!("Unable to start ipc at {}: {err:?}", args.ipc_socket.display());
+			None
+		},
+	};
+	let signals = match Signals::new() {
+		Ok(signals) => Some(signals),
+		Err(err) => {
+			log::warn!("Unable to handle signals: {err:?}");
+			None
+		},
+	};
+
+	// Note: Re-arranging collages waits for images, so quitting must interrupt the wait
+	if let Some(signals) = &signals {
+		images.set_interrupt(signals.quit_flag());
+	}
+
+	let mut paused = args.start_held;
+	let mut last_frame = Instant::now();
+	'frames: loop {
+		window.process_events();
+
+		// Process all ipc and signal commands
+		loop {
+			let command = ipc
+				.as_ref()
+				.and_then(Ipc::try_recv)
+				.or_else(|| signals.as_ref().and_then(Signals::try_recv));
+			let command = match command {
+				Some(command) => command,
+				None => break,
+			};
+
+			match command {
+				// Note: Skipping just starts fading into the next image
+				IpcCommand::Next => {
+					log::info!("Skipping to the next image");
+					for panel in &mut panels {
+						panel.progress = panel.progress.max(args.fade);
+					}
+				},
+				IpcCommand::Pause => {
+					log::info!("Pausing");
+					paused = true;
+				},
+				IpcCommand::Resume => {
+					log::info!("Resuming");
+					paused = false;
+				},
+				IpcCommand::TogglePause => {
+					paused = !paused;
+					match paused {
+						true => log::info!("Pausing"),
+						false => log::info!("Resuming"),
+					}
+				},
+				IpcCommand::SetDuration(duration) => {
+					log::info!("Setting duration to {duration:?}");
+					args.duration = duration;
+				},
+				IpcCommand::Reload => match images.rescan() {
+					true => log::info!("Rescanning images directory"),
+					false => log::warn!("Ignoring reload, not showing an images directory"),
+				},
+				IpcCommand::Quit => {
+					log::info!("Quitting");
+
+					// Clear the window, so we don't leave a stale frame behind
+					let (frame, stride) = window.frame_mut();
+					renderer.draw(frame, stride, &[], &DrawParams::new(&args, Effects::IDENTITY));
+					window.present();
+					break 'frames;
+				},
+				command => log::warn!("Ignoring {command:?}, unsupported with software rendering"),
+			}
+		}
+
+		// Draw
+		let (frame, stride) = window.frame_mut();
+		renderer.draw(frame, stride, &panels, &DrawParams::new(&args, Effects::IDENTITY));
+		window.present();
+
+		// Then update all panels
+		// Note: Frames may take longer than usual to draw, so we advance by the actual time elapsed
+		let dt = last_frame.elapsed();
+		last_frame = Instant::now();
+		for panel in &mut panels {
+			panel
+				.update(dt, &args, paused, &mut images)
+				.context("Unable to update panel")?;
+		}
+
+		// Re-arrange the collage, if any
+		if let args::Mode::Collage { interval, .. } = args.mode {
+			if arranged_at.elapsed() >= interval {
+				log::info!("Re-arranging collage");
+				panels = match self::create_panels(&args, window.size(), &mut images) {
+					Ok(panels) => panels,
+					Err(err) if signals.as_ref().map_or(false, Signals::quit_requested) => {
+						log::info!("Quitting while re-arranging collage: {err:?}");
+						break;
+					},
+					Err(err) => return Err(err).context("Unable to re-arrange collage"),
+				};
+				arranged_at = Instant::now();
+			}
+		}
+
+		// If we're out of images and all panels finished showing their last one, quit
+		let all_shown = panels
+			.iter()
+			.all(|panel| panel.next_image.is_none() && panel.progress >= args.fade - f32::EPSILON);
+		if images.is_finished() && all_shown {
+			log::info!("Finished showing all images");
+			break;
+		}
+
+		// And update the current images
+		for (panel, SoftwarePanel { cur_image, .. }) in panels.iter().enumerate() {
+			current_images.set(panel, Some(&cur_image.info.path));
+		}
+
+		// Finally wait until the next frame
+		if let Some(duration) = crate::FRAME_DURATION.checked_sub(last_frame.elapsed()) {
+			thread::sleep(duration);
+		}
+	}
+
+	// Stop loading images
+	if !images.stop(crate::LOADER_STOP_TIMEOUT) {
+		log::warn!(
+			"Image loader didn't stop within {:?}, exiting anyway",
+			crate::LOADER_STOP_TIMEOUT
+		);
+	}
+
+	Ok(())
+}
+
+/// Creates all panels on a `window_size` window, with images from `images`
+fn create_panels(args: &Args, window_size: [u32; 2], images: &mut Images) -> Result<Vec<SoftwarePanel>, anyhow::Error> {
+	match args.mode.layout() {
+		None => {
+			let image = images.next_image(Shape::of(window_size))?;
+			let image = SoftwareImage::new(image, window_size, args.scaling);
+			Ok(vec![SoftwarePanel::new(image, [0, 0], window_size)])
+		},
+		// Note: Panels are ordered from the bottom, as with open-gl
+		Some(layout) => layout
+			.window_cells(window_size, args.grid_gap, args.grid_margin)
+			.into_iter()
+			.enumerate()
+			.map(|(idx, cell)| {
+				let image = images.next_image(Shape::of(cell.size))?;
+				let image = SoftwareImage::new(image, cell.size, args.scaling);
+				let mut panel = SoftwarePanel::new(image, cell.top_left_pos(window_size[1]), cell.size);
+				panel.progress = panel::grid_progress(args, idx).unwrap_or_else(rand::random);
+				panel.duration_scale = panel::duration_scale(args);
+				Ok(panel)
+			})
+			.collect(),
+	}
+}
@@ -0,0 +1,349 @@
+//! Software renderer
+//!
+//! Draws panels on the cpu, for when open-gl is unavailable. Only scrolling and fading are
+//! supported, with each image sampled at it's nearest pixel, and without any effects.
+
+// Imports
+use crate::{
+	args::{Args, FadeSpace, Scaling},
+	images::{self, Images, LoadedImage},
+	panel,
+	renderer::DrawParams,
+	slide_info::{Shape, SlideInfo},
+	uvs::ImageUvs,
+};
+use anyhow::Context;
+use std::time::Duration;
+
+/// Number of entries of the table to convert linear colors back to srgb
+const TO_SRGB_LEN: usize = 4096;
+
+/// Software image
+#[derive(Debug)]
+pub struct SoftwareImage {
+	/// Info
+	pub info: SlideInfo,
+
+	/// Image
+	///
+	/// Animated images only show their first frame.
+	image: images::Image,
+
+	/// Uvs
+	uvs: ImageUvs,
+
+	/// Dominant color
+	dominant_color: [f32; 3],
+}
+
+impl SoftwareImage {
+	/// Creates a new image from an already loaded image, shown on a `window_size` panel
+	pub fn new(image: LoadedImage, [window_width, window_height]: [u32; 2], scaling: Scaling) -> Self {
+		let LoadedImage {
+			info,
+			frames,
+			dominant_color,
+			swap_dir,
+			..
+		} = image;
+		let image = frames.into_iter().next().expect("Loaded image had no frames").image;
+
+		let (image_width, image_height) = image.dimensions();
+		#[allow(clippy::cast_precision_loss)] // Image and window sizes are likely much lower than 2^24
+		let uvs = ImageUvs::new(
+			image_width as f32,
+			image_height as f32,
+			window_width as f32,
+			window_height as f32,
+			swap_dir,
+			scaling,
+		);
+
+		Self {
+			info,
+			image,
+			uvs,
+			dominant_color,
+		}
+	}
+
+	/// Samples this image at `uvs`, with the origin on the bottom-left, after `progress`.
+	///
+	/// Returns `None` outside of the image, if it's letterboxed.
+	fn sample(&self, [u, v]: [f32; 2], progress: f32, scaling: Scaling) -> Option<[u8; 3]> {
+		let [u, v] = match scaling {
+			Scaling::Fill => {
+				let [start_u, start_v] = self.uvs.start();
+				let [offset_u, offset_v] = self.uvs.offset(progress);
+				[
+					self::mirror(u.mul_add(start_u, offset_u)),
+					self::mirror(v.mul_add(start_v, offset_v)),
+				]
+			},
+			Scaling::Tile => {
+				let [scale_u, scale_v] = self.uvs.scale();
+				[(u * scale_u).rem_euclid(1.0), (v * scale_v).rem_euclid(1.0)]
+			},
+			Scaling::Fit | Scaling::ContainBlur | Scaling::Stretch | Scaling::Center => {
+				let [scale_u, scale_v] = self.uvs.scale();
+				let u = (u - 0.5).mul_add(scale_u, 0.5);
+				let v = (v - 0.5).mul_add(scale_v, 0.5);
+				if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+					return None;
+				}
+				[u, v]
+			},
+		};
+
+		// Note: Images have their first row at the top, so we flip them here
+		let (width, height) = self.image.dimensions();
+		// Note: `u` and `v` are within `0.0 ..= 1.0`, and images are much smaller than 2^24
+		#[allow(
+			clippy::cast_possible_truncation,
+			clippy::cast_sign_loss,
+			clippy::cast_precision_loss
+		)]
+		let (x, y) = (
+			((u * width as f32) as u32).min(width - 1),
+			(((1.0 - v) * height as f32) as u32).min(height - 1),
+		);
+		let bytes_per_pixel = self.image.bytes_per_pixel() as usize;
+		let idx = (y as usize * width as usize + x as usize) * bytes_per_pixel;
+		match self.image.as_raw()[idx..idx + 3] {
+			[red, green, blue] => Some([red, green, blue]),
+			_ => unreachable!("Pixel didn't have 3 channels"),
+		}
+	}
+}
+
+/// Software panel
+#[derive(Debug)]
+pub struct SoftwarePanel {
+	/// Current image
+	pub cur_image: SoftwareImage,
+
+	/// Next image, if loaded
+	pub next_image: Option<SoftwareImage>,
+
+	/// Progress through the current image
+	pub progress: f32,
+
+	/// Scale of the duration of the current image
+	pub duration_scale: f32,
+
+	/// Position of the top-left corner, in pixels
+	pub pos: [u32; 2],
+
+	/// Size, in pixels
+	pub size: [u32; 2],
+}
+
+impl SoftwarePanel {
+	/// Creates a new panel at `pos` with `size`, showing `cur_image`
+	pub const fn new(cur_image: SoftwareImage, pos: [u32; 2], size: [u32; 2]) -> Self {
+		Self {
+			cur_image,
+			next_image: None,
+			progress: 0.0,
+			duration_scale: 1.0,
+			pos,
+			size,
+		}
+	}
+
+	/// Updates this panel after `dt` has elapsed
+	pub fn update(
+		&mut self, dt: Duration, args: &Args, paused: bool, images: &mut Images,
+	) -> Result<(), anyhow::Error> {
+		// Increase the progress
+		// Note: When paused, we still finish any fade, so we don't stay stuck between images
+		let was_fading = self.progress >= args.fade;
+		if !paused || was_fading {
+			self.progress += dt.as_secs_f32() / (args.duration.as_secs_f32() * self.duration_scale);
+		}
+
+		// If the next image isn't loaded, try to load it, holding the current image right before
+		// the fade until it arrives
+		if self.next_image.is_none() {
+			self.next_image = images
+				.try_next_image(Shape::of(self.size))
+				.context("Unable to get next image")?
+				.map(|image| SoftwareImage::new(image, self.size, args.scaling));
+			if self.next_image.is_none() {
+				self.progress = self.progress.min(args.fade - f32::EPSILON);
+			}
+		}
+
+		// If we reached the end, swap the next to current
+		if self.progress >= 1.0 {
+			self.progress = 1.0 - args.fade;
+			if let Some(image) = self.next_image.take() {
+				self.cur_image = image;
+				self.duration_scale = panel::duration_scale(args);
+				log::info!("Showing {}", self.cur_image.info);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Software renderer
+#[derive(Debug)]
+pub struct SoftwareRenderer {
+	/// Linear value of each srgb value
+	to_linear: Vec<f32>,
+
+	/// Srgb value of each linear value, in `TO_SRGB_LEN` steps
+	to_srgb: Vec<u8>,
+}
+
+impl SoftwareRenderer {
+	/// Creates a renderer
+	// Note: Both tables are small, and all their values are within `0.0 ..= 1.0`
+	#[allow(
+		clippy::cast_precision_loss,
+		clippy::cast_possible_truncation,
+		clippy::cast_sign_loss
+	)]
+	pub fn new() -> Self {
+		let to_linear = (0..=255)
+			.map(|value| {
+				let value = value as f32 / 255.0;
+				match value <= 0.04045 {
+					true => value / 12.92,
+					false => ((value + 0.055) / 1.055).powf(2.4),
+				}
+			})
+			.collect();
+		let to_srgb = (0..TO_SRGB_LEN)
+			.map(|value| {
+				let value = value as f32 / (TO_SRGB_LEN - 1) as f32;
+				let value = match value <= 0.003_130_8 {
+					true => value * 12.92,
+					false => 1.055f32.mul_add(value.powf(1.0 / 2.4), -0.055),
+				};
+				(value * 255.0).round() as u8
+			})
+			.collect();
+
+		Self { to_linear, to_srgb }
+	}
+
+	/// Clears `frame` and draws all `panels` to it.
+	///
+	/// `frame` is made of rows of `stride` `0x00rrggbb` pixels.
+	pub fn draw(&self, frame: &mut [u32], stride: usize, panels: &[SoftwarePanel], params: &DrawParams) {
+		let border_color = self::pack(self::to_u8(params.border_color));
+		frame.fill(border_color);
+
+		for panel in panels {
+			self.draw_panel(frame, stride, panel, params);
+		}
+	}
+
+	/// Draws a single panel
+	fn draw_panel(&self, frame: &mut [u32], stride: usize, panel: &SoftwarePanel, params: &DrawParams) {
+		let SoftwarePanel {
+			cur_image,
+			next_image,
+			progress,
+			pos: [panel_x, panel_y],
+			size: [width, height],
+			..
+		} = panel;
+		let progress = *progress;
+
+		// Calculate the alpha and progress of the next image
+		let fade = params.fade;
+		let (next_alpha, next_progress) = match progress {
+			f if f >= fade => ((progress - fade) / (1.0 - fade), progress - fade),
+			_ => (0.0, 0.0),
+		};
+		let next_image = next_image.as_ref().filter(|_| next_alpha > 0.0);
+
+		// Get the letterbox color of each image
+		let letterbox_color = |image: &SoftwareImage| match params.dominant_bg {
+			true => self::to_u8(image.dominant_color),
+			false => self::to_u8(params.bg_color),
+		};
+		let (cur_letterbox_color, next_letterbox_color) =
+			(letterbox_color(cur_image), next_image.map_or([0; 3], letterbox_color));
+
+		for y in 0..*height {
+			let row = (*panel_y + y) as usize * stride + *panel_x as usize;
+			for x in 0..*width {
+				#[allow(clippy::cast_precision_loss)] // Panels are much smaller than 2^24
+				let uvs = [
+					(x as f32 + 0.5) / *width as f32,
+					1.0 - (y as f32 + 0.5) / *height as f32,
+				];
+
+				let cur_color = cur_image
+					.sample(uvs, progress, params.scaling)
+					.unwrap_or(cur_letterbox_color);
+				let color = match next_image {
+					Some(next_image) => {
+						let next_color = next_image
+							.sample(uvs, next_progress, params.scaling)
+							.unwrap_or(next_letterbox_color);
+						self.mix(cur_color, next_color, next_alpha, params.fade_space)
+					},
+					None => cur_color,
+				};
+
+				frame[row + x as usize] = self::pack(color);
+			}
+		}
+	}
+
+	/// Mixes `lhs` and `rhs` by `alpha`, in `fade_space`
+	// Note: The table is small, and all values are within `0.0 ..= 1.0`, before scaling
+	#[allow(
+		clippy::cast_precision_loss,
+		clippy::cast_possible_truncation,
+		clippy::cast_sign_loss
+	)]
+	fn mix(&self, lhs: [u8; 3], rhs: [u8; 3], alpha: f32, fade_space: FadeSpace) -> [u8; 3] {
+		let mut color = [0; 3];
+		for ((color, lhs), rhs) in color.iter_mut().zip(lhs).zip(rhs) {
+			*color = match fade_space {
+				FadeSpace::Linear => {
+					let lhs = self.to_linear[usize::from(lhs)];
+					let rhs = self.to_linear[usize::from(rhs)];
+					let value = (rhs - lhs).mul_add(alpha, lhs);
+					self.to_srgb[(value * (TO_SRGB_LEN - 1) as f32).round() as usize]
+				},
+				FadeSpace::Srgb => (f32::from(rhs) - f32::from(lhs)).mul_add(alpha, f32::from(lhs)).round() as u8,
+			};
+		}
+
+		color
+	}
+}
+
+impl Default for SoftwareRenderer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Mirrors `value` into `0.0 ..= 1.0`, as the open-gl mirrored repeat
+fn mirror(value: f32) -> f32 {
+	let value = value.rem_euclid(2.0);
+	match value > 1.0 {
+		true => 2.0 - value,
+		false => value,
+	}
+}
+
+/// Converts a color to `u8`s
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Colors are within `0.0 ..= 1.0`
+fn to_u8(color: [f32; 3]) -> [u8; 3] {
+	color.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Packs a color as `0x00rrggbb`
+fn pack([red, green, blue]: [u8; 3]) -> u32 {
+	u32::from_be_bytes([0, red, green, blue])
+}
@@ -0,0 +1,294 @@
+//! Software window
+//!
+//! Window drawn to by the cpu, for when open-gl is unavailable. Frames are copied to the window
+//! through shared memory, if the X server supports it, else they're sent over the connection.
+
+// Imports
+use anyhow::Context;
+use std::{
+	convert::TryInto,
+	mem::{self, MaybeUninit},
+	os::raw::c_int,
+	ptr, slice,
+	sync::atomic::{self, AtomicBool},
+};
+use x11::{xlib, xshm};
+
+// Note: `x11` only links `libXext`, which implements `xshm`, with unrelated features
+#[link(name = "Xext")]
+extern "C" {}
+
+/// If the X server failed to attach to the shared memory segment
+// Note: Remote X servers can't attach to it, but they only report it asynchronously, through the error handler
+static ATTACH_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Software window
+pub struct SoftwareWindow {
+	/// Display
+	display: *mut xlib::Display,
+
+	/// Id
+	id: u64,
+
+	/// Graphics context
+	gc: xlib::GC,
+
+	/// Image, holding the frame
+	image: *mut xlib::XImage,
+
+	/// Shared memory segment of the image, if any
+	// Note: Boxed, as the image keeps a pointer to it
+	shm: Option<Box<xshm::XShmSegmentInfo>>,
+
+	/// Frame, when not using shared memory
+	frame: Vec<u32>,
+
+	/// Size
+	size: [u32; 2],
+}
+
+impl SoftwareWindow {
+	/// Creates a software window from an existing x11 window
+	pub fn from_window_id(id: u64) -> Result<Self, anyhow::Error> {
+		// SAFETY: See `Window::from_window_id`.
+		let display = unsafe { xlib::XOpenDisplay(std::ptr::null()) };
+		anyhow::ensure!(!display.is_null(), "Unable to open display");
+
+		// Get the window attributes
+		// SAFETY: Even if `id` isn't a valid window, this should simply return `0`,
+		//         which we catch before the `assume_init` call.
+		let mut attrs = MaybeUninit::uninit();
+		if unsafe { xlib::XGetWindowAttributes(display, id, attrs.as_mut_ptr()) } == 0 {
+			anyhow::bail!("Unable to get window attributes");
+		}
+		let attrs: xlib::XWindowAttributes = unsafe { attrs.assume_init() };
+		let size @ [width, height] = [
+			attrs.width.try_into().context("Window width was negative")?,
+			attrs.height.try_into().context("Window height was negative")?,
+		];
+
+		// Note: We only write pixels as `0x00rrggbb`, so the visual must match it
+		// SAFETY: The visual of a window is always valid.
+		let visual = unsafe { &*attrs.visual };
+		anyhow::ensure!(
+			(attrs.depth == 24 || attrs.depth == 32) &&
+				visual.red_mask == 0xff_0000 &&
+				visual.green_mask == 0xff00 &&
+				visual.blue_mask == 0xff,
+			"Unsupported window visual with depth {} and masks {:#x}, {:#x}, {:#x}",
+			attrs.depth,
+			visual.red_mask,
+			visual.green_mask,
+			visual.blue_mask
+		);
+		let depth = attrs.depth.try_into().context("Window depth was negative")?;
+
+		// SAFETY: The display and window are valid and we don't pass any values.
+		let gc = unsafe { xlib::XCreateGC(display, id, 0, std::ptr::null_mut()) };
+
+		let mut window = Self {
+			display,
+			id,
+			gc,
+			image: std::ptr::null_mut(),
+			shm: None,
+			frame: vec![],
+			size,
+		};
+
+		// Create the image, in shared memory if possible
+		// SAFETY: The display is valid.
+		let (image, shm) = match unsafe { xshm::XShmQueryExtension(display) } {
+			0 => {
+				log::info!("X server doesn't support shared memory, sending frames over the connection instead");
+				(std::ptr::null_mut(), None)
+			},
+			_ => match window.create_shm_image(attrs.visual, depth) {
+				Ok((image, shm)) => (image, Some(shm)),
+				Err(err) => {
+					log::warn!(
+						"Unable to create shared memory image, sending frames over the connection instead: {err:?}"
+					);
+					(std::ptr::null_mut(), None)
+				},
+			},
+		};
+		window.image = image;
+		window.shm = shm;
+
+		if window.image.is_null() {
+			window.frame = vec![0; width as usize * height as usize];
+			// SAFETY: The frame has `width * height` 32-bit pixels and outlives the image,
+			//         which never frees it, as we take it back before destroying it.
+			window.image = unsafe {
+				xlib::XCreateImage(
+					display,
+					attrs.visual,
+					depth,
+					xlib::ZPixmap,
+					0,
+					window.frame.as_mut_ptr().cast(),
+					width,
+					height,
+					32,
+					0,
+				)
+			};
+			anyhow::ensure!(!window.image.is_null(), "Unable to create image");
+		}
+
+		Ok(window)
+	}
+
+	/// Creates an image in shared memory
+	fn create_shm_image(
+		&self, visual: *mut xlib::Visual, depth: u32,
+	) -> Result<(*mut xlib::XImage, Box<xshm::XShmSegmentInfo>), anyhow::Error> {
+		let mut shm = Box::new(xshm::XShmSegmentInfo {
+			shmseg:   0,
+			shmid:    -1,
+			shmaddr:  std::ptr::null_mut(),
+			readOnly: xlib::False,
+		});
+
+		// SAFETY: The display and visual are valid, and `shm` outlives the image.
+		let image = unsafe {
+			xshm::XShmCreateImage(
+				self.display,
+				visual,
+				depth,
+				xlib::ZPixmap,
+				std::ptr::null_mut(),
+				ptr::addr_of_mut!(*shm),
+				self.size[0],
+				self.size[1],
+			)
+		};
+		anyhow::ensure!(!image.is_null(), "Unable to create image");
+
+		// Then create the segment and attach to it, along with the X server
+		// Note: We mark the segment for removal once attached, so it's removed even if we crash
+		// SAFETY: The image was just created and `shm` is valid.
+		let res = unsafe {
+			#[allow(clippy::cast_sign_loss)] // Image sizes are never negative
+			let len = (*image).bytes_per_line as usize * (*image).height as usize;
+			shm.shmid = libc::shmget(libc::IPC_PRIVATE, len, libc::IPC_CREAT | 0o600);
+			match shm.shmid {
+				-1 => Err(anyhow::anyhow!("Unable to create shared memory segment")),
+				_ => {
+					shm.shmaddr = libc::shmat(shm.shmid, std::ptr::null(), 0).cast();
+					(*image).data = shm.shmaddr;
+					let prev_error_handler = xlib::XSetErrorHandler(Some(self::on_attach_error));
+					let is_attached =
+						shm.shmaddr as isize != -1 && xshm::XShmAttach(self.display, ptr::addr_of_mut!(*shm)) != 0;
+					xlib::XSync(self.display, xlib::False);
+					xlib::XSetErrorHandler(prev_error_handler);
+					let is_attached = is_attached && !ATTACH_FAILED.swap(false, atomic::Ordering::Relaxed);
+					libc::shmctl(shm.shmid, libc::IPC_RMID, std::ptr::null_mut());
+					match is_attached {
+						true => Ok(()),
+						false => Err(anyhow::anyhow!("Unable to attach shared memory segment")),
+					}
+				},
+			}
+		};
+		if let Err(err) = res {
+			// SAFETY: The image isn't used after this, and we only detach the segment if attached
+			unsafe {
+				if !shm.shmaddr.is_null() && shm.shmaddr as isize != -1 {
+					libc::shmdt(shm.shmaddr.cast());
+				}
+				(*image).data = std::ptr::null_mut();
+				xlib::XDestroyImage(image);
+			}
+			return Err(err);
+		}
+
+		Ok((image, shm))
+	}
+
+	/// Window size
+	pub const fn size(&self) -> [u32; 2] {
+		self.size
+	}
+
+	/// Returns the frame, as rows of `0x00rrggbb` pixels, along with the number of pixels per row.
+	///
+	/// Rows may have padding at the end, so they must be indexed using the returned stride.
+	pub fn frame_mut(&mut self) -> (&mut [u32], usize) {
+		// SAFETY: The image is valid, and it's data holds `height` rows of `bytes_per_line`, which
+		//         is a multiple of 4, as each pixel is 32 bits.
+		unsafe {
+			let image = &*self.image;
+			let stride = image.bytes_per_line as usize / mem::size_of::<u32>();
+			let frame = slice::from_raw_parts_mut(image.data.cast::<u32>(), stride * image.height as usize);
+			(frame, stride)
+		}
+	}
+
+	/// Copies the frame to the window
+	pub fn present(&mut self) {
+		let [width, height] = self.size;
+
+		// Note: We wait for the X server to finish copying, so we don't modify the frame while it's being read
+		// SAFETY: The display, window, graphics context and image are all valid.
+		unsafe {
+			match self.shm {
+				Some(_) => {
+					xshm::XShmPutImage(
+						self.display,
+						self.id,
+						self.gc,
+						self.image,
+						0,
+						0,
+						0,
+						0,
+						width,
+						height,
+						xlib::False,
+					);
+				},
+				None => {
+					xlib::XPutImage(self.display, self.id, self.gc, self.image, 0, 0, 0, 0, width, height);
+				},
+			}
+			xlib::XSync(self.display, xlib::False);
+		}
+	}
+
+	/// Processes all X events
+	pub fn process_events(&self) {
+		// SAFETY: Checking for events and receiving them should be safe.
+		while unsafe { xlib::XPending(self.display) } != 0 {
+			let mut event = MaybeUninit::uninit();
+			unsafe { xlib::XNextEvent(self.display, event.as_mut_ptr()) };
+		}
+	}
+}
+
+impl Drop for SoftwareWindow {
+	fn drop(&mut self) {
+		// SAFETY: Everything is valid and no longer used after this.
+		//         The image's data is owned by either the frame or the segment, so we take it back
+		//         before destroying it.
+		unsafe {
+			if !self.image.is_null() {
+				(*self.image).data = std::ptr::null_mut();
+				xlib::XDestroyImage(self.image);
+			}
+			if let Some(shm) = &mut self.shm {
+				xshm::XShmDetach(self.display, ptr::addr_of_mut!(**shm));
+				libc::shmdt(shm.shmaddr.cast());
+			}
+			xlib::XFreeGC(self.display, self.gc);
+			xlib::XCloseDisplay(self.display);
+		}
+	}
+}
+
+/// Error handler while attaching the shared memory segment
+unsafe extern "C" fn on_attach_error(_display: *mut xlib::Display, _event: *mut xlib::XErrorEvent) -> c_int {
+	ATTACH_FAILED.store(true, atomic::Ordering::Relaxed);
+	0
+}
@@ -0,0 +1,176 @@
+//! Library statistics
+//!
+//! Reports statistics about the images directory itself, along with how many times
+//! each image was shown and how long they took to load, if running with `--persist-history`.
+
+// Imports
+use anyhow::Context;
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+use zss::{
+	args::StatsArgs,
+	history::{ShowCounts, ShowStats},
+	ignores::Ignores,
+	images::{self, LoadOptions},
+};
+
+/// Resolution buckets, by their shortest side
+const RESOLUTION_BUCKETS: [(u32, &str); 5] = [
+	(0, "< 720p"),
+	(720, "720p"),
+	(1080, "1080p"),
+	(1440, "1440p"),
+	(2160, "4k+"),
+];
+
+/// Number of most and least shown images reported
+const SHOWN_IMAGES_LEN: usize = 5;
+
+/// Prints statistics about the images directory
+pub fn run(args: &StatsArgs) -> Result<(), anyhow::Error> {
+	let images_dir = std::env::current_dir()
+		.context("Unable to get current directory")?
+		.join(&args.images_dir);
+
+	let mut ignores = Ignores::new(images_dir.clone());
+	let mut paths = vec![];
+	self::collect_paths(&images_dir, &mut ignores, &mut paths).context("Unable to read images directory")?;
+
+	let mut total_size = 0;
+	let mut formats = BTreeMap::<String, usize>::new();
+	let mut resolutions = [0_usize; RESOLUTION_BUCKETS.len()];
+	let mut unreadable = 0;
+	let mut decode_times = vec![];
+	for path in &paths {
+		total_size += std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+
+		let format = path
+			.extension()
+			.and_then(|extension| extension.to_str())
+			.map_or_else(|| "(none)".to_owned(), str::to_lowercase);
+		*formats.entry(format).or_default() += 1;
+
+		// Note: Only the header is read for the size, so this is cheap
+		match image::image_dimensions(path) {
+			Ok((width, height)) => {
+				let bucket_idx = RESOLUTION_BUCKETS
+					.iter()
+					.rposition(|&(min_side, _)| width.min(height) >= min_side)
+					.expect("First bucket should always match");
+				resolutions[bucket_idx] += 1;
+			},
+			Err(err) => {
+				log::debug!("Unable to read dimensions of {path:?}: {err}");
+				unreadable += 1;
+			},
+		}
+
+		if args.decode {
+			let start = Instant::now();
+			match images::load_img(path, LoadOptions {
+				window_size:      args.size,
+				scaling:          args.scaling,
+				resize_filter:    args.resize_filter,
+				gpu_resize:       false,
+				rgb:              false,
+				max_texture_size: None,
+				cache_size:       0,
+			}) {
+				Ok(_) => decode_times.push(start.elapsed()),
+				Err(err) => log::debug!("Unable to load {path:?}: {err}"),
+			}
+		}
+	}
+
+	println!("Files: {} ({})", paths.len(), self::fmt_size(total_size));
+
+	println!("Formats:");
+	for (format, count) in &formats {
+		println!("\t{format}: {count}");
+	}
+
+	println!("Resolutions:");
+	for ((_, name), count) in RESOLUTION_BUCKETS.iter().zip(resolutions) {
+		println!("\t{name}: {count}");
+	}
+	println!("\tUnreadable: {unreadable}");
+
+	if args.decode {
+		#[allow(clippy::cast_possible_truncation)] // We won't have more than `2^32` images
+		match decode_times.len() {
+			0 => println!("Average load time: -"),
+			len => println!(
+				"Average load time: {:.2?} ({len} loaded)",
+				decode_times.iter().sum::<Duration>() / len as u32
+			),
+		}
+	}
+
+	// Note: Images never shown have no statistics, so they count as shown 0 times
+	let show_stats = ShowCounts::load().map(|show_counts| show_counts.stats());
+	match show_stats.filter(|show_stats| !show_stats.is_empty()) {
+		Some(show_stats) => {
+			let mut shown_paths = paths
+				.iter()
+				.map(|path| (path, show_stats.get(path).copied().unwrap_or_default()))
+				.collect::<Vec<_>>();
+			shown_paths.sort_by_key(|&(path, stats)| (std::cmp::Reverse(stats.shown), path));
+
+			println!("Most shown:");
+			for (path, stats) in shown_paths.iter().take(SHOWN_IMAGES_LEN) {
+				println!("\t{}: {}", path.display(), stats.shown);
+			}
+			println!("Least shown:");
+			for (path, stats) in shown_paths.iter().rev().take(SHOWN_IMAGES_LEN) {
+				println!("\t{}: {}", path.display(), stats.shown);
+			}
+
+			let (shown, load_secs) = shown_paths.iter().fold((0, 0.0), |(shown, load_secs), (_, stats)| {
+				(shown + stats.shown, load_secs + stats.load_secs)
+			});
+			let total_stats = ShowStats { shown, load_secs };
+			match total_stats.average_load_duration() {
+				Some(duration) => println!("Average load time while shown: {duration:.2?} ({shown} shown)"),
+				None => println!("Average load time while shown: -"),
+			}
+		},
+		None => println!("No show counts, run with `--persist-history` to record them"),
+	}
+
+	Ok(())
+}
+
+/// Collects all non-ignored paths within `dir`
+pub fn collect_paths(dir: &Path, ignores: &mut Ignores, paths: &mut Vec<PathBuf>) -> Result<(), anyhow::Error> {
+	for entry in std::fs::read_dir(dir).context("Unable to read directory")? {
+		let entry = entry.context("Unable to read directory entry")?;
+		let file_type = entry.file_type().context("Unable to get entry file type")?;
+		let path = entry.path();
+
+		match file_type.is_dir() {
+			true => self::collect_paths(&path, ignores, paths).context("Unable to collect sub-directory")?,
+			false if ignores.is_ignored(&path) => (),
+			false => paths.push(path),
+		}
+	}
+
+	Ok(())
+}
+
+/// Formats a size in bytes
+#[allow(clippy::cast_precision_loss)] // We don't need exact sizes
+pub fn fmt_size(size: u64) -> String {
+	const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+	let mut size = size as f64;
+	let mut unit_idx = 0;
+	while size >= 1024.0 && unit_idx + 1 < UNITS.len() {
+		size /= 1024.0;
+		unit_idx += 1;
+	}
+
+	format!("{size:.2} {}", UNITS[unit_idx])
+}
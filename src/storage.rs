@@ -0,0 +1,192 @@
+//! Persistent storage
+//!
+//! All persisted files are written atomically, by writing to a temporary file
+//! and renaming it over the original, so a crash or power loss never leaves
+//! a partially written file behind.
+//!
+//! Versioned files are stored as toml, along with their schema version, and
+//! any corrupt or outdated files are moved aside and replaced by their default.
+
+// Imports
+use anyhow::Context;
+use std::{
+	ffi::OsString,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::atomic::{self, AtomicUsize},
+	time::SystemTime,
+};
+
+/// Returns the cache directory, `$XDG_CACHE_HOME/zss`
+pub fn cache_dir() -> Option<PathBuf> {
+	let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+		.map(PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+	Some(cache_dir.join("zss"))
+}
+
+/// Returns a hash of `parts`, for naming cached files.
+///
+/// Unlike `DefaultHasher`, this is stable across rust versions, so the cached files are still found after updating.
+pub fn stable_hash(parts: &[&[u8]]) -> u64 {
+	// Note: Uses 64-bit FNV-1a
+	const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+	const PRIME: u64 = 0x0100_0000_01b3;
+
+	// Note: We hash each part's length before it, so adjacent parts can't be confused with each other
+	let mut hash = OFFSET_BASIS;
+	for part in parts {
+		for &byte in (part.len() as u64).to_le_bytes().iter().chain(*part) {
+			hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+		}
+	}
+
+	hash
+}
+
+/// Returns the state directory, `$XDG_STATE_HOME/zss`
+pub fn state_dir() -> Option<PathBuf> {
+	let state_dir = std::env::var_os("XDG_STATE_HOME")
+		.map(PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+
+	Some(state_dir.join("zss"))
+}
+
+/// Creates a new file in `dir`, named `{prefix}-{pid}-{idx}{suffix}`.
+///
+/// The file is always newly created, so it's never shared with another thread
+/// or instance, and is never a symlink to somewhere else.
+pub fn create_unique(dir: &Path, prefix: &str, suffix: &str) -> Result<(fs::File, PathBuf), anyhow::Error> {
+	/// Index of the next file
+	static NEXT_IDX: AtomicUsize = AtomicUsize::new(0);
+
+	loop {
+		let idx = NEXT_IDX.fetch_add(1, atomic::Ordering::Relaxed);
+		let path = dir.join(format!("{prefix}-{}-{idx}{suffix}", std::process::id()));
+		match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+			Ok(file) => return Ok((file, path)),
+			// Note: A previous instance with the same pid may have left it behind
+			Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+			Err(err) => return Err(err).with_context(|| format!("Unable to create {}", path.display())),
+		}
+	}
+}
+
+/// Removes the oldest files in `dir` until all of them fit within `max_size` bytes
+pub fn evict(dir: &Path, max_size: u64) -> Result<(), anyhow::Error> {
+	let mut entries = vec![];
+	for entry in fs::read_dir(dir).context("Unable to read directory")? {
+		let entry = entry.context("Unable to read directory entry")?;
+		let metadata = entry.metadata().context("Unable to get entry metadata")?;
+		let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+		entries.push((modified, metadata.len(), entry.path()));
+	}
+
+	let mut total_size = entries.iter().map(|&(_, size, _)| size).sum::<u64>();
+	entries.sort_unstable_by_key(|&(modified, ..)| modified);
+	for (_, size, path) in entries {
+		if total_size <= max_size {
+			break;
+		}
+
+		log::debug!("Removing cached file {path:?}");
+		match fs::remove_file(&path) {
+			Ok(()) => (),
+			// Note: Another instance may have removed it already
+			Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+			Err(err) => return Err(err).with_context(|| format!("Unable to remove {path:?}")),
+		}
+		total_size -= size;
+	}
+
+	Ok(())
+}
+
+/// Writes `data` to `path` atomically
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), anyhow::Error> {
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).context("Unable to create parent directory")?;
+	}
+
+	// Note: The temporary file must be in the same directory, so the rename doesn't cross filesystems,
+	//       and unique, so other instances writing the same file don't write to it at the same time.
+	let dir = path.parent().unwrap_or_else(|| Path::new("."));
+	let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+	let (mut file, tmp_path) =
+		self::create_unique(dir, &format!(".{file_name}"), ".tmp").context("Unable to create temporary file")?;
+	let res = file
+		.write_all(data)
+		.context("Unable to write temporary file")
+		.and_then(|()| file.sync_all().context("Unable to sync temporary file"))
+		.and_then(|()| fs::rename(&tmp_path, path).context("Unable to replace file"));
+
+	// Note: If we failed, we remove the temporary file, so it isn't left behind
+	if res.is_err() {
+		let _ = fs::remove_file(&tmp_path);
+	}
+	res
+}
+
+/// Versioned file contents
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Versioned<T> {
+	/// Schema version
+	version: u32,
+
+	/// Data
+	data: T,
+}
+
+/// Saves `value` with schema version `version` to `path`
+pub fn save<T: serde::Serialize>(path: &Path, version: u32, value: &T) -> Result<(), anyhow::Error> {
+	let contents = toml::to_string(&Versioned { version, data: value }).context("Unable to serialize")?;
+	self::write_atomic(path, contents.as_bytes())
+}
+
+/// Loads a value with schema version `version` from `path`.
+///
+/// If the file doesn't exist, returns the default. If it's corrupt, or has a different
+/// version, it's moved aside to `{path}.corrupt` and the default is returned.
+pub fn load<T: serde::de::DeserializeOwned + Default>(path: &Path, version: u32) -> T {
+	let contents = match std::fs::read_to_string(path) {
+		Ok(contents) => contents,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => return T::default(),
+		Err(err) => {
+			log::warn!("Unable to read {path:?}, using defaults: {err}");
+			return T::default();
+		},
+	};
+
+	let res = toml::from_str::<Versioned<T>>(&contents)
+		.context("Unable to parse file")
+		.and_then(|versioned| match versioned.version == version {
+			true => Ok(versioned.data),
+			false => Err(anyhow::anyhow!(
+				"Found version {}, expected version {version}",
+				versioned.version
+			)),
+		});
+
+	match res {
+		Ok(value) => value,
+		Err(err) => {
+			let corrupt_path = self::with_suffix(path, ".corrupt");
+			log::warn!("Unable to load {path:?}, moving it to {corrupt_path:?} and using defaults: {err:?}");
+			if let Err(err) = std::fs::rename(path, &corrupt_path) {
+				log::warn!("Unable to move {path:?} to {corrupt_path:?}: {err}");
+			}
+
+			T::default()
+		},
+	}
+}
+
+/// Returns `path` with `suffix` appended to it's file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut file_name = path.file_name().map_or_else(OsString::new, ToOwned::to_owned);
+	file_name.push(suffix);
+	path.with_file_name(file_name)
+}
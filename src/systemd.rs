@@ -0,0 +1,124 @@
+//! Systemd
+//!
+//! When running as a service, notifies systemd once we're ready, and keeps pinging it's
+//! watchdog, if enabled with `WatchdogSec`, so a stuck instance is restarted.
+//!
+//! Implements the `sd_notify` protocol directly, so we don't depend on `libsystemd`.
+
+// Imports
+use anyhow::Context;
+use std::{
+	env,
+	os::unix::net::UnixDatagram,
+	path::PathBuf,
+	time::{Duration, Instant},
+};
+
+/// Systemd notifier
+#[derive(Debug)]
+pub struct Systemd {
+	/// Socket
+	socket: UnixDatagram,
+
+	/// Notify socket path
+	path: PathBuf,
+
+	/// Watchdog, if enabled
+	watchdog: Option<Watchdog>,
+}
+
+impl Systemd {
+	/// Creates the notifier, if we're running under systemd
+	pub fn from_env() -> Result<Option<Self>, anyhow::Error> {
+		let path = match env::var_os("NOTIFY_SOCKET") {
+			Some(path) => PathBuf::from(path),
+			None => return Ok(None),
+		};
+		// Note: Rust doesn't support abstract socket addresses, which start with `@`
+		anyhow::ensure!(
+			!path.to_string_lossy().starts_with('@'),
+			"Abstract notify sockets aren't supported, found {:?}",
+			path
+		);
+		let socket = UnixDatagram::unbound().context("Unable to create socket")?;
+
+		let watchdog = Watchdog::from_env().context("Unable to get watchdog")?;
+		if let Some(watchdog) = &watchdog {
+			log::info!("Pinging the systemd watchdog every {:?}", watchdog.interval);
+		}
+
+		Ok(Some(Self { socket, path, watchdog }))
+	}
+
+	/// Notifies systemd that we're ready
+	pub fn ready(&self) -> Result<(), anyhow::Error> {
+		self.notify("READY=1")
+	}
+
+	/// Notifies systemd that we're stopping
+	pub fn stopping(&self) -> Result<(), anyhow::Error> {
+		self.notify("STOPPING=1")
+	}
+
+	/// Pings the watchdog, if enough time has passed since the last ping
+	pub fn ping_watchdog(&mut self) -> Result<(), anyhow::Error> {
+		let watchdog = match &mut self.watchdog {
+			Some(watchdog) => watchdog,
+			None => return Ok(()),
+		};
+		if watchdog
+			.last_ping
+			.map_or(false, |last_ping| last_ping.elapsed() < watchdog.interval)
+		{
+			return Ok(());
+		}
+
+		watchdog.last_ping = Some(Instant::now());
+		self.notify("WATCHDOG=1")
+	}
+
+	/// Sends `state` to systemd
+	fn notify(&self, state: &str) -> Result<(), anyhow::Error> {
+		self.socket
+			.send_to(state.as_bytes(), &self.path)
+			.with_context(|| format!("Unable to send {state:?} to {}", self.path.display()))?;
+		Ok(())
+	}
+}
+
+/// Watchdog
+#[derive(Debug)]
+struct Watchdog {
+	/// Interval between pings
+	interval: Duration,
+
+	/// Last ping
+	last_ping: Option<Instant>,
+}
+
+impl Watchdog {
+	/// Gets the watchdog from the environment, if enabled for us
+	fn from_env() -> Result<Option<Self>, anyhow::Error> {
+		let timeout = match env::var("WATCHDOG_USEC") {
+			Ok(timeout) => timeout,
+			Err(_) => return Ok(None),
+		};
+
+		// Note: If the watchdog is for another process, such as our parent, we mustn't ping it
+		if let Ok(pid) = env::var("WATCHDOG_PID") {
+			let pid = pid.parse::<u32>().context("Unable to parse `WATCHDOG_PID`")?;
+			if pid != std::process::id() {
+				return Ok(None);
+			}
+		}
+
+		// Note: We ping twice per timeout, as recommended, so a slow frame doesn't trigger it
+		let timeout = timeout.parse::<u64>().context("Unable to parse `WATCHDOG_USEC`")?;
+		let interval = Duration::from_micros(timeout) / 2;
+
+		Ok(Some(Self {
+			interval,
+			last_ping: None,
+		}))
+	}
+}
@@ -0,0 +1,117 @@
+//! Tempo
+
+// Imports
+use std::time::{Duration, Instant};
+
+/// Minimum beats per minute
+pub const MIN_BPM: f32 = 1.0;
+
+/// Maximum beats per minute
+pub const MAX_BPM: f32 = 1000.0;
+
+/// Minimum beat period, at the maximum beats per minute
+const MIN_BEAT_PERIOD: Duration = Duration::from_millis(60);
+
+/// Maximum time between taps for them to count towards the same tempo
+const MAX_TAP_GAP: Duration = Duration::from_secs(2);
+
+/// Maximum number of recent taps averaged
+const MAX_TAPS: usize = 8;
+
+/// Tempo.
+///
+/// External tick source that triggers transitions every `beats_per_image` beats.
+#[derive(Debug)]
+pub struct Tempo {
+	/// Beat period
+	beat_period: Duration,
+
+	/// Beats per image
+	beats_per_image: usize,
+
+	/// Next beat
+	next_beat: Instant,
+
+	/// Beats since the last transition
+	beats: usize,
+
+	/// Recent taps
+	taps: Vec<Instant>,
+}
+
+impl Tempo {
+	/// Creates a new tempo from it's beats per minute, clamped to `MIN_BPM ..= MAX_BPM`.
+	///
+	/// # Panics
+	/// Panics if `bpm` is `NaN`.
+	pub fn new(bpm: f32, beats_per_image: usize) -> Self {
+		let beat_period = Duration::from_secs_f32(60.0 / bpm.clamp(MIN_BPM, MAX_BPM));
+		Self {
+			beat_period,
+			beats_per_image,
+			next_beat: Instant::now() + beat_period,
+			beats: 0,
+			taps: vec![],
+		}
+	}
+
+	/// Returns the beat period
+	pub const fn beat_period(&self) -> Duration {
+		self.beat_period
+	}
+
+	/// Returns the beats per minute
+	pub fn bpm(&self) -> f32 {
+		60.0 / self.beat_period.as_secs_f32()
+	}
+
+	/// Registers a tap at `now`.
+	///
+	/// Re-aligns the beat to the tap and, with at least 2 recent taps,
+	/// adjusts the tempo to their average period.
+	pub fn tap(&mut self, now: Instant) {
+		// If the last tap was too long ago, start over
+		if self
+			.taps
+			.last()
+			.map_or(false, |&last_tap| now.saturating_duration_since(last_tap) > MAX_TAP_GAP)
+		{
+			self.taps.clear();
+		}
+		self.taps.push(now);
+		if self.taps.len() > MAX_TAPS {
+			self.taps.remove(0);
+		}
+
+		// Note: Taps may be at the same instant, so we clamp the period, else we'd never
+		//       get past the next beat when ticking
+		if let [first_tap, .., last_tap] = self.taps[..] {
+			#[allow(clippy::cast_possible_truncation)] // There are at most `MAX_TAPS` taps
+			let intervals = (self.taps.len() - 1) as u32;
+			self.beat_period = (last_tap.saturating_duration_since(first_tap) / intervals).max(MIN_BEAT_PERIOD);
+
+			let bpm = self.bpm();
+			log::info!("Tapped tempo: {bpm:.1} bpm");
+		}
+
+		// Note: We make the tap itself a beat that triggers a transition
+		self.next_beat = now;
+		self.beats = self.beats_per_image.saturating_sub(1);
+	}
+
+	/// Advances the tempo to `now` and returns if a transition should be triggered
+	pub fn tick(&mut self, now: Instant) -> bool {
+		let mut triggered = false;
+		while now >= self.next_beat {
+			self.next_beat += self.beat_period;
+			self.beats += 1;
+
+			if self.beats >= self.beats_per_image {
+				self.beats = 0;
+				triggered = true;
+			}
+		}
+
+		triggered
+	}
+}
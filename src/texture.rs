@@ -0,0 +1,85 @@
+//! Textures
+
+// Imports
+use crate::images::Image;
+use glium::{
+	texture::{ClientFormat, CompressedTexture2d, RawImage2d, UncompressedFloatFormat},
+	uniforms::{AsUniformValue, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction, UniformValue},
+	Texture2d,
+};
+use std::borrow::Cow;
+
+/// Texture of an image
+#[derive(Debug)]
+pub enum ImageTexture {
+	/// Uncompressed, along with it's format
+	Uncompressed(Texture2d, UncompressedFloatFormat),
+
+	/// Compressed, without mipmaps
+	Compressed(CompressedTexture2d),
+}
+
+impl ImageTexture {
+	/// Writes `image` over the whole texture
+	pub fn write(&self, image: &Image) {
+		let (width, height) = image.dimensions();
+		let rect = glium::Rect {
+			left: 0,
+			bottom: 0,
+			width,
+			height,
+		};
+		let format = match image {
+			Image::Rgba(_) => ClientFormat::U8U8U8U8,
+			Image::Rgb(_) => ClientFormat::U8U8U8,
+		};
+		let data = RawImage2d {
+			data: Cow::Borrowed(image.as_raw()),
+			width,
+			height,
+			format,
+		};
+
+		match self {
+			Self::Uncompressed(texture, _) => texture.write(rect, data),
+			Self::Compressed(texture) => texture.write(rect, data),
+		}
+	}
+
+	/// Returns a sampler of this texture, wrapping with `wrap_function`
+	pub fn sampled(&self, wrap_function: SamplerWrapFunction) -> ImageSampler<'_> {
+		let minify_filter = match self {
+			Self::Uncompressed(..) => MinifySamplerFilter::LinearMipmapLinear,
+			// Note: Without mipmaps, sampling with them would make the texture incomplete
+			Self::Compressed(_) => MinifySamplerFilter::Linear,
+		};
+
+		ImageSampler {
+			texture:  self,
+			behavior: SamplerBehavior {
+				wrap_function: (wrap_function, wrap_function, wrap_function),
+				minify_filter,
+				..SamplerBehavior::default()
+			},
+		}
+	}
+}
+
+/// Sampler of an image's texture
+#[derive(Clone, Copy, Debug)]
+pub struct ImageSampler<'a> {
+	/// Texture
+	texture: &'a ImageTexture,
+
+	/// Behavior
+	behavior: SamplerBehavior,
+}
+
+impl AsUniformValue for ImageSampler<'_> {
+	fn as_uniform_value(&self) -> UniformValue<'_> {
+		match self.texture {
+			ImageTexture::Uncompressed(texture, _) => UniformValue::Texture2d(texture, Some(self.behavior)),
+			ImageTexture::Compressed(texture) => UniformValue::CompressedTexture2d(texture, Some(self.behavior)),
+		}
+	}
+}
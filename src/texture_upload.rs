@@ -0,0 +1,202 @@
+//! Texture uploads
+//!
+//! Uploading a large image all at once may take long enough to miss a frame, so
+//! images are instead staged through a pixel buffer, a few rows at a time, spread
+//! over several frames.
+//!
+//! Compressed textures are compressed by the gpu, so they're uploaded all at once,
+//! but only on the first step, so it still happens ahead of time.
+
+// Imports
+use crate::{args::TextureCompression, glium_facade::GliumFacade, images::Image, texture::ImageTexture};
+use anyhow::Context;
+use glium::{
+	backend::Facade,
+	buffer::{Buffer, BufferMode, BufferType},
+	texture::{CompressedFormat, CompressedMipmapsOption, PixelValue, UncompressedFloatFormat},
+	Texture2d,
+};
+use image::{RgbImage, RgbaImage};
+use std::{convert::TryInto, mem, ops::Range};
+
+/// Staged texture upload
+#[derive(Debug)]
+pub struct TextureUpload {
+	/// Image being uploaded, along with it's pixel buffer
+	staging: Staging,
+
+	/// Next row to upload
+	next_row: u32,
+}
+
+/// Image being uploaded, along with it's pixel buffer
+#[derive(Debug)]
+enum Staging {
+	/// Rgba
+	Rgba(RgbaImage, Buffer<[(u8, u8, u8, u8)]>),
+
+	/// Rgb
+	Rgb(RgbImage, Buffer<[(u8, u8, u8)]>),
+
+	/// Compressed by the gpu
+	Compressed(Image),
+}
+
+impl TextureUpload {
+	/// Maximum number of bytes uploaded each frame
+	pub const FRAME_BYTES: usize = 4 * 1024 * 1024;
+
+	/// Starts uploading `image`, returning the texture it's being uploaded to.
+	///
+	/// The texture is compressed with `compression`, if any.
+	/// It's contents are undefined until the upload is finished.
+	pub fn new(
+		facade: &GliumFacade, image: Image, compression: Option<TextureCompression>,
+	) -> Result<(ImageTexture, Self), anyhow::Error> {
+		let (width, height) = image.dimensions();
+		if let Some(compression) = compression {
+			let texture = glium::texture::CompressedTexture2d::empty_with_format(
+				facade,
+				self::compressed_format(compression),
+				CompressedMipmapsOption::NoMipmap,
+				width,
+				height,
+			)
+			.context("Unable to create compressed texture")?;
+
+			let upload = Self {
+				staging:  Staging::Compressed(image),
+				next_row: 0,
+			};
+			return Ok((ImageTexture::Compressed(texture), upload));
+		}
+
+		let format = match image {
+			Image::Rgba(_) => UncompressedFloatFormat::U8U8U8U8,
+			Image::Rgb(_) => UncompressedFloatFormat::U8U8U8,
+		};
+		let texture = facade
+			.empty_texture(width, height, format)
+			.context("Unable to create texture")?;
+
+		let len = width as usize * height as usize;
+		let staging = match image {
+			Image::Rgba(image) => Staging::Rgba(image, self::create_buffer(facade, len)?),
+			Image::Rgb(image) => Staging::Rgb(image, self::create_buffer(facade, len)?),
+		};
+
+		Ok((ImageTexture::Uncompressed(texture, format), Self {
+			staging,
+			next_row: 0,
+		}))
+	}
+
+	/// Returns if the gpu supports compressing textures with `compression`
+	pub fn supports_compression(facade: &GliumFacade, compression: TextureCompression) -> bool {
+		self::compressed_format(compression).is_supported(&**facade.get_context())
+	}
+
+	/// Uploads at most `max_bytes` (rounded up to a row) of the remaining image into `texture`.
+	///
+	/// Returns the number of bytes uploaded.
+	pub fn step(&mut self, texture: &ImageTexture, max_bytes: usize) -> usize {
+		let (width, height) = self.dimensions();
+		let bytes_per_pixel = match &self.staging {
+			Staging::Rgba(..) => 4,
+			Staging::Rgb(..) => 3,
+			Staging::Compressed(image) => image.bytes_per_pixel() as usize,
+		};
+		let row_bytes = bytes_per_pixel * width as usize;
+		let max_rows: u32 = match self.staging {
+			Staging::Compressed(_) => u32::MAX,
+			_ => (max_bytes / row_bytes).max(1).try_into().unwrap_or(u32::MAX),
+		};
+		let rows = self.next_row..self.next_row.saturating_add(max_rows).min(height);
+		if rows.is_empty() {
+			return 0;
+		}
+
+		// Note: We only convert the rows we upload, so the cost of it is spread too
+		let bytes = (rows.start as usize * row_bytes)..(rows.end as usize * row_bytes);
+		match (&self.staging, texture) {
+			(Staging::Rgba(image, buffer), ImageTexture::Uncompressed(texture, _)) => {
+				let pixels = image.as_raw()[bytes]
+					.chunks_exact(4)
+					.map(|pixel| (pixel[0], pixel[1], pixel[2], pixel[3]))
+					.collect::<Vec<_>>();
+				self::upload_rows(texture, buffer, width, rows.clone(), &pixels);
+			},
+			(Staging::Rgb(image, buffer), ImageTexture::Uncompressed(texture, _)) => {
+				let pixels = image.as_raw()[bytes]
+					.chunks_exact(3)
+					.map(|pixel| (pixel[0], pixel[1], pixel[2]))
+					.collect::<Vec<_>>();
+				self::upload_rows(texture, buffer, width, rows.clone(), &pixels);
+			},
+			(Staging::Compressed(image), ImageTexture::Compressed(_)) => texture.write(image),
+			_ => unreachable!("Texture didn't match the staged image"),
+		}
+		self.next_row = rows.end;
+
+		if let (true, ImageTexture::Uncompressed(texture, _)) = (self.is_finished(), texture) {
+			// SAFETY: All our uncompressed textures are created with mipmaps.
+			unsafe { texture.generate_mipmaps() };
+		}
+
+		rows.len() * row_bytes
+	}
+
+	/// Returns if the whole image has been uploaded
+	pub fn is_finished(&self) -> bool {
+		let (_, height) = self.dimensions();
+		self.next_row >= height
+	}
+
+	/// Returns the dimensions of the image being uploaded
+	fn dimensions(&self) -> (u32, u32) {
+		match &self.staging {
+			Staging::Rgba(image, _) => image.dimensions(),
+			Staging::Rgb(image, _) => image.dimensions(),
+			Staging::Compressed(image) => image.dimensions(),
+		}
+	}
+}
+
+/// Returns the compressed format for `compression`
+const fn compressed_format(compression: TextureCompression) -> CompressedFormat {
+	match compression {
+		TextureCompression::Bc1 => CompressedFormat::S3tcDxt1NoAlpha,
+		TextureCompression::Bc7 => CompressedFormat::BptcUnorm4,
+	}
+}
+
+/// Creates a pixel buffer for `len` pixels
+fn create_buffer<P: PixelValue>(facade: &GliumFacade, len: usize) -> Result<Buffer<[P]>, anyhow::Error> {
+	Buffer::empty_array(facade, BufferType::PixelUnpackBuffer, len, BufferMode::Default)
+		.context("Unable to create pixel buffer")
+}
+
+/// Uploads `pixels` to `rows` of `texture`, through `buffer`
+fn upload_rows<P: PixelValue>(texture: &Texture2d, buffer: &Buffer<[P]>, width: u32, rows: Range<u32>, pixels: &[P]) {
+	let pixels_range = |rows: Range<u32>| (rows.start as usize * width as usize)..(rows.end as usize * width as usize);
+	buffer
+		.slice(pixels_range(rows.clone()))
+		.expect("Rows were outside of the pixel buffer")
+		.write(pixels);
+
+	// Note: The unpack alignment might be the default of 4 bytes, so if our rows aren't aligned
+	//       to it, we upload them one at a time, as the alignment only affects the gaps between rows.
+	let is_aligned = (width as usize * mem::size_of::<P>()) % 4 == 0;
+	let chunks = match is_aligned {
+		true => vec![rows],
+		false => rows.map(|row| row..(row + 1)).collect(),
+	};
+	for rows in chunks {
+		let slice = buffer
+			.slice(pixels_range(rows.clone()))
+			.expect("Rows were outside of the pixel buffer");
+		texture
+			.main_level()
+			.raw_upload_from_pixel_buffer(slice, 0..width, rows, 0..1);
+	}
+}
@@ -1,5 +1,8 @@
 //! Image uvs
 
+// Imports
+use crate::args::Scaling;
+
 /// Image uvs
 #[derive(Debug)]
 pub struct ImageUvs {
@@ -11,11 +14,16 @@ pub struct ImageUvs {
 
 	/// Swap direction
 	swap_dir: bool,
+
+	/// Scale of the window relative to the image, for all scalings except `Fill`
+	scale: [f32; 2],
 }
 
 impl ImageUvs {
 	/// Creates the uvs for an image
-	pub fn new(image_width: f32, image_height: f32, window_width: f32, window_height: f32, swap_dir: bool) -> Self {
+	pub fn new(
+		image_width: f32, image_height: f32, window_width: f32, window_height: f32, swap_dir: bool, scaling: Scaling,
+	) -> Self {
 		let (start, end) = match image_width / image_height >= window_width / window_height {
 			true => ([(window_width / image_width) / (window_height / image_height), 1.0], [
 				1.0, 1.0,
@@ -25,7 +33,24 @@ impl ImageUvs {
 			]),
 		};
 
-		Self { start, end, swap_dir }
+		let image_aspect_ratio = image_width / image_height;
+		let window_aspect_ratio = window_width / window_height;
+		let scale = match scaling {
+			// Note: `Fill` uses the start and end instead
+			Scaling::Fill | Scaling::Stretch => [1.0, 1.0],
+			Scaling::Fit | Scaling::ContainBlur => match image_aspect_ratio >= window_aspect_ratio {
+				true => [1.0, image_aspect_ratio / window_aspect_ratio],
+				false => [window_aspect_ratio / image_aspect_ratio, 1.0],
+			},
+			Scaling::Center | Scaling::Tile => [window_width / image_width, window_height / image_height],
+		};
+
+		Self {
+			start,
+			end,
+			swap_dir,
+			scale,
+		}
 	}
 
 	/// Returns the starting uvs
@@ -33,6 +58,16 @@ impl ImageUvs {
 		self.start
 	}
 
+	/// Returns the scale of the window relative to the image
+	pub const fn scale(&self) -> [f32; 2] {
+		self.scale
+	}
+
+	/// Reverses the scrolling direction
+	pub fn reverse(&mut self) {
+		self.swap_dir = !self.swap_dir;
+	}
+
 	/// Returns the offset given progress
 	pub fn offset(&self, f: f32) -> [f32; 2] {
 		let f = match self.swap_dir {
@@ -0,0 +1,214 @@
+//! Verify
+
+// Imports
+use anyhow::Context;
+use image::{imageops, Rgba, RgbaImage};
+use std::{
+	path::{Path, PathBuf},
+	rc::Rc,
+	slice,
+};
+use zss::{
+	args::{ResizeFilter, Scaling, VerifyArgs},
+	config::Config,
+	effects::Effects,
+	glium_backend::GliumBackend,
+	glium_facade::GliumFacade,
+	images::{self, LoadOptions},
+	panel::Panel,
+	renderer::{DrawParams, Renderer},
+	window::Window,
+	Image,
+};
+
+/// Renders deterministic frames and compares them against a reference
+#[allow(clippy::too_many_lines)] // TODO: Refactor
+pub fn run(args: &VerifyArgs) -> Result<(), anyhow::Error> {
+	// Create an offscreen window to render with
+	let window = Window::headless(args.size)
+		.map(Rc::new)
+		.context("Unable to create headless window")?;
+	let backend = GliumBackend::new(Rc::clone(&window)).context("Unable to create backend")?;
+	let facade = GliumFacade::new(backend).context("Unable to create glium facade")?;
+
+	let renderer = Renderer::new(&facade).context("Unable to create renderer")?;
+
+	// Load the config, if any, for how to draw the frames
+	let config = match &args.config_path {
+		Some(config_path) => Config::load(config_path)
+			.with_context(|| format!("Unable to load config from {}", config_path.display()))?,
+		None => Config::default(),
+	};
+	let fade = config.draw.fade()?.unwrap_or(args.fade);
+	let fade_space = config.draw.fade_space()?.unwrap_or(args.fade_space);
+	let scaling = config.draw.scaling()?.unwrap_or(Scaling::Fill);
+	let bg_color = config.draw.bg_color()?.unwrap_or([0.0, 0.0, 0.0]);
+
+	// Load the first 2 images, by path
+	// Note: We never swap the scroll direction to keep the frames deterministic
+	let mut paths = vec![];
+	self::collect_paths(&args.images_dir, &mut paths).context("Unable to read images directory")?;
+	paths.sort();
+	let mut loaded_images = paths
+		.iter()
+		.filter_map(|path| {
+			match images::load_img(path, LoadOptions {
+				window_size: args.size,
+				scaling,
+				resize_filter: ResizeFilter::Lanczos3,
+				gpu_resize: false,
+				rgb: false,
+				max_texture_size: Some(facade.max_texture_size()),
+				cache_size: 0,
+			}) {
+				Ok(image) => Some(image),
+				Err(err) => {
+					log::info!("Unable to load {path:?}: {err}");
+					None
+				},
+			}
+		})
+		.map(|image| -> Result<Image, anyhow::Error> {
+			let mut image = Image::from_image(&facade, image, args.size, scaling, None)?;
+			image.finish_upload();
+			Ok(image)
+		});
+	let cur_image = loaded_images.next().context("No images found")??;
+	let next_image = loaded_images.next().context("At least 2 images are required")??;
+	let mut panel = Panel::full(cur_image, next_image);
+	let params = DrawParams {
+		fade,
+		fade_space,
+		scaling,
+		kaleidoscope: None,
+		double_exposure: None,
+		exposure_mix: 0.0,
+		bg_color,
+		border_color: [0.0, 0.0, 0.0],
+		dominant_bg: false,
+		interpolation_samples: 1,
+		effects: Effects::IDENTITY,
+	};
+
+	// Then render each frame
+	let mut failed_frames = 0;
+	for frame in 0..args.frames {
+		#[allow(clippy::cast_precision_loss)] // Frame counts will be small
+		let progress = (frame as f32 + 0.5) / args.frames as f32;
+		panel.progress = progress;
+
+		let texture =
+			glium::Texture2d::empty(&facade, args.size[0], args.size[1]).context("Unable to create texture")?;
+		let mut surface =
+			glium::framebuffer::SimpleFrameBuffer::new(&facade, &texture).context("Unable to create framebuffer")?;
+		renderer
+			.draw(&mut surface, slice::from_ref(&panel), &params)
+			.context("Unable to draw frame")?;
+
+		// Note: Open-gl's origin is on the bottom-left, so we need to flip it
+		let raw: glium::texture::RawImage2d<u8> = texture.read();
+		let image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+			.context("Frame had an unexpected size")?;
+		let image = imageops::flip_vertical(&image);
+
+		let file_name = format!("frame{frame:03}.png");
+		if let Some(out) = &args.out {
+			let path = out.join(&file_name);
+			image
+				.save(&path)
+				.with_context(|| format!("Unable to save frame to {}", path.display()))?;
+		}
+
+		if let Some(compare) = &args.compare {
+			let path = compare.join(&file_name);
+			let reference = image::open(&path)
+				.with_context(|| format!("Unable to open reference frame {}", path.display()))?
+				.to_rgba8();
+			let (mean, max) = self::diff(&image, &reference)
+				.with_context(|| format!("Unable to compare against {}", path.display()))?;
+
+			let passed = mean <= args.threshold;
+			if !passed {
+				failed_frames += 1;
+			}
+			println!(
+				"{file_name}: mean ΔE {mean:.3}, max ΔE {max:.3} ({})",
+				if passed { "ok" } else { "FAILED" }
+			);
+		}
+	}
+
+	anyhow::ensure!(
+		failed_frames == 0,
+		"{failed_frames} frame(s) differed from the reference by more than {}",
+		args.threshold
+	);
+
+	Ok(())
+}
+
+/// Collects all files in directory `dir`, recursively
+fn collect_paths(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), anyhow::Error> {
+	for entry in std::fs::read_dir(dir).context("Unable to read directory")? {
+		let entry = entry.context("Unable to read directory entry")?;
+		let file_type = entry.file_type().context("Unable to get entry file type")?;
+
+		match file_type.is_dir() {
+			true => self::collect_paths(&entry.path(), paths).context("Unable to collect sub-directory")?,
+			false => paths.push(entry.path()),
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the mean and max perceptual difference (CIE76 delta E) between two images
+#[allow(clippy::suboptimal_flops)] // Keeping the formulas readable is more important
+fn diff(lhs: &RgbaImage, rhs: &RgbaImage) -> Result<(f32, f32), anyhow::Error> {
+	anyhow::ensure!(
+		lhs.dimensions() == rhs.dimensions(),
+		"Sizes differ ({:?} vs {:?})",
+		lhs.dimensions(),
+		rhs.dimensions()
+	);
+
+	let mut total = 0.0;
+	let mut max = 0.0_f32;
+	for (lhs, rhs) in lhs.pixels().zip(rhs.pixels()) {
+		let [lhs_l, lhs_a, lhs_b] = self::srgb_to_lab(*lhs);
+		let [rhs_l, rhs_a, rhs_b] = self::srgb_to_lab(*rhs);
+		let delta = ((lhs_l - rhs_l).powi(2) + (lhs_a - rhs_a).powi(2) + (lhs_b - rhs_b).powi(2)).sqrt();
+
+		total += f64::from(delta);
+		max = max.max(delta);
+	}
+
+	#[allow(clippy::cast_possible_truncation)] // We only need an approximate mean
+	let mean = (total / (f64::from(lhs.width()) * f64::from(lhs.height()))) as f32;
+	Ok((mean, max))
+}
+
+/// Converts an srgb color to CIE L*a*b*, with a D65 white point
+#[allow(clippy::suboptimal_flops)] // Keeping the formulas readable is more important
+fn srgb_to_lab(Rgba([red, green, blue, _]): Rgba<u8>) -> [f32; 3] {
+	let to_linear = |c: u8| {
+		let c = f32::from(c) / 255.0;
+		match c <= 0.040_45 {
+			true => c / 12.92,
+			false => ((c + 0.055) / 1.055).powf(2.4),
+		}
+	};
+	let [red, green, blue] = [to_linear(red), to_linear(green), to_linear(blue)];
+
+	let x = (0.412_456_4 * red + 0.357_576_1 * green + 0.180_437_5 * blue) / 0.950_47;
+	let y = 0.212_672_9 * red + 0.715_152_2 * green + 0.072_175 * blue;
+	let z = (0.019_333_9 * red + 0.119_192 * green + 0.950_304_1 * blue) / 1.088_83;
+
+	let f = |t: f32| match t > 0.008_856 {
+		true => t.cbrt(),
+		false => 7.787 * t + 16.0 / 116.0,
+	};
+	let [x, y, z] = [f(x), f(y), f(z)];
+
+	[116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
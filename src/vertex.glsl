@@ -2,6 +2,7 @@
 
 // Uniforms
 uniform mat4 mat;
+uniform vec2 tex_flip;
 
 // Inputs
 in vec2 vertex_pos;
@@ -13,7 +14,7 @@ out vec2 frag_tex;
 
 void main() {
 	frag_pos = vertex_pos;
-	frag_tex = vertex_tex;
+	frag_tex = mix(vertex_tex, 1.0 - vertex_tex, tex_flip);
 
 	vec4 pos = mat * vec4(vertex_pos, 0.0, 1.0);
 
@@ -0,0 +1,185 @@
+//! Videos
+//!
+//! Videos are decoded by an `ffmpeg` process, which streams the raw frames to us,
+//! so they never need to all be kept in memory.
+
+// Imports
+use crate::{
+	args::ResizeFilter,
+	images::{self, Frame, Image, LoadOptions, LoadedImage},
+	slide_info::{SlideInfo, SlideSource},
+};
+use anyhow::Context;
+use image::{RgbImage, RgbaImage};
+use std::{
+	io::Read,
+	path::Path,
+	process::{Child, Command, Stdio},
+	sync::mpsc,
+	thread,
+	time::{Duration, Instant},
+};
+
+/// Video
+#[derive(Debug)]
+pub struct Video {
+	/// Receiver for decoded frames
+	frame_rx: mpsc::Receiver<Image>,
+
+	/// Delay between frames
+	frame_delay: Duration,
+
+	/// Decoder process
+	decoder: Child,
+}
+
+impl Video {
+	/// Maximum number of frames decoded ahead of time
+	const FRAME_BACKLOG: usize = 4;
+
+	/// Returns the delay between frames
+	pub const fn frame_delay(&self) -> Duration {
+		self.frame_delay
+	}
+
+	/// Returns the next frame, returning `None` if not yet decoded
+	pub fn try_next_frame(&self) -> Option<Image> {
+		self.frame_rx.try_recv().ok()
+	}
+}
+
+impl Drop for Video {
+	fn drop(&mut self) {
+		// Note: The decoder might've already exited, so we ignore any errors
+		let _ = self.decoder.kill();
+		let _ = self.decoder.wait();
+	}
+}
+
+/// Loads a video from a path.
+///
+/// The first frame is decoded immediately, while the rest, looping forever,
+/// are streamed through [`LoadedImage::video`].
+pub fn load_video(path: &Path, load_options: LoadOptions) -> Result<LoadedImage, anyhow::Error> {
+	let load_start = Instant::now();
+
+	// Get the video's size and framerate
+	let output = Command::new("ffprobe")
+		.args(&[
+			"-v",
+			"error",
+			"-select_streams",
+			"v:0",
+			"-show_entries",
+			"stream=width,height,avg_frame_rate",
+			"-of",
+			"csv=p=0",
+		])
+		.arg(path)
+		.output()
+		.context("Unable to run `ffprobe`")?;
+	anyhow::ensure!(
+		output.status.success(),
+		"`ffprobe` failed: {}",
+		String::from_utf8_lossy(&output.stderr).trim()
+	);
+	let output = String::from_utf8(output.stdout).context("`ffprobe` output wasn't utf-8")?;
+	let (video_width, video_height, frame_delay) = match output.trim().split(',').collect::<Vec<_>>()[..] {
+		[width, height, frame_rate] => (
+			width.parse().context("Unable to parse video width")?,
+			height.parse().context("Unable to parse video height")?,
+			self::parse_frame_delay(frame_rate).context("Unable to parse video framerate")?,
+		),
+		_ => anyhow::bail!("Unexpected `ffprobe` output {:?}", output),
+	};
+	log::info!("Loaded {path:?} ({video_width}x{video_height} video)");
+
+	// Then have `ffmpeg` resize it, like images, and decode it
+	let (width, height) = images::resize_size(
+		(video_width, video_height),
+		load_options.window_size,
+		load_options.scaling,
+	)
+	.unwrap_or((video_width, video_height));
+	let (width, height) = load_options
+		.max_texture_size
+		.and_then(|max_texture_size| images::clamp_texture_size((width, height), max_texture_size))
+		.unwrap_or((width, height));
+	let flags = match load_options.resize_filter {
+		ResizeFilter::Nearest => "neighbor",
+		ResizeFilter::Triangle => "bilinear",
+		ResizeFilter::CatmullRom => "bicubic",
+		ResizeFilter::Lanczos3 => "lanczos",
+	};
+	let pix_fmt = match load_options.rgb {
+		true => "rgb24",
+		false => "rgba",
+	};
+	let mut decoder = Command::new("ffmpeg")
+		.args(&["-v", "error", "-stream_loop", "-1", "-i"])
+		.arg(path)
+		.args(&["-vf", &format!("scale={width}:{height}:flags={flags}")])
+		.args(&["-f", "rawvideo", "-pix_fmt", pix_fmt, "-"])
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.spawn()
+		.context("Unable to run `ffmpeg`")?;
+
+	// Stream all frames in a background thread
+	// Note: The thread exits once we're dropped, as the sender will error out
+	let mut stdout = decoder.stdout.take().expect("Decoder stdout was piped");
+	let (frame_tx, frame_rx) = mpsc::sync_channel(Video::FRAME_BACKLOG);
+	let rgb = load_options.rgb;
+	let bytes_per_pixel = match rgb {
+		true => 3,
+		false => 4,
+	};
+	let frame_len = bytes_per_pixel * (width as usize) * (height as usize);
+	thread::spawn(move || loop {
+		let mut frame = vec![0; frame_len];
+		if let Err(err) = stdout.read_exact(&mut frame) {
+			log::warn!("Unable to read video frame: {err}");
+			return;
+		}
+
+		let frame = match rgb {
+			true => Image::Rgb(RgbImage::from_raw(width, height, frame).expect("Frame had the wrong size")),
+			false => Image::Rgba(RgbaImage::from_raw(width, height, frame).expect("Frame had the wrong size")),
+		};
+		if frame_tx.send(frame).is_err() {
+			return;
+		}
+	});
+	let video = Video {
+		frame_rx,
+		frame_delay,
+		decoder,
+	};
+
+	// Then wait for the first frame
+	let image = video.frame_rx.recv().context("Unable to decode first frame")?;
+	let palette = images::palette(&image, images::PALETTE_LEN);
+	let dominant_color = palette.first().copied().unwrap_or_default();
+
+	Ok(LoadedImage {
+		info: SlideInfo::new(path, [video_width, video_height], SlideSource::Directory, load_start),
+		frames: vec![Frame {
+			image,
+			delay: frame_delay,
+		}],
+		dominant_color,
+		palette,
+		swap_dir: false,
+		video: Some(video),
+	})
+}
+
+/// Parses a frame delay from a `{numerator}/{denominator}` framerate
+fn parse_frame_delay(frame_rate: &str) -> Result<Duration, anyhow::Error> {
+	let (numerator, denominator) = frame_rate.split_once('/').context("Framerate wasn't a fraction")?;
+	let numerator: u32 = numerator.parse().context("Unable to parse numerator")?;
+	let denominator: u32 = denominator.parse().context("Unable to parse denominator")?;
+	anyhow::ensure!(numerator != 0 && denominator != 0, "Framerate must not be zero");
+
+	Ok(Duration::from_secs_f64(f64::from(denominator) / f64::from(numerator)))
+}
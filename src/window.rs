@@ -1,14 +1,66 @@
 //! Window
 
 // Imports
-use anyhow::Context;
+use crate::{glium_backend::GlSurface, overlay};
 use std::{
 	convert::TryInto,
+	error::Error,
+	ffi::{c_void, CStr, CString},
+	fmt,
 	mem::{self, MaybeUninit},
 	os::raw::c_int,
 };
 use x11::{glx, xlib};
 
+/// Window error
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WindowError {
+	/// Unable to open the display
+	OpenDisplay,
+
+	/// Unable to get the window attributes
+	GetAttributes,
+
+	/// Window size was negative
+	NegativeSize,
+
+	/// Size was too large for a pixel buffer
+	SizeTooLarge,
+
+	/// Unable to create the pixel buffer
+	CreatePixelBuffer,
+
+	/// No frame-buffer configs were found
+	NoFbConfigs,
+
+	/// Unable to get the function to create the gl context
+	GetCreateContextFn,
+
+	/// Unable to create the gl context
+	CreateGlContext,
+
+	/// Unable to make the gl context current
+	MakeContextCurrent,
+}
+
+impl fmt::Display for WindowError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::OpenDisplay => write!(f, "Unable to open display"),
+			Self::GetAttributes => write!(f, "Unable to get window attributes"),
+			Self::NegativeSize => write!(f, "Window size was negative"),
+			Self::SizeTooLarge => write!(f, "Size was too large"),
+			Self::CreatePixelBuffer => write!(f, "Unable to create pixel buffer"),
+			Self::NoFbConfigs => write!(f, "Unable to retrieve any valid fb configs"),
+			Self::GetCreateContextFn => write!(f, "Unable to get function to create gl context"),
+			Self::CreateGlContext => write!(f, "Unable to create gl context"),
+			Self::MakeContextCurrent => write!(f, "Failed to make context current"),
+		}
+	}
+}
+
+impl Error for WindowError {}
+
 /// Window
 pub struct Window {
 	/// Display
@@ -20,8 +72,11 @@ pub struct Window {
 	/// Gl context
 	gl_context: glx::GLXContext,
 
-	/// Attributes
-	attrs: xlib::XWindowAttributes,
+	/// Size
+	size: [u32; 2],
+
+	/// Scale factor
+	scale_factor: f32,
 }
 
 impl Window {
@@ -47,9 +102,23 @@ impl Window {
 		0x9126, 0x1,
 		0, 0
 	];
+	/// Extension required for `ROBUST_GL_CONFIG_ATTRIBUTES`
+	const ROBUSTNESS_EXTENSION: &'static str = "GLX_ARB_create_context_robustness";
+	/// Open-gl configuration attributes, with robust access and a lost context on gpu resets
+	///
+	/// Without these, a gpu reset would go unnoticed and we'd keep drawing garbage.
+	#[rustfmt::skip]
+	const ROBUST_GL_CONFIG_ATTRIBUTES: [i32; 12] = [
+		0x2091, 3,
+		0x2092, 0,
+		0x2094, 0x2 | 0x4,
+		0x9126, 0x1,
+		0x8256, 0x8252,
+		0, 0
+	];
 
 	/// Creates a window from an existing x11 window
-	pub fn from_window_id(id: u64) -> Result<Self, anyhow::Error> {
+	pub fn from_window_id(id: u64) -> Result<Self, WindowError> {
 		// Get the display and screen
 		// TODO: Window might not be from the default display, somehow obtain
 		//       the correct display eventually. Maybe same with screen?
@@ -63,12 +132,83 @@ impl Window {
 		// SAFETY: Even if `id` isn't a valid window, this should simply return `0`,
 		//         which we catch before the `assume_init` call.
 		let mut attrs = MaybeUninit::uninit();
-		anyhow::ensure!(
-			unsafe { xlib::XGetWindowAttributes(display, id, attrs.as_mut_ptr()) } != 0,
-			"Unable to get window attributes"
-		);
-		let attrs = unsafe { attrs.assume_init() };
+		if unsafe { xlib::XGetWindowAttributes(display, id, attrs.as_mut_ptr()) } == 0 {
+			return Err(WindowError::GetAttributes);
+		}
+		let attrs: xlib::XWindowAttributes = unsafe { attrs.assume_init() };
+		let size = [
+			attrs.width.try_into().map_err(|_| WindowError::NegativeSize)?,
+			attrs.height.try_into().map_err(|_| WindowError::NegativeSize)?,
+		];
+
+		// Then create the gl context
+		let (_, gl_context) = Self::create_gl_context(display, screen)?;
+
+		Ok(Self {
+			display,
+			id,
+			gl_context,
+			size,
+			scale_factor: Self::screen_scale_factor(display, screen),
+		})
+	}
+
+	/// Creates a headless window, backed by an offscreen pixel buffer
+	pub fn headless(size @ [width, height]: [u32; 2]) -> Result<Self, WindowError> {
+		// Get the display and screen
+		// SAFETY: See `from_window_id`.
+		let display = unsafe { xlib::XOpenDisplay(std::ptr::null()) };
+		if display.is_null() {
+			return Err(WindowError::OpenDisplay);
+		}
+		let screen = unsafe { xlib::XDefaultScreen(display) };
+
+		// Create the gl context
+		let (fb_config, gl_context) = Self::create_gl_context(display, screen)?;
+
+		// Then create the pixel buffer
+		#[rustfmt::skip]
+		let pbuffer_attrs = [
+			glx::GLX_PBUFFER_WIDTH , width.try_into().map_err(|_| WindowError::SizeTooLarge)?,
+			glx::GLX_PBUFFER_HEIGHT, height.try_into().map_err(|_| WindowError::SizeTooLarge)?,
+			glx::GLX_NONE,
+		];
+		// SAFETY: We terminate `pbuffer_attrs` and the fb config is valid.
+		let id = unsafe { glx::glXCreatePbuffer(display, fb_config, pbuffer_attrs.as_ptr()) };
+		if id == 0 {
+			return Err(WindowError::CreatePixelBuffer);
+		}
+
+		Ok(Self {
+			display,
+			id,
+			gl_context,
+			size,
+			scale_factor: Self::screen_scale_factor(display, screen),
+		})
+	}
+
+	/// Returns the scale factor of `screen`, from it's physical size
+	fn screen_scale_factor(display: *mut xlib::Display, screen: c_int) -> f32 {
+		// SAFETY: The display and screen are valid.
+		let (width, width_mm) = unsafe {
+			(
+				xlib::XDisplayWidth(display, screen),
+				xlib::XDisplayWidthMM(display, screen),
+			)
+		};
 
+		// Note: If either is negative, we just treat the physical size as unknown
+		match (width.try_into(), width_mm.try_into()) {
+			(Ok(width), Ok(width_mm)) => overlay::scale_factor(width, width_mm),
+			_ => 1.0,
+		}
+	}
+
+	/// Creates a gl context, returning it along with the frame-buffer config used
+	fn create_gl_context(
+		display: *mut xlib::Display, screen: c_int,
+	) -> Result<(glx::GLXFBConfig, glx::GLXContext), WindowError> {
 		// Get the frame-buffer configs
 		// SAFETY: We terminate the `FRAME_BUFFER_CONFIG_ATTRIBUTES` and aside
 		//         from that, the function should be inherently safe.
@@ -81,13 +221,17 @@ impl Window {
 				fb_configs_len.as_mut_ptr(),
 			)
 		};
-		anyhow::ensure!(!fb_configs.is_null(), "Unable to retrieve any valid fb configs");
+		if fb_configs.is_null() {
+			return Err(WindowError::NoFbConfigs);
+		}
 
 		// SAFETY: By here, we know the previous call succeeded and thus the variable
 		//         is initialized.
 		let fb_configs_len = unsafe { fb_configs_len.assume_init() };
 		log::info!("Found {fb_configs_len} frame-buffer configurations at {fb_configs:?}");
-		anyhow::ensure!(fb_configs_len != 0, "No fg configs found");
+		if fb_configs_len == 0 {
+			return Err(WindowError::NoFbConfigs);
+		}
 
 		// Then select the first one we find
 		// TODO: Maybe pick one based on something?
@@ -98,7 +242,7 @@ impl Window {
 		// SAFETY: The call to the function is safe, as we null terminate the string,
 		//         and the cast is also safe, as that's the signature of the returned function.
 		let create_gl_context = unsafe { glx::glXGetProcAddressARB(b"glXCreateContextAttribsARB\0" as *const _) }
-			.context("Unable to get function")?;
+			.ok_or(WindowError::GetCreateContextFn)?;
 		let create_gl_context: unsafe fn(
 			*mut xlib::Display,
 			glx::GLXFBConfig,
@@ -107,8 +251,25 @@ impl Window {
 			*const c_int,
 		) -> glx::GLXContext = unsafe { mem::transmute(create_gl_context) };
 
-		// Then create the context
-		// SAFETY: We null-terminate `GL_CONFIG_ATTRIBUTES`,
+		// Then create the context, robust if supported
+		// SAFETY: The display and screen are valid and the returned string is null-terminated.
+		let extensions = unsafe { CStr::from_ptr(glx::glXQueryExtensionsString(display, screen)) };
+		let is_robust = extensions
+			.to_string_lossy()
+			.split_whitespace()
+			.any(|extension| extension == Self::ROBUSTNESS_EXTENSION);
+		if !is_robust {
+			log::warn!(
+				"{} isn't supported, gpu resets won't be detected",
+				Self::ROBUSTNESS_EXTENSION
+			);
+		}
+		let gl_config_attributes = match is_robust {
+			true => &Self::ROBUST_GL_CONFIG_ATTRIBUTES[..],
+			false => &Self::GL_CONFIG_ATTRIBUTES[..],
+		};
+
+		// SAFETY: We null-terminate both configuration attributes,
 		//         every other argument has no possible UB and
 		//         the function should be inherently safe.
 		let gl_context = unsafe {
@@ -117,32 +278,29 @@ impl Window {
 				fb_config,
 				std::ptr::null_mut(),
 				xlib::True,
-				Self::GL_CONFIG_ATTRIBUTES.as_ptr(),
+				gl_config_attributes.as_ptr(),
 			)
 		};
-		anyhow::ensure!(!gl_context.is_null(), "Unable to get gl context");
+		if gl_context.is_null() {
+			return Err(WindowError::CreateGlContext);
+		}
 
-		Ok(Self {
-			display,
-			id,
-			gl_context,
-			attrs,
-		})
+		Ok((fb_config, gl_context))
 	}
 
 	/// Window size
-	pub fn size(&self) -> [u32; 2] {
-		[self.width(), self.height()]
+	pub const fn size(&self) -> [u32; 2] {
+		self.size
 	}
 
 	/// Window width
-	pub fn width(&self) -> u32 {
-		self.attrs.width.try_into().expect("Window width was negative")
+	pub const fn width(&self) -> u32 {
+		self.size[0]
 	}
 
 	/// Window height
-	pub fn height(&self) -> u32 {
-		self.attrs.height.try_into().expect("Window height was negative")
+	pub const fn height(&self) -> u32 {
+		self.size[1]
 	}
 
 	/// Processes all X events
@@ -162,13 +320,15 @@ impl Window {
 	}
 
 	/// Makes the current gl context current
-	pub fn make_context_current(&self) -> Result<(), anyhow::Error> {
+	pub fn make_context_current(&self) -> Result<(), WindowError> {
 		// SAFETY: The display, window id and gl context are known to be valid, thus
 		//         the call should be safe.
 		let res = unsafe { glx::glXMakeContextCurrent(self.display, self.id, self.id, self.gl_context) };
 
-		anyhow::ensure!(res == 1, "Failed to make context current");
-		Ok(())
+		match res {
+			1 => Ok(()),
+			_ => Err(WindowError::MakeContextCurrent),
+		}
 	}
 
 	/// Swaps buffers
@@ -180,3 +340,54 @@ impl Window {
 		}
 	}
 }
+
+impl GlSurface for Window {
+	fn size(&self) -> [u32; 2] {
+		self.size
+	}
+
+	fn scale_factor(&self) -> f32 {
+		self.scale_factor
+	}
+
+	fn process_events(&self) {
+		Self::process_events(self);
+	}
+
+	fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError> {
+		Self::swap_buffers(self);
+		Ok(())
+	}
+
+	fn get_proc_address(&self, name: &str) -> *const c_void {
+		let name_cstr = CString::new(name).expect("Unable to create c-string from name");
+		// SAFETY: `glXGetProcAddressARB` should be safe to call with any string.
+		match unsafe { glx::glXGetProcAddressARB(name_cstr.as_ptr().cast()) } {
+			Some(f) => f as *const _,
+			None => std::ptr::null(),
+		}
+	}
+
+	fn is_context_current(&self) -> bool {
+		Self::is_context_current(self)
+	}
+
+	fn make_context_current(&self) -> Result<(), anyhow::Error> {
+		Self::make_context_current(self)?;
+		Ok(())
+	}
+}
+
+impl Drop for Window {
+	fn drop(&mut self) {
+		// Note: Closing the display also destroys the pixel buffer, for headless windows
+		// SAFETY: The display and gl context are valid and no longer used after this.
+		unsafe {
+			if self.is_context_current() {
+				glx::glXMakeContextCurrent(self.display, 0, 0, std::ptr::null_mut());
+			}
+			glx::glXDestroyContext(self.display, self.gl_context);
+			xlib::XCloseDisplay(self.display);
+		}
+	}
+}